@@ -0,0 +1,91 @@
+//! Per-asset cost of validating attributes against a CI type's schema, with
+//! the schema compiled for every asset (as before the shared cache) and with
+//! compiled schemas taken from `SchemaCache`.
+//!
+//! Run with `cargo bench --bench schema_validation`. The asset count can be
+//! changed with `SCHEMA_BENCH_ASSETS`.
+
+use chrono::Utc;
+use crate_backend::utils::SchemaCache;
+use jsonschema::JSONSchema;
+use serde_json::{json, Value};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+fn server_schema() -> Value {
+    json!({
+        "type": "object",
+        "required": ["hostname", "ip_address", "os", "cpu_cores", "memory_gb"],
+        "properties": {
+            "hostname": { "type": "string", "pattern": "^[a-z0-9-]+$", "maxLength": 63 },
+            "domain": { "type": "string", "format": "hostname" },
+            "ip_address": { "type": "string", "format": "ipv4" },
+            "os": { "type": "string", "enum": ["linux", "windows", "bsd"] },
+            "cpu_cores": { "type": "integer", "minimum": 1, "maximum": 512 },
+            "memory_gb": { "type": "number", "minimum": 0.5 },
+            "tags": { "type": "array", "items": { "type": "string" }, "uniqueItems": true },
+            "warranty_end": { "type": "string", "format": "date" },
+            "owner_email": { "type": "string", "format": "email" },
+            "rack": {
+                "type": "object",
+                "properties": {
+                    "room": { "type": "string" },
+                    "position": { "type": "integer", "minimum": 1, "maximum": 48 }
+                }
+            }
+        }
+    })
+}
+
+fn asset(i: usize) -> Value {
+    json!({
+        "hostname": format!("web-{:05}", i),
+        "domain": "example.com",
+        "ip_address": format!("10.{}.{}.{}", (i >> 16) & 255, (i >> 8) & 255, i & 255),
+        "os": "linux",
+        "cpu_cores": 8,
+        "memory_gb": 32,
+        "tags": ["web", "production"],
+        "warranty_end": "2027-06-30",
+        "owner_email": "ops@example.com",
+        "rack": { "room": "A1", "position": (i % 48) + 1 }
+    })
+}
+
+fn report(label: &str, elapsed: Duration, assets: usize) -> f64 {
+    let per_asset = elapsed.as_secs_f64() * 1e6 / assets as f64;
+    println!("{:<32} {:>10.2} ms total {:>10.2} µs/asset", label, elapsed.as_secs_f64() * 1e3, per_asset);
+    per_asset
+}
+
+fn main() {
+    let assets: usize = std::env::var("SCHEMA_BENCH_ASSETS")
+        .ok()
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(5_000);
+
+    let schema = server_schema();
+    let attributes: Vec<Value> = (0..assets).map(asset).collect();
+
+    // Before: every asset write compiled the schema again
+    let start = Instant::now();
+    for attributes in &attributes {
+        let compiled = JSONSchema::compile(&schema).expect("schema compiles");
+        black_box(compiled.is_valid(attributes));
+    }
+    let uncached = report("compile per asset", start.elapsed(), assets);
+
+    // After: one compilation per CI type version, shared through the cache
+    let cache = SchemaCache::new();
+    let ci_type_id = Uuid::new_v4();
+    let updated_at = Utc::now();
+    let start = Instant::now();
+    for attributes in &attributes {
+        let compiled = cache.get_or_compile(ci_type_id, updated_at, &schema).expect("schema compiles");
+        black_box(compiled.is_valid(attributes));
+    }
+    let cached = report("cached compiled schema", start.elapsed(), assets);
+
+    println!("speedup: {:.1}x", uncached / cached);
+}
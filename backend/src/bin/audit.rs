@@ -0,0 +1,105 @@
+//! Audit log tooling for operators and auditors.
+//!
+//!     audit verify [--from-sequence N]
+//!     audit export --from <RFC 3339> --to <RFC 3339> [--out FILE]
+//!
+//! Reads the same POSTGRES_* and AUDIT_EXPORT_SIGNING_KEY variables as the
+//! server. `verify` exits with status 1 when the chain is broken.
+
+use chrono::{DateTime, Utc};
+use std::process::ExitCode;
+
+use crate_backend::{
+    config::{AuditConfig, PostgreSQLConfig},
+    database::{get_pg_pool, AuditRepository, RbacRepository},
+    middleware::AuditExportSigner,
+    models::AuditExportQuery,
+    services::AuditService,
+    error::{AppError, AppResult},
+};
+
+const USAGE: &str = "usage:
+    audit verify [--from-sequence N]
+    audit export --from <RFC 3339> --to <RFC 3339> [--out FILE]";
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    match run(&args).await {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::from(2)
+        }
+    }
+}
+
+async fn run(args: &[String]) -> AppResult<ExitCode> {
+    let (command, options) = match args.split_first() {
+        Some((command, options)) => (command.as_str(), options),
+        None => return Err(AppError::bad_request(USAGE)),
+    };
+
+    let pool = get_pg_pool(&PostgreSQLConfig::from_env()).await?;
+    let audit_service = AuditService::new(AuditRepository::new(pool.clone()), RbacRepository::new(pool));
+
+    match command {
+        "verify" => {
+            let from_sequence = option(options, "--from-sequence")?
+                .map(|value| value.parse::<i64>())
+                .transpose()
+                .map_err(|_| AppError::bad_request("--from-sequence must be a number"))?;
+
+            let report = audit_service.verify_chain(from_sequence).await?;
+            println!("{}", serde_json::to_string_pretty(&report)?);
+
+            Ok(if report.valid { ExitCode::SUCCESS } else { ExitCode::from(1) })
+        }
+        "export" => {
+            let query = AuditExportQuery {
+                from: required_time(options, "--from")?,
+                to: required_time(options, "--to")?,
+            };
+
+            let signing_key = AuditConfig::from_env()
+                .export_signing_key
+                .ok_or_else(|| AppError::configuration("AUDIT_EXPORT_SIGNING_KEY must be set to export"))?;
+            let signer = AuditExportSigner::from_config(&signing_key)?;
+
+            let export = audit_service.export(&query, &signer, None).await?;
+            let json = serde_json::to_string_pretty(&export)?;
+
+            match option(options, "--out")? {
+                Some(path) => {
+                    std::fs::write(path, json)?;
+                    eprintln!("Wrote {} audit entries to {}", export.manifest.entry_count, path);
+                }
+                None => println!("{}", json),
+            }
+
+            Ok(ExitCode::SUCCESS)
+        }
+        _ => Err(AppError::bad_request(USAGE)),
+    }
+}
+
+/// Value following `name`, if the flag was given
+fn option<'a>(options: &'a [String], name: &str) -> AppResult<Option<&'a str>> {
+    match options.iter().position(|option| option == name) {
+        Some(index) => options
+            .get(index + 1)
+            .map(|value| Some(value.as_str()))
+            .ok_or_else(|| AppError::bad_request(format!("{} needs a value", name))),
+        None => Ok(None),
+    }
+}
+
+fn required_time(options: &[String], name: &str) -> AppResult<DateTime<Utc>> {
+    let value = option(options, name)?
+        .ok_or_else(|| AppError::bad_request(format!("{} is required\n{}", name, USAGE)))?;
+
+    DateTime::parse_from_rfc3339(value)
+        .map(|time| time.with_timezone(&Utc))
+        .map_err(|_| AppError::bad_request(format!("{} must be an RFC 3339 timestamp", name)))
+}
@@ -1,5 +1,6 @@
 use serde::Deserialize;
 use std::env;
+use std::net::IpAddr;
 use super::database::{DatabaseConfig};
 
 #[derive(Debug, Clone, Deserialize)]
@@ -7,6 +8,10 @@ pub struct AppConfig {
     pub server: ServerConfig,
     pub database: DatabaseConfig,
     pub auth: AuthConfig,
+    pub oidc: Option<OidcConfig>, // None unless OIDC_ISSUER_URL is set
+    pub notifications: NotificationConfig,
+    pub audit: AuditConfig,
+    pub trash: TrashConfig,
     pub logging: LoggingConfig,
     pub cors: CorsConfig,
 }
@@ -15,13 +20,78 @@ pub struct AppConfig {
 pub struct ServerConfig {
     pub host: String,
     pub port: u16,
+    pub trusted_proxies: Vec<IpAddr>, // Peers whose X-Forwarded-For and X-Real-IP headers are believed
 }
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct AuthConfig {
     pub jwt_secret: String,
+    pub jwt_key_id: String,
+    pub jwt_previous_secrets: Vec<JwtSecretKeyConfig>,
+    pub jwt_public_keys: Vec<JwtPemKeyConfig>,
+    pub jwt_signing_key: Option<JwtPemKeyConfig>,
     pub jwt_expiration_hours: u64,
+    pub refresh_token_expiration_days: u64,
     pub password_min_length: u32,
+    pub default_role: Option<String>, // Role bound to newly registered users
+    pub password_reset_token_ttl_minutes: u64,
+    pub password_reset_url: Option<String>, // Frontend page the reset email links to
+}
+
+/// A retired HS256 secret that is still accepted for verification
+#[derive(Debug, Clone, Deserialize)]
+pub struct JwtSecretKeyConfig {
+    pub kid: String,
+    pub secret: String,
+}
+
+/// An asymmetric key (RS256 or EdDSA) loaded from a PEM file
+#[derive(Debug, Clone, Deserialize)]
+pub struct JwtPemKeyConfig {
+    pub kid: String,
+    pub algorithm: String,
+    pub path: String,
+}
+
+/// OpenID Connect single sign-on, offered alongside local passwords
+#[derive(Debug, Clone, Deserialize)]
+pub struct OidcConfig {
+    pub issuer_url: String,
+    pub client_id: String,
+    pub client_secret: Option<String>, // None for public clients relying on PKCE alone
+    pub redirect_uri: String,
+    pub scopes: Vec<String>,
+    pub auto_provision: bool,  // Create a user on first login
+    pub link_by_email: bool,   // Link to an existing user with the same verified email
+    pub role_claim: String,    // Dotted path into the ID token, e.g. "groups" or "realm_access.roles"
+    pub admin_values: Vec<String>, // Claim values granting is_admin; empty leaves is_admin untouched
+    pub role_mappings: Vec<OidcRoleMapping>,
+}
+
+/// Binds a CMDB role to users whose role claim contains `claim_value`
+#[derive(Debug, Clone, Deserialize)]
+pub struct OidcRoleMapping {
+    pub claim_value: String,
+    pub role: String,
+}
+
+/// Where outgoing notifications (e.g. password reset emails) are delivered
+#[derive(Debug, Clone, Deserialize)]
+pub struct NotificationConfig {
+    pub backend: String, // "log" or "file"
+    pub file_path: String,
+}
+
+/// Audit log evidence handed to external auditors
+#[derive(Debug, Clone, Deserialize)]
+pub struct AuditConfig {
+    pub export_signing_key: Option<JwtPemKeyConfig>, // RS256 or EdDSA; exports are unavailable without it
+}
+
+/// Soft-deleted assets and relationships
+#[derive(Debug, Clone, Deserialize)]
+pub struct TrashConfig {
+    pub retention_days: u64, // The nightly cleanup job purges what has been deleted this long
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -46,6 +116,18 @@ impl ServerConfig {
                 .ok()
                 .and_then(|p| p.parse().ok())
                 .unwrap_or(3000),
+            // Format: ip,ip
+            trusted_proxies: env::var("TRUSTED_PROXIES")
+                .ok()
+                .map(|proxies| {
+                    proxies
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|proxy| !proxy.is_empty())
+                        .map(|proxy| proxy.parse().expect("TRUSTED_PROXIES must be a comma-separated list of IP addresses"))
+                        .collect()
+                })
+                .unwrap_or_default(),
         }
     }
 }
@@ -55,14 +137,154 @@ impl AuthConfig {
         Self {
             jwt_secret: env::var("JWT_SECRET")
                 .expect("JWT_SECRET must be set"),
+            jwt_key_id: env::var("JWT_KEY_ID").unwrap_or_else(|_| "primary".to_string()),
+            // Format: kid:secret,kid:secret
+            jwt_previous_secrets: env::var("JWT_PREVIOUS_SECRETS")
+                .ok()
+                .map(|keys| {
+                    keys.split(',')
+                        .filter_map(|entry| entry.trim().split_once(':'))
+                        .map(|(kid, secret)| JwtSecretKeyConfig {
+                            kid: kid.to_string(),
+                            secret: secret.to_string(),
+                        })
+                        .collect()
+                })
+                .unwrap_or_default(),
+            // Format: kid:algorithm:path,kid:algorithm:path
+            jwt_public_keys: env::var("JWT_PUBLIC_KEYS")
+                .ok()
+                .map(|keys| keys.split(',').filter_map(JwtPemKeyConfig::parse).collect())
+                .unwrap_or_default(),
+            // Format: kid:algorithm:path
+            jwt_signing_key: env::var("JWT_SIGNING_KEY")
+                .ok()
+                .and_then(|key| JwtPemKeyConfig::parse(&key)),
             jwt_expiration_hours: env::var("JWT_EXPIRATION_HOURS")
                 .ok()
                 .and_then(|h| h.parse().ok())
                 .unwrap_or(24),
+            refresh_token_expiration_days: env::var("REFRESH_TOKEN_EXPIRATION_DAYS")
+                .ok()
+                .and_then(|d| d.parse().ok())
+                .unwrap_or(30),
             password_min_length: env::var("PASSWORD_MIN_LENGTH")
                 .ok()
                 .and_then(|l| l.parse().ok())
                 .unwrap_or(8),
+            // Empty disables the default binding
+            default_role: match env::var("RBAC_DEFAULT_ROLE") {
+                Ok(role) if role.trim().is_empty() => None,
+                Ok(role) => Some(role.trim().to_string()),
+                Err(_) => Some("viewer".to_string()),
+            },
+            password_reset_token_ttl_minutes: env::var("PASSWORD_RESET_TOKEN_TTL_MINUTES")
+                .ok()
+                .and_then(|m| m.parse().ok())
+                .unwrap_or(60),
+            password_reset_url: env::var("PASSWORD_RESET_URL").ok().filter(|url| !url.is_empty()),
+        }
+    }
+}
+
+impl JwtPemKeyConfig {
+    fn parse(entry: &str) -> Option<Self> {
+        let mut parts = entry.trim().splitn(3, ':');
+        let kid = parts.next().filter(|kid| !kid.is_empty())?;
+        let algorithm = parts.next()?;
+        let path = parts.next()?;
+
+        Some(Self {
+            kid: kid.to_string(),
+            algorithm: algorithm.to_string(),
+            path: path.to_string(),
+        })
+    }
+}
+
+impl OidcConfig {
+    pub fn from_env() -> Option<Self> {
+        let issuer_url = env::var("OIDC_ISSUER_URL").ok().filter(|url| !url.trim().is_empty())?;
+
+        Some(Self {
+            issuer_url: issuer_url.trim().to_string(),
+            client_id: env::var("OIDC_CLIENT_ID")
+                .expect("OIDC_CLIENT_ID must be set when OIDC_ISSUER_URL is set"),
+            client_secret: env::var("OIDC_CLIENT_SECRET").ok().filter(|secret| !secret.is_empty()),
+            redirect_uri: env::var("OIDC_REDIRECT_URI")
+                .expect("OIDC_REDIRECT_URI must be set when OIDC_ISSUER_URL is set"),
+            scopes: env::var("OIDC_SCOPES")
+                .ok()
+                .map(|scopes| scopes.split_whitespace().map(|s| s.to_string()).collect())
+                .unwrap_or_else(|| vec!["openid".to_string(), "email".to_string(), "profile".to_string()]),
+            auto_provision: env::var("OIDC_AUTO_PROVISION")
+                .ok()
+                .and_then(|p| p.parse().ok())
+                .unwrap_or(true),
+            // Linking trusts the provider's email verification, so it is opt-in
+            link_by_email: env::var("OIDC_LINK_BY_EMAIL")
+                .ok()
+                .and_then(|l| l.parse().ok())
+                .unwrap_or(false),
+            role_claim: env::var("OIDC_ROLE_CLAIM").unwrap_or_else(|_| "groups".to_string()),
+            admin_values: env::var("OIDC_ADMIN_VALUES")
+                .ok()
+                .map(|values| {
+                    values.split(',')
+                        .map(|value| value.trim().to_string())
+                        .filter(|value| !value.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            // Format: claim_value:role,claim_value:role (the last ':' separates the role)
+            role_mappings: env::var("OIDC_ROLE_MAPPINGS")
+                .ok()
+                .map(|mappings| {
+                    mappings.split(',')
+                        .filter_map(|entry| entry.trim().rsplit_once(':'))
+                        .map(|(claim_value, role)| OidcRoleMapping {
+                            claim_value: claim_value.to_string(),
+                            role: role.to_string(),
+                        })
+                        .collect()
+                })
+                .unwrap_or_default(),
+        })
+    }
+}
+
+impl NotificationConfig {
+    pub fn from_env() -> Self {
+        Self {
+            backend: env::var("NOTIFIER").unwrap_or_else(|_| "log".to_string()),
+            file_path: env::var("NOTIFIER_FILE_PATH").unwrap_or_else(|_| "notifications.log".to_string()),
+        }
+    }
+}
+
+impl AuditConfig {
+    pub fn from_env() -> Self {
+        Self {
+            // Format: kid:algorithm:path
+            export_signing_key: env::var("AUDIT_EXPORT_SIGNING_KEY")
+                .ok()
+                .and_then(|key| JwtPemKeyConfig::parse(&key)),
+        }
+    }
+}
+
+impl TrashConfig {
+    pub fn from_env() -> Self {
+        Self {
+            retention_days: env::var("TRASH_RETENTION_DAYS")
+                .ok()
+                .map(|d| {
+                    d.parse()
+                        .ok()
+                        .filter(|days| *days > 0)
+                        .expect("TRASH_RETENTION_DAYS must be a positive number of days")
+                })
+                .unwrap_or(30),
         }
     }
 }
@@ -95,6 +317,7 @@ impl CorsConfig {
                     "content-type".to_string(),
                     "accept".to_string(),
                     "x-requested-with".to_string(),
+                    "x-api-key".to_string(),
                 ]),
             allow_credentials: env::var("CORS_ALLOW_CREDENTIALS")
                 .ok()
@@ -110,6 +333,10 @@ impl AppConfig {
             server: ServerConfig::from_env(),
             database: DatabaseConfig::from_env(),
             auth: AuthConfig::from_env(),
+            oidc: OidcConfig::from_env(),
+            notifications: NotificationConfig::from_env(),
+            audit: AuditConfig::from_env(),
+            trash: TrashConfig::from_env(),
             logging: LoggingConfig::from_env(),
             cors: CorsConfig::from_env(),
         }
@@ -1,5 +1,5 @@
 pub mod app;
 pub mod database;
 
-pub use app::{AppConfig, CorsConfig};
+pub use app::{AppConfig, AuthConfig, CorsConfig, JwtSecretKeyConfig, JwtPemKeyConfig, OidcConfig, OidcRoleMapping, NotificationConfig, AuditConfig, TrashConfig};
 pub use database::{DatabaseConfig, PostgreSQLConfig, Neo4jConfig};
\ No newline at end of file
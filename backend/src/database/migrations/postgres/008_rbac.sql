@@ -0,0 +1,64 @@
+-- Roles group a set of permissions
+CREATE TABLE roles (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    name VARCHAR(100) UNIQUE NOT NULL,
+    description TEXT,
+    is_system BOOLEAN DEFAULT false, -- Seeded roles that can't be deleted
+    created_by UUID REFERENCES users(id),
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+-- Per-resource grants held by a role
+CREATE TABLE role_permissions (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    role_id UUID NOT NULL REFERENCES roles(id) ON DELETE CASCADE,
+    resource_type VARCHAR(50) NOT NULL,
+    action VARCHAR(20) NOT NULL,
+    -- CI type (for ci_type/ci_asset), relationship type (for relationship_type/relationship)
+    -- or lifecycle type (for lifecycle); NULL grants the action on every instance
+    scope_id UUID NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+
+    CHECK (resource_type IN ('ci_type', 'ci_asset', 'relationship_type', 'relationship', 'lifecycle')),
+    CHECK (action IN ('read', 'create', 'update', 'delete', 'manage'))
+);
+
+CREATE UNIQUE INDEX idx_role_permissions_unique ON role_permissions(
+    role_id, resource_type, action, COALESCE(scope_id, '00000000-0000-0000-0000-000000000000'::uuid)
+);
+CREATE INDEX idx_role_permissions_role_id ON role_permissions(role_id);
+
+-- Users holding a role
+CREATE TABLE role_bindings (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    role_id UUID NOT NULL REFERENCES roles(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    created_by UUID REFERENCES users(id),
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+
+    UNIQUE(role_id, user_id)
+);
+
+CREATE INDEX idx_role_bindings_user_id ON role_bindings(user_id);
+
+CREATE TRIGGER update_roles_updated_at BEFORE UPDATE ON roles
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+-- Seed system roles
+INSERT INTO roles (name, description, is_system) VALUES
+    ('viewer', 'Read access to all CMDB data', true),
+    ('editor', 'Full access to all CMDB data', true);
+
+INSERT INTO role_permissions (role_id, resource_type, action)
+SELECT r.id, t.resource_type, CASE WHEN r.name = 'viewer' THEN 'read' ELSE 'manage' END
+FROM roles r
+CROSS JOIN (VALUES ('ci_type'), ('ci_asset'), ('relationship_type'), ('relationship'), ('lifecycle')) AS t(resource_type)
+WHERE r.name IN ('viewer', 'editor');
+
+-- Existing users keep read access; write access now has to be granted explicitly
+INSERT INTO role_bindings (role_id, user_id)
+SELECT r.id, u.id
+FROM roles r
+CROSS JOIN users u
+WHERE r.name = 'viewer' AND u.is_admin = false;
@@ -0,0 +1,45 @@
+-- Service accounts are principals in the users table so that created_by,
+-- performed_by and role bindings work for them unchanged. They can't log in
+-- with a password and authenticate with API keys instead.
+ALTER TABLE users ADD COLUMN is_service_account BOOLEAN DEFAULT false;
+
+CREATE TABLE service_accounts (
+    id UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    name VARCHAR(100) UNIQUE NOT NULL,
+    description TEXT,
+    created_by UUID NOT NULL REFERENCES users(id),
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+-- API keys (only a SHA-256 hash of the key is stored)
+CREATE TABLE api_keys (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    service_account_id UUID NOT NULL REFERENCES service_accounts(id) ON DELETE CASCADE,
+    name VARCHAR(100) NOT NULL,
+    key_prefix VARCHAR(16) NOT NULL, -- Shown in listings so keys can be told apart
+    key_hash VARCHAR(64) UNIQUE NOT NULL,
+    expires_at TIMESTAMP WITH TIME ZONE NULL,
+    last_used_at TIMESTAMP WITH TIME ZONE NULL,
+    revoked_at TIMESTAMP WITH TIME ZONE NULL,
+    created_by UUID NOT NULL REFERENCES users(id),
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE INDEX idx_api_keys_service_account_id ON api_keys(service_account_id);
+
+-- Grants held by an API key; same shape as role_permissions
+CREATE TABLE api_key_permissions (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    api_key_id UUID NOT NULL REFERENCES api_keys(id) ON DELETE CASCADE,
+    resource_type VARCHAR(50) NOT NULL,
+    action VARCHAR(20) NOT NULL,
+    scope_id UUID NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+
+    CHECK (resource_type IN ('ci_type', 'ci_asset', 'relationship_type', 'relationship', 'lifecycle')),
+    CHECK (action IN ('read', 'create', 'update', 'delete', 'manage'))
+);
+
+CREATE UNIQUE INDEX idx_api_key_permissions_unique ON api_key_permissions(
+    api_key_id, resource_type, action, COALESCE(scope_id, '00000000-0000-0000-0000-000000000000'::uuid)
+);
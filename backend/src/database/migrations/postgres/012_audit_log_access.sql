@@ -0,0 +1,22 @@
+-- Audit log reads are granted through RBAC like the rest of the CMDB data
+ALTER TABLE role_permissions DROP CONSTRAINT role_permissions_resource_type_check;
+ALTER TABLE role_permissions ADD CONSTRAINT role_permissions_resource_type_check
+    CHECK (resource_type IN ('ci_type', 'ci_asset', 'relationship_type', 'relationship', 'lifecycle', 'audit_log'));
+
+ALTER TABLE api_key_permissions DROP CONSTRAINT api_key_permissions_resource_type_check;
+ALTER TABLE api_key_permissions ADD CONSTRAINT api_key_permissions_resource_type_check
+    CHECK (resource_type IN ('ci_type', 'ci_asset', 'relationship_type', 'relationship', 'lifecycle', 'audit_log'));
+
+-- Keyset pagination walks the log newest first
+CREATE INDEX idx_audit_log_created_at_id ON audit_log(created_at DESC, id DESC);
+CREATE INDEX idx_audit_log_entity_created_at ON audit_log(entity_type, entity_id, created_at DESC);
+
+-- Auditors can read everything, including the audit log, but change nothing
+INSERT INTO roles (name, description, is_system) VALUES
+    ('auditor', 'Read access to all CMDB data and the audit log', true);
+
+INSERT INTO role_permissions (role_id, resource_type, action)
+SELECT r.id, t.resource_type, 'read'
+FROM roles r
+CROSS JOIN (VALUES ('ci_type'), ('ci_asset'), ('relationship_type'), ('relationship'), ('lifecycle'), ('audit_log')) AS t(resource_type)
+WHERE r.name = 'auditor';
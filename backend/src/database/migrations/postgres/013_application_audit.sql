@@ -0,0 +1,41 @@
+-- CI types, assets, relationship types and relationships are audited by the
+-- application, which knows the acting user, client IP and user agent. Only the
+-- lifecycle tables keep their triggers.
+
+-- lifecycle_states has no created_by column, so the old trigger failed on every
+-- write, and its fallback attributed changes to an arbitrary user. Attribute
+-- state changes to whoever owns the lifecycle type instead.
+CREATE OR REPLACE FUNCTION lifecycle_states_audit_trigger() RETURNS TRIGGER AS $$
+DECLARE
+    type_id UUID;
+    actor UUID;
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        type_id := OLD.lifecycle_type_id;
+    ELSE
+        type_id := NEW.lifecycle_type_id;
+    END IF;
+
+    SELECT created_by INTO actor FROM lifecycle_types WHERE id = type_id;
+
+    -- Cascading deletes from lifecycle_types leave no owner to attribute to
+    IF actor IS NULL THEN
+        RETURN NULL;
+    END IF;
+
+    IF TG_OP = 'INSERT' THEN
+        INSERT INTO audit_log (entity_type, entity_id, action, new_values, performed_by)
+        VALUES ('lifecycle_states', NEW.id, 'INSERT', row_to_json(NEW), actor);
+        RETURN NEW;
+    ELSIF TG_OP = 'UPDATE' THEN
+        INSERT INTO audit_log (entity_type, entity_id, action, old_values, new_values, performed_by)
+        VALUES ('lifecycle_states', NEW.id, 'UPDATE', row_to_json(OLD), row_to_json(NEW), actor);
+        RETURN NEW;
+    ELSIF TG_OP = 'DELETE' THEN
+        INSERT INTO audit_log (entity_type, entity_id, action, old_values, performed_by)
+        VALUES ('lifecycle_states', OLD.id, 'DELETE', row_to_json(OLD), actor);
+        RETURN OLD;
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,99 @@
+-- Hash-chain the audit log so edited or deleted entries can be detected.
+--
+-- Every entry stores the hash of its predecessor and
+--     entry_hash = sha256(prev_hash || audit_log_canonical(entry))
+-- in hex. chain_seq numbers entries without gaps in chain order, which is
+-- commit order rather than created_at order.
+
+ALTER TABLE audit_log
+    ADD COLUMN chain_seq BIGINT,
+    ADD COLUMN prev_hash TEXT,
+    ADD COLUMN entry_hash TEXT;
+
+-- The exact text that is hashed. A JSON array keeps field boundaries
+-- unambiguous, and exports include it so hashes can be recomputed offline.
+CREATE FUNCTION audit_log_canonical(entry audit_log) RETURNS TEXT AS $$
+    SELECT jsonb_build_array(
+        entry.chain_seq,
+        entry.id,
+        entry.entity_type,
+        entry.entity_id,
+        entry.action,
+        entry.old_values,
+        entry.new_values,
+        entry.performed_by,
+        host(entry.ip_address),
+        entry.user_agent,
+        to_char(entry.created_at AT TIME ZONE 'UTC', 'YYYY-MM-DD"T"HH24:MI:SS.US"Z"')
+    )::text
+$$ LANGUAGE sql STABLE;
+
+CREATE FUNCTION audit_log_entry_hash(prev_hash TEXT, entry audit_log) RETURNS TEXT AS $$
+    SELECT encode(sha256(convert_to(prev_hash || audit_log_canonical(entry), 'UTF8')), 'hex')
+$$ LANGUAGE sql STABLE;
+
+-- Chain the existing entries in the order they were written
+DO $$
+DECLARE
+    entry audit_log;
+    last_hash TEXT := repeat('0', 64);
+    seq BIGINT := 0;
+BEGIN
+    FOR entry IN SELECT * FROM audit_log ORDER BY created_at NULLS FIRST, id LOOP
+        seq := seq + 1;
+        entry.chain_seq := seq;
+        entry.prev_hash := last_hash;
+        entry.entry_hash := audit_log_entry_hash(last_hash, entry);
+
+        UPDATE audit_log
+        SET chain_seq = entry.chain_seq, prev_hash = entry.prev_hash, entry_hash = entry.entry_hash
+        WHERE id = entry.id;
+
+        last_hash := entry.entry_hash;
+    END LOOP;
+END;
+$$;
+
+ALTER TABLE audit_log
+    ALTER COLUMN chain_seq SET NOT NULL,
+    ALTER COLUMN prev_hash SET NOT NULL,
+    ALTER COLUMN entry_hash SET NOT NULL;
+
+CREATE UNIQUE INDEX idx_audit_log_chain_seq ON audit_log(chain_seq);
+
+-- Link each new entry to the current head. The advisory lock is held until
+-- commit, so concurrent writers queue up and each sees its predecessor.
+CREATE FUNCTION audit_log_chain_trigger() RETURNS TRIGGER AS $$
+DECLARE
+    last_seq BIGINT;
+    last_hash TEXT;
+BEGIN
+    PERFORM pg_advisory_xact_lock(hashtext('audit_log_chain'));
+
+    SELECT chain_seq, entry_hash INTO last_seq, last_hash
+    FROM audit_log
+    ORDER BY chain_seq DESC
+    LIMIT 1;
+
+    NEW.created_at := COALESCE(NEW.created_at, NOW());
+    NEW.chain_seq := COALESCE(last_seq, 0) + 1;
+    NEW.prev_hash := COALESCE(last_hash, repeat('0', 64));
+    NEW.entry_hash := audit_log_entry_hash(NEW.prev_hash, NEW);
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER audit_log_chain
+    BEFORE INSERT ON audit_log
+    FOR EACH ROW EXECUTE FUNCTION audit_log_chain_trigger();
+
+-- The chain detects tampering; this stops it happening by accident
+CREATE FUNCTION audit_log_immutable_trigger() RETURNS TRIGGER AS $$
+BEGIN
+    RAISE EXCEPTION 'audit_log entries cannot be modified or deleted';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER audit_log_immutable
+    BEFORE UPDATE OR DELETE ON audit_log
+    FOR EACH ROW EXECUTE FUNCTION audit_log_immutable_trigger();
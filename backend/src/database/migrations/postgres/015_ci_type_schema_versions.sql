@@ -0,0 +1,30 @@
+-- Every schema a CI type has had, so assets can say which one they conform to.
+--
+-- ci_types.schema_version is the current version (NULL while the type has no
+-- schema). ci_assets.schema_version is the version the asset's attributes were
+-- last validated against; NULL for assets written before versions existed.
+
+CREATE TABLE ci_type_schema_versions (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    ci_type_id UUID NOT NULL REFERENCES ci_types(id) ON DELETE CASCADE,
+    version INTEGER NOT NULL,
+    schema JSONB NOT NULL,
+    migration JSONB NULL, -- How existing assets were rewritten to fit this version
+    created_by UUID NOT NULL REFERENCES users(id),
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+
+    CONSTRAINT unique_ci_type_schema_version UNIQUE (ci_type_id, version)
+);
+
+ALTER TABLE ci_types ADD COLUMN schema_version INTEGER;
+ALTER TABLE ci_assets ADD COLUMN schema_version INTEGER;
+
+CREATE INDEX idx_ci_assets_schema_version ON ci_assets(ci_type_id, schema_version);
+
+-- Existing schemas become version 1
+INSERT INTO ci_type_schema_versions (ci_type_id, version, schema, created_by, created_at)
+SELECT id, 1, attributes->'schema', created_by, updated_at
+FROM ci_types
+WHERE attributes ? 'schema';
+
+UPDATE ci_types SET schema_version = 1 WHERE attributes ? 'schema';
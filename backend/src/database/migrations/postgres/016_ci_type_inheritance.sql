@@ -0,0 +1,27 @@
+-- CI types can extend a parent type and inherit its attribute schema.
+-- Abstract types only exist to be extended and cannot have assets.
+
+ALTER TABLE ci_types
+    ADD COLUMN parent_id UUID REFERENCES ci_types(id),
+    ADD COLUMN is_abstract BOOLEAN NOT NULL DEFAULT false,
+    ADD CONSTRAINT ci_types_not_own_parent CHECK (parent_id <> id);
+
+CREATE INDEX idx_ci_types_parent_id ON ci_types(parent_id);
+
+-- A type and all of its live descendants. UNION rather than UNION ALL, so a
+-- cycle that slipped past the application ends the walk instead of looping.
+CREATE FUNCTION ci_type_subtree(root UUID) RETURNS SETOF UUID AS $$
+    WITH RECURSIVE subtree(id) AS (
+        SELECT root
+        UNION
+        SELECT t.id
+        FROM ci_types t
+        JOIN subtree s ON t.parent_id = s.id
+        WHERE t.deleted_at IS NULL
+    )
+    SELECT id FROM subtree
+$$ LANGUAGE sql STABLE;
+
+-- Whether relationship type constraints also accept subtypes of the
+-- constrained CI types
+ALTER TABLE relationship_types ADD COLUMN include_subtypes BOOLEAN NOT NULL DEFAULT true;
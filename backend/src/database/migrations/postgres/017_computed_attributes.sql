@@ -0,0 +1,8 @@
+-- Values of the derived attributes a CI type declares under
+-- attributes.computed. Kept apart from the client-written attributes so they
+-- can never be written directly, and stored so they can be searched and
+-- filtered like any other attribute.
+
+ALTER TABLE ci_assets ADD COLUMN computed_attributes JSONB NOT NULL DEFAULT '{}';
+
+CREATE INDEX idx_ci_assets_computed_attributes ON ci_assets USING GIN (computed_attributes);
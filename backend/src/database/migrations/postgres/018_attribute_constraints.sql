@@ -0,0 +1,15 @@
+-- Values held by assets for attributes their CI type's schema marks unique.
+-- The unique index is what enforces the constraint, so two concurrent writes
+-- cannot both claim a value. scope is the CI type the value is unique within,
+-- or NULL for values unique across all assets.
+
+CREATE TABLE ci_asset_unique_values (
+    asset_id UUID NOT NULL REFERENCES ci_assets(id) ON DELETE CASCADE,
+    attribute TEXT NOT NULL,
+    scope UUID REFERENCES ci_types(id),
+    value JSONB NOT NULL,
+    PRIMARY KEY (asset_id, attribute)
+);
+
+CREATE UNIQUE INDEX idx_ci_asset_unique_values_claim ON ci_asset_unique_values
+    (attribute, COALESCE(scope, '00000000-0000-0000-0000-000000000000'::uuid), value);
@@ -0,0 +1,5 @@
+-- Attribute filters compile equality and CONTAINS to `attributes @> ...`,
+-- which this index serves. jsonb_path_ops is smaller and faster than the
+-- default operator class and supports exactly that operator.
+
+CREATE INDEX idx_ci_assets_attributes ON ci_assets USING GIN (attributes jsonb_path_ops);
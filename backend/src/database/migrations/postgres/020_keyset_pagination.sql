@@ -0,0 +1,5 @@
+-- Lists page by keyset on (created_at, id), newest first, after any sort keys
+CREATE INDEX idx_ci_assets_created_at_id ON ci_assets(created_at DESC, id DESC) WHERE deleted_at IS NULL;
+CREATE INDEX idx_ci_assets_type_created_at_id ON ci_assets(ci_type_id, created_at DESC, id DESC) WHERE deleted_at IS NULL;
+CREATE INDEX idx_ci_types_created_at_id ON ci_types(created_at DESC, id DESC) WHERE deleted_at IS NULL;
+CREATE INDEX idx_relationships_created_at_id ON relationships(created_at DESC, id DESC) WHERE deleted_at IS NULL;
@@ -0,0 +1,16 @@
+-- Outcomes of requests sent with an Idempotency-Key header, replayed when the
+-- same caller retries with the same key. Written in the transaction of the
+-- change itself, so a change and its record commit together. The cleanup job
+-- purges expired ones.
+CREATE TABLE idempotency_keys (
+    user_id UUID NOT NULL, -- The caller: a user or a service account
+    key VARCHAR(255) NOT NULL,
+    endpoint VARCHAR(255) NOT NULL, -- e.g. "PUT /ci-assets/upsert"
+    request_hash VARCHAR(64) NOT NULL, -- SHA-256 hex digest of the request body
+    response JSONB NOT NULL,
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    PRIMARY KEY (user_id, key)
+);
+
+CREATE INDEX idx_idempotency_keys_expires_at ON idempotency_keys(expires_at);
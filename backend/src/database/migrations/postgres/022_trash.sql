@@ -0,0 +1,24 @@
+-- Soft-deleted assets and relationships stay in the trash, where they can be
+-- listed and restored, until the cleanup job purges them after the retention
+-- period (TRASH_RETENTION_DAYS).
+
+-- Set on relationships that went to the trash because one of their assets was
+-- deleted, so that restoring the asset brings them back. It is always one of
+-- the relationship's own assets, so purging that asset removes the row anyway.
+ALTER TABLE relationships
+    ADD COLUMN deleted_with_asset_id UUID REFERENCES ci_assets(id) ON DELETE CASCADE;
+
+CREATE INDEX idx_relationships_deleted_with_asset ON relationships(deleted_with_asset_id)
+    WHERE deleted_with_asset_id IS NOT NULL;
+
+-- Relationships of assets deleted before deletes cascaded
+UPDATE relationships r
+SET deleted_at = a.deleted_at, deleted_by = a.deleted_by, deleted_with_asset_id = a.id
+FROM ci_assets a
+WHERE r.deleted_at IS NULL
+  AND a.deleted_at IS NOT NULL
+  AND a.id IN (r.from_ci_asset_id, r.to_ci_asset_id);
+
+-- The trash pages by keyset on (deleted_at, id), most recently deleted first
+CREATE INDEX idx_ci_assets_deleted_at_id ON ci_assets(deleted_at DESC, id DESC) WHERE deleted_at IS NOT NULL;
+CREATE INDEX idx_relationships_deleted_at_id ON relationships(deleted_at DESC, id DESC) WHERE deleted_at IS NOT NULL;
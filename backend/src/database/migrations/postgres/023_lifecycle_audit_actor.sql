@@ -0,0 +1,54 @@
+-- The lifecycle audit triggers attributed every change to the lifecycle type's
+-- creator. The application now names the acting user for each transaction
+-- that writes lifecycle types or states (SET LOCAL app.user_id), and the
+-- triggers record that user. Writes that don't name one are rejected rather
+-- than attributed to someone who didn't make them.
+
+CREATE OR REPLACE FUNCTION audit_actor() RETURNS UUID AS $$
+DECLARE
+    actor UUID := NULLIF(current_setting('app.user_id', true), '')::UUID;
+BEGIN
+    IF actor IS NULL THEN
+        RAISE EXCEPTION 'app.user_id is not set; audited writes must name the acting user with SET LOCAL app.user_id';
+    END IF;
+    RETURN actor;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION lifecycle_types_audit_trigger() RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP = 'INSERT' THEN
+        INSERT INTO audit_log (entity_type, entity_id, action, new_values, performed_by)
+        VALUES ('lifecycle_types', NEW.id, 'INSERT', row_to_json(NEW), audit_actor());
+        RETURN NEW;
+    ELSIF TG_OP = 'UPDATE' THEN
+        INSERT INTO audit_log (entity_type, entity_id, action, old_values, new_values, performed_by)
+        VALUES ('lifecycle_types', NEW.id, 'UPDATE', row_to_json(OLD), row_to_json(NEW), audit_actor());
+        RETURN NEW;
+    ELSIF TG_OP = 'DELETE' THEN
+        INSERT INTO audit_log (entity_type, entity_id, action, old_values, performed_by)
+        VALUES ('lifecycle_types', OLD.id, 'DELETE', row_to_json(OLD), audit_actor());
+        RETURN OLD;
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION lifecycle_states_audit_trigger() RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP = 'INSERT' THEN
+        INSERT INTO audit_log (entity_type, entity_id, action, new_values, performed_by)
+        VALUES ('lifecycle_states', NEW.id, 'INSERT', row_to_json(NEW), audit_actor());
+        RETURN NEW;
+    ELSIF TG_OP = 'UPDATE' THEN
+        INSERT INTO audit_log (entity_type, entity_id, action, old_values, new_values, performed_by)
+        VALUES ('lifecycle_states', NEW.id, 'UPDATE', row_to_json(OLD), row_to_json(NEW), audit_actor());
+        RETURN NEW;
+    ELSIF TG_OP = 'DELETE' THEN
+        INSERT INTO audit_log (entity_type, entity_id, action, old_values, performed_by)
+        VALUES ('lifecycle_states', OLD.id, 'DELETE', row_to_json(OLD), audit_actor());
+        RETURN OLD;
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,7 @@
+-- The user background jobs act as, so that what they change (e.g. purging the
+-- trash) is attributed in the audit log. Like a service account it has no
+-- usable password and is left out of user listings and email lookups; unlike
+-- one it has no API keys, and it is inactive, so nothing can authenticate as it.
+INSERT INTO users (id, email, password_hash, first_name, last_name, is_active, is_admin, is_service_account)
+VALUES ('00000000-0000-0000-0000-000000000000', 'system@localhost', '!', 'System', '', false, false, true)
+ON CONFLICT (id) DO NOTHING;
@@ -2,6 +2,8 @@ pub mod postgres;
 pub mod neo4j;
 pub mod repositories;
 
-pub use postgres::{PgPool, get_pg_pool, run_migrations};
+pub use postgres::{PgPool, get_pg_pool, run_migrations, is_unique_violation, set_audit_actor};
+#[cfg(test)]
+pub use postgres::test_pool;
 pub use neo4j::{Neo4jPool, get_neo4j_pool, run_initializations};
 pub use repositories::*;
\ No newline at end of file
@@ -1,8 +1,9 @@
 use crate::config::PostgreSQLConfig;
 use sqlx::postgres::{PgPoolOptions, PgConnectOptions};
-use sqlx::{Pool, Postgres};
+use sqlx::{PgConnection, Pool, Postgres};
 use std::str::FromStr;
 use anyhow::Result;
+use uuid::Uuid;
 
 pub type PgPool = Pool<Postgres>;
 
@@ -33,4 +34,38 @@ pub async fn run_migrations(pool: &PgPool) -> Result<()> {
         .run(pool)
         .await?;
     Ok(())
-}
\ No newline at end of file
+}
+
+/// A migrated pool on `TEST_DATABASE_URL` for tests that need PostgreSQL.
+/// None when the variable isn't set; such tests then pass without running.
+#[cfg(test)]
+pub async fn test_pool() -> Option<PgPool> {
+    let url = std::env::var("TEST_DATABASE_URL").ok()?;
+    let pool = PgPoolOptions::new()
+        .max_connections(5)
+        .connect(&url)
+        .await
+        .expect("TEST_DATABASE_URL is set but not reachable");
+    run_migrations(&pool).await.expect("Failed to migrate the test database");
+    Some(pool)
+}
+
+/// Whether a repository error is a Postgres unique constraint violation
+pub fn is_unique_violation(error: &anyhow::Error) -> bool {
+    error
+        .downcast_ref::<sqlx::Error>()
+        .and_then(|e| e.as_database_error())
+        .map(|e| e.is_unique_violation())
+        .unwrap_or(false)
+}
+
+/// Make `user_id` the actor the audit triggers record for the rest of the
+/// transaction. This is `SET LOCAL app.user_id`, which can't take a bind
+/// parameter.
+pub async fn set_audit_actor(conn: &mut PgConnection, user_id: Uuid) -> sqlx::Result<()> {
+    sqlx::query("SELECT set_config('app.user_id', $1, true)")
+        .bind(user_id.to_string())
+        .execute(conn)
+        .await?;
+    Ok(())
+}
@@ -1,11 +1,24 @@
 use crate::database::PgPool;
+use crate::models::{AuditLog, AuditLogEntry, AuditLogFilter, AuditedEntity, AuditChainEntry};
+use crate::utils::KeysetCursor;
 use anyhow::Result;
+use chrono::{DateTime, Utc};
 use serde_json::Value;
-use sqlx::{postgres::PgRow, Row};
+use sqlx::{postgres::PgRow, PgConnection, Row};
 use std::net::IpAddr;
 use uuid::Uuid;
 
-#[derive(Debug)]
+/// The `AuditLogFilter` conditions, bound as $1 to $6
+const AUDIT_LOG_CONDITIONS: &str = r#"
+    ($1::text IS NULL OR a.entity_type = $1)
+    AND ($2::uuid IS NULL OR a.entity_id = $2)
+    AND ($3::text IS NULL OR LOWER(a.action) = LOWER($3))
+    AND ($4::uuid IS NULL OR a.performed_by = $4)
+    AND ($5::timestamptz IS NULL OR a.created_at >= $5)
+    AND ($6::timestamptz IS NULL OR a.created_at <= $6)
+"#;
+
+#[derive(Debug, Clone)]
 pub struct AuditRepository {
     pool: PgPool,
 }
@@ -15,8 +28,11 @@ impl AuditRepository {
         Self { pool }
     }
 
+    /// Insert an audit row on the caller's connection, normally the transaction
+    /// that made the change, so both commit or roll back together
     pub async fn create_audit_log(
         &self,
+        conn: &mut PgConnection,
         entity_type: &str,
         entity_id: Uuid,
         action: &str,
@@ -50,71 +66,207 @@ impl AuditRepository {
         .bind(performed_by)
         .bind(ip_addr as Option<IpAddr>)
         .bind(user_agent)
-        .execute(&self.pool)
+        .execute(&mut *conn)
         .await?;
 
         Ok(id)
     }
 
+    /// The row as JSON, the same shape the lifecycle triggers store. Locks the
+    /// row so the snapshot stays accurate until the transaction ends.
+    pub async fn snapshot(
+        &self,
+        conn: &mut PgConnection,
+        entity: AuditedEntity,
+        entity_id: Uuid,
+    ) -> Result<Option<Value>> {
+        // The table name comes from a fixed list, never from input
+        let query = format!(
+            "SELECT to_jsonb(t) AS snapshot FROM {} t WHERE t.id = $1 FOR UPDATE",
+            entity.table()
+        );
+
+        let snapshot = sqlx::query_scalar(&query)
+            .bind(entity_id)
+            .fetch_optional(&mut *conn)
+            .await?;
+
+        Ok(snapshot)
+    }
+
+    /// One page of audit entries matching the filter, newest first, starting
+    /// after `cursor`. The diff is left for the caller to compute.
     pub async fn get_audit_logs(
         &self,
-        entity_type: Option<&str>,
-        entity_id: Option<Uuid>,
-        performed_by: Option<Uuid>,
-        from_date: Option<chrono::DateTime<chrono::Utc>>,
-        to_date: Option<chrono::DateTime<chrono::Utc>>,
+        filter: &AuditLogFilter,
+        cursor: Option<&KeysetCursor>,
         limit: i64,
-        offset: i64,
-    ) -> Result<Vec<(
-        Uuid,
-        String,
-        Uuid,
-        String,
-        Option<Value>,
-        Option<Value>,
-        Uuid,
-        Option<IpAddr>,
-        Option<String>,
-        chrono::DateTime<chrono::Utc>
-    )>> {
-        // Simplified query - for production, you'd want proper parameterized queries
-        let query = r#"
+    ) -> Result<Vec<AuditLogEntry>> {
+        let rows = sqlx::query(&format!(
+            r#"
             SELECT
-                id, entity_type, entity_id, action, old_values, new_values,
-                performed_by, ip_address, user_agent, created_at
-            FROM audit_log
-            WHERE ($1::text IS NULL OR entity_type = $1)
-            AND ($2::uuid IS NULL OR entity_id = $2)
-            AND ($3::uuid IS NULL OR performed_by = $3)
-            AND ($4::timestamp IS NULL OR created_at >= $4)
-            AND ($5::timestamp IS NULL OR created_at <= $5)
-            ORDER BY created_at DESC LIMIT $6 OFFSET $7
-        "#;
-
-        let rows = sqlx::query(query)
+                a.id, a.entity_type, a.entity_id, a.action, a.old_values, a.new_values,
+                a.performed_by, a.ip_address, a.user_agent, a.created_at,
+                a.chain_seq, a.prev_hash, a.entry_hash,
+                u.first_name, u.last_name, u.email
+            FROM audit_log a
+            LEFT JOIN users u ON a.performed_by = u.id
+            WHERE {}
+              AND ($7::timestamptz IS NULL OR (a.created_at, a.id) < ($7, $8))
+            ORDER BY a.created_at DESC, a.id DESC
+            LIMIT $9
+            "#,
+            AUDIT_LOG_CONDITIONS
+        ))
+        .bind(&filter.entity_type)
+        .bind(filter.entity_id)
+        .bind(&filter.action)
+        .bind(filter.performed_by)
+        .bind(filter.created_after)
+        .bind(filter.created_before)
+        .bind(cursor.map(|c| c.created_at))
+        .bind(cursor.map(|c| c.id))
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(map_audit_log_entry).collect())
+    }
+
+    /// Number of audit entries matching the filter
+    pub async fn count_audit_logs(&self, filter: &AuditLogFilter) -> Result<i64> {
+        let count: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM audit_log a WHERE {}", AUDIT_LOG_CONDITIONS))
+            .bind(&filter.entity_type)
+            .bind(filter.entity_id)
+            .bind(&filter.action)
+            .bind(filter.performed_by)
+            .bind(filter.created_after)
+            .bind(filter.created_before)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(count)
+    }
+
+    /// Every audit entry for one entity, oldest first
+    pub async fn get_entity_history(
+        &self,
+        entity_type: &str,
+        entity_id: Uuid,
+    ) -> Result<Vec<AuditLogEntry>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT
+                a.id, a.entity_type, a.entity_id, a.action, a.old_values, a.new_values,
+                a.performed_by, a.ip_address, a.user_agent, a.created_at,
+                a.chain_seq, a.prev_hash, a.entry_hash,
+                u.first_name, u.last_name, u.email
+            FROM audit_log a
+            LEFT JOIN users u ON a.performed_by = u.id
+            WHERE a.entity_type = $1 AND a.entity_id = $2
+            ORDER BY a.chain_seq
+            "#
+        )
         .bind(entity_type)
         .bind(entity_id)
-        .bind(performed_by)
-        .bind(from_date)
-        .bind(to_date)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(map_audit_log_entry).collect())
+    }
+
+    /// Entries in chain order after `after_sequence`, up to and including
+    /// `up_to` when given, with the text each hash was computed over
+    pub async fn get_chain_entries(
+        &self,
+        after_sequence: i64,
+        up_to: Option<i64>,
+        limit: i64,
+    ) -> Result<Vec<AuditChainEntry>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT
+                a.id, a.entity_type, a.entity_id, a.action, a.old_values, a.new_values,
+                a.performed_by, a.ip_address, a.user_agent, a.created_at,
+                a.chain_seq, a.prev_hash, a.entry_hash,
+                audit_log_canonical(a) AS canonical
+            FROM audit_log a
+            WHERE a.chain_seq > $1
+              AND ($2::bigint IS NULL OR a.chain_seq <= $2)
+            ORDER BY a.chain_seq
+            LIMIT $3
+            "#
+        )
+        .bind(after_sequence)
+        .bind(up_to)
         .bind(limit)
-        .bind(offset)
         .fetch_all(&self.pool)
         .await?;
 
-        Ok(rows.into_iter()
-            .map(|r: PgRow| (
-                r.get("id"),
-                r.get("entity_type"),
-                r.get("entity_id"),
-                r.get("action"),
-                r.get("old_values"),
-                r.get("new_values"),
-                r.get("performed_by"),
-                r.get::<Option<IpAddr>, _>("ip_address"),
-                r.get("user_agent"),
-                r.get("created_at")
-            ))
+        Ok(rows
+            .iter()
+            .map(|r| AuditChainEntry {
+                log: map_audit_log(r),
+                canonical: r.get("canonical"),
+            })
             .collect())
     }
-}
\ No newline at end of file
+
+    /// First and last chain sequence of entries created in `[from, to)`
+    pub async fn get_chain_range(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Option<(i64, i64)>> {
+        let row = sqlx::query(
+            r#"
+            SELECT MIN(chain_seq) AS first_sequence, MAX(chain_seq) AS last_sequence
+            FROM audit_log
+            WHERE created_at >= $1 AND created_at < $2
+            "#
+        )
+        .bind(from)
+        .bind(to)
+        .fetch_one(&self.pool)
+        .await?;
+
+        let first: Option<i64> = row.get("first_sequence");
+        let last: Option<i64> = row.get("last_sequence");
+        Ok(first.zip(last))
+    }
+}
+
+fn map_audit_log(r: &PgRow) -> AuditLog {
+    AuditLog {
+        id: r.get("id"),
+        entity_type: r.get("entity_type"),
+        entity_id: r.get("entity_id"),
+        action: r.get("action"),
+        old_values: r.get("old_values"),
+        new_values: r.get("new_values"),
+        performed_by: r.get("performed_by"),
+        ip_address: r.get::<Option<IpAddr>, _>("ip_address").map(|ip| ip.to_string()),
+        user_agent: r.get("user_agent"),
+        created_at: r.get("created_at"),
+        chain_seq: r.get("chain_seq"),
+        prev_hash: r.get("prev_hash"),
+        entry_hash: r.get("entry_hash"),
+    }
+}
+
+fn map_audit_log_entry(r: &PgRow) -> AuditLogEntry {
+    let first_name: Option<String> = r.get("first_name");
+    let last_name: Option<String> = r.get("last_name");
+    let performed_by_name = match (first_name, last_name) {
+        (Some(first), Some(last)) => Some(format!("{} {}", first, last).trim().to_string()),
+        (Some(name), None) | (None, Some(name)) => Some(name),
+        (None, None) => None,
+    };
+
+    AuditLogEntry {
+        log: map_audit_log(r),
+        performed_by_name,
+        performed_by_email: r.get("email"),
+        diff: None,
+    }
+}
@@ -1,11 +1,32 @@
 use crate::database::PgPool;
-use crate::models::{CIType, CIAssetFilter};
+use crate::models::{CIType, CIAsset, CIAssetFilter, CITypeSchemaVersion, DeletedCIAsset};
+use crate::utils::{UniqueValue, AssetFilter, AssetField, FilterOp, SortKey, KeysetCursor};
 use anyhow::Result;
 use chrono::{DateTime, Utc};
 use serde_json::Value;
-use sqlx::{postgres::PgRow, Row};
+use sqlx::{postgres::PgRow, PgConnection, Postgres, QueryBuilder, Row, Transaction};
 use uuid::Uuid;
 
+/// A type followed by its ancestors, nearest first
+const CI_TYPE_LINEAGE_QUERY: &str = r#"
+    WITH RECURSIVE lineage(id, depth, path) AS (
+        SELECT id, 0, ARRAY[id]
+        FROM ci_types
+        WHERE id = $1 AND deleted_at IS NULL
+        UNION ALL
+        SELECT t.id, l.depth + 1, l.path || t.id
+        FROM lineage l
+        JOIN ci_types c ON c.id = l.id
+        JOIN ci_types t ON t.id = c.parent_id
+        WHERE t.deleted_at IS NULL AND NOT t.id = ANY(l.path)
+    )
+    SELECT t.id, t.name, t.description, t.attributes, t.created_by, t.created_at, t.updated_at,
+           t.deleted_at, t.schema_version, t.parent_id, t.is_abstract
+    FROM lineage l
+    JOIN ci_types t ON t.id = l.id
+    ORDER BY l.depth
+    "#;
+
 #[derive(Debug, Clone)]
 pub struct CIRepository {
     pool: PgPool,
@@ -16,29 +37,40 @@ impl CIRepository {
         Self { pool }
     }
 
+    /// Start a transaction for a change and its audit row. The mutations below
+    /// take the connection so the caller decides what commits together.
+    pub async fn begin(&self) -> Result<Transaction<'static, Postgres>> {
+        Ok(self.pool.begin().await?)
+    }
+
     // CI Type CRUD operations
 
     pub async fn create_ci_type(
         &self,
+        conn: &mut PgConnection,
         name: &str,
         description: Option<&str>,
         attributes: &Value,
+        parent_id: Option<Uuid>,
+        is_abstract: bool,
         created_by: Uuid,
     ) -> Result<Uuid> {
         let id = Uuid::new_v4();
 
         sqlx::query(
             r#"
-            INSERT INTO ci_types (id, name, description, attributes, created_by, created_at, updated_at)
-            VALUES ($1, $2, $3, $4, $5, NOW(), NOW())
+            INSERT INTO ci_types (id, name, description, attributes, parent_id, is_abstract, created_by, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, NOW(), NOW())
             "#
         )
         .bind(id)
         .bind(name)
         .bind(description)
         .bind(attributes)
+        .bind(parent_id)
+        .bind(is_abstract)
         .bind(created_by)
-        .execute(&self.pool)
+        .execute(&mut *conn)
         .await?;
 
         Ok(id)
@@ -47,7 +79,8 @@ impl CIRepository {
     pub async fn get_ci_type_by_id(&self, id: Uuid) -> Result<Option<CIType>> {
         let row = sqlx::query(
             r#"
-            SELECT id, name, description, attributes, created_by, created_at, updated_at, deleted_at
+            SELECT id, name, description, attributes, created_by, created_at, updated_at, deleted_at, schema_version,
+                   parent_id, is_abstract
             FROM ci_types
             WHERE id = $1 AND deleted_at IS NULL
             "#
@@ -65,13 +98,17 @@ impl CIRepository {
             created_at: r.get("created_at"),
             updated_at: r.get("updated_at"),
             deleted_at: r.get("deleted_at"),
+            schema_version: r.get("schema_version"),
+            parent_id: r.get("parent_id"),
+            is_abstract: r.get("is_abstract"),
         }))
     }
 
     pub async fn get_ci_type_by_name(&self, name: &str) -> Result<Option<CIType>> {
         let row = sqlx::query(
             r#"
-            SELECT id, name, description, attributes, created_by, created_at, updated_at, deleted_at
+            SELECT id, name, description, attributes, created_by, created_at, updated_at, deleted_at, schema_version,
+                   parent_id, is_abstract
             FROM ci_types
             WHERE name = $1 AND deleted_at IS NULL
             "#
@@ -89,25 +126,36 @@ impl CIRepository {
             created_at: r.get("created_at"),
             updated_at: r.get("updated_at"),
             deleted_at: r.get("deleted_at"),
+            schema_version: r.get("schema_version"),
+            parent_id: r.get("parent_id"),
+            is_abstract: r.get("is_abstract"),
         }))
     }
 
+    /// One page of CI types, newest first, starting after `cursor` and
+    /// restricted to `allowed_ids` when given
     pub async fn list_ci_types(
         &self,
+        cursor: Option<&KeysetCursor>,
         limit: i64,
-        offset: i64,
+        allowed_ids: Option<&[Uuid]>,
     ) -> Result<Vec<CIType>> {
         let rows = sqlx::query(
             r#"
-            SELECT id, name, description, attributes, created_by, created_at, updated_at, deleted_at
+            SELECT id, name, description, attributes, created_by, created_at, updated_at, deleted_at, schema_version,
+                   parent_id, is_abstract
             FROM ci_types
             WHERE deleted_at IS NULL
-            ORDER BY created_at DESC
-            LIMIT $1 OFFSET $2
+            AND ($1::uuid[] IS NULL OR id = ANY($1))
+            AND ($2::timestamptz IS NULL OR (created_at, id) < ($2, $3))
+            ORDER BY created_at DESC, id DESC
+            LIMIT $4
             "#
         )
+        .bind(allowed_ids)
+        .bind(cursor.map(|c| c.created_at))
+        .bind(cursor.map(|c| c.id))
         .bind(limit)
-        .bind(offset)
         .fetch_all(&self.pool)
         .await?;
 
@@ -121,16 +169,22 @@ impl CIRepository {
                 created_at: r.get("created_at"),
                 updated_at: r.get("updated_at"),
                 deleted_at: r.get("deleted_at"),
+                schema_version: r.get("schema_version"),
+                parent_id: r.get("parent_id"),
+                is_abstract: r.get("is_abstract"),
             })
             .collect())
     }
 
     pub async fn update_ci_type(
         &self,
+        conn: &mut PgConnection,
         id: Uuid,
         name: Option<&str>,
         description: Option<&str>,
         attributes: Option<&Value>,
+        parent_id: Option<Option<Uuid>>, // Some(None) detaches the type from its parent
+        is_abstract: Option<bool>,
     ) -> Result<bool> {
         let result = sqlx::query(
             r#"
@@ -139,21 +193,26 @@ impl CIRepository {
                 name = COALESCE($1, name),
                 description = COALESCE($2, description),
                 attributes = COALESCE($3, attributes),
+                parent_id = CASE WHEN $4 THEN $5 ELSE parent_id END,
+                is_abstract = COALESCE($6, is_abstract),
                 updated_at = NOW()
-            WHERE id = $4 AND deleted_at IS NULL
+            WHERE id = $7 AND deleted_at IS NULL
             "#
         )
         .bind(name)
         .bind(description)
         .bind(attributes)
+        .bind(parent_id.is_some())
+        .bind(parent_id.flatten())
+        .bind(is_abstract)
         .bind(id)
-        .execute(&self.pool)
+        .execute(&mut *conn)
         .await?;
 
         Ok(result.rows_affected() > 0)
     }
 
-    pub async fn delete_ci_type(&self, id: Uuid) -> Result<bool> {
+    pub async fn delete_ci_type(&self, conn: &mut PgConnection, id: Uuid) -> Result<bool> {
         let result = sqlx::query(
             r#"
             UPDATE ci_types
@@ -162,47 +221,279 @@ impl CIRepository {
             "#
         )
         .bind(id)
-        .execute(&self.pool)
+        .execute(&mut *conn)
         .await?;
 
         Ok(result.rows_affected() > 0)
     }
 
-    pub async fn count_ci_types(&self) -> Result<i64> {
+    pub async fn count_ci_types(&self, allowed_ids: Option<&[Uuid]>) -> Result<i64> {
         let count: i64 = sqlx::query_scalar(
             r#"
             SELECT COUNT(*)
             FROM ci_types
             WHERE deleted_at IS NULL
+            AND ($1::uuid[] IS NULL OR id = ANY($1))
             "#
         )
+        .bind(allowed_ids)
         .fetch_one(&self.pool)
         .await?;
 
         Ok(count)
     }
 
+    // CI Type hierarchy
+
+    /// The type followed by its ancestors, nearest first. Stops at a deleted
+    /// ancestor, and at a repeat should the hierarchy ever contain a cycle.
+    pub async fn get_ci_type_lineage(&self, id: Uuid) -> Result<Vec<CIType>> {
+        let rows = sqlx::query(CI_TYPE_LINEAGE_QUERY)
+            .bind(id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows.into_iter().map(map_ci_type).collect())
+    }
+
+    /// Take the hierarchy lock for the rest of the transaction and read the
+    /// lineage under it. Parent changes checked against this lineage can't
+    /// race each other into a cycle.
+    pub async fn lock_ci_type_lineage(&self, conn: &mut PgConnection, id: Uuid) -> Result<Vec<CIType>> {
+        sqlx::query("SELECT pg_advisory_xact_lock(hashtext('ci_type_hierarchy'))")
+            .execute(&mut *conn)
+            .await?;
+
+        let rows = sqlx::query(CI_TYPE_LINEAGE_QUERY)
+            .bind(id)
+            .fetch_all(&mut *conn)
+            .await?;
+
+        Ok(rows.into_iter().map(map_ci_type).collect())
+    }
+
+    /// Every live type below `id`, parents before their children
+    pub async fn get_ci_type_descendants(&self, id: Uuid) -> Result<Vec<CIType>> {
+        let rows = sqlx::query(
+            r#"
+            WITH RECURSIVE descendants(id, depth, path) AS (
+                SELECT id, 1, ARRAY[$1, id]
+                FROM ci_types
+                WHERE parent_id = $1 AND deleted_at IS NULL
+                UNION ALL
+                SELECT t.id, d.depth + 1, d.path || t.id
+                FROM descendants d
+                JOIN ci_types t ON t.parent_id = d.id
+                WHERE t.deleted_at IS NULL AND NOT t.id = ANY(d.path)
+            )
+            SELECT t.id, t.name, t.description, t.attributes, t.created_by, t.created_at, t.updated_at,
+                   t.deleted_at, t.schema_version, t.parent_id, t.is_abstract
+            FROM descendants d
+            JOIN ci_types t ON t.id = d.id
+            ORDER BY d.depth, t.name
+            "#
+        )
+        .bind(id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(map_ci_type).collect())
+    }
+
+    /// Whether `ci_type_id` is `ancestor_id` or one of its descendants
+    pub async fn is_ci_type_within(&self, ci_type_id: Uuid, ancestor_id: Uuid) -> Result<bool> {
+        let within: bool = sqlx::query_scalar(
+            "SELECT $1 IN (SELECT ci_type_subtree($2))"
+        )
+        .bind(ci_type_id)
+        .bind(ancestor_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(within)
+    }
+
+    pub async fn has_subtypes(&self, id: Uuid) -> Result<bool> {
+        let exists: bool = sqlx::query_scalar(
+            "SELECT EXISTS(SELECT 1 FROM ci_types WHERE parent_id = $1 AND deleted_at IS NULL)"
+        )
+        .bind(id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(exists)
+    }
+
+    // CI Type schema versions
+
+    /// Record `schema` as the CI type's next schema version and make it current
+    pub async fn create_schema_version(
+        &self,
+        conn: &mut PgConnection,
+        ci_type_id: Uuid,
+        schema: &Value,
+        migration: Option<&Value>,
+        created_by: Uuid,
+    ) -> Result<i32> {
+        let version: i32 = sqlx::query_scalar(
+            r#"
+            INSERT INTO ci_type_schema_versions (ci_type_id, version, schema, migration, created_by, created_at)
+            SELECT $1, COALESCE(MAX(version), 0) + 1, $2, $3, $4, NOW()
+            FROM ci_type_schema_versions
+            WHERE ci_type_id = $1
+            RETURNING version
+            "#
+        )
+        .bind(ci_type_id)
+        .bind(schema)
+        .bind(migration)
+        .bind(created_by)
+        .fetch_one(&mut *conn)
+        .await?;
+
+        sqlx::query("UPDATE ci_types SET schema_version = $1 WHERE id = $2")
+            .bind(version)
+            .bind(ci_type_id)
+            .execute(&mut *conn)
+            .await?;
+
+        Ok(version)
+    }
+
+    /// Detach the CI type from its schema versions once it has no schema
+    pub async fn clear_schema_version(&self, conn: &mut PgConnection, ci_type_id: Uuid) -> Result<()> {
+        sqlx::query("UPDATE ci_types SET schema_version = NULL WHERE id = $1")
+            .bind(ci_type_id)
+            .execute(&mut *conn)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn list_schema_versions(&self, ci_type_id: Uuid) -> Result<Vec<CITypeSchemaVersion>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, ci_type_id, version, schema, migration, created_by, created_at
+            FROM ci_type_schema_versions
+            WHERE ci_type_id = $1
+            ORDER BY version
+            "#
+        )
+        .bind(ci_type_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter()
+            .map(|r: PgRow| CITypeSchemaVersion {
+                id: r.get("id"),
+                ci_type_id: r.get("ci_type_id"),
+                version: r.get("version"),
+                schema: r.get("schema"),
+                migration: r.get("migration"),
+                created_by: r.get("created_by"),
+                created_at: r.get("created_at"),
+            })
+            .collect())
+    }
+
+    /// Live assets of a CI type in id order, one page after `after_id`.
+    /// `behind_version` limits them to assets not yet on that schema version.
+    pub async fn list_ci_assets_for_schema(
+        &self,
+        ci_type_id: Uuid,
+        behind_version: Option<i32>,
+        after_id: Option<Uuid>,
+        limit: i64,
+    ) -> Result<Vec<(Uuid, String, Value)>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, name, attributes
+            FROM ci_assets
+            WHERE ci_type_id = $1 AND deleted_at IS NULL
+            AND ($2::int IS NULL OR schema_version IS DISTINCT FROM $2)
+            AND ($3::uuid IS NULL OR id > $3)
+            ORDER BY id
+            LIMIT $4
+            "#
+        )
+        .bind(ci_type_id)
+        .bind(behind_version)
+        .bind(after_id)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter()
+            .map(|r: PgRow| (r.get("id"), r.get("name"), r.get("attributes")))
+            .collect())
+    }
+
+    /// Mark assets as conforming to a schema version without touching their attributes
+    pub async fn set_ci_assets_schema_version(&self, conn: &mut PgConnection, ids: &[Uuid], schema_version: i32) -> Result<u64> {
+        let result = sqlx::query(
+            "UPDATE ci_assets SET schema_version = $1 WHERE id = ANY($2) AND deleted_at IS NULL"
+        )
+        .bind(schema_version)
+        .bind(ids)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Store freshly evaluated computed attributes. Not an edit of the asset,
+    /// so `updated_at` and `updated_by` are left alone. With `if_attributes`
+    /// the values are only stored if the attributes they were evaluated from
+    /// are still the asset's.
+    pub async fn set_computed_attributes(
+        &self,
+        conn: &mut PgConnection,
+        id: Uuid,
+        computed_attributes: &Value,
+        if_attributes: Option<&Value>,
+    ) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            UPDATE ci_assets SET computed_attributes = $1
+            WHERE id = $2 AND deleted_at IS NULL
+            AND ($3::jsonb IS NULL OR attributes = $3)
+            "#
+        )
+        .bind(computed_attributes)
+        .bind(id)
+        .bind(if_attributes)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
     pub async fn create_ci_asset(
         &self,
+        conn: &mut PgConnection,
         ci_type_id: Uuid,
         name: &str,
         attributes: &Value,
+        computed_attributes: &Value,
+        schema_version: Option<i32>,
         created_by: Uuid,
     ) -> Result<Uuid> {
         let id = Uuid::new_v4();
 
         sqlx::query(
             r#"
-            INSERT INTO ci_assets (id, ci_type_id, name, attributes, created_by, created_at, updated_at)
-            VALUES ($1, $2, $3, $4, $5, NOW(), NOW())
+            INSERT INTO ci_assets (id, ci_type_id, name, attributes, computed_attributes, schema_version, created_by, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, NOW(), NOW())
             "#
         )
         .bind(id)
         .bind(ci_type_id)
         .bind(name)
         .bind(attributes)
+        .bind(computed_attributes)
+        .bind(schema_version)
         .bind(created_by)
-        .execute(&self.pool)
+        .execute(&mut *conn)
         .await?;
 
         Ok(id)
@@ -229,73 +520,85 @@ impl CIRepository {
         )))
     }
 
-    pub async fn list_ci_assets(
-        &self,
-        ci_type_id: Option<Uuid>,
-        limit: i64,
-        offset: i64,
-    ) -> Result<Vec<(Uuid, String, Value, Uuid)>> {
-        let rows = if let Some(type_id) = ci_type_id {
-            sqlx::query(
-                r#"
-                SELECT id, name, attributes, ci_type_id
-                FROM ci_assets
-                WHERE ci_type_id = $1 AND deleted_at IS NULL
-                ORDER BY created_at DESC
-                LIMIT $2 OFFSET $3
-                "#
-            )
-            .bind(type_id)
-            .bind(limit)
-            .bind(offset)
-            .fetch_all(&self.pool)
-            .await?
-        } else {
-            sqlx::query(
-                r#"
-                SELECT id, name, attributes, ci_type_id
-                FROM ci_assets
-                WHERE deleted_at IS NULL
-                ORDER BY created_at DESC
-                LIMIT $1 OFFSET $2
-                "#
-            )
-            .bind(limit)
-            .bind(offset)
-            .fetch_all(&self.pool)
-            .await?
-        };
+    pub async fn get_ci_asset_by_id(&self, id: Uuid) -> Result<Option<CIAsset>> {
+        let row = sqlx::query(
+            r#"
+            SELECT id, ci_type_id, name, attributes, computed_attributes, created_by, updated_by, created_at, updated_at
+            FROM ci_assets
+            WHERE id = $1 AND deleted_at IS NULL
+            "#
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
 
-        Ok(rows.into_iter()
-            .map(|r: PgRow| (
-                r.get("id"),
-                r.get("name"),
-                r.get("attributes"),
-                r.get("ci_type_id")
-            ))
-            .collect())
+        Ok(row.map(|r: PgRow| CIAsset {
+            id: r.get("id"),
+            ci_type_id: r.get("ci_type_id"),
+            name: r.get("name"),
+            attributes: r.get("attributes"),
+            computed_attributes: r.get("computed_attributes"),
+            created_by: r.get("created_by"),
+            updated_by: r.get("updated_by"),
+            created_at: r.get("created_at"),
+            updated_at: r.get("updated_at"),
+        }))
+    }
+
+    /// Live assets among `ids`, each with the name of its CI type
+    pub async fn get_ci_assets_by_ids(&self, ids: &[Uuid]) -> Result<Vec<(CIAsset, String)>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT a.id, a.ci_type_id, a.name, a.attributes, a.computed_attributes, a.created_by, a.updated_by,
+                   a.created_at, a.updated_at, t.name AS ci_type_name
+            FROM ci_assets a
+            JOIN ci_types t ON t.id = a.ci_type_id
+            WHERE a.id = ANY($1) AND a.deleted_at IS NULL
+            "#
+        )
+        .bind(ids)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|r: PgRow| (
+            CIAsset {
+                id: r.get("id"),
+                ci_type_id: r.get("ci_type_id"),
+                name: r.get("name"),
+                attributes: r.get("attributes"),
+                computed_attributes: r.get("computed_attributes"),
+                created_by: r.get("created_by"),
+                updated_by: r.get("updated_by"),
+                created_at: r.get("created_at"),
+                updated_at: r.get("updated_at"),
+            },
+            r.get("ci_type_name"),
+        )).collect())
     }
 
     pub async fn update_ci_asset(
         &self,
+        conn: &mut PgConnection,
         id: Uuid,
         name: Option<&str>,
         attributes: Option<&Value>,
+        schema_version: Option<i32>, // What the new attributes were validated against
         updated_by: Uuid,
     ) -> Result<bool> {
         let result = if let (Some(name), Some(attributes)) = (name, attributes) {
             sqlx::query(
                 r#"
                 UPDATE ci_assets
-                SET name = $1, attributes = $2, updated_by = $3, updated_at = NOW()
-                WHERE id = $4 AND deleted_at IS NULL
+                SET name = $1, attributes = $2, schema_version = $3, updated_by = $4, updated_at = NOW()
+                WHERE id = $5 AND deleted_at IS NULL
                 "#
             )
             .bind(name)
             .bind(attributes)
+            .bind(schema_version)
             .bind(updated_by)
             .bind(id)
-            .execute(&self.pool)
+            .execute(&mut *conn)
             .await?
         } else if let Some(name) = name {
             sqlx::query(
@@ -308,20 +611,21 @@ impl CIRepository {
             .bind(name)
             .bind(updated_by)
             .bind(id)
-            .execute(&self.pool)
+            .execute(&mut *conn)
             .await?
         } else if let Some(attributes) = attributes {
             sqlx::query(
                 r#"
                 UPDATE ci_assets
-                SET attributes = $1, updated_by = $2, updated_at = NOW()
-                WHERE id = $3 AND deleted_at IS NULL
+                SET attributes = $1, schema_version = $2, updated_by = $3, updated_at = NOW()
+                WHERE id = $4 AND deleted_at IS NULL
                 "#
             )
             .bind(attributes)
+            .bind(schema_version)
             .bind(updated_by)
             .bind(id)
-            .execute(&self.pool)
+            .execute(&mut *conn)
             .await?
         } else {
             return Ok(false);
@@ -330,7 +634,36 @@ impl CIRepository {
         Ok(result.rows_affected() > 0)
     }
 
-    pub async fn delete_ci_asset(&self, id: Uuid, deleted_by: Uuid) -> Result<bool> {
+    /// Overwrite an asset's name and attributes, undeleting it if it was soft-deleted
+    pub async fn restore_ci_asset(
+        &self,
+        conn: &mut PgConnection,
+        id: Uuid,
+        name: &str,
+        attributes: &Value,
+        schema_version: Option<i32>,
+        updated_by: Uuid,
+    ) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            UPDATE ci_assets
+            SET name = $1, attributes = $2, schema_version = $3, updated_by = $4, updated_at = NOW(),
+                deleted_at = NULL, deleted_by = NULL
+            WHERE id = $5
+            "#
+        )
+        .bind(name)
+        .bind(attributes)
+        .bind(schema_version)
+        .bind(updated_by)
+        .bind(id)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn delete_ci_asset(&self, conn: &mut PgConnection, id: Uuid, deleted_by: Uuid) -> Result<bool> {
         let result = sqlx::query(
             r#"
             UPDATE ci_assets
@@ -340,103 +673,214 @@ impl CIRepository {
         )
         .bind(deleted_by)
         .bind(id)
-        .execute(&self.pool)
+        .execute(&mut *conn)
         .await?;
 
         Ok(result.rows_affected() > 0)
     }
 
-    /// Enhanced asset listing with advanced filtering
-    pub async fn list_ci_assets_filtered(
+    // Attribute constraints
+
+    /// The asset other than `asset_id` that holds a unique attribute value
+    pub async fn find_unique_value_owner(
         &self,
-        filter: &CIAssetFilter,
-        limit: i64,
-        offset: i64,
-    ) -> Result<Vec<(Uuid, String, Value, Uuid)>> {
-        // Start with base query
-        let mut query = String::from(
+        conn: &mut PgConnection,
+        asset_id: Uuid,
+        unique_value: &UniqueValue,
+    ) -> Result<Option<Uuid>> {
+        let owner: Option<Uuid> = sqlx::query_scalar(
             r#"
-            SELECT id, name, attributes, ci_type_id
-            FROM ci_assets
-            WHERE deleted_at IS NULL
+            SELECT asset_id FROM ci_asset_unique_values
+            WHERE attribute = $1 AND scope IS NOT DISTINCT FROM $2 AND value = $3 AND asset_id <> $4
+            LIMIT 1
             "#
-        );
+        )
+        .bind(&unique_value.attribute)
+        .bind(unique_value.scope)
+        .bind(&unique_value.value)
+        .bind(asset_id)
+        .fetch_optional(&mut *conn)
+        .await?;
 
-        // Add conditions dynamically
-        let mut conditions = Vec::new();
+        Ok(owner)
+    }
 
-        if filter.ci_type_id.is_some() {
-            conditions.push("ci_type_id = $1");
-        }
-        if filter.name.is_some() {
-            conditions.push("name ILIKE $2");
-        }
-        if filter.created_by.is_some() {
-            conditions.push("created_by = $3");
-        }
-        if filter.created_after.is_some() {
-            conditions.push("created_at >= $4");
-        }
-        if filter.created_before.is_some() {
-            conditions.push("created_at <= $5");
-        }
+    /// The asset holding a unique value, such as the one a natural key identifies
+    pub async fn find_unique_value_holder(&self, unique_value: &UniqueValue) -> Result<Option<Uuid>> {
+        let holder: Option<Uuid> = sqlx::query_scalar(
+            r#"
+            SELECT asset_id FROM ci_asset_unique_values
+            WHERE attribute = $1 AND scope IS NOT DISTINCT FROM $2 AND value = $3
+            "#
+        )
+        .bind(&unique_value.attribute)
+        .bind(unique_value.scope)
+        .bind(&unique_value.value)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(holder)
+    }
+
+    /// An asset outside the subtree of `ci_type_id` that holds a unique
+    /// attribute value; used to check a subtree's assets before a schema change
+    pub async fn find_unique_value_owner_outside(&self, ci_type_id: Uuid, unique_value: &UniqueValue) -> Result<Option<Uuid>> {
+        let owner: Option<Uuid> = sqlx::query_scalar(
+            r#"
+            SELECT u.asset_id
+            FROM ci_asset_unique_values u
+            JOIN ci_assets a ON a.id = u.asset_id
+            WHERE u.attribute = $1 AND u.scope IS NOT DISTINCT FROM $2 AND u.value = $3
+            AND a.ci_type_id NOT IN (SELECT ci_type_subtree($4))
+            LIMIT 1
+            "#
+        )
+        .bind(&unique_value.attribute)
+        .bind(unique_value.scope)
+        .bind(&unique_value.value)
+        .bind(ci_type_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(owner)
+    }
+
+    /// Make `values` the unique attribute values an asset holds, releasing any
+    /// others. Fails with a unique violation if another asset holds one.
+    pub async fn replace_unique_values(&self, conn: &mut PgConnection, asset_id: Uuid, values: &[UniqueValue]) -> Result<()> {
+        self.release_unique_values(conn, asset_id).await?;
 
-        if !conditions.is_empty() {
-            query.push_str(" AND ");
-            query.push_str(&conditions.join(" AND "));
+        for unique_value in values {
+            sqlx::query(
+                "INSERT INTO ci_asset_unique_values (asset_id, attribute, scope, value) VALUES ($1, $2, $3, $4)"
+            )
+            .bind(asset_id)
+            .bind(&unique_value.attribute)
+            .bind(unique_value.scope)
+            .bind(&unique_value.value)
+            .execute(&mut *conn)
+            .await?;
         }
 
-        query.push_str(" ORDER BY created_at DESC ");
-        query.push_str(&format!("LIMIT {} OFFSET {}", limit, offset));
+        Ok(())
+    }
+
+    /// Free an asset's unique attribute values, e.g. once it is deleted
+    pub async fn release_unique_values(&self, conn: &mut PgConnection, asset_id: Uuid) -> Result<()> {
+        sqlx::query("DELETE FROM ci_asset_unique_values WHERE asset_id = $1")
+            .bind(asset_id)
+            .execute(&mut *conn)
+            .await?;
 
-        // Build query based on available filters
-        let mut query_builder = sqlx::query(&query);
+        Ok(())
+    }
+
+    /// Whether `id` is a live asset of `ci_type_id` or one of its subtypes
+    pub async fn is_ci_asset_of_type(&self, id: Uuid, ci_type_id: Uuid) -> Result<bool> {
+        let matches: bool = sqlx::query_scalar(
+            r#"
+            SELECT EXISTS(
+                SELECT 1 FROM ci_assets
+                WHERE id = $1 AND deleted_at IS NULL
+                AND ci_type_id IN (SELECT ci_type_subtree($2))
+            )
+            "#
+        )
+        .bind(id)
+        .bind(ci_type_id)
+        .fetch_one(&self.pool)
+        .await?;
 
-        if let Some(ci_type_id) = filter.ci_type_id {
-            query_builder = query_builder.bind(ci_type_id);
+        Ok(matches)
+    }
+
+    /// One page of assets matching the filter, starting after `cursor`.
+    /// `query` is a parsed attribute filter and `sort` comes before the
+    /// default newest-first order. Each asset comes with the cursor that
+    /// resumes after it.
+    pub async fn list_ci_assets_filtered(
+        &self,
+        filter: &CIAssetFilter,
+        query: Option<&AssetFilter>,
+        sort: &[SortKey],
+        allowed_type_ids: Option<&[Uuid]>,
+        cursor: Option<&KeysetCursor>,
+        limit: i64,
+    ) -> Result<Vec<((Uuid, String, Value, Uuid, Value), KeysetCursor)>> {
+        let mut builder = QueryBuilder::<Postgres>::new(
+            "SELECT id, name, attributes, ci_type_id, computed_attributes, created_at"
+        );
+        for (i, key) in sort.iter().enumerate() {
+            builder.push(", ");
+            push_sort_expression(&mut builder, &key.field);
+            builder.push(format!(" AS sort_{}", i));
         }
-        if let Some(ref name) = filter.name {
-            let search_pattern = format!("%{}%", name);
-            query_builder = query_builder.bind(search_pattern);
-        } else {
-            query_builder = query_builder.bind::<Option<String>>(None);
+        builder.push(" FROM ci_assets WHERE ");
+        push_asset_conditions(&mut builder, filter, query, allowed_type_ids);
+        if let Some(cursor) = cursor {
+            builder.push(" AND ");
+            push_keyset_condition(&mut builder, sort, cursor);
         }
-        query_builder = query_builder.bind(filter.created_by);
-        query_builder = query_builder.bind(filter.created_after);
-        query_builder = query_builder.bind(filter.created_before);
 
-        let rows = query_builder
-            .fetch_all(&self.pool)
-            .await?;
+        builder.push(" ORDER BY ");
+        for (i, key) in sort.iter().enumerate() {
+            builder.push(format!("sort_{} {} NULLS LAST, ", i, if key.descending { "DESC" } else { "ASC" }));
+        }
+        builder.push("created_at DESC, id DESC LIMIT ").push_bind(limit);
+
+        let rows = builder.build().fetch_all(&self.pool).await?;
 
         Ok(rows.into_iter()
-            .map(|r: PgRow| (
-                r.get("id"),
-                r.get("name"),
-                r.get("attributes"),
-                r.get("ci_type_id")
-            ))
+            .map(|r: PgRow| {
+                let keys = sort.iter().enumerate()
+                    .map(|(i, key)| sort_value(&r, &format!("sort_{}", i), &key.field))
+                    .collect();
+                let cursor = KeysetCursor::with_keys(keys, r.get("created_at"), r.get("id"));
+                ((
+                    r.get("id"),
+                    r.get("name"),
+                    r.get("attributes"),
+                    r.get("ci_type_id"),
+                    r.get("computed_attributes")
+                ), cursor)
+            })
             .collect())
     }
 
+    /// Number of assets matching the filter
+    pub async fn count_ci_assets_filtered(
+        &self,
+        filter: &CIAssetFilter,
+        query: Option<&AssetFilter>,
+        allowed_type_ids: Option<&[Uuid]>,
+    ) -> Result<i64> {
+        let mut builder = QueryBuilder::<Postgres>::new("SELECT COUNT(*) FROM ci_assets WHERE ");
+        push_asset_conditions(&mut builder, filter, query, allowed_type_ids);
+
+        Ok(builder.build_query_scalar().fetch_one(&self.pool).await?)
+    }
+
     /// Full-text search for CI assets
     pub async fn search_ci_assets(
         &self,
         query_str: &str,
         limit: i64,
         offset: i64,
-    ) -> Result<Vec<(Uuid, String, Value, Uuid)>> {
+        allowed_type_ids: Option<&[Uuid]>,
+    ) -> Result<Vec<(Uuid, String, Value, Uuid, Value)>> {
         let search_pattern = format!("%{}%", query_str);
 
         let rows = sqlx::query(
             r#"
-            SELECT id, name, attributes, ci_type_id
+            SELECT id, name, attributes, ci_type_id, computed_attributes
             FROM ci_assets
             WHERE deleted_at IS NULL
             AND (
                 name ILIKE $1
                 OR attributes::text ILIKE $1
+                OR computed_attributes::text ILIKE $1
             )
+            AND ($4::uuid[] IS NULL OR ci_type_id = ANY($4))
             ORDER BY created_at DESC
             LIMIT $2 OFFSET $3
             "#
@@ -444,6 +888,7 @@ impl CIRepository {
         .bind(&search_pattern)
         .bind(limit)
         .bind(offset)
+        .bind(allowed_type_ids)
         .fetch_all(&self.pool)
         .await?;
 
@@ -452,8 +897,572 @@ impl CIRepository {
                 r.get("id"),
                 r.get("name"),
                 r.get("attributes"),
-                r.get("ci_type_id")
+                r.get("ci_type_id"),
+                r.get("computed_attributes")
             ))
             .collect())
     }
-}
\ No newline at end of file
+
+    // Trash
+
+    /// One page of soft-deleted assets, most recently deleted first, starting
+    /// after `cursor`, which is keyed on `deleted_at` rather than `created_at`
+    pub async fn list_deleted_ci_assets(
+        &self,
+        ci_type_id: Option<Uuid>,
+        allowed_type_ids: Option<&[Uuid]>,
+        cursor: Option<&KeysetCursor>,
+        limit: i64,
+    ) -> Result<Vec<DeletedCIAsset>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT a.id, a.name, a.ci_type_id, t.name AS ci_type_name, a.attributes, a.deleted_at, a.deleted_by,
+                   u.first_name || ' ' || u.last_name AS deleted_by_name
+            FROM ci_assets a
+            JOIN ci_types t ON t.id = a.ci_type_id
+            LEFT JOIN users u ON u.id = a.deleted_by
+            WHERE a.deleted_at IS NOT NULL
+            AND ($1::uuid IS NULL OR a.ci_type_id = $1)
+            AND ($2::uuid[] IS NULL OR a.ci_type_id = ANY($2))
+            AND ($3::timestamptz IS NULL OR (a.deleted_at, a.id) < ($3, $4))
+            ORDER BY a.deleted_at DESC, a.id DESC
+            LIMIT $5
+            "#
+        )
+        .bind(ci_type_id)
+        .bind(allowed_type_ids)
+        .bind(cursor.map(|cursor| cursor.created_at))
+        .bind(cursor.map(|cursor| cursor.id))
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter()
+            .map(|r: PgRow| DeletedCIAsset {
+                id: r.get("id"),
+                name: r.get("name"),
+                ci_type_id: r.get("ci_type_id"),
+                ci_type_name: r.get("ci_type_name"),
+                attributes: r.get("attributes"),
+                deleted_at: r.get("deleted_at"),
+                deleted_by: r.get("deleted_by"),
+                deleted_by_name: r.get("deleted_by_name"),
+            })
+            .collect())
+    }
+
+    /// Number of soft-deleted assets the trash would list
+    pub async fn count_deleted_ci_assets(&self, ci_type_id: Option<Uuid>, allowed_type_ids: Option<&[Uuid]>) -> Result<i64> {
+        let count: i64 = sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*) FROM ci_assets
+            WHERE deleted_at IS NOT NULL
+            AND ($1::uuid IS NULL OR ci_type_id = $1)
+            AND ($2::uuid[] IS NULL OR ci_type_id = ANY($2))
+            "#
+        )
+        .bind(ci_type_id)
+        .bind(allowed_type_ids)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(count)
+    }
+
+    /// Up to `limit` assets soft-deleted before `deleted_before`, locked for
+    /// purging. Assets another purge has locked are skipped.
+    pub async fn purgeable_ci_asset_ids(
+        &self,
+        conn: &mut PgConnection,
+        deleted_before: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<Uuid>> {
+        let ids = sqlx::query_scalar(
+            r#"
+            SELECT id FROM ci_assets
+            WHERE deleted_at < $1
+            ORDER BY deleted_at
+            LIMIT $2
+            FOR UPDATE SKIP LOCKED
+            "#
+        )
+        .bind(deleted_before)
+        .bind(limit)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(ids)
+    }
+
+    /// Permanently delete assets, with their lifecycle statuses and
+    /// valuations. Their relationships must be purged first; unique values go
+    /// with them through foreign keys. Returns how many were purged.
+    pub async fn purge_ci_assets(&self, conn: &mut PgConnection, ids: &[Uuid]) -> Result<u64> {
+        if ids.is_empty() {
+            return Ok(0);
+        }
+
+        sqlx::query(
+            r#"
+            DELETE FROM amortization_entries
+            WHERE valuation_id IN (SELECT id FROM valuation_records WHERE ci_asset_id = ANY($1))
+            "#
+        )
+        .bind(ids)
+        .execute(&mut *conn)
+        .await?;
+        sqlx::query("DELETE FROM valuation_records WHERE ci_asset_id = ANY($1)")
+            .bind(ids)
+            .execute(&mut *conn)
+            .await?;
+        sqlx::query("DELETE FROM ci_lifecycle_status WHERE ci_asset_id = ANY($1)")
+            .bind(ids)
+            .execute(&mut *conn)
+            .await?;
+        let result = sqlx::query("DELETE FROM ci_assets WHERE id = ANY($1)")
+            .bind(ids)
+            .execute(&mut *conn)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+fn map_ci_type(r: PgRow) -> CIType {
+    CIType {
+        id: r.get("id"),
+        name: r.get("name"),
+        description: r.get("description"),
+        attributes: r.get("attributes"),
+        created_by: r.get("created_by"),
+        created_at: r.get("created_at"),
+        updated_at: r.get("updated_at"),
+        deleted_at: r.get("deleted_at"),
+        schema_version: r.get("schema_version"),
+        parent_id: r.get("parent_id"),
+        is_abstract: r.get("is_abstract"),
+    }
+}
+
+// Attribute filters. Field names map to fixed column expressions and every
+// value is bound, so nothing from a filter is spliced into the SQL text.
+
+/// A value an attribute filter binds
+#[derive(Debug, Clone, PartialEq)]
+enum FilterParam {
+    Json(Value),
+    Path(Vec<String>),
+    Text(String),
+    Texts(Vec<String>),
+}
+
+/// Where attribute filter SQL is written: a `QueryBuilder`, or in tests a
+/// recorder of the SQL text and the values bound
+trait FilterSql {
+    fn push(&mut self, sql: &str) -> &mut Self;
+    fn push_param(&mut self, param: FilterParam) -> &mut Self;
+}
+
+impl FilterSql for QueryBuilder<'_, Postgres> {
+    fn push(&mut self, sql: &str) -> &mut Self {
+        QueryBuilder::push(self, sql)
+    }
+
+    fn push_param(&mut self, param: FilterParam) -> &mut Self {
+        match param {
+            FilterParam::Json(value) => self.push_bind(value),
+            FilterParam::Path(path) => self.push_bind(path),
+            FilterParam::Text(text) => self.push_bind(text),
+            FilterParam::Texts(texts) => self.push_bind(texts),
+        }
+    }
+}
+
+fn push_asset_filter<B: FilterSql>(builder: &mut B, filter: &AssetFilter) {
+    match filter {
+        AssetFilter::And(terms) | AssetFilter::Or(terms) => {
+            let joiner = if matches!(filter, AssetFilter::And(_)) { " AND " } else { " OR " };
+            builder.push("(");
+            for (index, term) in terms.iter().enumerate() {
+                if index > 0 {
+                    builder.push(joiner);
+                }
+                push_asset_filter(builder, term);
+            }
+            builder.push(")");
+        }
+        AssetFilter::Not(inner) => {
+            builder.push("NOT (");
+            push_asset_filter(builder, inner);
+            builder.push(")");
+        }
+        // A missing attribute fails the condition rather than making it
+        // unknown, so NOT around it behaves as expected
+        AssetFilter::Condition(field, op) => {
+            builder.push("COALESCE((");
+            match field {
+                AssetField::Attribute(path) => push_json_condition(builder, "attributes", path, op),
+                AssetField::Computed(path) => push_json_condition(builder, "computed_attributes", path, op),
+                AssetField::Name => push_column_condition(builder, "name", "", op),
+                AssetField::CreatedAt => push_column_condition(builder, "created_at", "::timestamptz", op),
+                AssetField::UpdatedAt => push_column_condition(builder, "updated_at", "::timestamptz", op),
+            }
+            builder.push("), false)");
+        }
+    }
+}
+
+/// Conditions on a JSONB column. Equality and CONTAINS use `@>`, which the
+/// GIN indexes on `attributes` and `computed_attributes` serve.
+fn push_json_condition<B: FilterSql>(builder: &mut B, column: &'static str, path: &[String], op: &FilterOp) {
+    match op {
+        FilterOp::Eq(Value::Null) => push_json_condition(builder, column, path, &FilterOp::IsNull),
+        FilterOp::Ne(Value::Null) => push_json_condition(builder, column, path, &FilterOp::IsNotNull),
+        FilterOp::Eq(value) => {
+            builder.push(column).push(" @> ").push_param(FilterParam::Json(AssetField::nest(path, value.clone())));
+        }
+        FilterOp::Ne(value) => {
+            builder.push("NOT ").push(column).push(" @> ").push_param(FilterParam::Json(AssetField::nest(path, value.clone())));
+        }
+        FilterOp::In(values) => {
+            builder.push("(");
+            for (index, value) in values.iter().enumerate() {
+                if index > 0 {
+                    builder.push(" OR ");
+                }
+                push_json_condition(builder, column, path, &FilterOp::Eq(value.clone()));
+            }
+            builder.push(")");
+        }
+        FilterOp::Lt(value) | FilterOp::Le(value) | FilterOp::Gt(value) | FilterOp::Ge(value) => {
+            let operator = match op {
+                FilterOp::Lt(_) => " < ",
+                FilterOp::Le(_) => " <= ",
+                FilterOp::Gt(_) => " > ",
+                _ => " >= ",
+            };
+            // Values of another JSON type don't match rather than fail the cast
+            let (json_type, cast) = if value.is_number() { ("number", "::numeric") } else { ("string", "") };
+            builder.push("CASE WHEN jsonb_typeof(").push(column).push(" #> ").push_param(FilterParam::Path(path.to_vec()))
+                .push(") = '").push(json_type).push("'")
+                .push(" THEN (").push(column).push(" #>> ").push_param(FilterParam::Path(path.to_vec())).push(")").push(cast)
+                .push(" END").push(operator);
+            match value {
+                Value::String(text) => builder.push_param(FilterParam::Text(text.clone())),
+                other => builder.push_param(FilterParam::Text(other.to_string())).push("::numeric"),
+            };
+        }
+        FilterOp::Contains(item) => {
+            builder.push(column).push(" @> ").push_param(FilterParam::Json(AssetField::nest(path, Value::Array(vec![item.clone()]))));
+        }
+        FilterOp::Like(pattern) => {
+            builder.push(column).push(" #>> ").push_param(FilterParam::Path(path.to_vec())).push(" ILIKE ").push_param(FilterParam::Text(pattern.clone()));
+        }
+        FilterOp::IsNull => {
+            builder.push("(").push(column).push(" #> ").push_param(FilterParam::Path(path.to_vec()))
+                .push(" IS NULL OR jsonb_typeof(").push(column).push(" #> ").push_param(FilterParam::Path(path.to_vec())).push(") = 'null')");
+        }
+        FilterOp::IsNotNull => {
+            builder.push("jsonb_typeof(").push(column).push(" #> ").push_param(FilterParam::Path(path.to_vec())).push(") <> 'null'");
+        }
+    }
+}
+
+/// Conditions on a plain column. The parser only lets strings through for
+/// these, and timestamps only as valid RFC 3339.
+fn push_column_condition<B: FilterSql>(builder: &mut B, column: &'static str, cast: &'static str, op: &FilterOp) {
+    let text = |value: &Value| value.as_str().unwrap_or_default().to_string();
+
+    match op {
+        FilterOp::Eq(value) | FilterOp::Ne(value) | FilterOp::Lt(value)
+        | FilterOp::Le(value) | FilterOp::Gt(value) | FilterOp::Ge(value) => {
+            let operator = match op {
+                FilterOp::Eq(_) => " = ",
+                FilterOp::Ne(_) => " <> ",
+                FilterOp::Lt(_) => " < ",
+                FilterOp::Le(_) => " <= ",
+                FilterOp::Gt(_) => " > ",
+                _ => " >= ",
+            };
+            builder.push(column).push(operator).push_param(FilterParam::Text(text(value))).push(cast);
+        }
+        FilterOp::In(values) => {
+            builder.push(column).push(" = ANY(").push_param(FilterParam::Texts(values.iter().map(text).collect()))
+                .push(if cast.is_empty() { ")" } else { "::timestamptz[])" });
+        }
+        FilterOp::Like(pattern) => {
+            builder.push(column).push("::text ILIKE ").push_param(FilterParam::Text(pattern.clone()));
+        }
+        FilterOp::IsNull => {
+            builder.push(column).push(" IS NULL");
+        }
+        FilterOp::IsNotNull => {
+            builder.push(column).push(" IS NOT NULL");
+        }
+        // Rejected by the parser for plain columns
+        FilterOp::Contains(_) => {
+            builder.push("false");
+        }
+    }
+}
+
+/// The `CIAssetFilter` conditions, the parsed attribute filter and the
+/// caller's readable CI types
+fn push_asset_conditions(
+    builder: &mut QueryBuilder<'_, Postgres>,
+    filter: &CIAssetFilter,
+    query: Option<&AssetFilter>,
+    allowed_type_ids: Option<&[Uuid]>,
+) {
+    builder.push("deleted_at IS NULL");
+    if let Some(ci_type_id) = filter.ci_type_id {
+        builder.push(" AND (ci_type_id = ").push_bind(ci_type_id);
+        if filter.include_subtypes {
+            builder.push(" OR ci_type_id IN (SELECT ci_type_subtree(").push_bind(ci_type_id).push("))");
+        }
+        builder.push(")");
+    }
+    if let Some(ref name) = filter.name {
+        builder.push(" AND name ILIKE ").push_bind(format!("%{}%", name));
+    }
+    if let Some(created_by) = filter.created_by {
+        builder.push(" AND created_by = ").push_bind(created_by);
+    }
+    if let Some(created_after) = filter.created_after {
+        builder.push(" AND created_at >= ").push_bind(created_after);
+    }
+    if let Some(created_before) = filter.created_before {
+        builder.push(" AND created_at <= ").push_bind(created_before);
+    }
+    if let Some(allowed_type_ids) = allowed_type_ids {
+        builder.push(" AND ci_type_id = ANY(").push_bind(allowed_type_ids.to_vec()).push(")");
+    }
+    if let Some(query) = query {
+        builder.push(" AND ");
+        push_asset_filter(builder, query);
+    }
+}
+
+/// What an asset list is sorted by. A JSON null sorts like a missing value.
+fn push_sort_expression(builder: &mut QueryBuilder<'_, Postgres>, field: &AssetField) {
+    match field {
+        AssetField::Name => builder.push("name"),
+        AssetField::CreatedAt => builder.push("created_at"),
+        AssetField::UpdatedAt => builder.push("updated_at"),
+        AssetField::Attribute(path) => builder.push("NULLIF(attributes #> ").push_bind(path.clone()).push(", 'null')"),
+        AssetField::Computed(path) => builder.push("NULLIF(computed_attributes #> ").push_bind(path.clone()).push(", 'null')"),
+    };
+}
+
+/// Rows after `cursor` in the order of `sort` followed by `created_at DESC, id DESC`:
+/// equal on every earlier key and after it on one, or equal on all keys and older
+fn push_keyset_condition(builder: &mut QueryBuilder<'_, Postgres>, sort: &[SortKey], cursor: &KeysetCursor) {
+    builder.push("(");
+    for (i, key) in sort.iter().enumerate() {
+        for (earlier, value) in sort[..i].iter().zip(&cursor.keys) {
+            push_sort_equal(builder, &earlier.field, value);
+            builder.push(" AND ");
+        }
+        push_sort_after(builder, key, &cursor.keys[i]);
+        builder.push(" OR ");
+    }
+    for (key, value) in sort.iter().zip(&cursor.keys) {
+        push_sort_equal(builder, &key.field, value);
+        builder.push(" AND ");
+    }
+    builder.push("(created_at, id) < (").push_bind(cursor.created_at).push(", ").push_bind(cursor.id).push("))");
+}
+
+fn push_sort_equal(builder: &mut QueryBuilder<'_, Postgres>, field: &AssetField, value: &Value) {
+    push_sort_expression(builder, field);
+    if value.is_null() {
+        builder.push(" IS NULL");
+    } else {
+        builder.push(" = ");
+        push_sort_value(builder, field, value);
+    }
+}
+
+fn push_sort_after(builder: &mut QueryBuilder<'_, Postgres>, key: &SortKey, value: &Value) {
+    // Missing values sort last in either direction, so nothing follows them on this key
+    if value.is_null() {
+        builder.push("false");
+        return;
+    }
+    builder.push("(");
+    push_sort_expression(builder, &key.field);
+    builder.push(if key.descending { " < " } else { " > " });
+    push_sort_value(builder, &key.field, value);
+    builder.push(" OR ");
+    push_sort_expression(builder, &key.field);
+    builder.push(" IS NULL)");
+}
+
+/// Bind a cursor's sort value as the type of the field's expression. A value
+/// of the wrong type binds as NULL and matches nothing.
+fn push_sort_value(builder: &mut QueryBuilder<'_, Postgres>, field: &AssetField, value: &Value) {
+    match field {
+        AssetField::Name => builder.push_bind(value.as_str().map(str::to_string)),
+        AssetField::CreatedAt | AssetField::UpdatedAt => builder.push_bind(
+            value.as_str()
+                .and_then(|timestamp| DateTime::parse_from_rfc3339(timestamp).ok())
+                .map(|timestamp| timestamp.with_timezone(&Utc)),
+        ),
+        AssetField::Attribute(_) | AssetField::Computed(_) => builder.push_bind(value.clone()),
+    };
+}
+
+/// A row's value for a sort key, as kept in cursors
+fn sort_value(row: &PgRow, column: &str, field: &AssetField) -> Value {
+    match field {
+        AssetField::Name => Value::String(row.get(column)),
+        AssetField::CreatedAt | AssetField::UpdatedAt => Value::String(row.get::<DateTime<Utc>, _>(column).to_rfc3339()),
+        AssetField::Attribute(_) | AssetField::Computed(_) => row.get::<Option<Value>, _>(column).unwrap_or(Value::Null),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{parse_filter, QueryFields};
+    use serde_json::json;
+
+    /// The SQL a filter compiles to, with `$n` for each bound value
+    #[derive(Default)]
+    struct RecordedSql {
+        sql: String,
+        params: Vec<FilterParam>,
+    }
+
+    impl FilterSql for RecordedSql {
+        fn push(&mut self, sql: &str) -> &mut Self {
+            self.sql.push_str(sql);
+            self
+        }
+
+        fn push_param(&mut self, param: FilterParam) -> &mut Self {
+            self.params.push(param);
+            self.sql.push_str(&format!("${}", self.params.len()));
+            self
+        }
+    }
+
+    fn compile(source: &str) -> (String, Vec<FilterParam>) {
+        let filter = parse_filter(source, &QueryFields::untyped()).unwrap();
+        let mut recorded = RecordedSql::default();
+        push_asset_filter(&mut recorded, &filter);
+        (recorded.sql, recorded.params)
+    }
+
+    fn path(path: &[&str]) -> FilterParam {
+        FilterParam::Path(path.iter().map(|segment| segment.to_string()).collect())
+    }
+
+    fn text(text: &str) -> FilterParam {
+        FilterParam::Text(text.to_string())
+    }
+
+    #[test]
+    fn equality_uses_containment() {
+        assert_eq!(
+            compile("attributes.rack.room = 'B2'"),
+            ("COALESCE((attributes @> $1), false)".to_string(), vec![FilterParam::Json(json!({"rack": {"room": "B2"}}))]),
+        );
+        assert_eq!(
+            compile("computed.age_days != 3"),
+            ("COALESCE((NOT computed_attributes @> $1), false)".to_string(), vec![FilterParam::Json(json!({"age_days": 3}))]),
+        );
+        assert_eq!(
+            compile("attributes.tags CONTAINS 'pci'"),
+            ("COALESCE((attributes @> $1), false)".to_string(), vec![FilterParam::Json(json!({"tags": ["pci"]}))]),
+        );
+        assert_eq!(
+            compile("attributes.env IN ('prod', 'stage')"),
+            (
+                "COALESCE(((attributes @> $1 OR attributes @> $2)), false)".to_string(),
+                vec![FilterParam::Json(json!({"env": "prod"})), FilterParam::Json(json!({"env": "stage"}))],
+            ),
+        );
+    }
+
+    #[test]
+    fn ordering_compares_values_of_the_literal_type() {
+        assert_eq!(
+            compile("attributes.ram_gb >= 64"),
+            (
+                "COALESCE((CASE WHEN jsonb_typeof(attributes #> $1) = 'number' THEN (attributes #>> $2)::numeric END >= $3::numeric), false)".to_string(),
+                vec![path(&["ram_gb"]), path(&["ram_gb"]), text("64")],
+            ),
+        );
+        assert_eq!(
+            compile("attributes.os < 'm'"),
+            (
+                "COALESCE((CASE WHEN jsonb_typeof(attributes #> $1) = 'string' THEN (attributes #>> $2) END < $3), false)".to_string(),
+                vec![path(&["os"]), path(&["os"]), text("m")],
+            ),
+        );
+    }
+
+    #[test]
+    fn null_checks_treat_json_null_as_missing() {
+        assert_eq!(
+            compile("attributes.owner IS NULL"),
+            (
+                "COALESCE(((attributes #> $1 IS NULL OR jsonb_typeof(attributes #> $2) = 'null')), false)".to_string(),
+                vec![path(&["owner"]), path(&["owner"])],
+            ),
+        );
+        assert_eq!(compile("attributes.owner = null"), compile("attributes.owner IS NULL"));
+        assert_eq!(
+            compile("attributes.owner != null"),
+            ("COALESCE((jsonb_typeof(attributes #> $1) <> 'null'), false)".to_string(), vec![path(&["owner"])]),
+        );
+    }
+
+    #[test]
+    fn plain_columns_bind_text() {
+        assert_eq!(
+            compile("name LIKE 'web%'"),
+            ("COALESCE((name::text ILIKE $1), false)".to_string(), vec![text("web%")]),
+        );
+        assert_eq!(
+            compile("created_at > '2024-01-01T00:00:00Z'"),
+            ("COALESCE((created_at > $1::timestamptz), false)".to_string(), vec![text("2024-01-01T00:00:00Z")]),
+        );
+        assert_eq!(
+            compile("name IN ('a', 'b')"),
+            (
+                "COALESCE((name = ANY($1)), false)".to_string(),
+                vec![FilterParam::Texts(vec!["a".to_string(), "b".to_string()])],
+            ),
+        );
+        assert_eq!(
+            compile("updated_at IN ('2024-01-01T00:00:00Z')"),
+            (
+                "COALESCE((updated_at = ANY($1::timestamptz[])), false)".to_string(),
+                vec![FilterParam::Texts(vec!["2024-01-01T00:00:00Z".to_string()])],
+            ),
+        );
+    }
+
+    #[test]
+    fn boolean_structure_is_parenthesized() {
+        let (sql, params) = compile("NOT (attributes.a = 1 OR name = 'x') AND attributes.b IS NOT NULL");
+        assert_eq!(
+            sql,
+            "(NOT ((COALESCE((attributes @> $1), false) OR COALESCE((name = $2), false))) \
+             AND COALESCE((jsonb_typeof(attributes #> $3) <> 'null'), false))",
+        );
+        assert_eq!(params, vec![FilterParam::Json(json!({"a": 1})), text("x"), path(&["b"])]);
+    }
+
+    #[test]
+    fn values_never_reach_the_sql_text() {
+        let (sql, params) = compile(r#"attributes.os = "'; DROP TABLE ci_assets; --" OR name LIKE "%' OR 1=1 --""#);
+        assert_eq!(sql, "(COALESCE((attributes @> $1), false) OR COALESCE((name::text ILIKE $2), false))");
+        assert_eq!(
+            params,
+            vec![FilterParam::Json(json!({"os": "'; DROP TABLE ci_assets; --"})), text("%' OR 1=1 --")],
+        );
+    }
+}
@@ -1,9 +1,14 @@
 use crate::database::Neo4jPool;
+use crate::models::RelationshipWithDetails;
 use anyhow::{Result, Context};
 use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use uuid::Uuid;
 use neo4rs::{query, BoltType};
 
+/// Rows sent per `UNWIND` query by the batch operations
+const GRAPH_BATCH_SIZE: usize = 500;
+
 #[derive(Debug)]
 pub struct GraphRepository {
     pool: Neo4jPool,
@@ -22,6 +27,7 @@ pub struct GraphNode {
 pub struct GraphRelationship {
     pub id: Option<Uuid>,
     pub relationship_type: String,
+    pub relationship_type_id: Uuid,
     pub from_node_id: Uuid,
     pub to_node_id: Uuid,
     pub attributes: Value,
@@ -29,6 +35,79 @@ pub struct GraphRelationship {
     pub to_ci_type: String,
 }
 
+/// A node next to another one, and the edge joining them
+#[derive(Debug, Clone)]
+pub struct GraphNeighbor {
+    pub node: GraphNode,
+    pub relationship_type: String,
+    pub relationship_type_id: Uuid,
+}
+
+/// The CI types and relationship types whose nodes and edges a caller may
+/// read. `None` leaves that side unrestricted, like `readable_scopes`.
+#[derive(Debug, Clone, Default)]
+pub struct GraphScope {
+    pub ci_type_ids: Option<Vec<Uuid>>,
+    pub relationship_type_ids: Option<Vec<Uuid>>,
+}
+
+impl GraphScope {
+    pub fn allows_node(&self, ci_type_id: Uuid) -> bool {
+        self.ci_type_ids.as_ref().map_or(true, |ids| ids.contains(&ci_type_id))
+    }
+
+    pub fn allows_edge(&self, relationship_type_id: Uuid) -> bool {
+        self.relationship_type_ids.as_ref().map_or(true, |ids| ids.contains(&relationship_type_id))
+    }
+
+    /// Drop the nodes and edges outside the scope, and the edges left
+    /// without one of their ends
+    pub fn restrict(&self, nodes: Vec<GraphNode>, edges: Vec<GraphRelationship>) -> (Vec<GraphNode>, Vec<GraphRelationship>) {
+        let nodes: Vec<GraphNode> = nodes.into_iter().filter(|node| self.allows_node(node.ci_type_id)).collect();
+        let node_ids: HashSet<Uuid> = nodes.iter().map(|node| node.id).collect();
+        let edges = edges.into_iter()
+            .filter(|edge| {
+                self.allows_edge(edge.relationship_type_id)
+                    && node_ids.contains(&edge.from_node_id)
+                    && node_ids.contains(&edge.to_node_id)
+            })
+            .collect();
+        (nodes, edges)
+    }
+
+    /// The scope as Cypher parameters: `$allowed_ci_type_ids` and
+    /// `$allowed_relationship_type_ids`, for the conditions `ci_type_condition`
+    /// and `relationship_type_condition` add
+    fn params(&self, mut q: neo4rs::Query) -> neo4rs::Query {
+        if let Some(ids) = &self.ci_type_ids {
+            q = q.param("allowed_ci_type_ids", ids.iter().map(Uuid::to_string).collect::<Vec<_>>());
+        }
+        if let Some(ids) = &self.relationship_type_ids {
+            q = q.param("allowed_relationship_type_ids", ids.iter().map(Uuid::to_string).collect::<Vec<_>>());
+        }
+        q
+    }
+
+    /// The Cypher condition keeping `node` within the scope, if any
+    fn ci_type_condition(&self, node: &str) -> Option<String> {
+        self.ci_type_ids.as_ref().map(|_| format!("{}.type_id IN $allowed_ci_type_ids", node))
+    }
+
+    /// The Cypher condition keeping `edge` within the scope, if any
+    fn relationship_type_condition(&self, edge: &str) -> Option<String> {
+        self.relationship_type_ids.as_ref().map(|_| format!("{}.type_id IN $allowed_relationship_type_ids", edge))
+    }
+}
+
+/// `WHERE` with the conditions joined by `AND`, or nothing without any
+fn where_clause(conditions: &[String]) -> String {
+    if conditions.is_empty() {
+        String::new()
+    } else {
+        format!("WHERE {}", conditions.join(" AND "))
+    }
+}
+
 impl GraphRepository {
     pub fn new(pool: Neo4jPool) -> Self {
         Self { pool }
@@ -112,11 +191,7 @@ impl GraphRepository {
     ) -> Result<()> {
         let graph = self.pool.graph();
 
-        // Sanitize relationship type for Neo4j (replace spaces/special chars with underscores)
-        let rel_type_name = relationship_type
-            .to_uppercase()
-            .replace(" ", "_")
-            .replace("-", "_");
+        let rel_type_name = relationship_label(relationship_type);
 
         let props = properties.unwrap_or(Value::Object(serde_json::Map::new()));
 
@@ -155,6 +230,86 @@ impl GraphRepository {
         Ok(())
     }
 
+    /// Create or update many CI asset nodes, one `UNWIND` query per batch
+    pub async fn upsert_ci_nodes(&self, nodes: &[GraphNode]) -> Result<()> {
+        let graph = self.pool.graph();
+
+        let cypher = r#"
+            UNWIND $nodes AS node
+            MERGE (a:CIAsset {id: node.id})
+            SET a.name = node.name,
+                a.type = node.ci_type,
+                a.type_id = node.ci_type_id,
+                a.attributes = node.attributes,
+                a.updated_at = datetime()
+        "#;
+
+        for batch in nodes.chunks(GRAPH_BATCH_SIZE) {
+            let rows = batch.iter()
+                .map(|node| Ok(BoltType::from(HashMap::from([
+                    ("id", BoltType::from(node.id.to_string())),
+                    ("name", BoltType::from(node.name.as_str())),
+                    ("ci_type", BoltType::from(node.ci_type.as_str())),
+                    ("ci_type_id", BoltType::from(node.ci_type_id.to_string())),
+                    ("attributes", BoltType::from(serde_json::to_string(&node.attributes)?)),
+                ]))))
+                .collect::<Result<Vec<_>>>()?;
+
+            graph.run(query(cypher).param("nodes", rows)).await
+                .context("Failed to create/update CI nodes in Neo4j")?;
+        }
+
+        tracing::debug!("Created/updated {} CI nodes in Neo4j", nodes.len());
+        Ok(())
+    }
+
+    /// Create or update many relationships. The relationship type is the
+    /// edge label, which Cypher can't take as a parameter, so there is one
+    /// `UNWIND` query per type and batch.
+    pub async fn upsert_relationships(&self, relationships: &[RelationshipWithDetails]) -> Result<()> {
+        let graph = self.pool.graph();
+
+        let mut by_label: BTreeMap<String, Vec<&RelationshipWithDetails>> = BTreeMap::new();
+        for relationship in relationships {
+            by_label.entry(relationship_label(&relationship.relationship_type_name)).or_default().push(relationship);
+        }
+
+        for (label, relationships) in by_label {
+            let cypher = format!(r#"
+                UNWIND $relationships AS rel
+                MATCH (from:CIAsset {{id: rel.from_id}})
+                MATCH (to:CIAsset {{id: rel.to_id}})
+                MERGE (from)-[r:{} {{type_id: rel.type_id}}]->(to)
+                SET r.attributes = rel.attributes,
+                    r.from_ci_type = rel.from_ci_type,
+                    r.to_ci_type = rel.to_ci_type,
+                    r.is_bidirectional = rel.is_bidirectional,
+                    r.created_at = coalesce(r.created_at, datetime()),
+                    r.updated_at = datetime()
+            "#, label);
+
+            for batch in relationships.chunks(GRAPH_BATCH_SIZE) {
+                let rows = batch.iter()
+                    .map(|relationship| Ok(BoltType::from(HashMap::from([
+                        ("from_id", BoltType::from(relationship.from_ci_asset_id.to_string())),
+                        ("to_id", BoltType::from(relationship.to_ci_asset_id.to_string())),
+                        ("type_id", BoltType::from(relationship.relationship_type_id.to_string())),
+                        ("attributes", BoltType::from(serde_json::to_string(&relationship.attributes)?)),
+                        ("from_ci_type", BoltType::from(relationship.from_ci_type_name.as_str())),
+                        ("to_ci_type", BoltType::from(relationship.to_ci_type_name.as_str())),
+                        ("is_bidirectional", BoltType::from(relationship.is_bidirectional)),
+                    ]))))
+                    .collect::<Result<Vec<_>>>()?;
+
+                graph.run(query(&cypher).param("relationships", rows)).await
+                    .context("Failed to create relationships in Neo4j")?;
+            }
+        }
+
+        tracing::debug!("Created/updated {} relationships in Neo4j", relationships.len());
+        Ok(())
+    }
+
     /// Delete a CI asset node and all its relationships
     pub async fn delete_node(&self, asset_id: Uuid) -> Result<()> {
         let graph = self.pool.graph();
@@ -174,6 +329,27 @@ impl GraphRepository {
         Ok(())
     }
 
+    /// Delete many CI asset nodes and their relationships, one `UNWIND`
+    /// query per batch
+    pub async fn delete_nodes(&self, asset_ids: &[Uuid]) -> Result<()> {
+        let graph = self.pool.graph();
+
+        let cypher = r#"
+            UNWIND $asset_ids AS asset_id
+            MATCH (a:CIAsset {id: asset_id})
+            DETACH DELETE a
+        "#;
+
+        for batch in asset_ids.chunks(GRAPH_BATCH_SIZE) {
+            let ids: Vec<String> = batch.iter().map(Uuid::to_string).collect();
+            graph.run(query(cypher).param("asset_ids", ids)).await
+                .context("Failed to delete CI nodes from Neo4j")?;
+        }
+
+        tracing::debug!("Deleted {} CI nodes from Neo4j", asset_ids.len());
+        Ok(())
+    }
+
     /// Delete a specific relationship
     pub async fn delete_relationship(
         &self,
@@ -200,23 +376,60 @@ impl GraphRepository {
         Ok(())
     }
 
-    /// Get all nodes related to a specific CI asset
+    /// Delete many relationships, one `UNWIND` query per batch
+    pub async fn delete_relationships(&self, relationships: &[RelationshipWithDetails]) -> Result<()> {
+        let graph = self.pool.graph();
+
+        let cypher = r#"
+            UNWIND $relationships AS rel
+            MATCH (from:CIAsset {id: rel.from_id})-[r {type_id: rel.type_id}]->(to:CIAsset {id: rel.to_id})
+            DELETE r
+        "#;
+
+        for batch in relationships.chunks(GRAPH_BATCH_SIZE) {
+            let rows: Vec<BoltType> = batch.iter()
+                .map(|relationship| BoltType::from(HashMap::from([
+                    ("from_id", relationship.from_ci_asset_id.to_string()),
+                    ("to_id", relationship.to_ci_asset_id.to_string()),
+                    ("type_id", relationship.relationship_type_id.to_string()),
+                ])))
+                .collect();
+
+            graph.run(query(cypher).param("relationships", rows)).await
+                .context("Failed to delete relationships from Neo4j")?;
+        }
+
+        tracing::debug!("Deleted {} relationships from Neo4j", relationships.len());
+        Ok(())
+    }
+
+    /// Get all nodes related to a specific CI asset, within `scope`. Nothing
+    /// is returned for an asset outside it.
     pub async fn get_related_nodes(
         &self,
         asset_id: Uuid,
-    ) -> Result<Vec<(Uuid, String, String, String, Value)>> {
+        scope: &GraphScope,
+    ) -> Result<Vec<GraphNeighbor>> {
         let graph = self.pool.graph();
 
-        let cypher = r#"
-            MATCH (a:CIAsset {id: $asset_id})-[r]-(related:CIAsset)
+        let conditions: Vec<String> = [
+            scope.ci_type_condition("a"),
+            scope.ci_type_condition("related"),
+            scope.relationship_type_condition("r"),
+        ].into_iter().flatten().collect();
+
+        let cypher = format!(r#"
+            MATCH (a:CIAsset {{id: $asset_id}})-[r]-(related:CIAsset)
+            {}
             RETURN DISTINCT related.id as id, related.name as name,
-                   related.type as ci_type, type(r) as rel_type,
+                   related.type as ci_type, related.type_id as ci_type_id,
+                   type(r) as rel_type, r.type_id as rel_type_id,
                    related.attributes as attributes
             LIMIT 100
-        "#;
+        "#, where_clause(&conditions));
 
-        let q = query(cypher)
-            .param("asset_id", asset_id.to_string());
+        let q = scope.params(query(&cypher)
+            .param("asset_id", asset_id.to_string()));
 
         let mut result = graph.execute(q).await
             .context("Failed to get related nodes from Neo4j")?;
@@ -228,45 +441,54 @@ impl GraphRepository {
             let id = Uuid::parse_str(&id_str).unwrap_or_default();
             let name: String = row.get("name").unwrap_or_default();
             let ci_type: String = row.get("ci_type").unwrap_or_default();
+            let type_id_str: String = row.get("ci_type_id").unwrap_or_default();
+            let ci_type_id = Uuid::parse_str(&type_id_str).unwrap_or_default();
             let rel_type: String = row.get("rel_type").unwrap_or_default();
+            let rel_type_id_str: String = row.get("rel_type_id").unwrap_or_default();
+            let rel_type_id = Uuid::parse_str(&rel_type_id_str).unwrap_or_default();
             let attrs_str: String = row.get("attributes").unwrap_or_else(|_| "{}".to_string());
             let attributes: Value = serde_json::from_str(&attrs_str).unwrap_or(Value::Object(serde_json::Map::new()));
 
-            related_nodes.push((id, name, ci_type, rel_type, attributes));
+            related_nodes.push(GraphNeighbor {
+                node: GraphNode { id, name, ci_type, ci_type_id, attributes },
+                relationship_type: rel_type,
+                relationship_type_id: rel_type_id,
+            });
         }
 
         Ok(related_nodes)
     }
 
-    /// Get the full graph with optional filtering
+    /// Get the full graph with optional filtering, within `scope`
     pub async fn get_full_graph(
         &self,
         node_limit: Option<u32>,
-        ci_type_filter: Option<&str>,
+        ci_type_filter: Option<&[String]>,
+        scope: &GraphScope,
     ) -> Result<(Vec<GraphNode>, Vec<GraphRelationship>)> {
         let graph = self.pool.graph();
 
         let limit = node_limit.unwrap_or(1000);
 
-        // Build the MATCH clause with optional type filter
-        let match_clause = if let Some(ci_type) = ci_type_filter {
-            format!("MATCH (a:CIAsset {{type: $ci_type}})")
-        } else {
-            "MATCH (a:CIAsset)".to_string()
-        };
+        // Optional type filter, and the CI types the caller may read
+        let conditions: Vec<String> = [
+            ci_type_filter.map(|_| "a.type IN $ci_types".to_string()),
+            scope.ci_type_condition("a"),
+        ].into_iter().flatten().collect();
 
         let node_cypher = format!(r#"
+            MATCH (a:CIAsset)
             {}
             RETURN a.id as id, a.name as name, a.type as ci_type,
                    a.type_id as ci_type_id, a.attributes as attributes
             LIMIT $limit
-        "#, match_clause);
+        "#, where_clause(&conditions));
 
-        let mut q = query(&node_cypher)
-            .param("limit", limit as i64);
+        let mut q = scope.params(query(&node_cypher)
+            .param("limit", limit as i64));
 
-        if let Some(ci_type) = ci_type_filter {
-            q = q.param("ci_type", ci_type);
+        if let Some(ci_types) = ci_type_filter {
+            q = q.param("ci_types", ci_types.to_vec());
         }
 
         let mut result = graph.execute(q).await
@@ -299,22 +521,30 @@ impl GraphRepository {
         let mut relationships = Vec::new();
 
         if !node_ids.is_empty() {
-            let rel_cypher = r#"
+            let conditions: Vec<String> = [
+                Some("from.id IN $node_ids AND to.id IN $node_ids".to_string()),
+                scope.relationship_type_condition("r"),
+            ].into_iter().flatten().collect();
+
+            let rel_cypher = format!(r#"
                 MATCH (from:CIAsset)-[r]->(to:CIAsset)
-                WHERE from.id IN $node_ids AND to.id IN $node_ids
-                RETURN type(r) as rel_type, from.id as from_id, to.id as to_id,
+                {}
+                RETURN type(r) as rel_type, r.type_id as rel_type_id,
+                       from.id as from_id, to.id as to_id,
                        from.type as from_type, to.type as to_type,
                        r.attributes as attributes
-            "#;
+            "#, where_clause(&conditions));
 
-            let q = query(rel_cypher)
-                .param("node_ids", node_ids);
+            let q = scope.params(query(&rel_cypher)
+                .param("node_ids", node_ids));
 
             let mut result = graph.execute(q).await
                 .context("Failed to get relationships from Neo4j")?;
 
             while let Some(row) = result.next().await? {
                 let rel_type: String = row.get("rel_type").unwrap_or_default();
+                let rel_type_id_str: String = row.get("rel_type_id").unwrap_or_default();
+                let rel_type_id = Uuid::parse_str(&rel_type_id_str).unwrap_or_default();
                 let from_id_str: String = row.get("from_id").unwrap_or_default();
                 let to_id_str: String = row.get("to_id").unwrap_or_default();
                 let from_id = Uuid::parse_str(&from_id_str).unwrap_or_default();
@@ -327,6 +557,7 @@ impl GraphRepository {
                 relationships.push(GraphRelationship {
                     id: None,
                     relationship_type: rel_type,
+                    relationship_type_id: rel_type_id,
                     from_node_id: from_id,
                     to_node_id: to_id,
                     attributes,
@@ -339,24 +570,28 @@ impl GraphRepository {
         Ok((nodes, relationships))
     }
 
-    /// Search for CI assets using full-text search
-    pub async fn search_assets(&self, search_term: &str, limit: Option<u32>) -> Result<Vec<GraphNode>> {
+    /// Search for CI assets using full-text search, within `scope`
+    pub async fn search_assets(&self, search_term: &str, limit: Option<u32>, scope: &GraphScope) -> Result<Vec<GraphNode>> {
         let graph = self.pool.graph();
 
         let search_limit = limit.unwrap_or(20);
 
-        let cypher = r#"
+        let conditions: Vec<String> = [
+            Some("(toLower(a.name) CONTAINS toLower($search_term) OR toLower(a.type) CONTAINS toLower($search_term))".to_string()),
+            scope.ci_type_condition("a"),
+        ].into_iter().flatten().collect();
+
+        let cypher = format!(r#"
             MATCH (a:CIAsset)
-            WHERE toLower(a.name) CONTAINS toLower($search_term)
-               OR toLower(a.type) CONTAINS toLower($search_term)
+            {}
             RETURN a.id as id, a.name as name, a.type as ci_type,
                    a.type_id as ci_type_id, a.attributes as attributes
             LIMIT $limit
-        "#;
+        "#, where_clause(&conditions));
 
-        let q = query(cypher)
+        let q = scope.params(query(&cypher)
             .param("search_term", search_term)
-            .param("limit", search_limit as i64);
+            .param("limit", search_limit as i64));
 
         let mut result = graph.execute(q).await
             .context("Failed to search assets in Neo4j")?;
@@ -399,3 +634,34 @@ impl GraphRepository {
         Ok(())
     }
 }
+
+/// Relationship type names as Neo4j edge labels, with spaces and dashes
+/// replaced by underscores. The label goes into the Cypher text, so it is
+/// always backtick-quoted, with backticks in the name doubled.
+fn relationship_label(relationship_type: &str) -> String {
+    let label = relationship_type
+        .to_uppercase()
+        .replace(" ", "_")
+        .replace("-", "_")
+        .replace('`', "``");
+    format!("`{}`", label)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relationship_labels_are_quoted() {
+        assert_eq!(relationship_label("runs on"), "`RUNS_ON`");
+        assert_eq!(relationship_label("depends-on"), "`DEPENDS_ON`");
+    }
+
+    #[test]
+    fn relationship_labels_cannot_break_out_of_the_quotes() {
+        assert_eq!(
+            relationship_label("x`]->(to) DETACH DELETE to //"),
+            "`X``]_>(TO)_DETACH_DELETE_TO_//`",
+        );
+    }
+}
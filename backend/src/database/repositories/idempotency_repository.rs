@@ -0,0 +1,90 @@
+use crate::database::PgPool;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use sqlx::{postgres::PgRow, PgConnection, Row};
+use uuid::Uuid;
+
+/// The recorded outcome of a request made with an Idempotency-Key
+#[derive(Debug, Clone)]
+pub struct IdempotentResponse {
+    pub endpoint: String,
+    pub request_hash: String,
+    pub response: Value,
+}
+
+#[derive(Debug, Clone)]
+pub struct IdempotencyRepository {
+    pool: PgPool,
+}
+
+impl IdempotencyRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// The unexpired outcome recorded for a caller's key
+    pub async fn find(&self, user_id: Uuid, key: &str) -> Result<Option<IdempotentResponse>> {
+        let row = sqlx::query(
+            r#"
+            SELECT endpoint, request_hash, response
+            FROM idempotency_keys
+            WHERE user_id = $1 AND key = $2 AND expires_at > NOW()
+            "#
+        )
+        .bind(user_id)
+        .bind(key)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(|r: PgRow| IdempotentResponse {
+            endpoint: r.get("endpoint"),
+            request_hash: r.get("request_hash"),
+            response: r.get("response"),
+        }))
+    }
+
+    /// Record an outcome on the transaction of the change it describes.
+    /// Returns false when the key already holds an unexpired outcome, such as
+    /// one a concurrent retry committed first.
+    pub async fn record(
+        &self,
+        conn: &mut PgConnection,
+        user_id: Uuid,
+        key: &str,
+        endpoint: &str,
+        request_hash: &str,
+        response: &Value,
+        expires_at: DateTime<Utc>,
+    ) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO idempotency_keys (user_id, key, endpoint, request_hash, response, expires_at, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, NOW())
+            ON CONFLICT (user_id, key) DO UPDATE
+            SET endpoint = EXCLUDED.endpoint, request_hash = EXCLUDED.request_hash, response = EXCLUDED.response,
+                expires_at = EXCLUDED.expires_at, created_at = EXCLUDED.created_at
+            WHERE idempotency_keys.expires_at <= NOW()
+            "#
+        )
+        .bind(user_id)
+        .bind(key)
+        .bind(endpoint)
+        .bind(request_hash)
+        .bind(response)
+        .bind(expires_at)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Delete outcomes no retry can ask for anymore
+    pub async fn purge_expired(&self) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM idempotency_keys WHERE expires_at < NOW()")
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+}
@@ -5,10 +5,12 @@ use crate::{
         CreateLifecycleTypeRequest, UpdateLifecycleTypeRequest,
         CreateLifecycleStateRequest, UpdateLifecycleStateRequest,
         CreateLifecycleTransitionRequest, CreateCITypeLifecycleRequest,
-        LifecycleTypeResponse, LifecycleTypeSummary,
+        LifecycleTypeResponse, LifecycleTypeSummary, LifecycleTypeFilter,
     },
+    utils::KeysetCursor,
 };
-use sqlx::{postgres::PgRow, Row, PgPool};
+use crate::database::set_audit_actor;
+use sqlx::{postgres::PgRow, Postgres, Row, PgPool, Transaction};
 use uuid::Uuid;
 use chrono::Utc;
 
@@ -22,12 +24,24 @@ impl LifecycleRepository {
         Self { pool }
     }
 
+    /// A transaction whose writes the lifecycle audit triggers attribute to
+    /// `performed_by`
+    async fn begin_as(&self, performed_by: Uuid) -> AppResult<Transaction<'static, Postgres>> {
+        let mut tx = self.pool.begin().await
+            .map_err(|e| AppError::internal(format!("Failed to begin transaction: {}", e)))?;
+        set_audit_actor(&mut tx, performed_by).await
+            .map_err(|e| AppError::internal(format!("Failed to set audit actor: {}", e)))?;
+        Ok(tx)
+    }
+
     // Lifecycle Types CRUD
     pub async fn create_lifecycle_type(
         &self,
         request: &CreateLifecycleTypeRequest,
         created_by: Uuid,
     ) -> AppResult<LifecycleType> {
+        let mut tx = self.begin_as(created_by).await?;
+
         let id = Uuid::new_v4();
         let default_color = request.default_color.as_deref().unwrap_or("#6B7280");
 
@@ -45,10 +59,13 @@ impl LifecycleRepository {
         .bind(&request.description)
         .bind(default_color)
         .bind(created_by)
-        .fetch_one(&self.pool)
+        .fetch_one(&mut *tx)
         .await
         .map_err(|e| AppError::internal(format!("Failed to create lifecycle type: {}", e)))?;
 
+        tx.commit().await
+            .map_err(|e| AppError::internal(format!("Failed to commit lifecycle change: {}", e)))?;
+
         Ok(LifecycleType {
             id: row.get("id"),
             name: row.get("name"),
@@ -159,8 +176,16 @@ impl LifecycleRepository {
         }))
     }
 
-    pub async fn list_lifecycle_types(&self, include_inactive: bool) -> AppResult<Vec<LifecycleTypeSummary>> {
-        let sql = if include_inactive {
+    /// One page of lifecycle types, by name, starting after `cursor` and
+    /// restricted to `allowed_ids` when given
+    pub async fn list_lifecycle_types(
+        &self,
+        filter: &LifecycleTypeFilter,
+        allowed_ids: Option<&[Uuid]>,
+        cursor: Option<&KeysetCursor>,
+        limit: i64,
+    ) -> AppResult<Vec<LifecycleTypeSummary>> {
+        let rows = sqlx::query(
             r#"
             SELECT
                 lt.id, lt.name, lt.description, lt.default_color,
@@ -179,35 +204,22 @@ impl LifecycleRepository {
                 GROUP BY lifecycle_type_id
             ) ci_type_counts ON lt.id = ci_type_counts.lifecycle_type_id
             WHERE lt.deleted_at IS NULL
-            ORDER BY lt.name
-            "#
-        } else {
-            r#"
-            SELECT
-                lt.id, lt.name, lt.description, lt.default_color,
-                lt.is_active, lt.created_at,
-                COALESCE(state_counts.state_count, 0) as state_count,
-                COALESCE(ci_type_counts.ci_type_count, 0) as ci_type_count
-            FROM lifecycle_types lt
-            LEFT JOIN (
-                SELECT lifecycle_type_id, COUNT(*) as state_count
-                FROM lifecycle_states
-                GROUP BY lifecycle_type_id
-            ) state_counts ON lt.id = state_counts.lifecycle_type_id
-            LEFT JOIN (
-                SELECT lifecycle_type_id, COUNT(*) as ci_type_count
-                FROM ci_type_lifecycles
-                GROUP BY lifecycle_type_id
-            ) ci_type_counts ON lt.id = ci_type_counts.lifecycle_type_id
-            WHERE lt.deleted_at IS NULL AND lt.is_active = true
-            ORDER BY lt.name
+              AND ($1 OR lt.is_active = true)
+              AND ($2::uuid[] IS NULL OR lt.id = ANY($2))
+              AND ($3::timestamptz IS NULL OR lt.name > $4 OR (lt.name = $4 AND (lt.created_at, lt.id) < ($3, $5)))
+            ORDER BY lt.name ASC, lt.created_at DESC, lt.id DESC
+            LIMIT $6
             "#
-        };
-
-        let rows = sqlx::query(sql)
-            .fetch_all(&self.pool)
-            .await
-            .map_err(|e| AppError::internal(format!("Failed to list lifecycle types: {}", e)))?;
+        )
+        .bind(filter.include_inactive)
+        .bind(allowed_ids)
+        .bind(cursor.map(|c| c.created_at))
+        .bind(cursor.and_then(KeysetCursor::text_key))
+        .bind(cursor.map(|c| c.id))
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AppError::internal(format!("Failed to list lifecycle types: {}", e)))?;
 
         let result: Vec<LifecycleTypeSummary> = rows.into_iter().map(|row: PgRow| LifecycleTypeSummary {
             id: row.get("id"),
@@ -223,11 +235,42 @@ impl LifecycleRepository {
         Ok(result)
     }
 
+    /// Whether an active lifecycle type already has this name
+    pub async fn lifecycle_type_name_exists(&self, name: &str) -> AppResult<bool> {
+        sqlx::query_scalar(
+            "SELECT EXISTS(SELECT 1 FROM lifecycle_types WHERE name = $1 AND deleted_at IS NULL AND is_active = true)"
+        )
+        .bind(name)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| AppError::internal(format!("Failed to check lifecycle type name: {}", e)))
+    }
+
+    pub async fn count_lifecycle_types(&self, filter: &LifecycleTypeFilter, allowed_ids: Option<&[Uuid]>) -> AppResult<i64> {
+        sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*)
+            FROM lifecycle_types lt
+            WHERE lt.deleted_at IS NULL
+              AND ($1 OR lt.is_active = true)
+              AND ($2::uuid[] IS NULL OR lt.id = ANY($2))
+            "#
+        )
+        .bind(filter.include_inactive)
+        .bind(allowed_ids)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| AppError::internal(format!("Failed to count lifecycle types: {}", e)))
+    }
+
     pub async fn update_lifecycle_type(
         &self,
         id: Uuid,
         request: &UpdateLifecycleTypeRequest,
+        performed_by: Uuid,
     ) -> AppResult<LifecycleType> {
+        let mut tx = self.begin_as(performed_by).await?;
+
         // Use individual UPDATE statements for each field to keep it simple
         let mut updated = false;
 
@@ -235,7 +278,7 @@ impl LifecycleRepository {
             sqlx::query("UPDATE lifecycle_types SET name = $1, updated_at = NOW() WHERE id = $2")
                 .bind(name)
                 .bind(id)
-                .execute(&self.pool)
+                .execute(&mut *tx)
                 .await
                 .map_err(|e| AppError::internal(format!("Failed to update lifecycle type name: {}", e)))?;
             updated = true;
@@ -245,7 +288,7 @@ impl LifecycleRepository {
             sqlx::query("UPDATE lifecycle_types SET description = $1, updated_at = NOW() WHERE id = $2")
                 .bind(description)
                 .bind(id)
-                .execute(&self.pool)
+                .execute(&mut *tx)
                 .await
                 .map_err(|e| AppError::internal(format!("Failed to update lifecycle type description: {}", e)))?;
             updated = true;
@@ -255,7 +298,7 @@ impl LifecycleRepository {
             sqlx::query("UPDATE lifecycle_types SET default_color = $1, updated_at = NOW() WHERE id = $2")
                 .bind(default_color)
                 .bind(id)
-                .execute(&self.pool)
+                .execute(&mut *tx)
                 .await
                 .map_err(|e| AppError::internal(format!("Failed to update lifecycle type default_color: {}", e)))?;
             updated = true;
@@ -265,7 +308,7 @@ impl LifecycleRepository {
             sqlx::query("UPDATE lifecycle_types SET is_active = $1, updated_at = NOW() WHERE id = $2")
                 .bind(is_active)
                 .bind(id)
-                .execute(&self.pool)
+                .execute(&mut *tx)
                 .await
                 .map_err(|e| AppError::internal(format!("Failed to update lifecycle type is_active: {}", e)))?;
             updated = true;
@@ -275,19 +318,27 @@ impl LifecycleRepository {
             return Err(AppError::validation("No fields to update".to_string()));
         }
 
+        tx.commit().await
+            .map_err(|e| AppError::internal(format!("Failed to commit lifecycle change: {}", e)))?;
+
         // Fetch the updated record
         self.get_lifecycle_type(id).await?.ok_or_else(|| {
             AppError::not_found("Lifecycle type not found after update".to_string())
         })
     }
 
-    pub async fn delete_lifecycle_type(&self, id: Uuid) -> AppResult<bool> {
+    pub async fn delete_lifecycle_type(&self, id: Uuid, performed_by: Uuid) -> AppResult<bool> {
+        let mut tx = self.begin_as(performed_by).await?;
+
         let result = sqlx::query("UPDATE lifecycle_types SET deleted_at = NOW() WHERE id = $1 AND deleted_at IS NULL")
             .bind(id)
-            .execute(&self.pool)
+            .execute(&mut *tx)
             .await
             .map_err(|e| AppError::internal(format!("Failed to delete lifecycle type: {}", e)))?;
 
+        tx.commit().await
+            .map_err(|e| AppError::internal(format!("Failed to commit lifecycle change: {}", e)))?;
+
         Ok(result.rows_affected() > 0)
     }
 
@@ -295,7 +346,10 @@ impl LifecycleRepository {
     pub async fn create_lifecycle_state(
         &self,
         request: &CreateLifecycleStateRequest,
+        performed_by: Uuid,
     ) -> AppResult<LifecycleState> {
+        let mut tx = self.begin_as(performed_by).await?;
+
         let id = Uuid::new_v4();
         let color = request.color.as_deref().unwrap_or("#6B7280");
         let is_initial_state = request.is_initial_state.unwrap_or(false);
@@ -320,10 +374,13 @@ impl LifecycleRepository {
         .bind(request.order_index)
         .bind(is_initial_state)
         .bind(is_terminal_state)
-        .fetch_one(&self.pool)
+        .fetch_one(&mut *tx)
         .await
         .map_err(|e| AppError::internal(format!("Failed to create lifecycle state: {}", e)))?;
 
+        tx.commit().await
+            .map_err(|e| AppError::internal(format!("Failed to commit lifecycle change: {}", e)))?;
+
         Ok(LifecycleState {
             id: row.get("id"),
             lifecycle_type_id: row.get("lifecycle_type_id"),
@@ -371,7 +428,10 @@ impl LifecycleRepository {
         &self,
         id: Uuid,
         request: &UpdateLifecycleStateRequest,
+        performed_by: Uuid,
     ) -> AppResult<LifecycleState> {
+        let mut tx = self.begin_as(performed_by).await?;
+
         // Use individual UPDATE statements for each field to keep it simple
         let mut updated = false;
 
@@ -379,7 +439,7 @@ impl LifecycleRepository {
             sqlx::query("UPDATE lifecycle_states SET name = $1, updated_at = NOW() WHERE id = $2")
                 .bind(name)
                 .bind(id)
-                .execute(&self.pool)
+                .execute(&mut *tx)
                 .await
                 .map_err(|e| AppError::internal(format!("Failed to update lifecycle state name: {}", e)))?;
             updated = true;
@@ -389,7 +449,7 @@ impl LifecycleRepository {
             sqlx::query("UPDATE lifecycle_states SET description = $1, updated_at = NOW() WHERE id = $2")
                 .bind(description)
                 .bind(id)
-                .execute(&self.pool)
+                .execute(&mut *tx)
                 .await
                 .map_err(|e| AppError::internal(format!("Failed to update lifecycle state description: {}", e)))?;
             updated = true;
@@ -399,7 +459,7 @@ impl LifecycleRepository {
             sqlx::query("UPDATE lifecycle_states SET color = $1, updated_at = NOW() WHERE id = $2")
                 .bind(color)
                 .bind(id)
-                .execute(&self.pool)
+                .execute(&mut *tx)
                 .await
                 .map_err(|e| AppError::internal(format!("Failed to update lifecycle state color: {}", e)))?;
             updated = true;
@@ -409,7 +469,7 @@ impl LifecycleRepository {
             sqlx::query("UPDATE lifecycle_states SET order_index = $1, updated_at = NOW() WHERE id = $2")
                 .bind(order_index)
                 .bind(id)
-                .execute(&self.pool)
+                .execute(&mut *tx)
                 .await
                 .map_err(|e| AppError::internal(format!("Failed to update lifecycle state order_index: {}", e)))?;
             updated = true;
@@ -419,7 +479,7 @@ impl LifecycleRepository {
             sqlx::query("UPDATE lifecycle_states SET is_initial_state = $1, updated_at = NOW() WHERE id = $2")
                 .bind(is_initial_state)
                 .bind(id)
-                .execute(&self.pool)
+                .execute(&mut *tx)
                 .await
                 .map_err(|e| AppError::internal(format!("Failed to update lifecycle state is_initial_state: {}", e)))?;
             updated = true;
@@ -429,7 +489,7 @@ impl LifecycleRepository {
             sqlx::query("UPDATE lifecycle_states SET is_terminal_state = $1, updated_at = NOW() WHERE id = $2")
                 .bind(is_terminal_state)
                 .bind(id)
-                .execute(&self.pool)
+                .execute(&mut *tx)
                 .await
                 .map_err(|e| AppError::internal(format!("Failed to update lifecycle state is_terminal_state: {}", e)))?;
             updated = true;
@@ -439,19 +499,27 @@ impl LifecycleRepository {
             return Err(AppError::validation("No fields to update".to_string()));
         }
 
+        tx.commit().await
+            .map_err(|e| AppError::internal(format!("Failed to commit lifecycle change: {}", e)))?;
+
         // Fetch the updated record
         self.get_lifecycle_state(id).await?.ok_or_else(|| {
             AppError::not_found("Lifecycle state not found after update".to_string())
         })
     }
 
-    pub async fn delete_lifecycle_state(&self, id: Uuid) -> AppResult<bool> {
+    pub async fn delete_lifecycle_state(&self, id: Uuid, performed_by: Uuid) -> AppResult<bool> {
+        let mut tx = self.begin_as(performed_by).await?;
+
         let result = sqlx::query("DELETE FROM lifecycle_states WHERE id = $1")
             .bind(id)
-            .execute(&self.pool)
+            .execute(&mut *tx)
             .await
             .map_err(|e| AppError::internal(format!("Failed to delete lifecycle state: {}", e)))?;
 
+        tx.commit().await
+            .map_err(|e| AppError::internal(format!("Failed to commit lifecycle change: {}", e)))?;
+
         Ok(result.rows_affected() > 0)
     }
 
@@ -4,10 +4,22 @@ pub mod valuation_repository;
 pub mod graph_repository;
 pub mod lifecycle_repository;
 pub mod relationship_repository;
+pub mod user_repository;
+pub mod session_repository;
+pub mod rbac_repository;
+pub mod service_account_repository;
+pub mod oidc_repository;
+pub mod idempotency_repository;
 
 pub use ci_repository::*;
 pub use audit_repository::*;
 pub use valuation_repository::*;
 pub use graph_repository::*;
 pub use lifecycle_repository::*;
-pub use relationship_repository::*;
\ No newline at end of file
+pub use relationship_repository::*;
+pub use user_repository::*;
+pub use session_repository::*;
+pub use rbac_repository::*;
+pub use service_account_repository::*;
+pub use oidc_repository::*;
+pub use idempotency_repository::*;
\ No newline at end of file
@@ -0,0 +1,135 @@
+use crate::database::PgPool;
+use crate::models::User;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::{postgres::PgRow, Row};
+use uuid::Uuid;
+
+use super::user_repository::map_user;
+
+/// A pending authorization-code login, as started by the authorize endpoint
+#[derive(Debug, Clone)]
+pub struct OidcLoginState {
+    pub nonce: String,
+    pub code_verifier: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct OidcRepository {
+    pool: PgPool,
+}
+
+impl OidcRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create_login_state(
+        &self,
+        state_hash: &str,
+        nonce: &str,
+        code_verifier: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO oidc_login_states (state_hash, nonce, code_verifier, expires_at, created_at)
+            VALUES ($1, $2, $3, $4, NOW())
+            "#
+        )
+        .bind(state_hash)
+        .bind(nonce)
+        .bind(code_verifier)
+        .bind(expires_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Consume a login state; each one can complete at most one login
+    pub async fn take_login_state(&self, state_hash: &str) -> Result<Option<OidcLoginState>> {
+        let row = sqlx::query(
+            r#"
+            DELETE FROM oidc_login_states
+            WHERE state_hash = $1 AND expires_at > NOW()
+            RETURNING nonce, code_verifier
+            "#
+        )
+        .bind(state_hash)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(|r: PgRow| OidcLoginState {
+            nonce: r.get("nonce"),
+            code_verifier: r.get("code_verifier"),
+        }))
+    }
+
+    pub async fn find_user_by_identity(&self, issuer: &str, subject: &str) -> Result<Option<User>> {
+        let row = sqlx::query(
+            r#"
+            SELECT u.id, u.email, u.first_name, u.last_name, u.is_active, u.is_admin, u.created_at, u.updated_at
+            FROM user_identities i
+            JOIN users u ON i.user_id = u.id
+            WHERE i.issuer = $1 AND i.subject = $2 AND u.deleted_at IS NULL
+            "#
+        )
+        .bind(issuer)
+        .bind(subject)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(|r: PgRow| map_user(&r)))
+    }
+
+    pub async fn link_identity(
+        &self,
+        user_id: Uuid,
+        issuer: &str,
+        subject: &str,
+        email: Option<&str>,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO user_identities (id, user_id, issuer, subject, email, last_login_at, created_at)
+            VALUES ($1, $2, $3, $4, $5, NOW(), NOW())
+            "#
+        )
+        .bind(Uuid::new_v4())
+        .bind(user_id)
+        .bind(issuer)
+        .bind(subject)
+        .bind(email)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn record_identity_login(&self, issuer: &str, subject: &str, email: Option<&str>) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE user_identities
+            SET last_login_at = NOW(), email = COALESCE($3, email)
+            WHERE issuer = $1 AND subject = $2
+            "#
+        )
+        .bind(issuer)
+        .bind(subject)
+        .bind(email)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Delete login states that were started but never completed
+    pub async fn purge_expired_login_states(&self) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM oidc_login_states WHERE expires_at < NOW()")
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+}
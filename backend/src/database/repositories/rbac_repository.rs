@@ -0,0 +1,350 @@
+use crate::database::PgPool;
+use crate::models::{GrantPermissionRequest, PermissionAction, ResourceType, Role, RoleBinding, RolePermission};
+use crate::utils::KeysetCursor;
+use anyhow::Result;
+use sqlx::{postgres::PgRow, Row};
+use uuid::Uuid;
+
+/// A single grant held by a user through one of their roles
+#[derive(Debug, Clone)]
+pub struct PermissionGrant {
+    pub resource_type: ResourceType,
+    pub action: PermissionAction,
+    pub scope_id: Option<Uuid>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RbacRepository {
+    pool: PgPool,
+}
+
+impl RbacRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Every grant the user holds, across all of their roles
+    pub async fn get_user_grants(&self, user_id: Uuid) -> Result<Vec<PermissionGrant>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT DISTINCT rp.resource_type, rp.action, rp.scope_id
+            FROM role_bindings rb
+            JOIN role_permissions rp ON rp.role_id = rb.role_id
+            WHERE rb.user_id = $1
+            "#
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|r| {
+                Ok(PermissionGrant {
+                    resource_type: parse_column(r, "resource_type")?,
+                    action: parse_column(r, "action")?,
+                    scope_id: r.get("scope_id"),
+                })
+            })
+            .collect()
+    }
+
+    /// The grants of a single API key; keys don't inherit their service account's roles
+    pub async fn get_api_key_grants(&self, api_key_id: Uuid) -> Result<Vec<PermissionGrant>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT resource_type, action, scope_id
+            FROM api_key_permissions
+            WHERE api_key_id = $1
+            "#
+        )
+        .bind(api_key_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|r| {
+                Ok(PermissionGrant {
+                    resource_type: parse_column(r, "resource_type")?,
+                    action: parse_column(r, "action")?,
+                    scope_id: r.get("scope_id"),
+                })
+            })
+            .collect()
+    }
+
+    /// One page of roles, by name, starting after `cursor`
+    pub async fn list_roles(&self, cursor: Option<&KeysetCursor>, limit: i64) -> Result<Vec<Role>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, name, description, is_system, created_by, created_at, updated_at
+            FROM roles
+            WHERE $1::timestamptz IS NULL OR name > $2 OR (name = $2 AND (created_at, id) < ($1, $3))
+            ORDER BY name ASC, created_at DESC, id DESC
+            LIMIT $4
+            "#
+        )
+        .bind(cursor.map(|c| c.created_at))
+        .bind(cursor.and_then(KeysetCursor::text_key))
+        .bind(cursor.map(|c| c.id))
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(map_role).collect())
+    }
+
+    pub async fn count_roles(&self) -> Result<i64> {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM roles")
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(count)
+    }
+
+    pub async fn get_role(&self, id: Uuid) -> Result<Option<Role>> {
+        let row = sqlx::query(
+            r#"
+            SELECT id, name, description, is_system, created_by, created_at, updated_at
+            FROM roles
+            WHERE id = $1
+            "#
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.as_ref().map(map_role))
+    }
+
+    pub async fn get_role_by_name(&self, name: &str) -> Result<Option<Role>> {
+        let row = sqlx::query(
+            r#"
+            SELECT id, name, description, is_system, created_by, created_at, updated_at
+            FROM roles
+            WHERE LOWER(name) = LOWER($1)
+            "#
+        )
+        .bind(name)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.as_ref().map(map_role))
+    }
+
+    /// Create a role together with its initial permissions
+    pub async fn create_role(
+        &self,
+        name: &str,
+        description: Option<&str>,
+        permissions: &[GrantPermissionRequest],
+        created_by: Uuid,
+    ) -> Result<Role> {
+        let mut tx = self.pool.begin().await?;
+
+        let row = sqlx::query(
+            r#"
+            INSERT INTO roles (id, name, description, is_system, created_by, created_at, updated_at)
+            VALUES ($1, $2, $3, false, $4, NOW(), NOW())
+            RETURNING id, name, description, is_system, created_by, created_at, updated_at
+            "#
+        )
+        .bind(Uuid::new_v4())
+        .bind(name)
+        .bind(description)
+        .bind(created_by)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let role = map_role(&row);
+
+        for permission in permissions {
+            sqlx::query(
+                r#"
+                INSERT INTO role_permissions (id, role_id, resource_type, action, scope_id, created_at)
+                VALUES ($1, $2, $3, $4, $5, NOW())
+                ON CONFLICT DO NOTHING
+                "#
+            )
+            .bind(Uuid::new_v4())
+            .bind(role.id)
+            .bind(permission.resource_type.as_str())
+            .bind(permission.action.as_str())
+            .bind(permission.scope_id)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(role)
+    }
+
+    pub async fn update_role(
+        &self,
+        id: Uuid,
+        name: Option<&str>,
+        description: Option<&str>,
+    ) -> Result<Option<Role>> {
+        let row = sqlx::query(
+            r#"
+            UPDATE roles
+            SET name = COALESCE($1, name),
+                description = COALESCE($2, description),
+                updated_at = NOW()
+            WHERE id = $3
+            RETURNING id, name, description, is_system, created_by, created_at, updated_at
+            "#
+        )
+        .bind(name)
+        .bind(description)
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.as_ref().map(map_role))
+    }
+
+    /// Delete a role; permissions and bindings go with it
+    pub async fn delete_role(&self, id: Uuid) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM roles WHERE id = $1 AND is_system = false")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn list_role_permissions(&self, role_id: Uuid) -> Result<Vec<RolePermission>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, role_id, resource_type, action, scope_id, created_at
+            FROM role_permissions
+            WHERE role_id = $1
+            ORDER BY resource_type, action
+            "#
+        )
+        .bind(role_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(map_role_permission).collect()
+    }
+
+    /// Grant a permission to a role; returns None if the role already holds it
+    pub async fn add_role_permission(
+        &self,
+        role_id: Uuid,
+        permission: &GrantPermissionRequest,
+    ) -> Result<Option<RolePermission>> {
+        let row = sqlx::query(
+            r#"
+            INSERT INTO role_permissions (id, role_id, resource_type, action, scope_id, created_at)
+            VALUES ($1, $2, $3, $4, $5, NOW())
+            ON CONFLICT DO NOTHING
+            RETURNING id, role_id, resource_type, action, scope_id, created_at
+            "#
+        )
+        .bind(Uuid::new_v4())
+        .bind(role_id)
+        .bind(permission.resource_type.as_str())
+        .bind(permission.action.as_str())
+        .bind(permission.scope_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.as_ref().map(map_role_permission).transpose()
+    }
+
+    pub async fn remove_role_permission(&self, role_id: Uuid, permission_id: Uuid) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM role_permissions WHERE id = $1 AND role_id = $2")
+            .bind(permission_id)
+            .bind(role_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn list_role_bindings(&self, role_id: Uuid) -> Result<Vec<RoleBinding>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT rb.id, rb.role_id, rb.user_id, u.email as user_email, rb.created_by, rb.created_at
+            FROM role_bindings rb
+            JOIN users u ON rb.user_id = u.id
+            WHERE rb.role_id = $1
+            ORDER BY u.email ASC
+            "#
+        )
+        .bind(role_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(map_role_binding).collect())
+    }
+
+    /// Bind a user to a role; binding an already-bound user is a no-op
+    pub async fn bind_role(&self, role_id: Uuid, user_id: Uuid, created_by: Option<Uuid>) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO role_bindings (id, role_id, user_id, created_by, created_at)
+            VALUES ($1, $2, $3, $4, NOW())
+            ON CONFLICT (role_id, user_id) DO NOTHING
+            "#
+        )
+        .bind(Uuid::new_v4())
+        .bind(role_id)
+        .bind(user_id)
+        .bind(created_by)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn unbind_role(&self, role_id: Uuid, user_id: Uuid) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM role_bindings WHERE role_id = $1 AND user_id = $2")
+            .bind(role_id)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+pub(crate) fn parse_column<T: std::str::FromStr<Err = String>>(r: &PgRow, column: &str) -> Result<T> {
+    r.get::<String, _>(column).parse().map_err(anyhow::Error::msg)
+}
+
+fn map_role(r: &PgRow) -> Role {
+    Role {
+        id: r.get("id"),
+        name: r.get("name"),
+        description: r.get("description"),
+        is_system: r.get::<Option<bool>, _>("is_system").unwrap_or(false),
+        created_by: r.get("created_by"),
+        created_at: r.get("created_at"),
+        updated_at: r.get("updated_at"),
+    }
+}
+
+fn map_role_permission(r: &PgRow) -> Result<RolePermission> {
+    Ok(RolePermission {
+        id: r.get("id"),
+        role_id: r.get("role_id"),
+        resource_type: parse_column(r, "resource_type")?,
+        action: parse_column(r, "action")?,
+        scope_id: r.get("scope_id"),
+        created_at: r.get("created_at"),
+    })
+}
+
+fn map_role_binding(r: &PgRow) -> RoleBinding {
+    RoleBinding {
+        id: r.get("id"),
+        role_id: r.get("role_id"),
+        user_id: r.get("user_id"),
+        user_email: r.get("user_email"),
+        created_by: r.get("created_by"),
+        created_at: r.get("created_at"),
+    }
+}
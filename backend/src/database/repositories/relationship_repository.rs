@@ -4,11 +4,36 @@ use crate::models::{
     UpdateRelationshipTypeRequest, RelationshipTypeFilter, RelationshipTypeResponse,
     RelationshipTypeSummary,
     Relationship, CreateRelationshipRequest, UpdateRelationshipRequest,
-    RelationshipFilter, RelationshipResponse, RelationshipWithDetails
+    RelationshipFilter, RelationshipResponse, RelationshipWithDetails,
+    DeletedRelationshipFilter, DeletedRelationship
 };
-use sqlx::{PgPool, Row};
+use crate::utils::KeysetCursor;
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use sqlx::{postgres::PgRow, PgConnection, PgPool, Postgres, QueryBuilder, Row, Transaction};
 use uuid::Uuid;
 
+/// A relationship with the names of its type, assets and creator; callers
+/// add the WHERE clause
+const RELATIONSHIP_DETAILS_QUERY: &str = r#"
+    SELECT
+        r.id, r.relationship_type_id, r.from_ci_asset_id, r.to_ci_asset_id,
+        r.attributes, r.created_by, r.created_at, r.updated_at,
+        rt.name as relationship_type_name, rt.is_bidirectional,
+        from_asset.name as from_ci_asset_name,
+        to_asset.name as to_ci_asset_name,
+        from_type.name as from_ci_type_name,
+        to_type.name as to_ci_type_name,
+        u.first_name || ' ' || u.last_name as created_by_name
+    FROM relationships r
+    JOIN relationship_types rt ON r.relationship_type_id = rt.id
+    JOIN ci_assets from_asset ON r.from_ci_asset_id = from_asset.id
+    JOIN ci_assets to_asset ON r.to_ci_asset_id = to_asset.id
+    JOIN ci_types from_type ON from_asset.ci_type_id = from_type.id
+    JOIN ci_types to_type ON to_asset.ci_type_id = to_type.id
+    JOIN users u ON r.created_by = u.id
+"#;
+
 #[derive(Clone)]
 pub struct RelationshipRepository {
     pool: PgPool,
@@ -19,8 +44,14 @@ impl RelationshipRepository {
         Self { pool }
     }
 
+    /// Start a transaction for a change and its audit row
+    pub async fn begin(&self) -> Result<Transaction<'static, Postgres>> {
+        Ok(self.pool.begin().await?)
+    }
+
     pub async fn create(
         &self,
+        conn: &mut PgConnection,
         request: &CreateRelationshipTypeRequest,
         created_by: Uuid,
     ) -> Result<RelationshipType> {
@@ -30,12 +61,12 @@ impl RelationshipRepository {
             r#"
             INSERT INTO relationship_types (
                 name, description, from_ci_type_id, to_ci_type_id,
-                is_bidirectional, reverse_name, attributes_schema, created_by
+                is_bidirectional, reverse_name, attributes_schema, include_subtypes, created_by
             )
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
             RETURNING
                 id, name, description, from_ci_type_id, to_ci_type_id,
-                is_bidirectional, reverse_name, attributes_schema, created_by,
+                is_bidirectional, reverse_name, attributes_schema, include_subtypes, created_by,
                 created_at, updated_at
             "#
         )
@@ -46,8 +77,9 @@ impl RelationshipRepository {
         .bind(request.is_bidirectional)
         .bind(&request.reverse_name)
         .bind(attributes_schema)
+        .bind(request.include_subtypes)
         .bind(created_by)
-        .fetch_one(&self.pool)
+        .fetch_one(&mut *conn)
         .await?;
 
         Ok(RelationshipType {
@@ -59,6 +91,7 @@ impl RelationshipRepository {
             is_bidirectional: row.get("is_bidirectional"),
             reverse_name: row.get("reverse_name"),
             attributes_schema: row.get("attributes_schema"),
+            include_subtypes: row.get("include_subtypes"),
             created_by: row.get("created_by"),
             created_at: row.get("created_at"),
             updated_at: row.get("updated_at"),
@@ -70,7 +103,7 @@ impl RelationshipRepository {
             r#"
             SELECT
                 id, name, description, from_ci_type_id, to_ci_type_id,
-                is_bidirectional, reverse_name, attributes_schema, created_by,
+                is_bidirectional, reverse_name, attributes_schema, include_subtypes, created_by,
                 created_at, updated_at
             FROM relationship_types
             WHERE id = $1 AND deleted_at IS NULL
@@ -90,6 +123,7 @@ impl RelationshipRepository {
                 is_bidirectional: row.get("is_bidirectional"),
                 reverse_name: row.get("reverse_name"),
                 attributes_schema: row.get("attributes_schema"),
+                include_subtypes: row.get("include_subtypes"),
                 created_by: row.get("created_by"),
                 created_at: row.get("created_at"),
                 updated_at: row.get("updated_at"),
@@ -98,80 +132,37 @@ impl RelationshipRepository {
         }
     }
 
-    pub async fn list(&self, filter: &RelationshipTypeFilter) -> Result<Vec<RelationshipTypeSummary>> {
-        let limit = filter.limit.unwrap_or(50);
-        let offset = filter.offset.unwrap_or(0);
-
-        let mut query = String::from(
+    /// One page of relationship types, by name, starting after `cursor` and
+    /// restricted to `allowed_ids` when given
+    pub async fn list(
+        &self,
+        filter: &RelationshipTypeFilter,
+        allowed_ids: Option<&[Uuid]>,
+        cursor: Option<&KeysetCursor>,
+        limit: i64,
+    ) -> Result<Vec<RelationshipTypeSummary>> {
+        let mut builder = QueryBuilder::<Postgres>::new(
             r#"
             SELECT
-                rt.id, rt.name, rt.description, rt.is_bidirectional, rt.reverse_name,
+                rt.id, rt.name, rt.description, rt.is_bidirectional, rt.reverse_name, rt.created_at,
                 from_ct.name as from_ci_type_name,
                 to_ct.name as to_ci_type_name,
-                0 as relationship_count
+                0::bigint as relationship_count
             FROM relationship_types rt
             LEFT JOIN ci_types from_ct ON rt.from_ci_type_id = from_ct.id
             LEFT JOIN ci_types to_ct ON rt.to_ci_type_id = to_ct.id
-            WHERE rt.deleted_at IS NULL
-            "#
+            WHERE "#
         );
-
-        let mut param_count = 0;
-
-        if let Some(_search) = &filter.search {
-            param_count += 1;
-            query.push_str(&format!(" AND (rt.name ILIKE ${} OR rt.description ILIKE ${}) ", param_count, param_count + 1));
-            param_count += 1;
-        }
-
-        if let Some(_from_ci_type_id) = filter.from_ci_type_id {
-            param_count += 1;
-            query.push_str(&format!(" AND rt.from_ci_type_id = ${} ", param_count));
-        }
-
-        if let Some(_to_ci_type_id) = filter.to_ci_type_id {
-            param_count += 1;
-            query.push_str(&format!(" AND rt.to_ci_type_id = ${} ", param_count));
-        }
-
-        if let Some(_is_bidirectional) = filter.is_bidirectional {
-            param_count += 1;
-            query.push_str(&format!(" AND rt.is_bidirectional = ${} ", param_count));
-        }
-
-        query.push_str(" ORDER BY rt.name ASC ");
-        query.push_str(&format!(" LIMIT {} OFFSET {} ", limit, offset));
-
-        // Prepare search patterns if needed
-        let (search_pattern1, search_pattern2) = if let Some(search) = &filter.search {
-            let sp1 = format!("%{}%", search);
-            let sp2 = sp1.clone();
-            (Some(sp1), Some(sp2))
-        } else {
-            (None, None)
-        };
-
-        let mut query_builder = sqlx::query(&query);
-
-        if let Some(ref sp1) = search_pattern1 {
-            query_builder = query_builder.bind(sp1);
-        }
-        if let Some(ref sp2) = search_pattern2 {
-            query_builder = query_builder.bind(sp2);
-        }
-        if let Some(from_ci_type_id) = filter.from_ci_type_id {
-            query_builder = query_builder.bind(from_ci_type_id);
-        }
-        if let Some(to_ci_type_id) = filter.to_ci_type_id {
-            query_builder = query_builder.bind(to_ci_type_id);
-        }
-        if let Some(is_bidirectional) = filter.is_bidirectional {
-            query_builder = query_builder.bind(is_bidirectional);
+        push_relationship_type_conditions(&mut builder, filter, allowed_ids);
+        if let Some(cursor) = cursor {
+            builder.push(" AND (rt.name > ").push_bind(cursor.text_key().map(str::to_string))
+                .push(" OR (rt.name = ").push_bind(cursor.text_key().map(str::to_string))
+                .push(" AND (rt.created_at, rt.id) < (").push_bind(cursor.created_at)
+                .push(", ").push_bind(cursor.id).push(")))");
         }
+        builder.push(" ORDER BY rt.name ASC, rt.created_at DESC, rt.id DESC LIMIT ").push_bind(limit);
 
-        let rows = query_builder
-            .fetch_all(&self.pool)
-            .await?;
+        let rows = builder.build().fetch_all(&self.pool).await?;
 
         let relationship_types = rows.into_iter().map(|row| {
             RelationshipTypeSummary {
@@ -183,14 +174,24 @@ impl RelationshipRepository {
                 from_ci_type_name: row.get("from_ci_type_name"),
                 to_ci_type_name: row.get("to_ci_type_name"),
                 relationship_count: row.get("relationship_count"),
+                created_at: row.get("created_at"),
             }
         }).collect();
 
         Ok(relationship_types)
     }
 
+    /// Number of relationship types matching the filter
+    pub async fn count(&self, filter: &RelationshipTypeFilter, allowed_ids: Option<&[Uuid]>) -> Result<i64> {
+        let mut builder = QueryBuilder::<Postgres>::new("SELECT COUNT(*) FROM relationship_types rt WHERE ");
+        push_relationship_type_conditions(&mut builder, filter, allowed_ids);
+
+        Ok(builder.build_query_scalar().fetch_one(&self.pool).await?)
+    }
+
     pub async fn update(
         &self,
+        conn: &mut PgConnection,
         id: Uuid,
         request: &UpdateRelationshipTypeRequest,
     ) -> Result<RelationshipType> {
@@ -227,15 +228,19 @@ impl RelationshipRepository {
             UPDATE relationship_types
             SET name = COALESCE($1, name),
                 description = COALESCE($2, description),
+                include_subtypes = COALESCE($3, include_subtypes),
+                attributes_schema = COALESCE($4, attributes_schema),
                 updated_at = NOW()
-            WHERE id = $3 AND deleted_at IS NULL
+            WHERE id = $5 AND deleted_at IS NULL
             RETURNING *
             "#
         )
         .bind(&request.name)
         .bind(&request.description)
+        .bind(request.include_subtypes)
+        .bind(&request.attributes_schema)
         .bind(id)
-        .fetch_one(&self.pool)
+        .fetch_one(&mut *conn)
         .await?;
 
         Ok(RelationshipType {
@@ -247,18 +252,19 @@ impl RelationshipRepository {
             is_bidirectional: row.get("is_bidirectional"),
             reverse_name: row.get("reverse_name"),
             attributes_schema: row.get("attributes_schema"),
+            include_subtypes: row.get("include_subtypes"),
             created_by: row.get("created_by"),
             created_at: row.get("created_at"),
             updated_at: row.get("updated_at"),
         })
     }
 
-    pub async fn delete(&self, id: Uuid) -> Result<()> {
+    pub async fn delete(&self, conn: &mut PgConnection, id: Uuid) -> Result<()> {
         let result = sqlx::query(
             "UPDATE relationship_types SET deleted_at = NOW() WHERE id = $1 AND deleted_at IS NULL"
         )
         .bind(id)
-        .execute(&self.pool)
+        .execute(&mut *conn)
         .await?;
 
         if result.rows_affected() == 0 {
@@ -294,6 +300,7 @@ impl RelationshipRepository {
     /// Create a new relationship instance between two CI assets
     pub async fn create_relationship(
         &self,
+        conn: &mut PgConnection,
         request: &CreateRelationshipRequest,
         created_by: Uuid,
     ) -> Result<Relationship> {
@@ -314,7 +321,7 @@ impl RelationshipRepository {
         .bind(request.to_ci_asset_id)
         .bind(attributes)
         .bind(created_by)
-        .fetch_one(&self.pool)
+        .fetch_one(&mut *conn)
         .await?;
 
         Ok(Relationship {
@@ -331,84 +338,34 @@ impl RelationshipRepository {
 
     /// Get a relationship by ID with full details
     pub async fn get_relationship_by_id(&self, id: Uuid) -> Result<Option<RelationshipWithDetails>> {
-        let row = sqlx::query(
-            r#"
-            SELECT
-                r.id, r.relationship_type_id, r.from_ci_asset_id, r.to_ci_asset_id,
-                r.attributes, r.created_by, r.created_at, r.updated_at,
-                rt.name as relationship_type_name, rt.is_bidirectional,
-                from_asset.name as from_ci_asset_name,
-                to_asset.name as to_ci_asset_name,
-                from_type.name as from_ci_type_name,
-                to_type.name as to_ci_type_name,
-                u.first_name || ' ' || u.last_name as created_by_name
-            FROM relationships r
-            JOIN relationship_types rt ON r.relationship_type_id = rt.id
-            JOIN ci_assets from_asset ON r.from_ci_asset_id = from_asset.id
-            JOIN ci_assets to_asset ON r.to_ci_asset_id = to_asset.id
-            JOIN ci_types from_type ON from_asset.ci_type_id = from_type.id
-            JOIN ci_types to_type ON to_asset.ci_type_id = to_type.id
-            JOIN users u ON r.created_by = u.id
-            WHERE r.id = $1 AND r.deleted_at IS NULL
-            "#
-        )
-        .bind(id)
-        .fetch_optional(&self.pool)
-        .await?;
+        let row = sqlx::query(&format!("{} WHERE r.id = $1 AND r.deleted_at IS NULL", RELATIONSHIP_DETAILS_QUERY))
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
 
-        match row {
-            Some(row) => Ok(Some(RelationshipWithDetails {
-                id: row.get("id"),
-                relationship_type_id: row.get("relationship_type_id"),
-                relationship_type_name: row.get("relationship_type_name"),
-                is_bidirectional: row.get("is_bidirectional"),
-                from_ci_asset_id: row.get("from_ci_asset_id"),
-                from_ci_asset_name: row.get("from_ci_asset_name"),
-                from_ci_type_name: row.get("from_ci_type_name"),
-                to_ci_asset_id: row.get("to_ci_asset_id"),
-                to_ci_asset_name: row.get("to_ci_asset_name"),
-                to_ci_type_name: row.get("to_ci_type_name"),
-                attributes: row.get("attributes"),
-                created_by: row.get("created_by"),
-                created_by_name: row.get("created_by_name"),
-                created_at: row.get("created_at"),
-                updated_at: row.get("updated_at"),
-            })),
-            None => Ok(None),
-        }
+        Ok(row.as_ref().map(relationship_details))
     }
 
-    /// List relationships with optional filtering
-    pub async fn list_relationships(&self, filter: &RelationshipFilter) -> Result<Vec<RelationshipResponse>> {
-        let limit = filter.limit.unwrap_or(100);
-        let offset = filter.offset.unwrap_or(0);
-
-        let mut where_clauses = vec!["r.deleted_at IS NULL"];
-        let mut param_count = 0;
-
-        if filter.relationship_type_id.is_some() {
-            param_count += 1;
-            where_clauses.push(&format!("r.relationship_type_id = ${}", param_count));
-        }
-
-        if filter.from_ci_asset_id.is_some() {
-            param_count += 1;
-            where_clauses.push(&format!("r.from_ci_asset_id = ${}", param_count));
-        }
-
-        if filter.to_ci_asset_id.is_some() {
-            param_count += 1;
-            where_clauses.push(&format!("r.to_ci_asset_id = ${}", param_count));
-        }
-
-        if filter.ci_asset_id.is_some() {
-            param_count += 1;
-            where_clauses.push(&format!("(r.from_ci_asset_id = ${} OR r.to_ci_asset_id = ${})", param_count, param_count));
-        }
+    /// Live relationships among `ids`, with full details
+    pub async fn get_relationships_by_ids(&self, ids: &[Uuid]) -> Result<Vec<RelationshipWithDetails>> {
+        let rows = sqlx::query(&format!("{} WHERE r.id = ANY($1) AND r.deleted_at IS NULL", RELATIONSHIP_DETAILS_QUERY))
+            .bind(ids)
+            .fetch_all(&self.pool)
+            .await?;
 
-        let where_clause = where_clauses.join(" AND ");
+        Ok(rows.iter().map(relationship_details).collect())
+    }
 
-        let query = format!(
+    /// One page of relationships, newest first, starting after `cursor` and
+    /// restricted to `allowed_type_ids` relationship types when given
+    pub async fn list_relationships(
+        &self,
+        filter: &RelationshipFilter,
+        allowed_type_ids: Option<&[Uuid]>,
+        cursor: Option<&KeysetCursor>,
+        limit: i64,
+    ) -> Result<Vec<RelationshipResponse>> {
+        let mut builder = QueryBuilder::<Postgres>::new(
             r#"
             SELECT
                 r.id, r.relationship_type_id, r.from_ci_asset_id, r.to_ci_asset_id,
@@ -426,31 +383,16 @@ impl RelationshipRepository {
             JOIN ci_types from_type ON from_asset.ci_type_id = from_type.id
             JOIN ci_types to_type ON to_asset.ci_type_id = to_type.id
             JOIN users u ON r.created_by = u.id
-            WHERE {}
-            ORDER BY r.created_at DESC
-            LIMIT {} OFFSET {}
-            "#,
-            where_clause, limit, offset
+            WHERE "#
         );
-
-        let mut query_builder = sqlx::query(&query);
-
-        if let Some(type_id) = filter.relationship_type_id {
-            query_builder = query_builder.bind(type_id);
-        }
-        if let Some(from_id) = filter.from_ci_asset_id {
-            query_builder = query_builder.bind(from_id);
-        }
-        if let Some(to_id) = filter.to_ci_asset_id {
-            query_builder = query_builder.bind(to_id);
-        }
-        if let Some(asset_id) = filter.ci_asset_id {
-            query_builder = query_builder.bind(asset_id);
+        push_relationship_conditions(&mut builder, filter, allowed_type_ids);
+        if let Some(cursor) = cursor {
+            builder.push(" AND (r.created_at, r.id) < (").push_bind(cursor.created_at)
+                .push(", ").push_bind(cursor.id).push(")");
         }
+        builder.push(" ORDER BY r.created_at DESC, r.id DESC LIMIT ").push_bind(limit);
 
-        let rows = query_builder
-            .fetch_all(&self.pool)
-            .await?;
+        let rows = builder.build().fetch_all(&self.pool).await?;
 
         let relationships = rows.into_iter().map(|row| {
             RelationshipResponse {
@@ -475,9 +417,18 @@ impl RelationshipRepository {
         Ok(relationships)
     }
 
+    /// Number of relationships matching the filter
+    pub async fn count_relationships(&self, filter: &RelationshipFilter, allowed_type_ids: Option<&[Uuid]>) -> Result<i64> {
+        let mut builder = QueryBuilder::<Postgres>::new("SELECT COUNT(*) FROM relationships r WHERE ");
+        push_relationship_conditions(&mut builder, filter, allowed_type_ids);
+
+        Ok(builder.build_query_scalar().fetch_one(&self.pool).await?)
+    }
+
     /// Update a relationship's attributes
     pub async fn update_relationship(
         &self,
+        conn: &mut PgConnection,
         id: Uuid,
         request: &UpdateRelationshipRequest,
     ) -> Result<Relationship> {
@@ -494,7 +445,7 @@ impl RelationshipRepository {
         )
         .bind(attributes)
         .bind(id)
-        .fetch_one(&self.pool)
+        .fetch_one(&mut *conn)
         .await?;
 
         Ok(Relationship {
@@ -509,13 +460,26 @@ impl RelationshipRepository {
         })
     }
 
-    /// Delete a relationship (soft delete)
-    pub async fn delete_relationship(&self, id: Uuid) -> Result<()> {
+    /// Delete a relationship (soft delete). `deleted_with_asset_id` is the
+    /// asset whose deletion takes the relationship along, if any.
+    pub async fn delete_relationship(
+        &self,
+        conn: &mut PgConnection,
+        id: Uuid,
+        deleted_by: Uuid,
+        deleted_with_asset_id: Option<Uuid>,
+    ) -> Result<()> {
         let result = sqlx::query(
-            "UPDATE relationships SET deleted_at = NOW() WHERE id = $1 AND deleted_at IS NULL"
+            r#"
+            UPDATE relationships
+            SET deleted_at = NOW(), deleted_by = $1, deleted_with_asset_id = $2
+            WHERE id = $3 AND deleted_at IS NULL
+            "#
         )
+        .bind(deleted_by)
+        .bind(deleted_with_asset_id)
         .bind(id)
-        .execute(&self.pool)
+        .execute(&mut *conn)
         .await?;
 
         if result.rows_affected() == 0 {
@@ -525,6 +489,256 @@ impl RelationshipRepository {
         Ok(())
     }
 
+    /// Overwrite a relationship's attributes, undeleting it if it was soft-deleted
+    pub async fn restore_relationship(&self, conn: &mut PgConnection, id: Uuid, attributes: &Value) -> Result<()> {
+        let result = sqlx::query(
+            r#"
+            UPDATE relationships
+            SET attributes = $1, updated_at = NOW(), deleted_at = NULL, deleted_by = NULL, deleted_with_asset_id = NULL
+            WHERE id = $2
+            "#
+        )
+        .bind(attributes)
+        .bind(id)
+        .execute(&mut *conn)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(anyhow::anyhow!("Relationship not found"));
+        }
+
+        Ok(())
+    }
+
+    /// One page of a relationship type's live relationships, in ID order, for
+    /// checking them against a schema: `(id, from_ci_asset_id, to_ci_asset_id, attributes)`
+    pub async fn list_relationships_for_schema(
+        &self,
+        relationship_type_id: Uuid,
+        after_id: Option<Uuid>,
+        limit: i64,
+    ) -> Result<Vec<(Uuid, Uuid, Uuid, Value)>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, from_ci_asset_id, to_ci_asset_id, attributes
+            FROM relationships
+            WHERE relationship_type_id = $1 AND deleted_at IS NULL
+            AND ($2::uuid IS NULL OR id > $2)
+            ORDER BY id
+            LIMIT $3
+            "#
+        )
+        .bind(relationship_type_id)
+        .bind(after_id)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter()
+            .map(|r| (r.get("id"), r.get("from_ci_asset_id"), r.get("to_ci_asset_id"), r.get("attributes")))
+            .collect())
+    }
+
+    /// IDs of the live relationships from or to an asset, locked until the
+    /// transaction ends
+    pub async fn live_relationship_ids_of_asset(&self, conn: &mut PgConnection, asset_id: Uuid) -> Result<Vec<Uuid>> {
+        let ids = sqlx::query_scalar(
+            r#"
+            SELECT id FROM relationships
+            WHERE (from_ci_asset_id = $1 OR to_ci_asset_id = $1) AND deleted_at IS NULL
+            FOR UPDATE
+            "#
+        )
+        .bind(asset_id)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(ids)
+    }
+
+    /// The relationships an asset's deletion took along, locked until the
+    /// transaction ends, with what restoring each one depends on
+    pub async fn relationships_deleted_with(&self, conn: &mut PgConnection, asset_id: Uuid) -> Result<Vec<CascadedRelationship>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT r.id, r.attributes, other.id AS other_asset_id,
+                   other.deleted_at IS NULL AS other_asset_live,
+                   rt.deleted_at IS NULL AND NOT EXISTS (
+                       SELECT 1 FROM relationships d
+                       WHERE d.relationship_type_id = r.relationship_type_id
+                         AND d.from_ci_asset_id = r.from_ci_asset_id
+                         AND d.to_ci_asset_id = r.to_ci_asset_id
+                         AND d.deleted_at IS NULL
+                   ) AS restorable
+            FROM relationships r
+            JOIN relationship_types rt ON rt.id = r.relationship_type_id
+            JOIN ci_assets other ON other.id =
+                CASE WHEN r.from_ci_asset_id = $1 THEN r.to_ci_asset_id ELSE r.from_ci_asset_id END
+            WHERE r.deleted_with_asset_id = $1 AND r.deleted_at IS NOT NULL
+            FOR UPDATE OF r
+            "#
+        )
+        .bind(asset_id)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(rows.into_iter()
+            .map(|r: PgRow| CascadedRelationship {
+                id: r.get("id"),
+                attributes: r.get("attributes"),
+                other_asset_id: r.get("other_asset_id"),
+                other_asset_live: r.get("other_asset_live"),
+                restorable: r.get("restorable"),
+            })
+            .collect())
+    }
+
+    /// Make a deleted relationship come back with another of its assets
+    /// instead, for when the one it was deleted with returns first
+    pub async fn set_deleted_with(&self, conn: &mut PgConnection, id: Uuid, asset_id: Uuid) -> Result<()> {
+        sqlx::query("UPDATE relationships SET deleted_with_asset_id = $1 WHERE id = $2 AND deleted_at IS NOT NULL")
+            .bind(asset_id)
+            .bind(id)
+            .execute(&mut *conn)
+            .await?;
+
+        Ok(())
+    }
+
+    /// One page of soft-deleted relationships, most recently deleted first,
+    /// starting after `cursor`, which is keyed on `deleted_at` rather than
+    /// `created_at`
+    pub async fn list_deleted_relationships(
+        &self,
+        filter: &DeletedRelationshipFilter,
+        allowed_type_ids: Option<&[Uuid]>,
+        cursor: Option<&KeysetCursor>,
+        limit: i64,
+    ) -> Result<Vec<DeletedRelationship>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT r.id, r.relationship_type_id, rt.name AS relationship_type_name,
+                   r.from_ci_asset_id, from_asset.name AS from_ci_asset_name,
+                   r.to_ci_asset_id, to_asset.name AS to_ci_asset_name,
+                   r.attributes, r.deleted_at, r.deleted_by, r.deleted_with_asset_id,
+                   u.first_name || ' ' || u.last_name AS deleted_by_name
+            FROM relationships r
+            JOIN relationship_types rt ON r.relationship_type_id = rt.id
+            JOIN ci_assets from_asset ON r.from_ci_asset_id = from_asset.id
+            JOIN ci_assets to_asset ON r.to_ci_asset_id = to_asset.id
+            LEFT JOIN users u ON u.id = r.deleted_by
+            WHERE r.deleted_at IS NOT NULL
+            AND ($1::uuid IS NULL OR r.relationship_type_id = $1)
+            AND ($2::uuid IS NULL OR r.from_ci_asset_id = $2 OR r.to_ci_asset_id = $2)
+            AND ($3::uuid[] IS NULL OR r.relationship_type_id = ANY($3))
+            AND ($4::timestamptz IS NULL OR (r.deleted_at, r.id) < ($4, $5))
+            ORDER BY r.deleted_at DESC, r.id DESC
+            LIMIT $6
+            "#
+        )
+        .bind(filter.relationship_type_id)
+        .bind(filter.ci_asset_id)
+        .bind(allowed_type_ids)
+        .bind(cursor.map(|cursor| cursor.created_at))
+        .bind(cursor.map(|cursor| cursor.id))
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter()
+            .map(|r: PgRow| DeletedRelationship {
+                id: r.get("id"),
+                relationship_type_id: r.get("relationship_type_id"),
+                relationship_type_name: r.get("relationship_type_name"),
+                from_ci_asset_id: r.get("from_ci_asset_id"),
+                from_ci_asset_name: r.get("from_ci_asset_name"),
+                to_ci_asset_id: r.get("to_ci_asset_id"),
+                to_ci_asset_name: r.get("to_ci_asset_name"),
+                attributes: r.get("attributes"),
+                deleted_at: r.get("deleted_at"),
+                deleted_by: r.get("deleted_by"),
+                deleted_by_name: r.get("deleted_by_name"),
+                deleted_with_asset_id: r.get("deleted_with_asset_id"),
+            })
+            .collect())
+    }
+
+    /// Number of soft-deleted relationships the trash would list
+    pub async fn count_deleted_relationships(&self, filter: &DeletedRelationshipFilter, allowed_type_ids: Option<&[Uuid]>) -> Result<i64> {
+        let count: i64 = sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*) FROM relationships r
+            WHERE r.deleted_at IS NOT NULL
+            AND ($1::uuid IS NULL OR r.relationship_type_id = $1)
+            AND ($2::uuid IS NULL OR r.from_ci_asset_id = $2 OR r.to_ci_asset_id = $2)
+            AND ($3::uuid[] IS NULL OR r.relationship_type_id = ANY($3))
+            "#
+        )
+        .bind(filter.relationship_type_id)
+        .bind(filter.ci_asset_id)
+        .bind(allowed_type_ids)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(count)
+    }
+
+    /// Up to `limit` relationships soft-deleted before `deleted_before`,
+    /// locked for purging. Relationships another purge has locked are skipped.
+    pub async fn purgeable_relationship_ids(
+        &self,
+        conn: &mut PgConnection,
+        deleted_before: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<Uuid>> {
+        let ids = sqlx::query_scalar(
+            r#"
+            SELECT id FROM relationships
+            WHERE deleted_at < $1
+            ORDER BY deleted_at
+            LIMIT $2
+            FOR UPDATE SKIP LOCKED
+            "#
+        )
+        .bind(deleted_before)
+        .bind(limit)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(ids)
+    }
+
+    /// Every relationship from or to one of the assets, deleted or not,
+    /// locked so that purging the assets can purge them first
+    pub async fn relationship_ids_of_assets(&self, conn: &mut PgConnection, asset_ids: &[Uuid]) -> Result<Vec<Uuid>> {
+        let ids = sqlx::query_scalar(
+            r#"
+            SELECT id FROM relationships
+            WHERE from_ci_asset_id = ANY($1) OR to_ci_asset_id = ANY($1)
+            FOR UPDATE
+            "#
+        )
+        .bind(asset_ids)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(ids)
+    }
+
+    /// Permanently delete relationships. Returns how many were purged.
+    pub async fn purge_relationships(&self, conn: &mut PgConnection, ids: &[Uuid]) -> Result<u64> {
+        if ids.is_empty() {
+            return Ok(0);
+        }
+
+        let result = sqlx::query("DELETE FROM relationships WHERE id = ANY($1)")
+            .bind(ids)
+            .execute(&mut *conn)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
     /// Check if a relationship already exists between two assets
     pub async fn relationship_exists(
         &self,
@@ -550,4 +764,85 @@ impl RelationshipRepository {
 
         Ok(count > 0)
     }
-}
\ No newline at end of file
+}
+
+/// A relationship that went to the trash with an asset being restored
+#[derive(Debug, Clone)]
+pub struct CascadedRelationship {
+    pub id: Uuid,
+    pub attributes: Value,
+    pub other_asset_id: Uuid,
+    pub other_asset_live: bool,
+    pub restorable: bool, // Its type is live and no duplicate was created since
+}
+
+/// The `RelationshipTypeFilter` conditions on `relationship_types rt`
+fn push_relationship_type_conditions(
+    builder: &mut QueryBuilder<'_, Postgres>,
+    filter: &RelationshipTypeFilter,
+    allowed_ids: Option<&[Uuid]>,
+) {
+    builder.push("rt.deleted_at IS NULL");
+    if let Some(search) = &filter.search {
+        let pattern = format!("%{}%", search);
+        builder.push(" AND (rt.name ILIKE ").push_bind(pattern.clone())
+            .push(" OR rt.description ILIKE ").push_bind(pattern).push(")");
+    }
+    if let Some(from_ci_type_id) = filter.from_ci_type_id {
+        builder.push(" AND rt.from_ci_type_id = ").push_bind(from_ci_type_id);
+    }
+    if let Some(to_ci_type_id) = filter.to_ci_type_id {
+        builder.push(" AND rt.to_ci_type_id = ").push_bind(to_ci_type_id);
+    }
+    if let Some(is_bidirectional) = filter.is_bidirectional {
+        builder.push(" AND rt.is_bidirectional = ").push_bind(is_bidirectional);
+    }
+    if let Some(allowed_ids) = allowed_ids {
+        builder.push(" AND rt.id = ANY(").push_bind(allowed_ids.to_vec()).push(")");
+    }
+}
+
+fn relationship_details(row: &PgRow) -> RelationshipWithDetails {
+    RelationshipWithDetails {
+        id: row.get("id"),
+        relationship_type_id: row.get("relationship_type_id"),
+        relationship_type_name: row.get("relationship_type_name"),
+        is_bidirectional: row.get("is_bidirectional"),
+        from_ci_asset_id: row.get("from_ci_asset_id"),
+        from_ci_asset_name: row.get("from_ci_asset_name"),
+        from_ci_type_name: row.get("from_ci_type_name"),
+        to_ci_asset_id: row.get("to_ci_asset_id"),
+        to_ci_asset_name: row.get("to_ci_asset_name"),
+        to_ci_type_name: row.get("to_ci_type_name"),
+        attributes: row.get("attributes"),
+        created_by: row.get("created_by"),
+        created_by_name: row.get("created_by_name"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+    }
+}
+
+/// The `RelationshipFilter` conditions on `relationships r`
+fn push_relationship_conditions(
+    builder: &mut QueryBuilder<'_, Postgres>,
+    filter: &RelationshipFilter,
+    allowed_type_ids: Option<&[Uuid]>,
+) {
+    builder.push("r.deleted_at IS NULL");
+    if let Some(type_id) = filter.relationship_type_id {
+        builder.push(" AND r.relationship_type_id = ").push_bind(type_id);
+    }
+    if let Some(from_id) = filter.from_ci_asset_id {
+        builder.push(" AND r.from_ci_asset_id = ").push_bind(from_id);
+    }
+    if let Some(to_id) = filter.to_ci_asset_id {
+        builder.push(" AND r.to_ci_asset_id = ").push_bind(to_id);
+    }
+    if let Some(asset_id) = filter.ci_asset_id {
+        builder.push(" AND (r.from_ci_asset_id = ").push_bind(asset_id)
+            .push(" OR r.to_ci_asset_id = ").push_bind(asset_id).push(")");
+    }
+    if let Some(allowed_type_ids) = allowed_type_ids {
+        builder.push(" AND r.relationship_type_id = ANY(").push_bind(allowed_type_ids.to_vec()).push(")");
+    }
+}
@@ -0,0 +1,338 @@
+use crate::database::PgPool;
+use crate::models::{ApiKey, ApiKeyPermission, GrantPermissionRequest, ServiceAccount, User};
+use crate::utils::KeysetCursor;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::{postgres::PgRow, Row};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use super::rbac_repository::parse_column;
+use super::user_repository::UNUSABLE_PASSWORD_HASH;
+
+#[derive(Debug, Clone)]
+pub struct ServiceAccountRepository {
+    pool: PgPool,
+}
+
+impl ServiceAccountRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Create the service account and the users row that backs it
+    pub async fn create_service_account(
+        &self,
+        name: &str,
+        description: Option<&str>,
+        created_by: Uuid,
+    ) -> Result<ServiceAccount> {
+        let id = Uuid::new_v4();
+        let mut tx = self.pool.begin().await?;
+
+        // The placeholder password hash can never match, and login skips
+        // service accounts anyway
+        sqlx::query(
+            r#"
+            INSERT INTO users (id, email, password_hash, first_name, last_name, is_active, is_admin, is_service_account, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, 'Service Account', true, false, true, NOW(), NOW())
+            "#
+        )
+        .bind(id)
+        .bind(format!("{}@service-accounts.invalid", id))
+        .bind(UNUSABLE_PASSWORD_HASH)
+        .bind(name)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO service_accounts (id, name, description, created_by, created_at)
+            VALUES ($1, $2, $3, $4, NOW())
+            "#
+        )
+        .bind(id)
+        .bind(name)
+        .bind(description)
+        .bind(created_by)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        self.get_service_account(id)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Failed to retrieve created service account"))
+    }
+
+    pub async fn get_service_account(&self, id: Uuid) -> Result<Option<ServiceAccount>> {
+        let row = sqlx::query(
+            r#"
+            SELECT sa.id, sa.name, sa.description, u.is_active, sa.created_by, sa.created_at
+            FROM service_accounts sa
+            JOIN users u ON sa.id = u.id
+            WHERE sa.id = $1 AND u.deleted_at IS NULL
+            "#
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.as_ref().map(map_service_account))
+    }
+
+    pub async fn name_exists(&self, name: &str) -> Result<bool> {
+        let count: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM service_accounts WHERE LOWER(name) = LOWER($1)"
+        )
+        .bind(name)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(count > 0)
+    }
+
+    /// One page of service accounts, by name, starting after `cursor`
+    pub async fn list_service_accounts(&self, cursor: Option<&KeysetCursor>, limit: i64) -> Result<Vec<ServiceAccount>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT sa.id, sa.name, sa.description, u.is_active, sa.created_by, sa.created_at
+            FROM service_accounts sa
+            JOIN users u ON sa.id = u.id
+            WHERE u.deleted_at IS NULL
+              AND ($1::timestamptz IS NULL OR sa.name > $2 OR (sa.name = $2 AND (sa.created_at, sa.id) < ($1, $3)))
+            ORDER BY sa.name ASC, sa.created_at DESC, sa.id DESC
+            LIMIT $4
+            "#
+        )
+        .bind(cursor.map(|c| c.created_at))
+        .bind(cursor.and_then(KeysetCursor::text_key))
+        .bind(cursor.map(|c| c.id))
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(map_service_account).collect())
+    }
+
+    pub async fn count_service_accounts(&self) -> Result<i64> {
+        let count: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM service_accounts sa JOIN users u ON sa.id = u.id WHERE u.deleted_at IS NULL"
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(count)
+    }
+
+    /// Deactivate a service account and revoke all of its keys
+    pub async fn deactivate_service_account(&self, id: Uuid) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+
+        let result = sqlx::query(
+            "UPDATE users SET is_active = false, updated_at = NOW() WHERE id = $1 AND is_service_account = true"
+        )
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            "UPDATE api_keys SET revoked_at = NOW() WHERE service_account_id = $1 AND revoked_at IS NULL"
+        )
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn create_api_key(
+        &self,
+        service_account_id: Uuid,
+        name: &str,
+        key_prefix: &str,
+        key_hash: &str,
+        expires_at: Option<DateTime<Utc>>,
+        permissions: &[GrantPermissionRequest],
+        created_by: Uuid,
+    ) -> Result<Uuid> {
+        let id = Uuid::new_v4();
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO api_keys (id, service_account_id, name, key_prefix, key_hash, expires_at, created_by, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, NOW())
+            "#
+        )
+        .bind(id)
+        .bind(service_account_id)
+        .bind(name)
+        .bind(key_prefix)
+        .bind(key_hash)
+        .bind(expires_at)
+        .bind(created_by)
+        .execute(&mut *tx)
+        .await?;
+
+        for permission in permissions {
+            sqlx::query(
+                r#"
+                INSERT INTO api_key_permissions (id, api_key_id, resource_type, action, scope_id, created_at)
+                VALUES ($1, $2, $3, $4, $5, NOW())
+                ON CONFLICT DO NOTHING
+                "#
+            )
+            .bind(Uuid::new_v4())
+            .bind(id)
+            .bind(permission.resource_type.as_str())
+            .bind(permission.action.as_str())
+            .bind(permission.scope_id)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(id)
+    }
+
+    /// All keys of a service account, including revoked and expired ones
+    pub async fn list_api_keys(&self, service_account_id: Uuid) -> Result<Vec<ApiKey>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, service_account_id, name, key_prefix, expires_at, last_used_at, revoked_at, created_by, created_at
+            FROM api_keys
+            WHERE service_account_id = $1
+            ORDER BY created_at DESC
+            "#
+        )
+        .bind(service_account_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let key_ids: Vec<Uuid> = rows.iter().map(|r| r.get("id")).collect();
+
+        let permission_rows = sqlx::query(
+            r#"
+            SELECT api_key_id, resource_type, action, scope_id
+            FROM api_key_permissions
+            WHERE api_key_id = ANY($1)
+            ORDER BY resource_type, action
+            "#
+        )
+        .bind(&key_ids)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut permissions: HashMap<Uuid, Vec<ApiKeyPermission>> = HashMap::new();
+        for r in &permission_rows {
+            permissions
+                .entry(r.get("api_key_id"))
+                .or_default()
+                .push(ApiKeyPermission {
+                    resource_type: parse_column(r, "resource_type")?,
+                    action: parse_column(r, "action")?,
+                    scope_id: r.get("scope_id"),
+                });
+        }
+
+        Ok(rows
+            .iter()
+            .map(|r| {
+                let id: Uuid = r.get("id");
+                ApiKey {
+                    id,
+                    service_account_id: r.get("service_account_id"),
+                    name: r.get("name"),
+                    key_prefix: r.get("key_prefix"),
+                    permissions: permissions.remove(&id).unwrap_or_default(),
+                    expires_at: r.get("expires_at"),
+                    last_used_at: r.get("last_used_at"),
+                    revoked_at: r.get("revoked_at"),
+                    created_by: r.get("created_by"),
+                    created_at: r.get("created_at"),
+                }
+            })
+            .collect())
+    }
+
+    pub async fn revoke_api_key(&self, service_account_id: Uuid, api_key_id: Uuid) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            UPDATE api_keys
+            SET revoked_at = NOW()
+            WHERE id = $1 AND service_account_id = $2 AND revoked_at IS NULL
+            "#
+        )
+        .bind(api_key_id)
+        .bind(service_account_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Resolve a presented key to its id and service account user, if the key
+    /// is unrevoked, unexpired and its account is active
+    pub async fn find_usable_api_key(&self, key_hash: &str) -> Result<Option<(Uuid, User)>> {
+        let row = sqlx::query(
+            r#"
+            SELECT k.id as api_key_id,
+                   u.id, u.email, u.first_name, u.last_name, u.is_active, u.is_admin, u.created_at, u.updated_at
+            FROM api_keys k
+            JOIN users u ON k.service_account_id = u.id
+            WHERE k.key_hash = $1
+              AND k.revoked_at IS NULL
+              AND (k.expires_at IS NULL OR k.expires_at > NOW())
+              AND u.is_active = true
+              AND u.deleted_at IS NULL
+            "#
+        )
+        .bind(key_hash)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(|r: PgRow| {
+            let user = User {
+                id: r.get("id"),
+                email: r.get("email"),
+                first_name: r.get("first_name"),
+                last_name: r.get("last_name"),
+                is_active: r.get::<Option<bool>, _>("is_active").unwrap_or(true),
+                is_admin: r.get::<Option<bool>, _>("is_admin").unwrap_or(false),
+                created_at: r.get("created_at"),
+                updated_at: r.get("updated_at"),
+            };
+            (r.get("api_key_id"), user)
+        }))
+    }
+
+    /// Record that a key was used; at most once a minute to avoid a write per request
+    pub async fn touch_api_key(&self, api_key_id: Uuid) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE api_keys
+            SET last_used_at = NOW()
+            WHERE id = $1
+              AND (last_used_at IS NULL OR last_used_at < NOW() - INTERVAL '1 minute')
+            "#
+        )
+        .bind(api_key_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+}
+
+fn map_service_account(r: &PgRow) -> ServiceAccount {
+    ServiceAccount {
+        id: r.get("id"),
+        name: r.get("name"),
+        description: r.get("description"),
+        is_active: r.get::<Option<bool>, _>("is_active").unwrap_or(true),
+        created_by: r.get("created_by"),
+        created_at: r.get("created_at"),
+    }
+}
@@ -0,0 +1,449 @@
+use crate::database::PgPool;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::{PgConnection, Row};
+use uuid::Uuid;
+
+/// Outcome of presenting a refresh token
+#[derive(Debug)]
+pub enum RefreshTokenUse {
+    /// The token was valid and has been replaced by the new one
+    Rotated { user_id: Uuid, family_id: Uuid },
+    /// The token had already been rotated or revoked; its family is now
+    /// revoked, along with every access token issued to the user so far
+    Reused { user_id: Uuid, family_id: Uuid },
+    Expired,
+    NotFound,
+}
+
+/// What the database currently says about a presented access token
+#[derive(Debug, Clone, Copy)]
+pub struct AccessTokenStatus {
+    pub revoked: bool,
+    pub is_active: bool,
+    pub is_admin: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct SessionRepository {
+    pool: PgPool,
+}
+
+impl SessionRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create_refresh_token(
+        &self,
+        user_id: Uuid,
+        family_id: Uuid,
+        token_hash: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<Uuid> {
+        let id = Uuid::new_v4();
+
+        sqlx::query(
+            r#"
+            INSERT INTO refresh_tokens (id, user_id, family_id, token_hash, expires_at, created_at)
+            VALUES ($1, $2, $3, $4, $5, NOW())
+            "#
+        )
+        .bind(id)
+        .bind(user_id)
+        .bind(family_id)
+        .bind(token_hash)
+        .bind(expires_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(id)
+    }
+
+    /// Consume a refresh token and, if it is still valid, store its replacement.
+    ///
+    /// Runs in one transaction with the presented row locked, so two concurrent
+    /// refreshes with the same token can't both succeed.
+    pub async fn rotate_refresh_token(
+        &self,
+        token_hash: &str,
+        new_token_hash: &str,
+        new_expires_at: DateTime<Utc>,
+    ) -> Result<RefreshTokenUse> {
+        let mut tx = self.pool.begin().await?;
+
+        let row = sqlx::query(
+            r#"
+            SELECT id, user_id, family_id, expires_at, used_at, revoked_at
+            FROM refresh_tokens
+            WHERE token_hash = $1
+            FOR UPDATE
+            "#
+        )
+        .bind(token_hash)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let row = match row {
+            Some(row) => row,
+            None => return Ok(RefreshTokenUse::NotFound),
+        };
+
+        let id: Uuid = row.get("id");
+        let user_id: Uuid = row.get("user_id");
+        let family_id: Uuid = row.get("family_id");
+        let expires_at: DateTime<Utc> = row.get("expires_at");
+        let used_at: Option<DateTime<Utc>> = row.get("used_at");
+        let revoked_at: Option<DateTime<Utc>> = row.get("revoked_at");
+
+        if used_at.is_some() || revoked_at.is_some() {
+            // Someone is replaying an old token: assume it was stolen and
+            // kill every token from the same login. Access tokens don't name
+            // their login, so all of the user's go; their other logins get
+            // new ones on their next refresh.
+            sqlx::query(
+                "UPDATE refresh_tokens SET revoked_at = NOW() WHERE family_id = $1 AND revoked_at IS NULL"
+            )
+            .bind(family_id)
+            .execute(&mut *tx)
+            .await?;
+
+            revoke_issued_access_tokens(&mut tx, user_id).await?;
+
+            tx.commit().await?;
+            return Ok(RefreshTokenUse::Reused { user_id, family_id });
+        }
+
+        if expires_at <= Utc::now() {
+            return Ok(RefreshTokenUse::Expired);
+        }
+
+        let new_id = Uuid::new_v4();
+
+        sqlx::query(
+            r#"
+            INSERT INTO refresh_tokens (id, user_id, family_id, token_hash, expires_at, created_at)
+            VALUES ($1, $2, $3, $4, $5, NOW())
+            "#
+        )
+        .bind(new_id)
+        .bind(user_id)
+        .bind(family_id)
+        .bind(new_token_hash)
+        .bind(new_expires_at)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            "UPDATE refresh_tokens SET used_at = NOW(), replaced_by = $1 WHERE id = $2"
+        )
+        .bind(new_id)
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(RefreshTokenUse::Rotated { user_id, family_id })
+    }
+
+    /// Revoke the whole family of the given refresh token, if it belongs to the user
+    pub async fn revoke_refresh_token_family(&self, token_hash: &str, user_id: Uuid) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            UPDATE refresh_tokens
+            SET revoked_at = NOW()
+            WHERE revoked_at IS NULL
+              AND family_id = (
+                  SELECT family_id FROM refresh_tokens WHERE token_hash = $1 AND user_id = $2
+              )
+            "#
+        )
+        .bind(token_hash)
+        .bind(user_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn revoke_access_token(
+        &self,
+        jti: &str,
+        user_id: Uuid,
+        expires_at: DateTime<Utc>,
+        reason: &str,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO revoked_tokens (jti, user_id, expires_at, reason, revoked_at)
+            VALUES ($1, $2, $3, $4, NOW())
+            ON CONFLICT (jti) DO NOTHING
+            "#
+        )
+        .bind(jti)
+        .bind(user_id)
+        .bind(expires_at)
+        .bind(reason)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Revoke every refresh token and every access token issued so far for a user
+    pub async fn revoke_all_user_sessions(&self, user_id: Uuid) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        revoke_user_sessions(&mut tx, user_id).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Revocation state of an access token together with its user's current
+    /// status, or `None` if the user no longer exists
+    pub async fn access_token_status(&self, jti: &str, user_id: Uuid, issued_at: DateTime<Utc>) -> Result<Option<AccessTokenStatus>> {
+        let row = sqlx::query(
+            r#"
+            SELECT
+                u.is_active,
+                u.is_admin,
+                EXISTS (SELECT 1 FROM revoked_tokens WHERE jti = $1)
+                OR (u.sessions_revoked_at IS NOT NULL AND u.sessions_revoked_at >= $3) AS revoked
+            FROM users u
+            WHERE u.id = $2 AND u.deleted_at IS NULL
+            "#
+        )
+        .bind(jti)
+        .bind(user_id)
+        .bind(issued_at)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(|r| AccessTokenStatus {
+            revoked: r.get("revoked"),
+            is_active: r.get::<Option<bool>, _>("is_active").unwrap_or(true),
+            is_admin: r.get::<Option<bool>, _>("is_admin").unwrap_or(false),
+        }))
+    }
+
+    pub async fn create_password_reset_token(
+        &self,
+        user_id: Uuid,
+        token_hash: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO password_reset_tokens (id, user_id, token_hash, expires_at, created_at)
+            VALUES ($1, $2, $3, $4, NOW())
+            "#
+        )
+        .bind(Uuid::new_v4())
+        .bind(user_id)
+        .bind(token_hash)
+        .bind(expires_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Whether a reset token was issued to the user after `since`, to throttle reset emails
+    pub async fn password_reset_requested_since(&self, user_id: Uuid, since: DateTime<Utc>) -> Result<bool> {
+        let exists: bool = sqlx::query_scalar(
+            "SELECT EXISTS (SELECT 1 FROM password_reset_tokens WHERE user_id = $1 AND created_at > $2)"
+        )
+        .bind(user_id)
+        .bind(since)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(exists)
+    }
+
+    /// Redeem a reset token for a new password, returning its user if the
+    /// token was unused and unexpired.
+    ///
+    /// The token is spent, the password set and every session revoked in one
+    /// transaction, so concurrent redemptions of a token can't both succeed
+    /// and a failure leaves the token usable. Every other outstanding token
+    /// of the user is spent as well, so an older reset email can't be used
+    /// after a newer one.
+    pub async fn redeem_password_reset_token(&self, token_hash: &str, password_hash: &str) -> Result<Option<Uuid>> {
+        let mut tx = self.pool.begin().await?;
+
+        let user_id: Option<Uuid> = sqlx::query_scalar(
+            r#"
+            UPDATE password_reset_tokens
+            SET used_at = NOW()
+            WHERE token_hash = $1 AND used_at IS NULL AND expires_at > NOW()
+            RETURNING user_id
+            "#
+        )
+        .bind(token_hash)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(user_id) = user_id else {
+            return Ok(None);
+        };
+
+        sqlx::query(
+            "UPDATE password_reset_tokens SET used_at = NOW() WHERE user_id = $1 AND used_at IS NULL"
+        )
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query("UPDATE users SET password_hash = $1, updated_at = NOW() WHERE id = $2")
+            .bind(password_hash)
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        revoke_user_sessions(&mut tx, user_id).await?;
+
+        tx.commit().await?;
+        Ok(Some(user_id))
+    }
+
+    /// Delete refresh tokens, revocation entries and reset tokens that can no longer matter
+    pub async fn purge_expired(&self) -> Result<u64> {
+        let refresh = sqlx::query("DELETE FROM refresh_tokens WHERE expires_at < NOW()")
+            .execute(&self.pool)
+            .await?;
+
+        let revoked = sqlx::query("DELETE FROM revoked_tokens WHERE expires_at < NOW()")
+            .execute(&self.pool)
+            .await?;
+
+        let password_resets = sqlx::query("DELETE FROM password_reset_tokens WHERE expires_at < NOW()")
+            .execute(&self.pool)
+            .await?;
+
+        Ok(refresh.rows_affected() + revoked.rows_affected() + password_resets.rows_affected())
+    }
+}
+
+/// Revoke every access token issued to the user so far and all their refresh tokens
+async fn revoke_user_sessions(conn: &mut PgConnection, user_id: Uuid) -> Result<()> {
+    revoke_issued_access_tokens(conn, user_id).await?;
+
+    sqlx::query(
+        "UPDATE refresh_tokens SET revoked_at = NOW() WHERE user_id = $1 AND revoked_at IS NULL"
+    )
+    .bind(user_id)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Revoke every access token issued to the user so far
+async fn revoke_issued_access_tokens(conn: &mut PgConnection, user_id: Uuid) -> Result<()> {
+    // Taken from the clock that stamps tokens (`iat_us`), so a token issued
+    // any time before this, even within the same second, compares as revoked
+    sqlx::query("UPDATE users SET sessions_revoked_at = $2 WHERE id = $1")
+        .bind(user_id)
+        .bind(Utc::now())
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{test_pool, UserRepository};
+    use chrono::{Duration, Timelike};
+
+    /// A fresh user and a refresh token starting a new family for them
+    async fn user_with_refresh_token(pool: &PgPool) -> (SessionRepository, Uuid, Uuid) {
+        let user = UserRepository::new(pool.clone())
+            .create_user(&format!("{}@example.com", Uuid::new_v4()), "!", "Jane", "Doe", false)
+            .await
+            .unwrap();
+        let sessions = SessionRepository::new(pool.clone());
+        let family_id = Uuid::new_v4();
+        sessions
+            .create_refresh_token(user.id, family_id, &token(family_id, 1), Utc::now() + Duration::days(1))
+            .await
+            .unwrap();
+        (sessions, user.id, family_id)
+    }
+
+    /// The hash of the family's nth refresh token; hashes are unique across tests
+    fn token(family_id: Uuid, n: u32) -> String {
+        format!("{}-{}", family_id, n)
+    }
+
+    async fn rotate(sessions: &SessionRepository, from: &str, to: &str) -> RefreshTokenUse {
+        sessions
+            .rotate_refresh_token(from, to, Utc::now() + Duration::days(1))
+            .await
+            .unwrap()
+    }
+
+    async fn is_revoked(sessions: &SessionRepository, user_id: Uuid, issued_at: DateTime<Utc>) -> bool {
+        sessions
+            .access_token_status(&Uuid::new_v4().to_string(), user_id, issued_at)
+            .await
+            .unwrap()
+            .unwrap()
+            .revoked
+    }
+
+    #[tokio::test]
+    async fn rotation_replaces_the_token_within_its_family() {
+        let Some(pool) = test_pool().await else { return };
+        let (sessions, user_id, family_id) = user_with_refresh_token(&pool).await;
+
+        assert!(matches!(
+            rotate(&sessions, &token(family_id, 1), &token(family_id, 2)).await,
+            RefreshTokenUse::Rotated { user_id: u, family_id: f } if u == user_id && f == family_id
+        ));
+        assert!(matches!(
+            rotate(&sessions, &token(family_id, 2), &token(family_id, 3)).await,
+            RefreshTokenUse::Rotated { .. }
+        ));
+        assert!(matches!(rotate(&sessions, "unknown", &token(family_id, 4)).await, RefreshTokenUse::NotFound));
+    }
+
+    #[tokio::test]
+    async fn reuse_revokes_the_family_and_issued_access_tokens() {
+        let Some(pool) = test_pool().await else { return };
+        let (sessions, user_id, family_id) = user_with_refresh_token(&pool).await;
+        assert!(matches!(rotate(&sessions, &token(family_id, 1), &token(family_id, 2)).await, RefreshTokenUse::Rotated { .. }));
+
+        // An access token minted from the family just before the replay
+        let issued_before = Utc::now();
+        assert!(!is_revoked(&sessions, user_id, issued_before).await);
+
+        assert!(matches!(
+            rotate(&sessions, &token(family_id, 1), &token(family_id, 3)).await,
+            RefreshTokenUse::Reused { family_id: f, .. } if f == family_id
+        ));
+
+        // The family's current token is dead too, not just the replayed one
+        assert!(matches!(rotate(&sessions, &token(family_id, 2), &token(family_id, 4)).await, RefreshTokenUse::Reused { .. }));
+        assert!(is_revoked(&sessions, user_id, issued_before).await);
+        assert!(!is_revoked(&sessions, user_id, Utc::now()).await);
+    }
+
+    #[tokio::test]
+    async fn revocation_catches_tokens_from_the_same_second() {
+        let Some(pool) = test_pool().await else { return };
+        let (sessions, user_id, family_id) = user_with_refresh_token(&pool).await;
+
+        let issued_before = Utc::now();
+        sessions.revoke_all_user_sessions(user_id).await.unwrap();
+        let issued_after = Utc::now();
+
+        assert!(is_revoked(&sessions, user_id, issued_before).await);
+        // Tokens of pre-`iat_us` clients only carry the second
+        assert!(is_revoked(&sessions, user_id, issued_before.with_nanosecond(0).unwrap()).await);
+        assert!(!is_revoked(&sessions, user_id, issued_after).await);
+        assert!(matches!(rotate(&sessions, &token(family_id, 1), &token(family_id, 2)).await, RefreshTokenUse::Reused { .. }));
+    }
+}
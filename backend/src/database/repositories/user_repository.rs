@@ -0,0 +1,267 @@
+use crate::database::PgPool;
+use crate::models::{User, UpdateUserRequest, UserFilter};
+use crate::utils::KeysetCursor;
+use anyhow::Result;
+use sqlx::{postgres::PgRow, Row};
+use uuid::Uuid;
+
+/// Stored as the password hash of users who can't log in with a password
+/// (service accounts, single sign-on users); it never matches a bcrypt hash
+pub const UNUSABLE_PASSWORD_HASH: &str = "!";
+
+/// The user background jobs act as, so that what they change is attributed in
+/// the audit log. It can't log in and isn't listed with the other users.
+pub const SYSTEM_USER_ID: Uuid = Uuid::nil();
+
+/// The `UserFilter` conditions, bound as $1 to $3
+const USER_CONDITIONS: &str = r#"
+    deleted_at IS NULL
+      AND is_service_account = false
+      AND ($1::text IS NULL OR email ILIKE $1 OR first_name ILIKE $1 OR last_name ILIKE $1)
+      AND ($2::boolean IS NULL OR is_active = $2)
+      AND ($3::boolean IS NULL OR is_admin = $3)
+"#;
+
+fn user_search_pattern(filter: &UserFilter) -> Option<String> {
+    filter.search
+        .as_deref()
+        .map(str::trim)
+        .filter(|search| !search.is_empty())
+        .map(|search| format!("%{}%", search))
+}
+
+/// Outcome of an admin update to a user
+#[derive(Debug)]
+pub enum UserUpdate {
+    Updated(User),
+    NotFound,
+    /// Refused: the user is the last active admin and would lose that status
+    LastAdmin,
+}
+
+#[derive(Debug, Clone)]
+pub struct UserRepository {
+    pool: PgPool,
+}
+
+impl UserRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create_user(
+        &self,
+        email: &str,
+        password_hash: &str,
+        first_name: &str,
+        last_name: &str,
+        is_admin: bool,
+    ) -> Result<User> {
+        let row = sqlx::query(
+            r#"
+            INSERT INTO users (id, email, password_hash, first_name, last_name, is_active, is_admin, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, true, $6, NOW(), NOW())
+            RETURNING id, email, first_name, last_name, is_active, is_admin, created_at, updated_at
+            "#
+        )
+        .bind(Uuid::new_v4())
+        .bind(email)
+        .bind(password_hash)
+        .bind(first_name)
+        .bind(last_name)
+        .bind(is_admin)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(map_user(&row))
+    }
+
+    pub async fn get_user_by_id(&self, id: Uuid) -> Result<Option<User>> {
+        let row = sqlx::query(
+            r#"
+            SELECT id, email, first_name, last_name, is_active, is_admin, created_at, updated_at
+            FROM users
+            WHERE id = $1 AND deleted_at IS NULL
+            "#
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(|r: PgRow| map_user(&r)))
+    }
+
+    /// Look up a user by email together with their password hash, for login.
+    pub async fn get_user_with_password_by_email(&self, email: &str) -> Result<Option<(User, String)>> {
+        let row = sqlx::query(
+            r#"
+            SELECT id, email, password_hash, first_name, last_name, is_active, is_admin, created_at, updated_at
+            FROM users
+            WHERE LOWER(email) = LOWER($1) AND deleted_at IS NULL AND is_service_account = false
+            "#
+        )
+        .bind(email)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(|r: PgRow| (map_user(&r), r.get("password_hash"))))
+    }
+
+    pub async fn get_user_by_email(&self, email: &str) -> Result<Option<User>> {
+        let row = sqlx::query(
+            r#"
+            SELECT id, email, first_name, last_name, is_active, is_admin, created_at, updated_at
+            FROM users
+            WHERE LOWER(email) = LOWER($1) AND deleted_at IS NULL AND is_service_account = false
+            "#
+        )
+        .bind(email)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(|r: PgRow| map_user(&r)))
+    }
+
+    /// One page of human users matching the filter, by email, starting after `cursor`
+    pub async fn list_users(&self, filter: &UserFilter, cursor: Option<&KeysetCursor>, limit: i64) -> Result<Vec<User>> {
+        let rows = sqlx::query(&format!(
+            r#"
+            SELECT id, email, first_name, last_name, is_active, is_admin, created_at, updated_at
+            FROM users
+            WHERE {}
+              AND ($4::timestamptz IS NULL OR email > $5 OR (email = $5 AND (created_at, id) < ($4, $6)))
+            ORDER BY email ASC, created_at DESC, id DESC
+            LIMIT $7
+            "#,
+            USER_CONDITIONS
+        ))
+        .bind(user_search_pattern(filter))
+        .bind(filter.is_active)
+        .bind(filter.is_admin)
+        .bind(cursor.map(|c| c.created_at))
+        .bind(cursor.and_then(KeysetCursor::text_key))
+        .bind(cursor.map(|c| c.id))
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(map_user).collect())
+    }
+
+    /// Number of human users matching the filter
+    pub async fn count_users(&self, filter: &UserFilter) -> Result<i64> {
+        let total: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM users WHERE {}", USER_CONDITIONS))
+            .bind(user_search_pattern(filter))
+            .bind(filter.is_active)
+            .bind(filter.is_admin)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(total)
+    }
+
+    /// Apply an admin update, refusing to demote or deactivate the last active admin.
+    ///
+    /// All active admins are locked first, so two admins demoting each other
+    /// at the same time can't both succeed.
+    pub async fn update_user(&self, id: Uuid, request: &UpdateUserRequest) -> Result<UserUpdate> {
+        let mut tx = self.pool.begin().await?;
+
+        let admin_ids: Vec<Uuid> = sqlx::query_scalar(
+            r#"
+            SELECT id FROM users
+            WHERE is_admin = true AND is_active = true AND deleted_at IS NULL AND is_service_account = false
+            FOR UPDATE
+            "#
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let exists: Option<Uuid> = sqlx::query_scalar(
+            "SELECT id FROM users WHERE id = $1 AND deleted_at IS NULL AND is_service_account = false FOR UPDATE"
+        )
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        if exists.is_none() {
+            return Ok(UserUpdate::NotFound);
+        }
+
+        let loses_admin = request.is_active == Some(false) || request.is_admin == Some(false);
+        if loses_admin && admin_ids.contains(&id) && admin_ids.len() <= 1 {
+            return Ok(UserUpdate::LastAdmin);
+        }
+
+        let row = sqlx::query(
+            r#"
+            UPDATE users
+            SET email = COALESCE($2, email),
+                first_name = COALESCE($3, first_name),
+                last_name = COALESCE($4, last_name),
+                is_active = COALESCE($5, is_active),
+                is_admin = COALESCE($6, is_admin),
+                updated_at = NOW()
+            WHERE id = $1
+            RETURNING id, email, first_name, last_name, is_active, is_admin, created_at, updated_at
+            "#
+        )
+        .bind(id)
+        .bind(&request.email)
+        .bind(&request.first_name)
+        .bind(&request.last_name)
+        .bind(request.is_active)
+        .bind(request.is_admin)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(UserUpdate::Updated(map_user(&row)))
+    }
+
+    /// Current password hash, for re-verifying the password before changing it
+    pub async fn get_password_hash(&self, id: Uuid) -> Result<Option<String>> {
+        let password_hash = sqlx::query_scalar(
+            "SELECT password_hash FROM users WHERE id = $1 AND deleted_at IS NULL"
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(password_hash)
+    }
+
+    pub async fn update_password(&self, id: Uuid, password_hash: &str) -> Result<()> {
+        sqlx::query("UPDATE users SET password_hash = $1, updated_at = NOW() WHERE id = $2")
+            .bind(password_hash)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn email_exists(&self, email: &str) -> Result<bool> {
+        let count: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM users WHERE LOWER(email) = LOWER($1)"
+        )
+        .bind(email)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(count > 0)
+    }
+}
+
+pub(crate) fn map_user(r: &PgRow) -> User {
+    User {
+        id: r.get("id"),
+        email: r.get("email"),
+        first_name: r.get("first_name"),
+        last_name: r.get("last_name"),
+        is_active: r.get::<Option<bool>, _>("is_active").unwrap_or(true),
+        is_admin: r.get::<Option<bool>, _>("is_admin").unwrap_or(false),
+        created_at: r.get("created_at"),
+        updated_at: r.get("updated_at"),
+    }
+}
@@ -1,11 +1,10 @@
-use axum::{
-    http::StatusCode,
-    response::{IntoResponse, Response},
-    Json,
-};
+use axum::response::{IntoResponse, Response};
 use serde_json::json;
 use thiserror::Error;
 
+use super::field_error::{validator_field_errors, FieldError, FieldErrorCode};
+use super::response::{ErrorDetails, ErrorResponse};
+
 pub type AppResult<T> = Result<T, AppError>;
 
 #[derive(Error, Debug)]
@@ -25,6 +24,9 @@ pub enum AppError {
     #[error("Validation error: {0}")]
     Validation(String),
 
+    #[error("Validation error: {0}")]
+    InvalidFields(String, Vec<FieldError>),
+
     #[error("Serialization error: {0}")]
     Serialization(#[from] serde_json::Error),
 
@@ -37,6 +39,9 @@ pub enum AppError {
     #[error("Conflict: {0}")]
     Conflict(String),
 
+    #[error("Conflict: {0}")]
+    ConflictingFields(String, Vec<FieldError>),
+
     #[error("Bad request: {0}")]
     BadRequest(String),
 
@@ -65,6 +70,12 @@ pub enum AppError {
     Generic(#[from] anyhow::Error),
 }
 
+impl From<validator::ValidationErrors> for AppError {
+    fn from(errors: validator::ValidationErrors) -> Self {
+        Self::InvalidFields("Request validation failed".to_string(), validator_field_errors(&errors))
+    }
+}
+
 impl AppError {
     pub fn internal<T: Into<String>>(message: T) -> Self {
         Self::Internal(message.into())
@@ -97,85 +108,208 @@ impl AppError {
     pub fn validation<T: Into<String>>(message: T) -> Self {
         Self::Validation(message.into())
     }
-}
 
-impl IntoResponse for AppError {
-    fn into_response(self) -> Response {
-        let (status, error_message) = match self {
-            AppError::Database(ref err) => {
-                tracing::error!("Database error: {:?}", err);
-                (StatusCode::INTERNAL_SERVER_ERROR, "Internal database error")
+    pub fn invalid_fields<T: Into<String>>(message: T, errors: Vec<FieldError>) -> Self {
+        Self::InvalidFields(message.into(), errors)
+    }
+
+    /// A conflict with existing data that can be pinned on specific fields
+    pub fn conflicting_fields<T: Into<String>>(message: T, errors: Vec<FieldError>) -> Self {
+        Self::ConflictingFields(message.into(), errors)
+    }
+
+    /// A single invalid field, with the error's message as the overall message
+    pub fn invalid_field(error: FieldError) -> Self {
+        Self::InvalidFields(error.message.clone(), vec![error])
+    }
+
+    /// Place the error under `parent`, e.g. "/operations/3": field paths are
+    /// prefixed, and errors about the whole request become errors at `parent`.
+    /// Permission and server errors are left as they are.
+    pub fn nested_in(self, parent: &str) -> Self {
+        let at_parent = |code, message: &str| vec![FieldError::new(parent, code, message)];
+        match self {
+            AppError::InvalidFields(message, errors) => {
+                AppError::InvalidFields(message, errors.into_iter().map(|error| error.nested_in(parent)).collect())
             }
-            AppError::Neo4j(ref err) => {
-                tracing::error!("Neo4j error: {:?}", err);
-                (StatusCode::INTERNAL_SERVER_ERROR, "Internal graph database error")
+            AppError::ConflictingFields(message, errors) => {
+                AppError::ConflictingFields(message, errors.into_iter().map(|error| error.nested_in(parent)).collect())
+            }
+            AppError::Validation(message) | AppError::BadRequest(message) => {
+                let errors = at_parent(FieldErrorCode::RuleViolation, &message);
+                AppError::InvalidFields(message, errors)
             }
-            AppError::Authentication(ref message) => {
-                (StatusCode::UNAUTHORIZED, message.as_str())
+            AppError::NotFound(message) => {
+                let errors = at_parent(FieldErrorCode::InvalidReference, &message);
+                AppError::InvalidFields(message, errors)
+            }
+            AppError::Conflict(message) => {
+                let errors = at_parent(FieldErrorCode::RuleViolation, &message);
+                AppError::ConflictingFields(message, errors)
+            }
+            other => other,
+        }
+    }
+
+    /// One error standing for several, such as those of the operations in a
+    /// bulk request: all their field errors, as a conflict if every one is.
+    /// An error that isn't about fields is returned instead, the first one.
+    pub fn combined<T: Into<String>>(message: T, errors: Vec<AppError>) -> Self {
+        let mut field_errors = Vec::new();
+        let mut all_conflicts = true;
+        for error in errors {
+            match error {
+                AppError::InvalidFields(_, errors) => {
+                    all_conflicts = false;
+                    field_errors.extend(errors);
+                }
+                AppError::ConflictingFields(_, errors) => field_errors.extend(errors),
+                other => return other,
+            }
+        }
+
+        if all_conflicts {
+            AppError::ConflictingFields(message.into(), field_errors)
+        } else {
+            AppError::InvalidFields(message.into(), field_errors)
+        }
+    }
+
+    /// The `error` object of the response for this error, for reporting it
+    /// inside another response, such as for one item of a bulk request.
+    /// Every variant has a fixed code; server-side failures are logged and
+    /// reported without their internals.
+    pub fn details(&self) -> ErrorDetails {
+        let (code, message, errors) = match self {
+            AppError::Authentication(message) => ("unauthorized", message.clone(), None),
+            AppError::Authorization(message) => ("forbidden", message.clone(), None),
+            // Validation failures always list field errors; a plain message
+            // is a rule that isn't tied to one field
+            AppError::Validation(message) => {
+                let errors = vec![FieldError::new("", FieldErrorCode::RuleViolation, message.clone())];
+                ("validation_failed", message.clone(), Some(errors))
             }
-            AppError::Authorization(ref message) => {
-                (StatusCode::FORBIDDEN, message.as_str())
+            AppError::InvalidFields(message, errors) => ("validation_failed", message.clone(), Some(errors.clone())),
+            AppError::NotFound(message) => ("not_found", message.clone(), None),
+            AppError::Conflict(message) => ("conflict", message.clone(), None),
+            AppError::ConflictingFields(message, errors) => ("conflict", message.clone(), Some(errors.clone())),
+            AppError::BadRequest(message) => ("bad_request", message.clone(), None),
+            AppError::Uuid(err) => {
+                tracing::warn!("UUID error: {:?}", err);
+                ("bad_request", "Invalid ID format".to_string(), None)
             }
-            AppError::Validation(ref message) => {
-                (StatusCode::BAD_REQUEST, message.as_str())
+            AppError::Csv(err) => {
+                tracing::warn!("CSV error: {:?}", err);
+                ("bad_request", "CSV processing error".to_string(), None)
             }
-            AppError::NotFound(ref message) => {
-                (StatusCode::NOT_FOUND, message.as_str())
+            AppError::Jwt(err) => {
+                tracing::warn!("JWT error: {:?}", err);
+                ("unauthorized", "Invalid authentication token".to_string(), None)
             }
-            AppError::Conflict(ref message) => {
-                (StatusCode::CONFLICT, message.as_str())
+            AppError::Database(err) => {
+                tracing::error!("Database error: {:?}", err);
+                ("internal_error", "Internal database error".to_string(), None)
             }
-            AppError::BadRequest(ref message) => {
-                (StatusCode::BAD_REQUEST, message.as_str())
+            AppError::Neo4j(err) => {
+                tracing::error!("Neo4j error: {:?}", err);
+                ("internal_error", "Internal graph database error".to_string(), None)
             }
-            AppError::Internal(ref message) => {
+            AppError::Internal(message) => {
                 tracing::error!("Internal error: {}", message);
-                (StatusCode::INTERNAL_SERVER_ERROR, "Internal server error")
+                ("internal_error", "Internal server error".to_string(), None)
             }
-            AppError::Configuration(ref message) => {
+            AppError::Configuration(message) => {
                 tracing::error!("Configuration error: {}", message);
-                (StatusCode::INTERNAL_SERVER_ERROR, "Server configuration error")
-            }
-            AppError::Jwt(ref err) => {
-                tracing::warn!("JWT error: {:?}", err);
-                (StatusCode::UNAUTHORIZED, "Invalid authentication token")
+                ("internal_error", "Server configuration error".to_string(), None)
             }
-            AppError::Bcrypt(ref err) => {
+            AppError::Bcrypt(err) => {
                 tracing::error!("Bcrypt error: {:?}", err);
-                (StatusCode::INTERNAL_SERVER_ERROR, "Password processing error")
+                ("internal_error", "Password processing error".to_string(), None)
             }
-            AppError::Io(ref err) => {
+            AppError::Io(err) => {
                 tracing::error!("IO error: {:?}", err);
-                (StatusCode::INTERNAL_SERVER_ERROR, "File system error")
+                ("internal_error", "File system error".to_string(), None)
             }
-            AppError::Csv(ref err) => {
-                tracing::error!("CSV error: {:?}", err);
-                (StatusCode::BAD_REQUEST, "CSV processing error")
-            }
-            AppError::Cron(ref err) => {
+            AppError::Cron(err) => {
                 tracing::error!("Cron error: {:?}", err);
-                (StatusCode::INTERNAL_SERVER_ERROR, "Scheduled job error")
+                ("internal_error", "Scheduled job error".to_string(), None)
             }
-            AppError::Serialization(ref err) => {
+            AppError::Serialization(err) => {
                 tracing::error!("Serialization error: {:?}", err);
-                (StatusCode::INTERNAL_SERVER_ERROR, "Data serialization error")
-            }
-            AppError::Uuid(ref err) => {
-                tracing::error!("UUID error: {:?}", err);
-                (StatusCode::BAD_REQUEST, "Invalid ID format")
+                ("internal_error", "Data serialization error".to_string(), None)
             }
-            AppError::Generic(ref err) => {
+            AppError::Generic(err) => {
                 tracing::error!("Generic error: {:?}", err);
-                (StatusCode::INTERNAL_SERVER_ERROR, "Internal server error")
+                ("internal_error", "Internal server error".to_string(), None)
             }
         };
 
-        let body = Json(json!({
-            "error": true,
-            "message": error_message,
-            "timestamp": chrono::Utc::now().to_rfc3339()
-        }));
+        ErrorDetails {
+            code: code.to_string(),
+            message,
+            details: errors.map(|errors| json!({ "errors": errors })),
+        }
+    }
+}
+
+/// Every error is answered with an `ErrorResponse`, whose code also decides the status
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        ErrorResponse::from(self.details()).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::StatusCode;
+    use serde_json::Value;
+
+    async fn respond(error: AppError) -> (StatusCode, Value) {
+        let response = error.into_response();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn every_error_has_the_same_shape() {
+        let cases = [
+            (AppError::bad_request("Bad cursor"), StatusCode::BAD_REQUEST, "bad_request"),
+            (AppError::validation("Too many items"), StatusCode::BAD_REQUEST, "validation_failed"),
+            (AppError::not_found("CI asset not found"), StatusCode::NOT_FOUND, "not_found"),
+            (AppError::conflict("Name taken"), StatusCode::CONFLICT, "conflict"),
+            (AppError::authentication("Invalid credentials"), StatusCode::UNAUTHORIZED, "unauthorized"),
+            (AppError::authorization("Not allowed"), StatusCode::FORBIDDEN, "forbidden"),
+            (AppError::internal("connection reset"), StatusCode::INTERNAL_SERVER_ERROR, "internal_error"),
+        ];
 
-        (status, body).into_response()
+        for (error, expected_status, expected_code) in cases {
+            let (status, body) = respond(error).await;
+            assert_eq!(status, expected_status);
+            assert_eq!(body["success"], false);
+            assert_eq!(body["error"]["code"], expected_code);
+            assert!(body["error"]["message"].is_string());
+            assert!(body.get("timestamp").is_some());
+        }
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn server_errors_hide_their_internals() {
+        let (_, body) = respond(AppError::internal("password=hunter2 rejected")).await;
+        assert_eq!(body["error"]["message"], "Internal server error");
+    }
+
+    #[tokio::test]
+    async fn field_errors_are_listed_in_details() {
+        let error = AppError::conflicting_fields(
+            "Attribute values are already in use",
+            vec![FieldError::new("/attributes/serial", FieldErrorCode::DuplicateValue, "taken")],
+        );
+
+        let (status, body) = respond(error).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(body["error"]["details"]["errors"][0]["path"], "/attributes/serial");
+        assert_eq!(body["error"]["details"]["errors"][0]["code"], "duplicate_value");
+    }
+}
@@ -0,0 +1,247 @@
+use jsonschema::error::{TypeKind, ValidationErrorKind};
+use serde::Serialize;
+use serde_json::{json, Value};
+use validator::{ValidationErrors, ValidationErrorsKind};
+
+/// Why a field failed validation. The serialized names are part of the API:
+/// clients branch on them, so existing codes must not be renamed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldErrorCode {
+    /// A required field is missing
+    Required,
+    /// The value has the wrong JSON type
+    InvalidType,
+    /// A string isn't in the expected format (email, URL, date-time, ...)
+    InvalidFormat,
+    /// A string or list is too short or too long
+    InvalidLength,
+    /// A number is below the minimum or above the maximum
+    OutOfRange,
+    /// A string doesn't match the required pattern
+    PatternMismatch,
+    /// The value isn't one of the allowed values
+    NotAllowed,
+    /// A list contains the same item twice
+    DuplicateItems,
+    /// An object has a field the schema doesn't allow
+    UnexpectedField,
+    /// The field is derived by the server and can't be set by clients
+    ReadOnly,
+    /// Another record already holds this value for a unique field
+    DuplicateValue,
+    /// The field should identify an existing record of a given type, and doesn't
+    InvalidReference,
+    /// The value fails some other schema or format check
+    InvalidValue,
+    /// The request breaks a rule of the service, such as a state transition
+    RuleViolation,
+}
+
+/// One field of a request that failed validation
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldError {
+    pub path: String, // JSON pointer to the field, e.g. "/attributes/port"; "" for the whole request
+    pub code: FieldErrorCode,
+    pub message: String,
+    pub expected: Option<Value>, // What would have been accepted, when that can be stated
+}
+
+impl FieldError {
+    pub fn new<P: Into<String>, M: Into<String>>(path: P, code: FieldErrorCode, message: M) -> Self {
+        Self {
+            path: path.into(),
+            code,
+            message: message.into(),
+            expected: None,
+        }
+    }
+
+    pub fn with_expected(mut self, expected: Value) -> Self {
+        self.expected = Some(expected);
+        self
+    }
+
+    /// Prefix the path, e.g. to place attribute errors under "/attributes"
+    pub fn nested_in(mut self, parent: &str) -> Self {
+        self.path = format!("{}{}", parent, self.path);
+        self
+    }
+}
+
+impl From<jsonschema::ValidationError<'_>> for FieldError {
+    fn from(error: jsonschema::ValidationError<'_>) -> Self {
+        let path = error.instance_path.to_string();
+        let message = error.to_string();
+
+        let (code, expected) = match &error.kind {
+            ValidationErrorKind::Required { property } => {
+                let name = property.as_str().unwrap_or_default();
+                return FieldError::new(format!("{}/{}", path, name), FieldErrorCode::Required, message);
+            }
+            ValidationErrorKind::Type { kind } => (FieldErrorCode::InvalidType, Some(match kind {
+                TypeKind::Single(primitive) => json!(primitive.to_string()),
+                TypeKind::Multiple(primitives) => json!(primitives.into_iter().map(|p| p.to_string()).collect::<Vec<_>>()),
+            })),
+            ValidationErrorKind::Format { format } => (FieldErrorCode::InvalidFormat, Some(json!(format))),
+            ValidationErrorKind::MinLength { limit } | ValidationErrorKind::MinItems { limit } => {
+                (FieldErrorCode::InvalidLength, Some(json!({ "min": limit })))
+            }
+            ValidationErrorKind::MaxLength { limit } | ValidationErrorKind::MaxItems { limit } => {
+                (FieldErrorCode::InvalidLength, Some(json!({ "max": limit })))
+            }
+            ValidationErrorKind::Minimum { limit } => (FieldErrorCode::OutOfRange, Some(json!({ "min": limit }))),
+            ValidationErrorKind::Maximum { limit } => (FieldErrorCode::OutOfRange, Some(json!({ "max": limit }))),
+            ValidationErrorKind::ExclusiveMinimum { limit } => (FieldErrorCode::OutOfRange, Some(json!({ "exclusive_min": limit }))),
+            ValidationErrorKind::ExclusiveMaximum { limit } => (FieldErrorCode::OutOfRange, Some(json!({ "exclusive_max": limit }))),
+            ValidationErrorKind::Pattern { pattern } => (FieldErrorCode::PatternMismatch, Some(json!(pattern))),
+            ValidationErrorKind::Enum { options } => (FieldErrorCode::NotAllowed, Some(options.clone())),
+            ValidationErrorKind::Constant { expected_value } => (FieldErrorCode::NotAllowed, Some(json!([expected_value]))),
+            ValidationErrorKind::UniqueItems => (FieldErrorCode::DuplicateItems, None),
+            ValidationErrorKind::AdditionalProperties { unexpected }
+            | ValidationErrorKind::UnevaluatedProperties { unexpected } => {
+                (FieldErrorCode::UnexpectedField, Some(json!({ "unexpected": unexpected })))
+            }
+            _ => (FieldErrorCode::InvalidValue, None),
+        };
+
+        FieldError { path, code, message, expected }
+    }
+}
+
+/// Flatten `validator` errors, including those of nested structs and lists
+pub fn validator_field_errors(errors: &ValidationErrors) -> Vec<FieldError> {
+    let mut field_errors = Vec::new();
+    collect_validator_errors("", errors, &mut field_errors);
+    field_errors.sort_by(|a, b| a.path.cmp(&b.path));
+    field_errors
+}
+
+fn collect_validator_errors(prefix: &str, errors: &ValidationErrors, out: &mut Vec<FieldError>) {
+    for (field, kind) in errors.errors() {
+        let path = format!("{}/{}", prefix, field);
+        match kind {
+            ValidationErrorsKind::Field(errors) => {
+                out.extend(errors.iter().map(|error| validator_field_error(&path, error)));
+            }
+            ValidationErrorsKind::Struct(errors) => collect_validator_errors(&path, errors, out),
+            ValidationErrorsKind::List(items) => {
+                for (index, errors) in items {
+                    collect_validator_errors(&format!("{}/{}", path, index), errors, out);
+                }
+            }
+        }
+    }
+}
+
+fn validator_field_error(path: &str, error: &validator::ValidationError) -> FieldError {
+    // Bounds given to the validator, e.g. `length(min = 1, max = 255)`
+    let bounds = || {
+        let bounds: serde_json::Map<String, Value> = error.params.iter()
+            .filter(|(name, _)| matches!(name.as_ref(), "min" | "max" | "equal"))
+            .map(|(name, value)| (name.to_string(), value.clone()))
+            .collect();
+        (!bounds.is_empty()).then(|| Value::Object(bounds))
+    };
+
+    let (code, expected) = match error.code.as_ref() {
+        "required" => (FieldErrorCode::Required, None),
+        "length" => (FieldErrorCode::InvalidLength, bounds()),
+        "range" => (FieldErrorCode::OutOfRange, bounds()),
+        "email" | "url" => (FieldErrorCode::InvalidFormat, Some(json!(error.code))),
+        "regex" => (FieldErrorCode::PatternMismatch, None),
+        _ => (FieldErrorCode::InvalidValue, None),
+    };
+
+    let message = match &error.message {
+        Some(message) => message.to_string(),
+        None => match &expected {
+            Some(expected) => format!("{} failed the {} check (expected {})", path, error.code, expected),
+            None => format!("{} failed the {} check", path, error.code),
+        },
+    };
+
+    FieldError { path: path.to_string(), code, message, expected }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonschema::JSONSchema;
+    use validator::Validate;
+
+    #[derive(Validate)]
+    struct Port {
+        #[validate(range(min = 1, max = 65535))]
+        number: i64,
+    }
+
+    #[derive(Validate)]
+    struct Host {
+        #[validate(length(min = 1, max = 10))]
+        name: String,
+        #[validate(email)]
+        contact: String,
+        #[validate(nested)]
+        ports: Vec<Port>,
+    }
+
+    fn schema_errors(schema: Value, instance: Value) -> Vec<FieldError> {
+        let schema = JSONSchema::compile(&schema).unwrap();
+        let mut errors: Vec<FieldError> = match schema.validate(&instance) {
+            Ok(()) => Vec::new(),
+            Err(errors) => errors.map(FieldError::from).collect(),
+        };
+        errors.sort_by(|a, b| a.path.cmp(&b.path));
+        errors
+    }
+
+    #[test]
+    fn validator_errors_keep_their_path_and_code() {
+        let host = Host {
+            name: String::new(),
+            contact: "not-an-email".to_string(),
+            ports: vec![Port { number: 22 }, Port { number: 0 }],
+        };
+
+        let errors = validator_field_errors(&host.validate().unwrap_err());
+        let found: Vec<(&str, FieldErrorCode)> = errors.iter().map(|error| (error.path.as_str(), error.code)).collect();
+        assert_eq!(found, vec![
+            ("/contact", FieldErrorCode::InvalidFormat),
+            ("/name", FieldErrorCode::InvalidLength),
+            ("/ports/1/number", FieldErrorCode::OutOfRange),
+        ]);
+        assert_eq!(errors[1].expected, Some(json!({ "min": 1, "max": 10 })));
+        assert_eq!(errors[2].expected, Some(json!({ "min": 1, "max": 65535 })));
+    }
+
+    #[test]
+    fn schema_errors_point_at_the_offending_value() {
+        let schema = json!({
+            "type": "object",
+            "required": ["hostname"],
+            "properties": {
+                "hostname": { "type": "string" },
+                "ports": { "type": "array", "items": { "type": "integer", "minimum": 1 } },
+            },
+        });
+
+        let errors = schema_errors(schema.clone(), json!({ "ports": [22, 0] }));
+        let found: Vec<(&str, FieldErrorCode)> = errors.iter().map(|error| (error.path.as_str(), error.code)).collect();
+        assert_eq!(found, vec![
+            ("/hostname", FieldErrorCode::Required),
+            ("/ports/1", FieldErrorCode::OutOfRange),
+        ]);
+        assert_eq!(errors[1].expected, Some(json!({ "min": 1 })));
+
+        let errors = schema_errors(schema, json!({ "hostname": 42 }));
+        assert_eq!((errors[0].path.as_str(), errors[0].code), ("/hostname", FieldErrorCode::InvalidType));
+        assert_eq!(errors[0].expected, Some(json!("string")));
+    }
+
+    #[test]
+    fn nesting_prefixes_the_path() {
+        let error = FieldError::new("/port", FieldErrorCode::OutOfRange, "Port is out of range").nested_in("/attributes");
+        assert_eq!(error.path, "/attributes/port");
+    }
+}
@@ -1,5 +1,7 @@
 pub mod app_error;
+pub mod field_error;
 pub mod response;
 
 pub use app_error::{AppError, AppResult};
-pub use response::{ApiResponse, PaginatedResponse, ErrorResponse};
\ No newline at end of file
+pub use field_error::{FieldError, FieldErrorCode, validator_field_errors};
+pub use response::{ApiResponse, PaginatedResponse, ErrorResponse, ErrorDetails};
\ No newline at end of file
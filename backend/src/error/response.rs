@@ -4,7 +4,9 @@ use axum::{
     Json,
 };
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{json, Value};
+
+use super::field_error::FieldError;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ApiResponse<T> {
@@ -76,6 +78,7 @@ where
     }
 }
 
+/// Envelope of every list endpoint
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PaginatedResponse<T> {
     pub success: bool,
@@ -87,33 +90,28 @@ pub struct PaginatedResponse<T> {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PaginationInfo {
-    pub page: u64,
-    pub per_page: u64,
-    pub total: u64,
-    pub total_pages: u64,
-    pub has_next: bool,
-    pub has_prev: bool,
+    pub limit: i64,
+    pub next_cursor: Option<String>, // Pass back as `cursor` for the next page; null on the last one
+    pub has_more: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<i64>, // Every match across pages, when asked for with `include_total=true`
 }
 
 impl<T> PaginatedResponse<T> {
     pub fn new(
         data: Vec<T>,
-        page: u64,
-        per_page: u64,
-        total: u64,
+        limit: i64,
+        next_cursor: Option<String>,
+        total: Option<i64>,
     ) -> Self {
-        let total_pages = (total + per_page - 1) / per_page;
-
         Self {
             success: true,
             data,
             pagination: PaginationInfo {
-                page,
-                per_page,
+                limit,
+                has_more: next_cursor.is_some(),
+                next_cursor,
                 total,
-                total_pages,
-                has_next: page < total_pages,
-                has_prev: page > 1,
             },
             message: None,
             timestamp: chrono::Utc::now().to_rfc3339(),
@@ -122,26 +120,14 @@ impl<T> PaginatedResponse<T> {
 
     pub fn with_message(
         data: Vec<T>,
-        page: u64,
-        per_page: u64,
-        total: u64,
+        limit: i64,
+        next_cursor: Option<String>,
+        total: Option<i64>,
         message: String,
     ) -> Self {
-        let total_pages = (total + per_page - 1) / per_page;
-
         Self {
-            success: true,
-            data,
-            pagination: PaginationInfo {
-                page,
-                per_page,
-                total,
-                total_pages,
-                has_next: page < total_pages,
-                has_prev: page > 1,
-            },
             message: Some(message),
-            timestamp: chrono::Utc::now().to_rfc3339(),
+            ..Self::new(data, limit, next_cursor, total)
         }
     }
 }
@@ -193,6 +179,22 @@ impl ErrorResponse {
             timestamp: chrono::Utc::now().to_rfc3339(),
         }
     }
+
+    /// A `validation_failed` error whose details list every offending field
+    /// as `{path, code, message, expected}`
+    pub fn validation_failed(message: String, errors: Vec<FieldError>) -> Self {
+        Self::with_details("validation_failed".to_string(), message, json!({ "errors": errors }))
+    }
+}
+
+impl From<ErrorDetails> for ErrorResponse {
+    fn from(error: ErrorDetails) -> Self {
+        Self {
+            success: false,
+            error,
+            timestamp: chrono::Utc::now().to_rfc3339(),
+        }
+    }
 }
 
 impl IntoResponse for ErrorResponse {
@@ -1,13 +1,84 @@
-use axum::{response::Json, extract::{Query, State}};
-use crate::error::AppResult;
+use axum::{
+    extract::{Query, State},
+    http::header,
+    response::{IntoResponse, Json},
+};
+use serde_json::{json, Value};
+
+use crate::{
+    error::{AppError, AppResult, PaginatedResponse},
+    models::{AuditLogFilter, AuditLogEntry, AuditChainVerifyQuery, AuditExportQuery},
+    middleware::AuthContext,
+    services::AuditService,
+    utils::PageRequest,
+};
 
 pub async fn get_audit_logs(
-    State(_app_state): State<crate::AppState>,
-    Query(_params): Query<serde_json::Value>,
-) -> AppResult<Json<serde_json::Value>> {
-    // TODO: Implement audit log retrieval
-    Ok(Json(serde_json::json!({
-        "data": [],
-        "message": "Audit logs retrieved successfully"
+    State(app_state): State<crate::AppState>,
+    auth_context: AuthContext,
+    Query(filter): Query<AuditLogFilter>,
+    Query(page): Query<PageRequest>,
+) -> AppResult<PaginatedResponse<AuditLogEntry>> {
+    let entries = audit_service(&app_state)
+        .list_audit_logs(&filter, &page, &auth_context)
+        .await?;
+
+    Ok(entries.into_response("Audit logs retrieved successfully"))
+}
+
+fn audit_service(app_state: &crate::AppState) -> AuditService {
+    AuditService::new(
+        app_state.database.audit_repository.clone(),
+        app_state.database.rbac_repository.clone(),
+    )
+}
+
+// Chain verification and export are admin-only; routes are mounted behind admin_middleware
+
+pub async fn verify_audit_chain(
+    State(app_state): State<crate::AppState>,
+    Query(query): Query<AuditChainVerifyQuery>,
+) -> AppResult<Json<Value>> {
+    let report = audit_service(&app_state).verify_chain(query.from_sequence).await?;
+
+    if let Some(ref broken_link) = report.broken_link {
+        tracing::error!(
+            "Audit chain verification failed at sequence {}: {}",
+            broken_link.chain_seq, broken_link.reason
+        );
+    }
+
+    Ok(Json(json!({
+        "data": report,
+        "message": if report.valid { "Audit chain verified" } else { "Audit chain is broken" }
     })))
-}
\ No newline at end of file
+}
+
+/// Download a signed export of the audit log for a time range
+pub async fn export_audit_log(
+    State(app_state): State<crate::AppState>,
+    auth_context: AuthContext,
+    Query(query): Query<AuditExportQuery>,
+) -> AppResult<impl IntoResponse> {
+    let signer = app_state
+        .audit_signer
+        .as_ref()
+        .ok_or_else(|| AppError::not_found("Signed audit export is not configured"))?;
+
+    let export = audit_service(&app_state)
+        .export(&query, signer, Some(auth_context.user_id))
+        .await?;
+
+    tracing::info!(
+        "User {} exported {} audit entries ({} to {})",
+        auth_context.user_id, export.manifest.entry_count, query.from, query.to
+    );
+
+    let filename = format!(
+        "attachment; filename=\"audit-log-{}-{}.json\"",
+        query.from.format("%Y%m%dT%H%M%SZ"),
+        query.to.format("%Y%m%dT%H%M%SZ")
+    );
+
+    Ok(([(header::CONTENT_DISPOSITION, filename)], Json(export)))
+}
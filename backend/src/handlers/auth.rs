@@ -1,48 +1,113 @@
 use axum::{
-    extract::{State, Request},
-    http::StatusCode,
+    extract::{Path, Query, State, Request},
+    http::{header, HeaderMap},
     response::Json,
 };
 use serde_json::json;
+use uuid::Uuid;
 
 use crate::{
     error::{AppError, AppResult},
-    models::{CreateUserRequest, LoginRequest, LoginResponse, UserResponse},
+    models::{
+        CreateUserRequest, LoginRequest, LoginResponse, UserResponse, RefreshTokenRequest, LogoutRequest,
+        OidcAuthorizeResponse, OidcCallbackQuery, ChangePasswordRequest, ForgotPasswordRequest, ResetPasswordRequest,
+    },
     middleware::{AuthContext, extract_auth_context},
-    utils::{hash_password, verify_password, create_jwt, validate_password_strength},
+    services::{AuthService, OidcService, PasswordService, LOGIN_STATE_COOKIE},
 };
 
+fn auth_service(app_state: &crate::AppState) -> AuthService {
+    AuthService::new(
+        app_state.database.user_repository.clone(),
+        app_state.database.session_repository.clone(),
+        app_state.database.rbac_repository.clone(),
+        app_state.jwt_keys.clone(),
+        &app_state.config.auth,
+    )
+}
+
+fn password_service(app_state: &crate::AppState) -> PasswordService {
+    PasswordService::new(
+        app_state.database.user_repository.clone(),
+        app_state.database.session_repository.clone(),
+        auth_service(app_state),
+        app_state.notifier.clone(),
+        &app_state.config.auth,
+    )
+}
+
+fn oidc_service(app_state: &crate::AppState) -> AppResult<OidcService> {
+    let oidc_client = app_state.oidc.clone()
+        .ok_or_else(|| AppError::not_found("Single sign-on is not configured"))?;
+
+    Ok(OidcService::new(
+        oidc_client,
+        app_state.database.oidc_repository.clone(),
+        app_state.database.user_repository.clone(),
+        app_state.database.rbac_repository.clone(),
+        auth_service(app_state),
+    ))
+}
+
 pub async fn login(
     State(app_state): State<crate::AppState>,
     Json(request): Json<LoginRequest>,
 ) -> AppResult<Json<LoginResponse>> {
-    // This would typically query the database for the user
-    // For now, we'll return a mock response
-
-    // TODO: Implement actual database query and password verification
-    let user_id = uuid::Uuid::new_v4();
-    let token = create_jwt(
-        user_id,
-        &request.email,
-        "Test",
-        "User",
-        false,
-        &app_state.config.auth.jwt_secret,
-        app_state.config.auth.jwt_expiration_hours,
-    )?;
-
-    let login_response = LoginResponse {
-        user: UserResponse {
-            id: user_id,
-            email: request.email,
-            first_name: "Test".to_string(),
-            last_name: "User".to_string(),
-            is_admin: false,
-            created_at: chrono::Utc::now(),
-        },
-        token,
-        expires_in: app_state.config.auth.jwt_expiration_hours * 3600,
-    };
+    let (login_response, user) = auth_service(&app_state)
+        .authenticate_user(request)
+        .await?;
+
+    tracing::info!("User {} logged in", user.id);
+
+    Ok(Json(login_response))
+}
+
+/// Start a single sign-on login; the client sends the browser to `authorization_url`.
+/// The login state cookie set here must come back with the callback.
+pub async fn oidc_authorize(
+    State(app_state): State<crate::AppState>,
+) -> AppResult<([(header::HeaderName, String); 1], Json<OidcAuthorizeResponse>)> {
+    let oidc_service = oidc_service(&app_state)?;
+    let response = oidc_service.begin_login().await?;
+    let cookie = oidc_service.login_state_cookie(&response.state);
+
+    Ok(([(header::SET_COOKIE, cookie)], Json(response)))
+}
+
+/// Redirect target of the identity provider; answers like a password login
+pub async fn oidc_callback(
+    State(app_state): State<crate::AppState>,
+    headers: HeaderMap,
+    Query(query): Query<OidcCallbackQuery>,
+) -> AppResult<([(header::HeaderName, String); 1], Json<LoginResponse>)> {
+    let oidc_service = oidc_service(&app_state)?;
+    let (login_response, user) = oidc_service
+        .complete_login(query, cookie_value(&headers, LOGIN_STATE_COOKIE))
+        .await?;
+
+    tracing::info!("User {} logged in via single sign-on", user.id);
+
+    Ok(([(header::SET_COOKIE, oidc_service.cleared_login_state_cookie())], Json(login_response)))
+}
+
+fn cookie_value<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|cookies| cookies.split(';'))
+        .filter_map(|cookie| cookie.trim().split_once('='))
+        .find(|(cookie_name, _)| *cookie_name == name)
+        .map(|(_, value)| value)
+}
+
+pub async fn refresh_token(
+    State(app_state): State<crate::AppState>,
+    Json(request): Json<RefreshTokenRequest>,
+) -> AppResult<Json<LoginResponse>> {
+    let login_response = auth_service(&app_state)
+        .refresh_session(request)
+        .await?;
 
     Ok(Json(login_response))
 }
@@ -51,47 +116,92 @@ pub async fn register(
     State(app_state): State<crate::AppState>,
     Json(request): Json<CreateUserRequest>,
 ) -> AppResult<Json<serde_json::Value>> {
-    // Validate password strength
-    validate_password_strength(&request.password)?;
-
-    // Hash password
-    let password_hash = hash_password(&request.password)?;
-
-    // TODO: Save user to database
-    let user_id = uuid::Uuid::new_v4();
+    let user = auth_service(&app_state).create_user(request).await?;
 
     let response = json!({
         "message": "User created successfully",
-        "user_id": user_id.to_string()
+        "user_id": user.id.to_string(),
+        "user": UserResponse::from(user)
     });
 
     Ok(Json(response))
 }
 
 pub async fn get_current_user(
+    State(app_state): State<crate::AppState>,
     request: Request,
 ) -> AppResult<Json<UserResponse>> {
     let auth_context = extract_auth_context(&request)?;
 
-    // TODO: Fetch user from database
-    let user_response = UserResponse {
-        id: auth_context.user_id,
-        email: auth_context.email,
-        first_name: auth_context.first_name,
-        last_name: auth_context.last_name,
-        is_admin: auth_context.is_admin,
-        created_at: chrono::Utc::now(),
-    };
-
-    Ok(Json(user_response))
+    let user = auth_service(&app_state).get_user(auth_context.user_id).await?;
+
+    Ok(Json(UserResponse::from(user)))
 }
 
-pub async fn logout() -> AppResult<Json<serde_json::Value>> {
-    // In a real implementation, you might want to invalidate the token
-    // For JWT, this is typically done client-side by deleting the token
+pub async fn logout(
+    State(app_state): State<crate::AppState>,
+    auth_context: AuthContext,
+    request: Option<Json<LogoutRequest>>,
+) -> AppResult<Json<serde_json::Value>> {
+    let request = request.map(|Json(request)| request).unwrap_or_default();
+
+    auth_service(&app_state)
+        .logout(&auth_context, request.refresh_token.as_deref())
+        .await?;
+
     let response = json!({
         "message": "Logged out successfully"
     });
 
     Ok(Json(response))
-}
\ No newline at end of file
+}
+
+pub async fn forgot_password(
+    State(app_state): State<crate::AppState>,
+    Json(request): Json<ForgotPasswordRequest>,
+) -> AppResult<Json<serde_json::Value>> {
+    password_service(&app_state).request_reset(request).await?;
+
+    // Same answer whether or not the email is registered
+    Ok(Json(json!({
+        "message": "If an account exists for this email, a password reset link has been sent"
+    })))
+}
+
+pub async fn reset_password(
+    State(app_state): State<crate::AppState>,
+    Json(request): Json<ResetPasswordRequest>,
+) -> AppResult<Json<serde_json::Value>> {
+    password_service(&app_state).confirm_reset(request).await?;
+
+    Ok(Json(json!({
+        "message": "Password reset successfully. Please log in with your new password"
+    })))
+}
+
+/// Change the caller's password; other sessions are logged out and new tokens returned
+pub async fn change_password(
+    State(app_state): State<crate::AppState>,
+    auth_context: AuthContext,
+    Json(request): Json<ChangePasswordRequest>,
+) -> AppResult<Json<LoginResponse>> {
+    let login_response = password_service(&app_state)
+        .change_password(&auth_context, request)
+        .await?;
+
+    Ok(Json(login_response))
+}
+
+/// Admin-only: log a user out of every session immediately
+pub async fn revoke_user_sessions(
+    State(app_state): State<crate::AppState>,
+    Path(user_id): Path<Uuid>,
+) -> AppResult<Json<serde_json::Value>> {
+    auth_service(&app_state).revoke_user_sessions(user_id).await?;
+
+    tracing::info!("Revoked all sessions for user {}", user_id);
+
+    Ok(Json(json!({
+        "message": "User sessions revoked successfully"
+    })))
+}
@@ -1,35 +1,42 @@
 use axum::{
     extract::{Path, Query, State},
-    http::StatusCode,
-    response::Json,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Json, Response},
 };
 use serde_json::{json, Value};
-use std::collections::HashMap;
 use uuid::Uuid;
 
 use crate::{
-    database::{CIRepository, GraphRepository},
-    error::{AppError, AppResult},
-    models::{CreateCITypeRequest, UpdateCITypeRequest, CreateCIAssetRequest, CIAssetFilter},
-    services::CIService,
+    database::GraphRepository,
+    error::{AppError, AppResult, ErrorResponse, PaginatedResponse},
+    models::{CIType, CreateCITypeRequest, UpdateCITypeRequest, CreateCIAssetRequest, CIAssetFilter, CIAssetAsOfQuery, RestoreVersionRequest, CIAssetOperation, BulkRequest, UpsertOutcome, DeletedCIAssetFilter, DeletedCIAsset},
+    services::{CIService, CITypeUpdate},
     middleware::AuthContext,
+    utils::PageRequest,
 };
 
+fn ci_service(app_state: &crate::AppState) -> CIService {
+    CIService::new(
+        app_state.database.ci_repository.clone(),
+        app_state.database.relationship_repository.clone(),
+        GraphRepository::new(app_state.neo4j_pool.clone()),
+        app_state.database.rbac_repository.clone(),
+        app_state.database.audit_repository.clone(),
+        app_state.database.idempotency_repository.clone(),
+        app_state.schema_cache.clone(),
+    )
+}
+
 pub async fn create_ci_type(
     State(app_state): State<crate::AppState>,
-    auth_context: crate::middleware::AuthContext,
+    auth_context: AuthContext,
     Json(request_data): Json<CreateCITypeRequest>,
 ) -> AppResult<Json<Value>> {
 
-    // Initialize repositories
-    let ci_repository = CIRepository::new(app_state.pg_pool.clone());
-    let graph_repository = GraphRepository::new(app_state.neo4j_pool.clone());
-
-    // Initialize service
-    let ci_service = CIService::new(ci_repository, graph_repository);
+    let ci_service = ci_service(&app_state);
 
     // Create CI type
-    let ci_type = ci_service.create_ci_type(request_data, auth_context.user_id).await?;
+    let ci_type = ci_service.create_ci_type(request_data, &auth_context).await?;
 
     Ok(Json(json!({
         "data": ci_type,
@@ -39,36 +46,14 @@ pub async fn create_ci_type(
 
 pub async fn list_ci_types(
     State(app_state): State<crate::AppState>,
-    Query(params): Query<HashMap<String, String>>,
-) -> AppResult<Json<Value>> {
-    // Parse query parameters
-    let limit: Option<i64> = params.get("limit")
-        .and_then(|s| s.parse().ok());
-    let offset: Option<i64> = params.get("offset")
-        .and_then(|s| s.parse().ok());
-
-    // Initialize repositories
-    let ci_repository = CIRepository::new(app_state.pg_pool.clone());
-    let graph_repository = GraphRepository::new(app_state.neo4j_pool.clone());
-
-    // Initialize service
-    let ci_service = CIService::new(ci_repository, graph_repository);
-
-    // List CI types
-    let ci_types = ci_service.list_ci_types(limit, offset).await?;
+    auth_context: AuthContext,
+    Query(page): Query<PageRequest>,
+) -> AppResult<PaginatedResponse<CIType>> {
+    let ci_service = ci_service(&app_state);
 
-    // Get total count
-    let total = ci_service.count_ci_types().await?;
+    let ci_types = ci_service.list_ci_types(&page, &auth_context).await?;
 
-    Ok(Json(json!({
-        "data": ci_types,
-        "pagination": {
-            "total": total,
-            "limit": limit.unwrap_or(50),
-            "offset": offset.unwrap_or(0)
-        },
-        "message": "CI types retrieved successfully"
-    })))
+    Ok(ci_types.into_response("CI types retrieved successfully"))
 }
 
 pub async fn create_ci_asset(
@@ -77,15 +62,10 @@ pub async fn create_ci_asset(
     Json(request_data): Json<CreateCIAssetRequest>,
 ) -> AppResult<Json<Value>> {
 
-    // Initialize repositories
-    let ci_repository = CIRepository::new(app_state.pg_pool.clone());
-    let graph_repository = GraphRepository::new(app_state.neo4j_pool.clone());
-
-    // Initialize service
-    let ci_service = CIService::new(ci_repository, graph_repository);
+    let ci_service = ci_service(&app_state);
 
     // Create CI asset
-    let asset_id = ci_service.create_ci_asset(request_data, auth_context.user_id).await?;
+    let asset_id = ci_service.create_ci_asset(request_data, &auth_context).await?;
 
     Ok(Json(json!({
         "data": {
@@ -97,50 +77,38 @@ pub async fn create_ci_asset(
 
 pub async fn list_ci_assets(
     State(app_state): State<crate::AppState>,
-    Query(params): Query<HashMap<String, String>>,
-) -> AppResult<Json<Value>> {
-    // Parse query parameters
-    let ci_type_id: Option<Uuid> = params.get("ci_type_id")
-        .and_then(|s| s.parse().ok());
-    let limit: Option<i64> = params.get("limit")
-        .and_then(|s| s.parse().ok());
-    let offset: Option<i64> = params.get("offset")
-        .and_then(|s| s.parse().ok());
-
-    // Initialize repositories
-    let ci_repository = CIRepository::new(app_state.pg_pool.clone());
-    let graph_repository = GraphRepository::new(app_state.neo4j_pool.clone());
-
-    // Initialize service
-    let ci_service = CIService::new(ci_repository, graph_repository);
+    auth_context: AuthContext,
+    Query(filter): Query<CIAssetFilter>,
+    Query(page): Query<PageRequest>,
+) -> AppResult<PaginatedResponse<(Uuid, String, Value, Uuid, Value)>> {
+    let ci_service = ci_service(&app_state);
 
-    // List CI assets
-    let ci_assets = ci_service.list_ci_assets(ci_type_id, limit, offset).await?;
+    // List CI assets matching the type, attribute filter and sort
+    let ci_assets = ci_service.list_ci_assets_filtered(&filter, &page, &auth_context).await?;
 
-    Ok(Json(json!({
-        "data": ci_assets,
-        "filters": {
-            "ci_type_id": ci_type_id.map(|id| id.to_string()),
-            "limit": limit.unwrap_or(50),
-            "offset": offset.unwrap_or(0)
-        },
-        "message": "CI assets retrieved successfully"
-    })))
+    Ok(ci_assets.into_response("CI assets retrieved successfully"))
 }
 
 pub async fn get_ci_asset(
     State(app_state): State<crate::AppState>,
+    auth_context: AuthContext,
     Path(id): Path<Uuid>,
+    Query(query): Query<CIAssetAsOfQuery>,
 ) -> AppResult<Json<Value>> {
-    // Initialize repositories
-    let ci_repository = CIRepository::new(app_state.pg_pool.clone());
-    let graph_repository = GraphRepository::new(app_state.neo4j_pool.clone());
+    let ci_service = ci_service(&app_state);
+
+    // Rebuild the asset from its audit history instead of reading the current row
+    if let Some(as_of) = query.as_of {
+        let ci_asset = ci_service.get_ci_asset_as_of(id, as_of, &auth_context).await?;
 
-    // Initialize service
-    let ci_service = CIService::new(ci_repository, graph_repository);
+        return Ok(Json(json!({
+            "data": ci_asset,
+            "message": "CI asset reconstructed successfully"
+        })));
+    }
 
     // Get CI asset
-    let ci_asset = ci_service.get_ci_asset(id).await?
+    let ci_asset = ci_service.get_ci_asset(id, &auth_context).await?
         .ok_or_else(|| AppError::not_found(&format!("CI asset with id '{}' not found", id)))?;
 
     Ok(Json(json!({
@@ -149,12 +117,28 @@ pub async fn get_ci_asset(
             "name": ci_asset.1,
             "attributes": ci_asset.2,
             "ci_type_id": ci_asset.3.to_string(),
-            "created_by": ci_asset.4.to_string()
+            "created_by": ci_asset.4.to_string(),
+            "computed_attributes": ci_asset.5
         },
         "message": "CI asset retrieved successfully"
     })))
 }
 
+pub async fn get_ci_asset_history(
+    State(app_state): State<crate::AppState>,
+    auth_context: AuthContext,
+    Path(id): Path<Uuid>,
+) -> AppResult<Json<Value>> {
+    let ci_service = ci_service(&app_state);
+
+    let versions = ci_service.get_ci_asset_history(id, &auth_context).await?;
+
+    Ok(Json(json!({
+        "data": versions,
+        "message": "CI asset history retrieved successfully"
+    })))
+}
+
 pub async fn update_ci_asset(
     State(app_state): State<crate::AppState>,
     auth_context: AuthContext,
@@ -162,12 +146,7 @@ pub async fn update_ci_asset(
     Json(request_data): Json<Value>,
 ) -> AppResult<Json<Value>> {
 
-    // Initialize repositories
-    let ci_repository = CIRepository::new(app_state.pg_pool.clone());
-    let graph_repository = GraphRepository::new(app_state.neo4j_pool.clone());
-
-    // Initialize service
-    let ci_service = CIService::new(ci_repository, graph_repository);
+    let ci_service = ci_service(&app_state);
 
     // Extract update parameters
     let name = request_data.get("name")
@@ -175,7 +154,7 @@ pub async fn update_ci_asset(
     let attributes = request_data.get("attributes");
 
     // Update CI asset
-    let updated = ci_service.update_ci_asset(id, name, attributes, auth_context.user_id).await?;
+    let updated = ci_service.update_ci_asset(id, name, attributes, &auth_context).await?;
 
     if !updated {
         return Err(AppError::not_found(&format!("CI asset with id '{}' not found", id)));
@@ -192,36 +171,118 @@ pub async fn delete_ci_asset(
     Path(id): Path<Uuid>,
 ) -> AppResult<Json<Value>> {
 
-    // Initialize repositories
-    let ci_repository = CIRepository::new(app_state.pg_pool.clone());
-    let graph_repository = GraphRepository::new(app_state.neo4j_pool.clone());
-
-    // Initialize service
-    let ci_service = CIService::new(ci_repository, graph_repository);
+    let ci_service = ci_service(&app_state);
 
     // Delete CI asset
-    ci_service.delete_ci_asset(id, auth_context.user_id).await?;
+    ci_service.delete_ci_asset(id, &auth_context).await?;
 
     Ok(Json(json!({
         "message": "CI asset deleted successfully"
     })))
 }
 
+/// Apply a batch of asset operations, atomically or best-effort
+pub async fn bulk_ci_assets(
+    State(app_state): State<crate::AppState>,
+    auth_context: AuthContext,
+    Json(request_data): Json<BulkRequest<CIAssetOperation>>,
+) -> AppResult<Json<Value>> {
+    let ci_service = ci_service(&app_state);
+
+    let result = ci_service.bulk_ci_assets(request_data, &auth_context).await?;
+    let message = format!("Applied {} of {} CI asset operations", result.applied, result.items.len());
+
+    Ok(Json(json!({
+        "data": result,
+        "message": message
+    })))
+}
+
+/// Create or update the asset identified by its CI type's natural key
+pub async fn upsert_ci_asset(
+    State(app_state): State<crate::AppState>,
+    auth_context: AuthContext,
+    headers: HeaderMap,
+    Json(request_data): Json<CreateCIAssetRequest>,
+) -> AppResult<Json<Value>> {
+    let ci_service = ci_service(&app_state);
+
+    let idempotency_key = match headers.get("Idempotency-Key") {
+        Some(value) => Some(value.to_str().map_err(|_| AppError::bad_request("Idempotency-Key must be visible ASCII"))?),
+        None => None,
+    };
+
+    let upserted = ci_service.upsert_ci_asset(request_data, idempotency_key, &auth_context).await?;
+    let message = match upserted.outcome {
+        UpsertOutcome::Created => "CI asset created successfully",
+        UpsertOutcome::Updated => "CI asset updated successfully",
+        UpsertOutcome::Unchanged => "CI asset already up to date",
+    };
+
+    Ok(Json(json!({
+        "data": upserted,
+        "message": message
+    })))
+}
+
+/// Restore an asset to an earlier version, undeleting it if needed
+pub async fn restore_ci_asset(
+    State(app_state): State<crate::AppState>,
+    auth_context: AuthContext,
+    Path(id): Path<Uuid>,
+    Json(request_data): Json<RestoreVersionRequest>,
+) -> AppResult<Json<Value>> {
+    let ci_service = ci_service(&app_state);
+
+    let restored = ci_service.restore_ci_asset(id, &request_data, &auth_context).await?;
+
+    Ok(Json(json!({
+        "data": restored,
+        "message": "CI asset restored successfully"
+    })))
+}
+
+/// Soft-deleted assets, most recently deleted first
+pub async fn list_deleted_ci_assets(
+    State(app_state): State<crate::AppState>,
+    auth_context: AuthContext,
+    Query(filter): Query<DeletedCIAssetFilter>,
+    Query(page): Query<PageRequest>,
+) -> AppResult<PaginatedResponse<DeletedCIAsset>> {
+    let ci_service = ci_service(&app_state);
+
+    let ci_assets = ci_service.list_deleted_ci_assets(&filter, &page, &auth_context).await?;
+
+    Ok(ci_assets.into_response("Deleted CI assets retrieved successfully"))
+}
+
+/// Take an asset out of the trash, along with the relationships deleted with it
+pub async fn restore_deleted_ci_asset(
+    State(app_state): State<crate::AppState>,
+    auth_context: AuthContext,
+    Path(id): Path<Uuid>,
+) -> AppResult<Json<Value>> {
+    let ci_service = ci_service(&app_state);
+
+    let restored = ci_service.restore_deleted_ci_asset(id, &auth_context).await?;
+
+    Ok(Json(json!({
+        "data": restored,
+        "message": "CI asset restored from the trash"
+    })))
+}
+
 // Additional CI Type handlers for complete CRUD
 
 pub async fn get_ci_type(
     State(app_state): State<crate::AppState>,
+    auth_context: AuthContext,
     Path(id): Path<Uuid>,
 ) -> AppResult<Json<Value>> {
-    // Initialize repositories
-    let ci_repository = CIRepository::new(app_state.pg_pool.clone());
-    let graph_repository = GraphRepository::new(app_state.neo4j_pool.clone());
-
-    // Initialize service
-    let ci_service = CIService::new(ci_repository, graph_repository);
+    let ci_service = ci_service(&app_state);
 
     // Get CI type
-    let ci_type = ci_service.get_ci_type_by_id(id).await?
+    let ci_type = ci_service.get_ci_type_by_id(id, &auth_context).await?
         .ok_or_else(|| AppError::not_found(&format!("CI type with id '{}' not found", id)))?;
 
     Ok(Json(json!({
@@ -232,38 +293,73 @@ pub async fn get_ci_type(
 
 pub async fn update_ci_type(
     State(app_state): State<crate::AppState>,
+    auth_context: AuthContext,
     Path(id): Path<Uuid>,
     Json(request_data): Json<UpdateCITypeRequest>,
+) -> AppResult<Response> {
+    let ci_service = ci_service(&app_state);
+
+    // Update CI type
+    let response = match ci_service.update_ci_type(id, request_data, &auth_context).await? {
+        CITypeUpdate::Applied { ci_type, schema_change } => Json(json!({
+            "data": ci_type,
+            "schema_change": schema_change,
+            "message": "CI type updated successfully"
+        })).into_response(),
+        CITypeUpdate::DryRun(report) => Json(json!({
+            "data": null,
+            "schema_change": report,
+            "message": "Dry run only; nothing was changed"
+        })).into_response(),
+        CITypeUpdate::Rejected(report) => ErrorResponse::with_details(
+            "conflict".to_string(),
+            format!("{} existing assets do not conform to the new schema", report.violation_count),
+            json!(report),
+        ).into_response(),
+    };
+
+    Ok(response)
+}
+
+pub async fn list_ci_type_schema_versions(
+    State(app_state): State<crate::AppState>,
+    auth_context: AuthContext,
+    Path(id): Path<Uuid>,
 ) -> AppResult<Json<Value>> {
-    // Initialize repositories
-    let ci_repository = CIRepository::new(app_state.pg_pool.clone());
-    let graph_repository = GraphRepository::new(app_state.neo4j_pool.clone());
+    let ci_service = ci_service(&app_state);
 
-    // Initialize service
-    let ci_service = CIService::new(ci_repository, graph_repository);
+    let versions = ci_service.list_ci_type_schema_versions(id, &auth_context).await?;
 
-    // Update CI type
-    let ci_type = ci_service.update_ci_type(id, request_data).await?;
+    Ok(Json(json!({
+        "data": versions,
+        "message": "CI type schema versions retrieved successfully"
+    })))
+}
+
+pub async fn get_ci_type_effective_schema(
+    State(app_state): State<crate::AppState>,
+    auth_context: AuthContext,
+    Path(id): Path<Uuid>,
+) -> AppResult<Json<Value>> {
+    let ci_service = ci_service(&app_state);
+
+    let effective_schema = ci_service.get_effective_schema(id, &auth_context).await?;
 
     Ok(Json(json!({
-        "data": ci_type,
-        "message": "CI type updated successfully"
+        "data": effective_schema,
+        "message": "CI type effective schema retrieved successfully"
     })))
 }
 
 pub async fn delete_ci_type(
     State(app_state): State<crate::AppState>,
+    auth_context: AuthContext,
     Path(id): Path<Uuid>,
 ) -> AppResult<Json<Value>> {
-    // Initialize repositories
-    let ci_repository = CIRepository::new(app_state.pg_pool.clone());
-    let graph_repository = GraphRepository::new(app_state.neo4j_pool.clone());
-
-    // Initialize service
-    let ci_service = CIService::new(ci_repository, graph_repository);
+    let ci_service = ci_service(&app_state);
 
     // Delete CI type
-    ci_service.delete_ci_type(id).await?;
+    ci_service.delete_ci_type(id, &auth_context).await?;
 
     Ok(Json(json!({
         "message": "CI type deleted successfully"
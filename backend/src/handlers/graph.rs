@@ -1,11 +1,15 @@
 use axum::{response::Json, extract::{Path, Query, State}, http::StatusCode};
+use crate::database::repositories::GraphScope;
 use crate::error::ApiResponse;
 use crate::middleware::AuthContext;
+use crate::models::ResourceType;
+use crate::services::PermissionSet;
 use serde::Deserialize;
 
 #[derive(Debug, Deserialize)]
 pub struct GraphDataQuery {
     pub ci_type: Option<String>,
+    pub include_subtypes: Option<bool>, // Also show assets of the type's subtypes
     pub limit: Option<u32>,
 }
 
@@ -21,24 +25,62 @@ pub struct GraphData {
     pub edges: Vec<crate::database::repositories::GraphRelationship>,
 }
 
+/// The nodes and edges the caller may read: assets of the CI types and
+/// relationships of the relationship types they hold read grants on
+async fn readable_graph_scope(app_state: &crate::AppState, auth: &AuthContext) -> Result<GraphScope, StatusCode> {
+    match PermissionSet::load(&app_state.database.rbac_repository, auth).await {
+        Ok(permissions) => Ok(graph_scope(&permissions)),
+        Err(e) => {
+            tracing::error!("Failed to load permissions: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+fn graph_scope(permissions: &PermissionSet) -> GraphScope {
+    GraphScope {
+        ci_type_ids: permissions.readable_scopes(ResourceType::CiAsset),
+        relationship_type_ids: permissions.readable_scopes(ResourceType::Relationship),
+    }
+}
+
 /// Get graph data with optional filtering
 pub async fn get_graph_data(
     State(app_state): State<crate::AppState>,
-    _auth: AuthContext,
+    auth: AuthContext,
     Query(params): Query<GraphDataQuery>,
 ) -> Result<Json<ApiResponse<GraphData>>, StatusCode> {
     let graph_repo = &app_state.database.graph_repository;
+    let scope = readable_graph_scope(&app_state, &auth).await?;
+
+    let ci_types = match params.ci_type {
+        Some(ci_type) if params.include_subtypes.unwrap_or(false) => {
+            match ci_type_with_subtypes(&app_state, ci_type).await {
+                Ok(ci_types) => Some(ci_types),
+                Err(e) => {
+                    tracing::error!("Failed to resolve CI subtypes: {}", e);
+                    return Err(StatusCode::INTERNAL_SERVER_ERROR);
+                }
+            }
+        }
+        Some(ci_type) => Some(vec![ci_type]),
+        None => None,
+    };
 
     match graph_repo.get_full_graph(
         params.limit,
-        params.ci_type.as_deref(),
+        ci_types.as_deref(),
+        &scope,
     ).await {
-        Ok((nodes, edges)) => Ok(Json(ApiResponse {
-            success: true,
-            data: Some(GraphData { nodes, edges }),
-            message: Some(format!("Retrieved {} nodes and {} relationships", nodes.len(), edges.len())),
-            timestamp: chrono::Utc::now().to_rfc3339(),
-        })),
+        Ok((nodes, edges)) => {
+            let (nodes, edges) = scope.restrict(nodes, edges);
+            Ok(Json(ApiResponse {
+                success: true,
+                message: Some(format!("Retrieved {} nodes and {} relationships", nodes.len(), edges.len())),
+                data: Some(GraphData { nodes, edges }),
+                timestamp: chrono::Utc::now().to_rfc3339(),
+            }))
+        }
         Err(e) => {
             tracing::error!("Failed to get graph data: {}", e);
             Ok(Json(ApiResponse {
@@ -51,25 +93,43 @@ pub async fn get_graph_data(
     }
 }
 
+/// The named CI type followed by the names of all its subtypes
+async fn ci_type_with_subtypes(app_state: &crate::AppState, ci_type: String) -> anyhow::Result<Vec<String>> {
+    let ci_repository = &app_state.database.ci_repository;
+
+    let Some(root) = ci_repository.get_ci_type_by_name(&ci_type).await? else {
+        return Ok(vec![ci_type]);
+    };
+
+    let descendants = ci_repository.get_ci_type_descendants(root.id).await?;
+    Ok(std::iter::once(root.name)
+        .chain(descendants.into_iter().map(|ci_type| ci_type.name))
+        .collect())
+}
+
 /// Get neighbors of a specific node
 pub async fn get_node_neighbors(
     State(app_state): State<crate::AppState>,
-    _auth: AuthContext,
+    auth: AuthContext,
     Path(id): Path<uuid::Uuid>,
 ) -> Result<Json<ApiResponse<Vec<serde_json::Value>>>, StatusCode> {
     let graph_repo = &app_state.database.graph_repository;
+    let scope = readable_graph_scope(&app_state, &auth).await?;
 
-    match graph_repo.get_related_nodes(id).await {
+    match graph_repo.get_related_nodes(id, &scope).await {
         Ok(neighbors) => {
-            let neighbor_data: Vec<serde_json::Value> = neighbors.into_iter().map(|(id, name, ci_type, rel_type, attributes)| {
-                serde_json::json!({
-                    "id": id,
-                    "name": name,
-                    "ci_type": ci_type,
-                    "relationship_type": rel_type,
-                    "attributes": attributes,
+            let neighbor_data: Vec<serde_json::Value> = neighbors.into_iter()
+                .filter(|neighbor| scope.allows_node(neighbor.node.ci_type_id) && scope.allows_edge(neighbor.relationship_type_id))
+                .map(|neighbor| {
+                    serde_json::json!({
+                        "id": neighbor.node.id,
+                        "name": neighbor.node.name,
+                        "ci_type": neighbor.node.ci_type,
+                        "relationship_type": neighbor.relationship_type,
+                        "attributes": neighbor.node.attributes,
+                    })
                 })
-            }).collect();
+                .collect();
 
             Ok(Json(ApiResponse {
                 success: true,
@@ -93,15 +153,16 @@ pub async fn get_node_neighbors(
 /// Search for nodes by name or type
 pub async fn search_nodes(
     State(app_state): State<crate::AppState>,
-    _auth: AuthContext,
+    auth: AuthContext,
     Query(params): Query<SearchQuery>,
 ) -> Result<Json<ApiResponse<Vec<crate::database::repositories::GraphNode>>>, StatusCode> {
     let graph_repo = &app_state.database.graph_repository;
+    let scope = readable_graph_scope(&app_state, &auth).await?;
 
-    match graph_repo.search_assets(&params.q, params.limit).await {
+    match graph_repo.search_assets(&params.q, params.limit, &scope).await {
         Ok(results) => Ok(Json(ApiResponse {
             success: true,
-            data: Some(results),
+            data: Some(results.into_iter().filter(|node| scope.allows_node(node.ci_type_id)).collect()),
             message: None,
             timestamp: chrono::Utc::now().to_rfc3339(),
         })),
@@ -115,4 +176,81 @@ pub async fn search_nodes(
             }))
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::repositories::{GraphNode, GraphRelationship, PermissionGrant};
+    use crate::models::PermissionAction;
+    use uuid::Uuid;
+
+    fn node(ci_type_id: Uuid) -> GraphNode {
+        GraphNode {
+            id: Uuid::new_v4(),
+            name: "node".to_string(),
+            ci_type: "Server".to_string(),
+            ci_type_id,
+            attributes: serde_json::json!({}),
+        }
+    }
+
+    fn edge(relationship_type_id: Uuid, from: &GraphNode, to: &GraphNode) -> GraphRelationship {
+        GraphRelationship {
+            id: None,
+            relationship_type: "RUNS_ON".to_string(),
+            relationship_type_id,
+            from_node_id: from.id,
+            to_node_id: to.id,
+            attributes: serde_json::json!({}),
+            from_ci_type: from.ci_type.clone(),
+            to_ci_type: to.ci_type.clone(),
+        }
+    }
+
+    fn read_grant(resource_type: ResourceType, scope_id: Option<Uuid>) -> PermissionGrant {
+        PermissionGrant { resource_type, action: PermissionAction::Read, scope_id }
+    }
+
+    #[test]
+    fn user_without_grants_sees_nothing() {
+        let (a, b) = (node(Uuid::new_v4()), node(Uuid::new_v4()));
+        let edges = vec![edge(Uuid::new_v4(), &a, &b)];
+
+        let scope = graph_scope(&PermissionSet::from_grants(Vec::new()));
+        let (nodes, edges) = scope.restrict(vec![a, b], edges);
+
+        assert_eq!(scope.ci_type_ids, Some(Vec::new()));
+        assert_eq!(scope.relationship_type_ids, Some(Vec::new()));
+        assert!(nodes.is_empty());
+        assert!(edges.is_empty());
+    }
+
+    #[test]
+    fn scoped_grants_keep_only_readable_nodes_and_edges() {
+        let (server_type, database_type, runs_on) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let (a, b, c) = (node(server_type), node(server_type), node(database_type));
+        let edges = vec![edge(runs_on, &a, &b), edge(Uuid::new_v4(), &a, &b), edge(runs_on, &a, &c)];
+
+        let scope = graph_scope(&PermissionSet::from_grants(vec![
+            read_grant(ResourceType::CiAsset, Some(server_type)),
+            read_grant(ResourceType::Relationship, Some(runs_on)),
+        ]));
+        let (nodes, edges) = scope.restrict(vec![a.clone(), b.clone(), c], edges);
+
+        assert_eq!(nodes.iter().map(|n| n.id).collect::<Vec<_>>(), vec![a.id, b.id]);
+        assert_eq!(edges.len(), 1);
+        assert_eq!((edges[0].from_node_id, edges[0].to_node_id), (a.id, b.id));
+    }
+
+    #[test]
+    fn unscoped_grants_leave_the_graph_unrestricted() {
+        let scope = graph_scope(&PermissionSet::from_grants(vec![
+            read_grant(ResourceType::CiAsset, None),
+            read_grant(ResourceType::Relationship, None),
+        ]));
+
+        assert!(scope.ci_type_ids.is_none());
+        assert!(scope.relationship_type_ids.is_none());
+    }
+}
@@ -9,15 +9,16 @@ use validator::Validate;
 
 use crate::{
     AppState,
-    error::{AppError, AppResult},
+    error::{AppError, AppResult, PaginatedResponse},
     models::{
-        LifecycleTypeSummary, LifecycleTypeResponse,
+        LifecycleTypeSummary, LifecycleTypeResponse, LifecycleTypeFilter,
         CreateLifecycleTypeRequest, UpdateLifecycleTypeRequest,
         CreateLifecycleStateRequest, UpdateLifecycleStateRequest,
         CreateCITypeLifecycleRequest,
     },
     services::LifecycleService,
     middleware::AuthContext,
+    utils::PageRequest,
 };
 
 // Lifecycle Types Handlers
@@ -30,6 +31,7 @@ pub async fn create_lifecycle_type(
     let lifecycle_service = LifecycleService::new(
         app_state.database.lifecycle_repository.clone(),
         app_state.database.ci_repository.clone(),
+        app_state.database.rbac_repository.clone(),
     );
 
     let lifecycle_type = lifecycle_service
@@ -51,10 +53,11 @@ pub async fn get_lifecycle_type(
     let lifecycle_service = LifecycleService::new(
         app_state.database.lifecycle_repository.clone(),
         app_state.database.ci_repository.clone(),
+        app_state.database.rbac_repository.clone(),
     );
 
     let lifecycle_type = lifecycle_service
-        .get_lifecycle_type(id)
+        .get_lifecycle_type(id, &auth_context)
         .await?;
 
     Ok(Json(lifecycle_type))
@@ -63,27 +66,20 @@ pub async fn get_lifecycle_type(
 pub async fn list_lifecycle_types(
     State(app_state): State<AppState>,
     auth_context: AuthContext,
-    Query(params): Query<serde_json::Value>,
-) -> AppResult<Json<Value>> {
+    Query(filter): Query<LifecycleTypeFilter>,
+    Query(page): Query<PageRequest>,
+) -> AppResult<PaginatedResponse<LifecycleTypeSummary>> {
     let lifecycle_service = LifecycleService::new(
         app_state.database.lifecycle_repository.clone(),
         app_state.database.ci_repository.clone(),
+        app_state.database.rbac_repository.clone(),
     );
 
-    let include_inactive = params
-        .get("include_inactive")
-        .and_then(|v| v.as_bool())
-        .unwrap_or(false);
-
     let lifecycle_types = lifecycle_service
-        .list_lifecycle_types(include_inactive)
+        .list_lifecycle_types(&filter, &page, &auth_context)
         .await?;
 
-    Ok(Json(json!({
-        "success": true,
-        "data": lifecycle_types,
-        "count": lifecycle_types.len()
-    })))
+    Ok(lifecycle_types.into_response("Lifecycle types retrieved successfully"))
 }
 
 pub async fn update_lifecycle_type(
@@ -95,10 +91,11 @@ pub async fn update_lifecycle_type(
     let lifecycle_service = LifecycleService::new(
         app_state.database.lifecycle_repository.clone(),
         app_state.database.ci_repository.clone(),
+        app_state.database.rbac_repository.clone(),
     );
 
     let lifecycle_type = lifecycle_service
-        .update_lifecycle_type(id, request)
+        .update_lifecycle_type(id, request, &auth_context)
         .await?;
 
     Ok(Json(json!({
@@ -116,6 +113,7 @@ pub async fn delete_lifecycle_type(
     let lifecycle_service = LifecycleService::new(
         app_state.database.lifecycle_repository.clone(),
         app_state.database.ci_repository.clone(),
+        app_state.database.rbac_repository.clone(),
     );
 
     lifecycle_service
@@ -138,6 +136,7 @@ pub async fn create_lifecycle_state(
     let lifecycle_service = LifecycleService::new(
         app_state.database.lifecycle_repository.clone(),
         app_state.database.ci_repository.clone(),
+        app_state.database.rbac_repository.clone(),
     );
 
     let lifecycle_state = lifecycle_service
@@ -159,10 +158,11 @@ pub async fn get_lifecycle_state(
     let lifecycle_service = LifecycleService::new(
         app_state.database.lifecycle_repository.clone(),
         app_state.database.ci_repository.clone(),
+        app_state.database.rbac_repository.clone(),
     );
 
     let lifecycle_state = lifecycle_service
-        .get_lifecycle_state(id)
+        .get_lifecycle_state(id, &auth_context)
         .await?;
 
     Ok(Json(json!({
@@ -180,10 +180,11 @@ pub async fn update_lifecycle_state(
     let lifecycle_service = LifecycleService::new(
         app_state.database.lifecycle_repository.clone(),
         app_state.database.ci_repository.clone(),
+        app_state.database.rbac_repository.clone(),
     );
 
     let lifecycle_state = lifecycle_service
-        .update_lifecycle_state(id, request)
+        .update_lifecycle_state(id, request, &auth_context)
         .await?;
 
     Ok(Json(json!({
@@ -201,6 +202,7 @@ pub async fn delete_lifecycle_state(
     let lifecycle_service = LifecycleService::new(
         app_state.database.lifecycle_repository.clone(),
         app_state.database.ci_repository.clone(),
+        app_state.database.rbac_repository.clone(),
     );
 
     lifecycle_service
@@ -223,6 +225,7 @@ pub async fn create_ci_type_lifecycle_mapping(
     let lifecycle_service = LifecycleService::new(
         app_state.database.lifecycle_repository.clone(),
         app_state.database.ci_repository.clone(),
+        app_state.database.rbac_repository.clone(),
     );
 
     let mapping = lifecycle_service
@@ -244,10 +247,11 @@ pub async fn get_lifecycles_for_ci_type(
     let lifecycle_service = LifecycleService::new(
         app_state.database.lifecycle_repository.clone(),
         app_state.database.ci_repository.clone(),
+        app_state.database.rbac_repository.clone(),
     );
 
     let lifecycles = lifecycle_service
-        .get_lifecycles_for_ci_type(ci_type_id)
+        .get_lifecycles_for_ci_type(ci_type_id, &auth_context)
         .await?;
 
     Ok(Json(json!({
@@ -7,6 +7,9 @@ pub mod amortization;
 pub mod import_export;
 pub mod lifecycle;
 pub mod relationship;
+pub mod roles;
+pub mod service_accounts;
+pub mod users;
 
 pub use auth::*;
 pub use dashboard::*;
@@ -16,4 +19,7 @@ pub use audit::*;
 pub use amortization::*;
 pub use import_export::*;
 pub use lifecycle::*;
-pub use relationship::*;
\ No newline at end of file
+pub use relationship::*;
+pub use roles::*;
+pub use service_accounts::*;
+pub use users::*;
\ No newline at end of file
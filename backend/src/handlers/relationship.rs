@@ -1,14 +1,17 @@
-use crate::services::RelationshipService;
+use crate::services::{RelationshipService, RelationshipTypeUpdate};
 use crate::models::{
     CreateRelationshipTypeRequest, UpdateRelationshipTypeRequest, RelationshipTypeFilter,
-    CreateRelationshipRequest, UpdateRelationshipRequest, RelationshipFilter
+    CreateRelationshipRequest, UpdateRelationshipRequest, RelationshipFilter, RestoreVersionRequest,
+    RelationshipOperation, BulkRequest, BulkResult, RelationshipWithDetails,
+    DeletedRelationshipFilter, DeletedRelationship
 };
 use crate::middleware::AuthContext;
-use crate::error::ApiResponse;
+use crate::error::{ApiResponse, AppError, AppResult, ErrorResponse, PaginatedResponse};
+use crate::utils::PageRequest;
 use axum::{
     extract::{Path, Query, State},
     http::StatusCode,
-    response::Json,
+    response::{IntoResponse, Json, Response},
     routing::{get, post},
     Router,
 };
@@ -22,7 +25,30 @@ pub fn relationship_routes() -> Router<crate::AppState> {
         .route("/relationship-types/:id", get(get_relationship_type).put(update_relationship_type).delete(delete_relationship_type))
         // Relationship Instances (Phase 3.1)
         .route("/relationships", get(list_relationships).post(create_relationship))
+        .route("/relationships/bulk", post(bulk_relationships))
         .route("/relationships/:id", get(get_relationship).put(update_relationship).delete(delete_relationship))
+        .route("/relationships/:id/restore", post(restore_relationship))
+        .route("/trash/relationships", get(list_deleted_relationships))
+        .route("/trash/relationships/:id/restore", post(restore_deleted_relationship))
+}
+
+/// Permission failures are answered with 403 rather than a `success: false` body
+fn reject_forbidden(error: &anyhow::Error) -> Result<(), StatusCode> {
+    match error.downcast_ref::<AppError>() {
+        Some(AppError::Authorization(_)) => Err(StatusCode::FORBIDDEN),
+        _ => Ok(()),
+    }
+}
+
+/// Validation failures are answered with 400 and the offending fields rather
+/// than a `success: false` body
+fn invalid_fields_response(error: &anyhow::Error) -> Option<Response> {
+    match error.downcast_ref::<AppError>() {
+        Some(AppError::InvalidFields(message, errors)) => {
+            Some(AppError::invalid_fields(message.clone(), errors.clone()).into_response())
+        }
+        _ => None,
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -31,33 +57,36 @@ pub struct ListRelationshipTypesQuery {
     pub from_ci_type_id: Option<String>,
     pub to_ci_type_id: Option<String>,
     pub is_bidirectional: Option<bool>,
-    pub limit: Option<i64>,
-    pub offset: Option<i64>,
 }
 
 pub async fn list_relationship_types(
     State(app_state): State<crate::AppState>,
-    _auth: AuthContext,
+    auth: AuthContext,
     Query(query): Query<ListRelationshipTypesQuery>,
-) -> Result<Json<ApiResponse<Vec<crate::models::RelationshipTypeSummary>>>, StatusCode> {
+    Query(page): Query<PageRequest>,
+) -> Result<Response, StatusCode> {
     let filter = RelationshipTypeFilter {
         search: query.search,
         from_ci_type_id: query.from_ci_type_id.and_then(|s| s.parse().ok()),
         to_ci_type_id: query.to_ci_type_id.and_then(|s| s.parse().ok()),
         is_bidirectional: query.is_bidirectional,
-        limit: query.limit,
-        offset: query.offset,
     };
 
     let relationship_service = RelationshipService::new(
         app_state.database.relationship_repository.clone(),
         app_state.database.ci_repository.clone(),
         app_state.database.graph_repository.clone(),
+        app_state.database.rbac_repository.clone(),
+        app_state.database.audit_repository.clone(),
+        app_state.schema_cache.clone(),
     );
 
-    match relationship_service.list_relationship_types(filter).await {
-        Ok(relationship_types) => Ok(Json(ApiResponse::success(relationship_types))),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    match relationship_service.list_relationship_types(filter, &page, &auth).await {
+        Ok(relationship_types) => Ok(relationship_types.into_response("Relationship types retrieved successfully").into_response()),
+        Err(e) => {
+            reject_forbidden(&e)?;
+            invalid_fields_response(&e).ok_or(StatusCode::INTERNAL_SERVER_ERROR)
+        }
     }
 }
 
@@ -65,43 +94,55 @@ pub async fn create_relationship_type(
     State(app_state): State<crate::AppState>,
     auth: AuthContext,
     Json(request): Json<CreateRelationshipTypeRequest>,
-) -> Result<Json<ApiResponse<crate::models::RelationshipType>>, StatusCode> {
+) -> Result<Response, StatusCode> {
     let relationship_service = RelationshipService::new(
         app_state.database.relationship_repository.clone(),
         app_state.database.ci_repository.clone(),
         app_state.database.graph_repository.clone(),
+        app_state.database.rbac_repository.clone(),
+        app_state.database.audit_repository.clone(),
+        app_state.schema_cache.clone(),
     );
 
-    match relationship_service.create_relationship_type(request, auth.user_id).await {
+    match relationship_service.create_relationship_type(request, &auth).await {
         Ok(relationship_type) => Ok(Json(ApiResponse {
             success: true,
             data: Some(relationship_type),
             message: Some("Relationship type created successfully".to_string()),
             timestamp: chrono::Utc::now().to_rfc3339(),
-        })),
-        Err(e) => Ok(Json(ApiResponse {
-            success: false,
-            data: None,
-            message: Some(format!("Error: {}", e)),
-            timestamp: chrono::Utc::now().to_rfc3339(),
-        })),
+        }).into_response()),
+        Err(e) => {
+            reject_forbidden(&e)?;
+            if let Some(response) = invalid_fields_response(&e) {
+                return Ok(response);
+            }
+            Ok(Json(ApiResponse::<()> {
+                success: false,
+                data: None,
+                message: Some(format!("Error: {}", e)),
+                timestamp: chrono::Utc::now().to_rfc3339(),
+            }).into_response())
+        }
     }
 }
 
 pub async fn get_relationship_type(
     State(app_state): State<crate::AppState>,
-    _auth: AuthContext,
+    auth: AuthContext,
     Path(id): Path<String>,
 ) -> Result<Json<ApiResponse<crate::models::RelationshipType>>, StatusCode> {
     let relationship_service = RelationshipService::new(
         app_state.database.relationship_repository.clone(),
         app_state.database.ci_repository.clone(),
         app_state.database.graph_repository.clone(),
+        app_state.database.rbac_repository.clone(),
+        app_state.database.audit_repository.clone(),
+        app_state.schema_cache.clone(),
     );
 
     let id = Uuid::parse_str(&id).map_err(|_| StatusCode::BAD_REQUEST)?;
 
-    match relationship_service.get_relationship_type(id).await {
+    match relationship_service.get_relationship_type(id, &auth).await {
         Ok(Some(relationship_type)) => Ok(Json(ApiResponse {
             success: true,
             data: Some(relationship_type),
@@ -114,66 +155,95 @@ pub async fn get_relationship_type(
             message: Some("Relationship type not found".to_string()),
             timestamp: chrono::Utc::now().to_rfc3339(),
         })),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Err(e) => {
+            reject_forbidden(&e)?;
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
     }
 }
 
 pub async fn update_relationship_type(
     State(app_state): State<crate::AppState>,
-    _auth: AuthContext,
+    auth: AuthContext,
     Path(id): Path<String>,
     Json(request): Json<UpdateRelationshipTypeRequest>,
-) -> Result<Json<ApiResponse<crate::models::RelationshipType>>, StatusCode> {
+) -> Result<Response, StatusCode> {
     let relationship_service = RelationshipService::new(
         app_state.database.relationship_repository.clone(),
         app_state.database.ci_repository.clone(),
         app_state.database.graph_repository.clone(),
+        app_state.database.rbac_repository.clone(),
+        app_state.database.audit_repository.clone(),
+        app_state.schema_cache.clone(),
     );
 
     let id = Uuid::parse_str(&id).map_err(|_| StatusCode::BAD_REQUEST)?;
 
-    match relationship_service.update_relationship_type(id, request).await {
-        Ok(relationship_type) => Ok(Json(ApiResponse {
+    match relationship_service.update_relationship_type(id, request, &auth).await {
+        Ok(RelationshipTypeUpdate::Applied(relationship_type)) => Ok(Json(ApiResponse {
             success: true,
             data: Some(relationship_type),
             message: Some("Relationship type updated successfully".to_string()),
             timestamp: chrono::Utc::now().to_rfc3339(),
-        })),
-        Err(e) => Ok(Json(ApiResponse {
-            success: false,
-            data: None,
-            message: Some(format!("Error: {}", e)),
+        }).into_response()),
+        Ok(RelationshipTypeUpdate::DryRun(report)) => Ok(Json(ApiResponse {
+            success: true,
+            data: Some(report),
+            message: Some("Dry run only; nothing was changed".to_string()),
             timestamp: chrono::Utc::now().to_rfc3339(),
-        })),
+        }).into_response()),
+        Ok(RelationshipTypeUpdate::Rejected(report)) => Ok(ErrorResponse::with_details(
+            "conflict".to_string(),
+            format!("{} existing relationships do not conform to the new attributes schema", report.violation_count),
+            serde_json::json!(report),
+        ).into_response()),
+        Err(e) => {
+            reject_forbidden(&e)?;
+            if let Some(response) = invalid_fields_response(&e) {
+                return Ok(response);
+            }
+            Ok(Json(ApiResponse::<()> {
+                success: false,
+                data: None,
+                message: Some(format!("Error: {}", e)),
+                timestamp: chrono::Utc::now().to_rfc3339(),
+            }).into_response())
+        }
     }
 }
 
 pub async fn delete_relationship_type(
     State(app_state): State<crate::AppState>,
-    _auth: AuthContext,
+    auth: AuthContext,
     Path(id): Path<String>,
 ) -> Result<Json<ApiResponse<()>>, StatusCode> {
     let relationship_service = RelationshipService::new(
         app_state.database.relationship_repository.clone(),
         app_state.database.ci_repository.clone(),
         app_state.database.graph_repository.clone(),
+        app_state.database.rbac_repository.clone(),
+        app_state.database.audit_repository.clone(),
+        app_state.schema_cache.clone(),
     );
 
     let id = Uuid::parse_str(&id).map_err(|_| StatusCode::BAD_REQUEST)?;
 
-    match relationship_service.delete_relationship_type(id).await {
+    match relationship_service.delete_relationship_type(id, &auth).await {
         Ok(()) => Ok(Json(ApiResponse {
             success: true,
             data: None,
             message: Some("Relationship type deleted successfully".to_string()),
             timestamp: chrono::Utc::now().to_rfc3339(),
         })),
-        Err(e) => Ok(Json(ApiResponse {
-            success: false,
-            data: None,
-            message: Some(format!("Error: {}", e)),
-            timestamp: chrono::Utc::now().to_rfc3339(),
-        })),
+        Err(e) => {
+            reject_forbidden(&e)?;
+            Ok(Json(ApiResponse {
+                success: false,
+                data: None,
+                message: Some(format!("Error: {}", e)),
+                timestamp: chrono::Utc::now().to_rfc3339(),
+            }))
+        }
     }
 }
 
@@ -185,44 +255,45 @@ pub struct ListRelationshipsQuery {
     pub ci_asset_id: Option<String>,
     pub from_ci_asset_id: Option<String>,
     pub to_ci_asset_id: Option<String>,
-    pub limit: Option<i64>,
-    pub offset: Option<i64>,
 }
 
 /// List relationships with optional filtering
 pub async fn list_relationships(
     State(app_state): State<crate::AppState>,
-    _auth: AuthContext,
+    auth: AuthContext,
     Query(query): Query<ListRelationshipsQuery>,
-) -> Result<Json<ApiResponse<Vec<crate::models::RelationshipResponse>>>, StatusCode> {
+    Query(page): Query<PageRequest>,
+) -> Result<Response, StatusCode> {
     let filter = RelationshipFilter {
         relationship_type_id: query.relationship_type_id.and_then(|s| s.parse().ok()),
         ci_asset_id: query.ci_asset_id.and_then(|s| s.parse().ok()),
         from_ci_asset_id: query.from_ci_asset_id.and_then(|s| s.parse().ok()),
         to_ci_asset_id: query.to_ci_asset_id.and_then(|s| s.parse().ok()),
-        limit: query.limit,
-        offset: query.offset,
     };
 
     let relationship_service = RelationshipService::new(
         app_state.database.relationship_repository.clone(),
         app_state.database.ci_repository.clone(),
         app_state.database.graph_repository.clone(),
+        app_state.database.rbac_repository.clone(),
+        app_state.database.audit_repository.clone(),
+        app_state.schema_cache.clone(),
     );
 
-    match relationship_service.list_relationship_instances(filter).await {
-        Ok(relationships) => Ok(Json(ApiResponse {
-            success: true,
-            data: Some(relationships),
-            message: None,
-            timestamp: chrono::Utc::now().to_rfc3339(),
-        })),
-        Err(e) => Ok(Json(ApiResponse {
-            success: false,
-            data: None,
-            message: Some(format!("Error: {}", e)),
-            timestamp: chrono::Utc::now().to_rfc3339(),
-        })),
+    match relationship_service.list_relationship_instances(filter, &page, &auth).await {
+        Ok(relationships) => Ok(relationships.into_response("Relationships retrieved successfully").into_response()),
+        Err(e) => {
+            reject_forbidden(&e)?;
+            if let Some(response) = invalid_fields_response(&e) {
+                return Ok(response);
+            }
+            Ok(Json(ApiResponse::<()> {
+                success: false,
+                data: None,
+                message: Some(format!("Error: {}", e)),
+                timestamp: chrono::Utc::now().to_rfc3339(),
+            }).into_response())
+        }
     }
 }
 
@@ -231,42 +302,54 @@ pub async fn create_relationship(
     State(app_state): State<crate::AppState>,
     auth: AuthContext,
     Json(request): Json<CreateRelationshipRequest>,
-) -> Result<Json<ApiResponse<crate::models::RelationshipWithDetails>>, StatusCode> {
+) -> Result<Response, StatusCode> {
     let relationship_service = RelationshipService::new(
         app_state.database.relationship_repository.clone(),
         app_state.database.ci_repository.clone(),
         app_state.database.graph_repository.clone(),
+        app_state.database.rbac_repository.clone(),
+        app_state.database.audit_repository.clone(),
+        app_state.schema_cache.clone(),
     );
 
-    match relationship_service.create_relationship_instance(request, auth.user_id).await {
+    match relationship_service.create_relationship_instance(request, &auth).await {
         Ok(relationship) => Ok(Json(ApiResponse {
             success: true,
             data: Some(relationship),
             message: Some("Relationship created successfully".to_string()),
             timestamp: chrono::Utc::now().to_rfc3339(),
-        })),
-        Err(e) => Ok(Json(ApiResponse {
-            success: false,
-            data: None,
-            message: Some(format!("Error: {}", e)),
-            timestamp: chrono::Utc::now().to_rfc3339(),
-        })),
+        }).into_response()),
+        Err(e) => {
+            reject_forbidden(&e)?;
+            if let Some(response) = invalid_fields_response(&e) {
+                return Ok(response);
+            }
+            Ok(Json(ApiResponse::<()> {
+                success: false,
+                data: None,
+                message: Some(format!("Error: {}", e)),
+                timestamp: chrono::Utc::now().to_rfc3339(),
+            }).into_response())
+        }
     }
 }
 
 /// Get a relationship by ID
 pub async fn get_relationship(
     State(app_state): State<crate::AppState>,
-    _auth: AuthContext,
+    auth: AuthContext,
     Path(id): Path<Uuid>,
 ) -> Result<Json<ApiResponse<Option<crate::models::RelationshipWithDetails>>>, StatusCode> {
     let relationship_service = RelationshipService::new(
         app_state.database.relationship_repository.clone(),
         app_state.database.ci_repository.clone(),
         app_state.database.graph_repository.clone(),
+        app_state.database.rbac_repository.clone(),
+        app_state.database.audit_repository.clone(),
+        app_state.schema_cache.clone(),
     );
 
-    match relationship_service.get_relationship_instance(id).await {
+    match relationship_service.get_relationship_instance(id, &auth).await {
         Ok(relationship) => {
             if relationship.is_none() {
                 return Ok(Json(ApiResponse {
@@ -283,68 +366,186 @@ pub async fn get_relationship(
                 timestamp: chrono::Utc::now().to_rfc3339(),
             }))
         }
-        Err(e) => Ok(Json(ApiResponse {
-            success: false,
-            data: None,
-            message: Some(format!("Error: {}", e)),
-            timestamp: chrono::Utc::now().to_rfc3339(),
-        })),
+        Err(e) => {
+            reject_forbidden(&e)?;
+            Ok(Json(ApiResponse {
+                success: false,
+                data: None,
+                message: Some(format!("Error: {}", e)),
+                timestamp: chrono::Utc::now().to_rfc3339(),
+            }))
+        }
     }
 }
 
 /// Update a relationship
 pub async fn update_relationship(
     State(app_state): State<crate::AppState>,
-    _auth: AuthContext,
+    auth: AuthContext,
     Path(id): Path<Uuid>,
     Json(request): Json<UpdateRelationshipRequest>,
-) -> Result<Json<ApiResponse<crate::models::RelationshipWithDetails>>, StatusCode> {
+) -> Result<Response, StatusCode> {
     let relationship_service = RelationshipService::new(
         app_state.database.relationship_repository.clone(),
         app_state.database.ci_repository.clone(),
         app_state.database.graph_repository.clone(),
+        app_state.database.rbac_repository.clone(),
+        app_state.database.audit_repository.clone(),
+        app_state.schema_cache.clone(),
     );
 
-    match relationship_service.update_relationship_instance(id, request).await {
+    match relationship_service.update_relationship_instance(id, request, &auth).await {
         Ok(relationship) => Ok(Json(ApiResponse {
             success: true,
             data: Some(relationship),
             message: Some("Relationship updated successfully".to_string()),
             timestamp: chrono::Utc::now().to_rfc3339(),
-        })),
-        Err(e) => Ok(Json(ApiResponse {
-            success: false,
-            data: None,
-            message: Some(format!("Error: {}", e)),
-            timestamp: chrono::Utc::now().to_rfc3339(),
-        })),
+        }).into_response()),
+        Err(e) => {
+            reject_forbidden(&e)?;
+            if let Some(response) = invalid_fields_response(&e) {
+                return Ok(response);
+            }
+            Ok(Json(ApiResponse::<()> {
+                success: false,
+                data: None,
+                message: Some(format!("Error: {}", e)),
+                timestamp: chrono::Utc::now().to_rfc3339(),
+            }).into_response())
+        }
     }
 }
 
 /// Delete a relationship
 pub async fn delete_relationship(
     State(app_state): State<crate::AppState>,
-    _auth: AuthContext,
+    auth: AuthContext,
     Path(id): Path<Uuid>,
 ) -> Result<Json<ApiResponse<Option<()>>>, StatusCode> {
     let relationship_service = RelationshipService::new(
         app_state.database.relationship_repository.clone(),
         app_state.database.ci_repository.clone(),
         app_state.database.graph_repository.clone(),
+        app_state.database.rbac_repository.clone(),
+        app_state.database.audit_repository.clone(),
+        app_state.schema_cache.clone(),
     );
 
-    match relationship_service.delete_relationship_instance(id).await {
+    match relationship_service.delete_relationship_instance(id, &auth).await {
         Ok(()) => Ok(Json(ApiResponse {
             success: true,
             data: None,
             message: Some("Relationship deleted successfully".to_string()),
             timestamp: chrono::Utc::now().to_rfc3339(),
         })),
-        Err(e) => Ok(Json(ApiResponse {
-            success: false,
-            data: None,
-            message: Some(format!("Error: {}", e)),
+        Err(e) => {
+            reject_forbidden(&e)?;
+            Ok(Json(ApiResponse {
+                success: false,
+                data: None,
+                message: Some(format!("Error: {}", e)),
+                timestamp: chrono::Utc::now().to_rfc3339(),
+            }))
+        }
+    }
+}
+
+/// Restore a relationship to an earlier version, undeleting it if needed
+pub async fn restore_relationship(
+    State(app_state): State<crate::AppState>,
+    auth: AuthContext,
+    Path(id): Path<Uuid>,
+    Json(request): Json<RestoreVersionRequest>,
+) -> Result<Response, StatusCode> {
+    let relationship_service = RelationshipService::new(
+        app_state.database.relationship_repository.clone(),
+        app_state.database.ci_repository.clone(),
+        app_state.database.graph_repository.clone(),
+        app_state.database.rbac_repository.clone(),
+        app_state.database.audit_repository.clone(),
+        app_state.schema_cache.clone(),
+    );
+
+    match relationship_service.restore_relationship_instance(id, &request, &auth).await {
+        Ok(relationship) => Ok(Json(ApiResponse {
+            success: true,
+            data: Some(relationship),
+            message: Some("Relationship restored successfully".to_string()),
             timestamp: chrono::Utc::now().to_rfc3339(),
-        })),
+        }).into_response()),
+        Err(e) => {
+            reject_forbidden(&e)?;
+            if let Some(response) = invalid_fields_response(&e) {
+                return Ok(response);
+            }
+            Ok(Json(ApiResponse::<()> {
+                success: false,
+                data: None,
+                message: Some(format!("Error: {}", e)),
+                timestamp: chrono::Utc::now().to_rfc3339(),
+            }).into_response())
+        }
     }
-}
\ No newline at end of file
+}
+
+/// Apply a batch of relationship operations, atomically or best-effort
+pub async fn bulk_relationships(
+    State(app_state): State<crate::AppState>,
+    auth: AuthContext,
+    Json(request): Json<BulkRequest<RelationshipOperation>>,
+) -> AppResult<Json<ApiResponse<BulkResult>>> {
+    let relationship_service = RelationshipService::new(
+        app_state.database.relationship_repository.clone(),
+        app_state.database.ci_repository.clone(),
+        app_state.database.graph_repository.clone(),
+        app_state.database.rbac_repository.clone(),
+        app_state.database.audit_repository.clone(),
+        app_state.schema_cache.clone(),
+    );
+
+    let result = relationship_service.bulk_relationships(request, &auth).await?;
+    let message = format!("Applied {} of {} relationship operations", result.applied, result.items.len());
+
+    Ok(Json(ApiResponse::success_with_message(result, message)))
+}
+
+/// Soft-deleted relationships, most recently deleted first
+pub async fn list_deleted_relationships(
+    State(app_state): State<crate::AppState>,
+    auth: AuthContext,
+    Query(filter): Query<DeletedRelationshipFilter>,
+    Query(page): Query<PageRequest>,
+) -> AppResult<PaginatedResponse<DeletedRelationship>> {
+    let relationship_service = RelationshipService::new(
+        app_state.database.relationship_repository.clone(),
+        app_state.database.ci_repository.clone(),
+        app_state.database.graph_repository.clone(),
+        app_state.database.rbac_repository.clone(),
+        app_state.database.audit_repository.clone(),
+        app_state.schema_cache.clone(),
+    );
+
+    let relationships = relationship_service.list_deleted_relationships(&filter, &page, &auth).await?;
+
+    Ok(relationships.into_response("Deleted relationships retrieved successfully"))
+}
+
+/// Take a relationship out of the trash; both its assets must be live
+pub async fn restore_deleted_relationship(
+    State(app_state): State<crate::AppState>,
+    auth: AuthContext,
+    Path(id): Path<Uuid>,
+) -> AppResult<Json<ApiResponse<RelationshipWithDetails>>> {
+    let relationship_service = RelationshipService::new(
+        app_state.database.relationship_repository.clone(),
+        app_state.database.ci_repository.clone(),
+        app_state.database.graph_repository.clone(),
+        app_state.database.rbac_repository.clone(),
+        app_state.database.audit_repository.clone(),
+        app_state.schema_cache.clone(),
+    );
+
+    let relationship = relationship_service.restore_deleted_relationship(id, &auth).await?;
+
+    Ok(Json(ApiResponse::success_with_message(relationship, "Relationship restored from the trash".to_string())))
+}
@@ -0,0 +1,131 @@
+use axum::{
+    extract::{Path, Query, State},
+    response::Json,
+};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::{
+    error::{AppResult, PaginatedResponse},
+    models::{CreateRoleRequest, UpdateRoleRequest, GrantPermissionRequest, CreateRoleBindingRequest, Role},
+    middleware::AuthContext,
+    services::RbacService,
+    utils::PageRequest,
+};
+
+// Role management is admin-only; routes are mounted behind admin_middleware
+
+fn rbac_service(app_state: &crate::AppState) -> RbacService {
+    RbacService::new(
+        app_state.database.rbac_repository.clone(),
+        app_state.database.user_repository.clone(),
+    )
+}
+
+pub async fn list_roles(
+    State(app_state): State<crate::AppState>,
+    Query(page): Query<PageRequest>,
+) -> AppResult<PaginatedResponse<Role>> {
+    let roles = rbac_service(&app_state).list_roles(&page).await?;
+
+    Ok(roles.into_response("Roles retrieved successfully"))
+}
+
+pub async fn create_role(
+    State(app_state): State<crate::AppState>,
+    auth_context: AuthContext,
+    Json(request): Json<CreateRoleRequest>,
+) -> AppResult<Json<Value>> {
+    let role = rbac_service(&app_state).create_role(request, &auth_context).await?;
+
+    Ok(Json(json!({
+        "data": role,
+        "message": "Role created successfully"
+    })))
+}
+
+pub async fn get_role(
+    State(app_state): State<crate::AppState>,
+    Path(id): Path<Uuid>,
+) -> AppResult<Json<Value>> {
+    let role = rbac_service(&app_state).get_role(id).await?;
+
+    Ok(Json(json!({
+        "data": role,
+        "message": "Role retrieved successfully"
+    })))
+}
+
+pub async fn update_role(
+    State(app_state): State<crate::AppState>,
+    Path(id): Path<Uuid>,
+    Json(request): Json<UpdateRoleRequest>,
+) -> AppResult<Json<Value>> {
+    let role = rbac_service(&app_state).update_role(id, request).await?;
+
+    Ok(Json(json!({
+        "data": role,
+        "message": "Role updated successfully"
+    })))
+}
+
+pub async fn delete_role(
+    State(app_state): State<crate::AppState>,
+    Path(id): Path<Uuid>,
+) -> AppResult<Json<Value>> {
+    rbac_service(&app_state).delete_role(id).await?;
+
+    Ok(Json(json!({
+        "message": "Role deleted successfully"
+    })))
+}
+
+pub async fn grant_role_permission(
+    State(app_state): State<crate::AppState>,
+    Path(id): Path<Uuid>,
+    Json(request): Json<GrantPermissionRequest>,
+) -> AppResult<Json<Value>> {
+    let permission = rbac_service(&app_state).grant_permission(id, request).await?;
+
+    Ok(Json(json!({
+        "data": permission,
+        "message": "Permission granted successfully"
+    })))
+}
+
+pub async fn revoke_role_permission(
+    State(app_state): State<crate::AppState>,
+    Path((id, permission_id)): Path<(Uuid, Uuid)>,
+) -> AppResult<Json<Value>> {
+    rbac_service(&app_state).revoke_permission(id, permission_id).await?;
+
+    Ok(Json(json!({
+        "message": "Permission revoked successfully"
+    })))
+}
+
+pub async fn create_role_binding(
+    State(app_state): State<crate::AppState>,
+    auth_context: AuthContext,
+    Path(id): Path<Uuid>,
+    Json(request): Json<CreateRoleBindingRequest>,
+) -> AppResult<Json<Value>> {
+    rbac_service(&app_state)
+        .bind_user(id, request.user_id, &auth_context)
+        .await?;
+
+    Ok(Json(json!({
+        "message": "User bound to role successfully"
+    })))
+}
+
+pub async fn delete_role_binding(
+    State(app_state): State<crate::AppState>,
+    Path((id, user_id)): Path<(Uuid, Uuid)>,
+) -> AppResult<Json<Value>> {
+    rbac_service(&app_state).unbind_user(id, user_id).await?;
+
+    Ok(Json(json!({
+        "message": "User removed from role successfully"
+    })))
+}
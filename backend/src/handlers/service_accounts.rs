@@ -0,0 +1,104 @@
+use axum::{
+    extract::{Path, Query, State},
+    response::Json,
+};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::{
+    error::{AppResult, PaginatedResponse},
+    models::{CreateServiceAccountRequest, CreateApiKeyRequest, ServiceAccount},
+    middleware::AuthContext,
+    services::ServiceAccountService,
+    utils::PageRequest,
+};
+
+// Service account management is admin-only; routes are mounted behind admin_middleware
+
+fn service_account_service(app_state: &crate::AppState) -> ServiceAccountService {
+    ServiceAccountService::new(app_state.database.service_account_repository.clone())
+}
+
+pub async fn list_service_accounts(
+    State(app_state): State<crate::AppState>,
+    Query(page): Query<PageRequest>,
+) -> AppResult<PaginatedResponse<ServiceAccount>> {
+    let service_accounts = service_account_service(&app_state)
+        .list_service_accounts(&page)
+        .await?;
+
+    Ok(service_accounts.into_response("Service accounts retrieved successfully"))
+}
+
+pub async fn create_service_account(
+    State(app_state): State<crate::AppState>,
+    auth_context: AuthContext,
+    Json(request): Json<CreateServiceAccountRequest>,
+) -> AppResult<Json<Value>> {
+    let service_account = service_account_service(&app_state)
+        .create_service_account(request, &auth_context)
+        .await?;
+
+    Ok(Json(json!({
+        "data": service_account,
+        "message": "Service account created successfully"
+    })))
+}
+
+pub async fn get_service_account(
+    State(app_state): State<crate::AppState>,
+    Path(id): Path<Uuid>,
+) -> AppResult<Json<Value>> {
+    let service_account = service_account_service(&app_state)
+        .get_service_account(id)
+        .await?;
+
+    Ok(Json(json!({
+        "data": service_account,
+        "message": "Service account retrieved successfully"
+    })))
+}
+
+pub async fn deactivate_service_account(
+    State(app_state): State<crate::AppState>,
+    Path(id): Path<Uuid>,
+) -> AppResult<Json<Value>> {
+    service_account_service(&app_state)
+        .deactivate_service_account(id)
+        .await?;
+
+    Ok(Json(json!({
+        "message": "Service account deactivated successfully"
+    })))
+}
+
+pub async fn create_api_key(
+    State(app_state): State<crate::AppState>,
+    auth_context: AuthContext,
+    Path(id): Path<Uuid>,
+    Json(request): Json<CreateApiKeyRequest>,
+) -> AppResult<Json<Value>> {
+    let created = service_account_service(&app_state)
+        .create_api_key(id, request, &auth_context)
+        .await?;
+
+    tracing::info!("Issued API key {} for service account {}", created.api_key.id, id);
+
+    Ok(Json(json!({
+        "data": created,
+        "message": "API key created successfully. Store the key now; it cannot be retrieved again"
+    })))
+}
+
+pub async fn revoke_api_key(
+    State(app_state): State<crate::AppState>,
+    Path((id, key_id)): Path<(Uuid, Uuid)>,
+) -> AppResult<Json<Value>> {
+    service_account_service(&app_state)
+        .revoke_api_key(id, key_id)
+        .await?;
+
+    Ok(Json(json!({
+        "message": "API key revoked successfully"
+    })))
+}
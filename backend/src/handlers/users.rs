@@ -0,0 +1,74 @@
+use axum::{
+    extract::{Path, Query, State},
+    response::Json,
+};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::{
+    error::{AppResult, PaginatedResponse},
+    models::{UpdateUserRequest, User, UserFilter},
+    services::UserService,
+    utils::PageRequest,
+};
+
+// User administration is admin-only; routes are mounted behind admin_middleware
+
+fn user_service(app_state: &crate::AppState) -> UserService {
+    UserService::new(
+        app_state.database.user_repository.clone(),
+        app_state.database.session_repository.clone(),
+    )
+}
+
+pub async fn list_users(
+    State(app_state): State<crate::AppState>,
+    Query(filter): Query<UserFilter>,
+    Query(page): Query<PageRequest>,
+) -> AppResult<PaginatedResponse<User>> {
+    let users = user_service(&app_state).list_users(&filter, &page).await?;
+
+    Ok(users.into_response("Users retrieved successfully"))
+}
+
+pub async fn get_user(
+    State(app_state): State<crate::AppState>,
+    Path(id): Path<Uuid>,
+) -> AppResult<Json<Value>> {
+    let user = user_service(&app_state).get_user(id).await?;
+
+    Ok(Json(json!({
+        "data": user,
+        "message": "User retrieved successfully"
+    })))
+}
+
+pub async fn update_user(
+    State(app_state): State<crate::AppState>,
+    Path(id): Path<Uuid>,
+    Json(request): Json<UpdateUserRequest>,
+) -> AppResult<Json<Value>> {
+    let user = user_service(&app_state).update_user(id, request).await?;
+
+    tracing::info!("Updated user {}", id);
+
+    Ok(Json(json!({
+        "data": user,
+        "message": "User updated successfully"
+    })))
+}
+
+/// Deactivate rather than delete, so the user's audit trail stays attributable
+pub async fn deactivate_user(
+    State(app_state): State<crate::AppState>,
+    Path(id): Path<Uuid>,
+) -> AppResult<Json<Value>> {
+    let user = user_service(&app_state).deactivate_user(id).await?;
+
+    tracing::info!("Deactivated user {}", id);
+
+    Ok(Json(json!({
+        "data": user,
+        "message": "User deactivated successfully"
+    })))
+}
@@ -1,10 +1,136 @@
-use crate::database::PgPool;
+use crate::database::{
+    PgPool, SessionRepository, OidcRepository, IdempotencyRepository, CIRepository, RelationshipRepository,
+    AuditRepository, RbacRepository,
+};
 use crate::error::{AppError, AppResult};
+use crate::middleware::AuthContext;
+use crate::models::{AuditAction, AuditedEntity};
+use crate::services::AuditService;
+use chrono::{DateTime, Duration, Utc};
+use sqlx::PgConnection;
+use uuid::Uuid;
 use tracing::{info, error};
 
-pub async fn run_cleanup_job(_pg_pool: PgPool) -> AppResult<()> {
-    // TODO: Implement data cleanup (old logs, temporary files, etc.)
+/// Trashed assets or relationships purged per transaction
+const PURGE_BATCH_SIZE: i64 = 500;
+
+pub async fn run_cleanup_job(pg_pool: PgPool, trash_retention_days: u64) -> AppResult<()> {
+    // Expired refresh tokens and revocation entries no longer affect authentication
+    let session_repository = SessionRepository::new(pg_pool.clone());
+    let purged_sessions = session_repository.purge_expired().await?;
+    info!("Purged {} expired session records", purged_sessions);
+
+    // Single sign-on logins that were started but never completed
+    let oidc_repository = OidcRepository::new(pg_pool.clone());
+    let purged_login_states = oidc_repository.purge_expired_login_states().await?;
+    info!("Purged {} expired OIDC login states", purged_login_states);
+
+    // Outcomes of idempotent requests past the window in which retries are replayed
+    let idempotency_repository = IdempotencyRepository::new(pg_pool.clone());
+    let purged_idempotency_keys = idempotency_repository.purge_expired().await?;
+    info!("Purged {} expired idempotency keys", purged_idempotency_keys);
+
+    // Trashed relationships and assets past the retention period; an asset
+    // takes whatever relationships it still has along. A retention period
+    // too long to represent keeps everything.
+    let Some(deleted_before) = i64::try_from(trash_retention_days)
+        .ok()
+        .and_then(Duration::try_days)
+        .and_then(|retention| Utc::now().checked_sub_signed(retention))
+    else {
+        info!("Trash retention of {} days is too long to purge anything", trash_retention_days);
+        return Ok(());
+    };
+    let trash = TrashPurge {
+        ci_repository: CIRepository::new(pg_pool.clone()),
+        relationship_repository: RelationshipRepository::new(pg_pool.clone()),
+        audit_service: AuditService::new(AuditRepository::new(pg_pool.clone()), RbacRepository::new(pg_pool)),
+        actor: AuthContext::system(),
+        deleted_before,
+    };
+
+    let mut purged_relationships = 0;
+    loop {
+        let purged = trash.purge_relationships().await?;
+        purged_relationships += purged;
+        if purged < PURGE_BATCH_SIZE as u64 {
+            break;
+        }
+    }
+
+    let mut purged_assets = 0;
+    loop {
+        let (purged, purged_with_assets) = trash.purge_ci_assets().await?;
+        purged_assets += purged;
+        purged_relationships += purged_with_assets;
+        if purged < PURGE_BATCH_SIZE as u64 {
+            break;
+        }
+    }
+    info!(
+        "Purged {} CI assets and {} relationships deleted over {} days ago",
+        purged_assets, purged_relationships, trash_retention_days
+    );
+
+    // TODO: Clean up old logs and temporary files
 
     info!("Cleanup job completed successfully");
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Purges trashed rows in batches, one transaction each, recording a delete
+/// by the system user in the audit log for every asset and relationship purged
+struct TrashPurge {
+    ci_repository: CIRepository,
+    relationship_repository: RelationshipRepository,
+    audit_service: AuditService,
+    actor: AuthContext,
+    deleted_before: DateTime<Utc>,
+}
+
+impl TrashPurge {
+    /// Purge one batch of relationships. Returns how many were purged.
+    async fn purge_relationships(&self) -> AppResult<u64> {
+        let mut tx = self.relationship_repository.begin().await?;
+
+        let ids = self.relationship_repository
+            .purgeable_relationship_ids(&mut tx, self.deleted_before, PURGE_BATCH_SIZE)
+            .await?;
+        self.record_purge(&mut tx, AuditedEntity::Relationship, &ids).await?;
+        let purged = self.relationship_repository.purge_relationships(&mut tx, &ids).await?;
+
+        tx.commit().await?;
+        Ok(purged)
+    }
+
+    /// Purge one batch of assets, along with any relationships they still
+    /// have. Returns how many assets and relationships were purged.
+    async fn purge_ci_assets(&self) -> AppResult<(u64, u64)> {
+        let mut tx = self.ci_repository.begin().await?;
+
+        let ids = self.ci_repository
+            .purgeable_ci_asset_ids(&mut tx, self.deleted_before, PURGE_BATCH_SIZE)
+            .await?;
+        let relationship_ids = self.relationship_repository
+            .relationship_ids_of_assets(&mut tx, &ids)
+            .await?;
+
+        self.record_purge(&mut tx, AuditedEntity::Relationship, &relationship_ids).await?;
+        let purged_relationships = self.relationship_repository.purge_relationships(&mut tx, &relationship_ids).await?;
+        self.record_purge(&mut tx, AuditedEntity::CiAsset, &ids).await?;
+        let purged_assets = self.ci_repository.purge_ci_assets(&mut tx, &ids).await?;
+
+        tx.commit().await?;
+        Ok((purged_assets, purged_relationships))
+    }
+
+    async fn record_purge(&self, conn: &mut PgConnection, entity: AuditedEntity, ids: &[Uuid]) -> AppResult<()> {
+        for &id in ids {
+            let old_values = self.audit_service.snapshot(conn, entity, id).await?;
+            self.audit_service
+                .record_change(conn, &self.actor, entity, id, AuditAction::Delete, old_values)
+                .await?;
+        }
+        Ok(())
+    }
+}
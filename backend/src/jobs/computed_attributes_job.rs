@@ -0,0 +1,47 @@
+use crate::database::{PgPool, CIRepository};
+use crate::error::AppResult;
+use crate::services::refresh_computed_attributes;
+use crate::utils::{ComputedAttributes, KeysetCursor};
+use tracing::{info, warn};
+
+/// CI types read per query while looking for date-dependent definitions
+const CI_TYPE_PAGE_SIZE: i64 = 200;
+
+/// Stored computed attributes are evaluated when assets are written, so those
+/// that depend on `today` (such as `warranty_expired`) go stale overnight.
+/// Re-evaluate them for every CI type that has such a definition, so that
+/// searches and filters over the stored values stay right.
+pub async fn run_computed_attributes_job(pg_pool: PgPool) -> AppResult<()> {
+    let ci_repository = CIRepository::new(pg_pool);
+
+    let mut refreshed = 0;
+    let mut cursor = None;
+    loop {
+        let ci_types = ci_repository.list_ci_types(cursor.as_ref(), CI_TYPE_PAGE_SIZE, None).await?;
+
+        for ci_type in &ci_types {
+            let lineage = ci_repository.get_ci_type_lineage(ci_type.id).await?;
+            let computed = match ComputedAttributes::from_layers(lineage.iter().rev().map(|ancestor| &ancestor.attributes)) {
+                Ok(computed) => computed,
+                Err(_) => {
+                    warn!("CI type {} has invalid computed attributes; skipped", ci_type.id);
+                    continue;
+                }
+            };
+
+            if computed.uses_today() {
+                refreshed += refresh_computed_attributes(&ci_repository, ci_type.id).await?;
+            }
+        }
+
+        match ci_types.last() {
+            Some(last) if ci_types.len() as i64 == CI_TYPE_PAGE_SIZE => {
+                cursor = Some(KeysetCursor::new(last.created_at, last.id));
+            }
+            _ => break,
+        }
+    }
+
+    info!("Refreshed computed attributes of {} CI assets", refreshed);
+    Ok(())
+}
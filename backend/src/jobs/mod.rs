@@ -1,7 +1,9 @@
 pub mod amortization_job;
 pub mod cleanup_job;
+pub mod computed_attributes_job;
 pub mod scheduler;
 
 pub use amortization_job::*;
 pub use cleanup_job::*;
+pub use computed_attributes_job::*;
 pub use scheduler::*;
\ No newline at end of file
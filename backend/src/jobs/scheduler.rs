@@ -1,11 +1,12 @@
 use crate::database::PgPool;
-use crate::jobs::{run_amortization_job, run_cleanup_job};
+use crate::jobs::{run_amortization_job, run_cleanup_job, run_computed_attributes_job};
 use crate::error::{AppError, AppResult};
 use tokio::time;
 use tracing::{info, error};
-use chrono::Timelike;
+use chrono::{DateTime, Timelike, Utc};
+use std::future::Future;
 
-pub async fn start_background_jobs(pg_pool: PgPool) -> AppResult<()> {
+pub async fn start_background_jobs(pg_pool: PgPool, trash_retention_days: u64) -> AppResult<()> {
     info!("Starting background jobs scheduler");
 
     // Start amortization job (daily at 2 AM)
@@ -27,21 +28,70 @@ pub async fn start_background_jobs(pg_pool: PgPool) -> AppResult<()> {
 
     // Start cleanup job (daily at 3 AM)
     let cleanup_pool = pg_pool.clone();
+    spawn_daily("cleanup", 3, move || run_cleanup_job(cleanup_pool.clone(), trash_retention_days));
+
+    // Refresh date-dependent computed attributes (daily at midnight)
+    let computed_attributes_pool = pg_pool.clone();
+    spawn_daily("computed attributes", 0, move || run_computed_attributes_job(computed_attributes_pool.clone()));
+
+    info!("Background jobs scheduler started");
+    Ok(())
+}
+
+/// Run `job` every day at `hour`:00 UTC, sleeping until each run is due
+fn spawn_daily<F, Fut>(name: &'static str, hour: u32, mut job: F)
+where
+    F: FnMut() -> Fut + Send + 'static,
+    Fut: Future<Output = AppResult<()>> + Send,
+{
     tokio::spawn(async move {
-        let mut interval = time::interval(time::Duration::from_secs(3600)); // Check every hour
+        let mut after = Utc::now();
 
         loop {
-            interval.tick().await;
+            let next = next_daily_run(hour, after);
+            let wait = (next - Utc::now()).to_std().unwrap_or_default();
+            time::sleep_until(time::Instant::now() + wait).await;
 
-            let now = chrono::Utc::now();
-            if now.hour() == 3 && now.minute() == 0 {
-                if let Err(e) = run_cleanup_job(cleanup_pool.clone()).await {
-                    error!("Error running cleanup job: {:?}", e);
-                }
+            if let Err(e) = job().await {
+                error!("Error running {} job: {:?}", name, e);
             }
+
+            // A run that overran the next one's time skips it rather than
+            // running twice in a row
+            after = next.max(Utc::now());
         }
     });
+}
 
-    info!("Background jobs scheduler started");
-    Ok(())
+/// The first `hour`:00 UTC strictly after `after`
+fn next_daily_run(hour: u32, after: DateTime<Utc>) -> DateTime<Utc> {
+    let today = after
+        .date_naive()
+        .and_hms_opt(hour, 0, 0)
+        .expect("hour must be below 24")
+        .and_utc();
+
+    if today > after {
+        today
+    } else {
+        today + chrono::Duration::days(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn next_daily_run_is_later_today_or_tomorrow() {
+        let at = |h, m| Utc.with_ymd_and_hms(2024, 2, 28, h, m, 0).unwrap();
+        let tomorrow = |h| Utc.with_ymd_and_hms(2024, 2, 29, h, 0, 0).unwrap();
+
+        assert_eq!(next_daily_run(3, at(1, 30)), at(3, 0));
+        assert_eq!(next_daily_run(3, at(3, 0)), tomorrow(3));
+        assert_eq!(next_daily_run(3, at(17, 45)), tomorrow(3));
+        assert_eq!(next_daily_run(0, at(0, 0)), tomorrow(0));
+        assert_eq!(next_daily_run(0, at(23, 59)), tomorrow(0));
+    }
 }
\ No newline at end of file
@@ -8,8 +8,10 @@ pub mod middleware;
 pub mod jobs;
 pub mod error;
 
-use database::{PgPool, Neo4jPool, CIRepository, LifecycleRepository, RelationshipRepository, GraphRepository};
-use middleware::RateLimiter;
+use database::{PgPool, Neo4jPool, CIRepository, LifecycleRepository, RelationshipRepository, GraphRepository, UserRepository, SessionRepository, RbacRepository, ServiceAccountRepository, OidcRepository, IdempotencyRepository, AuditRepository};
+use middleware::{AuditExportSigner, JwtKeyring, RateLimiter};
+use services::{Notifier, OidcClient};
+use utils::SchemaCache;
 use std::sync::Arc;
 
 // Database layer containing repositories
@@ -19,6 +21,13 @@ pub struct Database {
     pub lifecycle_repository: LifecycleRepository,
     pub relationship_repository: RelationshipRepository,
     pub graph_repository: Arc<GraphRepository>,
+    pub user_repository: UserRepository,
+    pub session_repository: SessionRepository,
+    pub rbac_repository: RbacRepository,
+    pub service_account_repository: ServiceAccountRepository,
+    pub oidc_repository: OidcRepository,
+    pub idempotency_repository: IdempotencyRepository,
+    pub audit_repository: AuditRepository,
 }
 
 impl Database {
@@ -26,8 +35,15 @@ impl Database {
         Self {
             ci_repository: CIRepository::new(pg_pool.clone()),
             lifecycle_repository: LifecycleRepository::new(pg_pool.clone()),
-            relationship_repository: RelationshipRepository::new(pg_pool),
+            relationship_repository: RelationshipRepository::new(pg_pool.clone()),
             graph_repository: Arc::new(GraphRepository::new(neo4j_pool)),
+            user_repository: UserRepository::new(pg_pool.clone()),
+            session_repository: SessionRepository::new(pg_pool.clone()),
+            rbac_repository: RbacRepository::new(pg_pool.clone()),
+            service_account_repository: ServiceAccountRepository::new(pg_pool.clone()),
+            oidc_repository: OidcRepository::new(pg_pool.clone()),
+            idempotency_repository: IdempotencyRepository::new(pg_pool.clone()),
+            audit_repository: AuditRepository::new(pg_pool),
         }
     }
 }
@@ -40,6 +56,11 @@ pub struct AppState {
     pub neo4j_pool: Neo4jPool,
     pub database: Database,
     pub rate_limiter: RateLimiter,
+    pub jwt_keys: JwtKeyring,
+    pub oidc: Option<OidcClient>, // None when single sign-on is not configured
+    pub notifier: Arc<dyn Notifier>,
+    pub audit_signer: Option<AuditExportSigner>, // None unless AUDIT_EXPORT_SIGNING_KEY is set
+    pub schema_cache: SchemaCache, // Compiled attribute schemas of CI and relationship types
 }
 
 impl AppState {
@@ -48,6 +69,10 @@ impl AppState {
         pg_pool: PgPool,
         neo4j_pool: Neo4jPool,
         rate_limiter: RateLimiter,
+        jwt_keys: JwtKeyring,
+        oidc: Option<OidcClient>,
+        notifier: Arc<dyn Notifier>,
+        audit_signer: Option<AuditExportSigner>,
     ) -> Self {
         Self {
             config,
@@ -55,6 +80,11 @@ impl AppState {
             neo4j_pool: neo4j_pool.clone(),
             database: Database::new(pg_pool, neo4j_pool),
             rate_limiter,
+            jwt_keys,
+            oidc,
+            notifier,
+            audit_signer,
+            schema_cache: SchemaCache::new(),
         }
     }
 }
@@ -13,14 +13,18 @@ use crate_backend::{
     AppState,
     config::AppConfig,
     database::{get_pg_pool, get_neo4j_pool, run_migrations, run_initializations, PgPool, Neo4jPool},
-    middleware::{auth_middleware, logging_middleware, cors_middleware, rate_limit_middleware, RateLimiter},
+    middleware::{auth_middleware, admin_middleware, logging_middleware, cors_middleware, rate_limit_middleware, AuditExportSigner, JwtKeyring, RateLimiter},
     handlers::{
-        auth::{login, register, get_current_user, logout},
+        auth::{
+            login, register, get_current_user, logout, refresh_token, revoke_user_sessions,
+            oidc_authorize, oidc_callback, forgot_password, reset_password, change_password
+        },
         dashboard::get_dashboard_stats,
         ci_management::{
             create_ci_type, list_ci_types, create_ci_asset, list_ci_assets,
-            get_ci_asset, update_ci_asset, delete_ci_asset, get_ci_type,
-            update_ci_type, delete_ci_type
+            get_ci_asset, get_ci_asset_history, update_ci_asset, delete_ci_asset, restore_ci_asset, bulk_ci_assets, upsert_ci_asset, list_deleted_ci_assets, restore_deleted_ci_asset, get_ci_type,
+            update_ci_type, delete_ci_type, list_ci_type_schema_versions,
+            get_ci_type_effective_schema
         },
         lifecycle::{
             create_lifecycle_type, get_lifecycle_type, list_lifecycle_types,
@@ -32,11 +36,21 @@ use crate_backend::{
             create_relationship_type, get_relationship_type, list_relationship_types,
             update_relationship_type, delete_relationship_type
         },
+        roles::{
+            list_roles, create_role, get_role, update_role, delete_role,
+            grant_role_permission, revoke_role_permission, create_role_binding, delete_role_binding
+        },
+        users::{list_users, get_user, update_user, deactivate_user},
+        service_accounts::{
+            list_service_accounts, create_service_account, get_service_account,
+            deactivate_service_account, create_api_key, revoke_api_key
+        },
         graph::{get_graph_data, get_node_neighbors, search_nodes},
-        audit::get_audit_logs,
+        audit::{get_audit_logs, verify_audit_chain, export_audit_log},
         amortization::{get_valuation_records, get_amortization_schedule},
         import_export::{import_ci_assets, export_ci_assets},
     },
+    services::{OidcClient, notifier_from_config},
     jobs::start_background_jobs,
     error::AppError,
 };
@@ -59,13 +73,25 @@ async fn main() -> Result<(), AppError> {
     run_initializations(&neo4j_pool).await?;
 
     // Start background jobs
-    start_background_jobs(pg_pool.clone()).await?;
+    start_background_jobs(pg_pool.clone(), config.trash.retention_days).await?;
 
     // Initialize rate limiter
     let rate_limiter = RateLimiter::new(100, std::time::Duration::from_secs(60)); // 100 requests per minute
 
+    // Load JWT signing and verification keys
+    let jwt_keys = JwtKeyring::from_config(&config.auth)?;
+
+    // Set up single sign-on if an OIDC provider is configured
+    let oidc_client = config.oidc.clone().map(OidcClient::new).transpose()?;
+
+    // Set up delivery of password reset emails and other notifications
+    let notifier = notifier_from_config(&config.notifications)?;
+
+    // Load the key audit log exports are signed with, if configured
+    let audit_signer = config.audit.export_signing_key.as_ref().map(AuditExportSigner::from_config).transpose()?;
+
     // Create application state
-    let app_state = AppState::new(config.clone(), pg_pool.clone(), neo4j_pool.clone(), rate_limiter);
+    let app_state = AppState::new(config.clone(), pg_pool.clone(), neo4j_pool.clone(), rate_limiter, jwt_keys, oidc_client, notifier, audit_signer);
 
     // Build the application router
     let app = create_app(app_state);
@@ -75,7 +101,8 @@ async fn main() -> Result<(), AppError> {
     tracing::info!("Starting server on {}", addr);
 
     let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app)
+    // Peer addresses are recorded in the audit log for requests without a proxy in front
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
         .await
         .map_err(|e| AppError::internal(format!("Failed to start server: {}", e)))?;
 
@@ -98,22 +125,62 @@ fn create_app(app_state: AppState) -> Router {
         .route("/health", get(health_check))
         .route("/auth/login", post(login))
         .route("/auth/register", post(register))
-        .route("/auth/logout", post(logout));
+        .route("/auth/refresh", post(refresh_token))
+        .route("/auth/oidc/authorize", get(oidc_authorize))
+        .route("/auth/oidc/callback", get(oidc_callback))
+        .route("/auth/forgot-password", post(forgot_password))
+        .route("/auth/reset-password", post(reset_password));
+
+    // Create admin-only routes (auth_middleware runs first, see below)
+    let admin_routes = Router::new()
+        .route("/users", get(list_users))
+        .route("/users/:id", get(get_user))
+        .route("/users/:id", put(update_user))
+        .route("/users/:id", delete(deactivate_user))
+        .route("/users/:id/sessions", delete(revoke_user_sessions))
+        .route("/roles", get(list_roles))
+        .route("/roles", post(create_role))
+        .route("/roles/:id", get(get_role))
+        .route("/roles/:id", put(update_role))
+        .route("/roles/:id", delete(delete_role))
+        .route("/roles/:id/permissions", post(grant_role_permission))
+        .route("/roles/:id/permissions/:permission_id", delete(revoke_role_permission))
+        .route("/roles/:id/bindings", post(create_role_binding))
+        .route("/roles/:id/bindings/:user_id", delete(delete_role_binding))
+        .route("/service-accounts", get(list_service_accounts))
+        .route("/service-accounts", post(create_service_account))
+        .route("/service-accounts/:id", get(get_service_account))
+        .route("/service-accounts/:id", delete(deactivate_service_account))
+        .route("/service-accounts/:id/api-keys", post(create_api_key))
+        .route("/service-accounts/:id/api-keys/:key_id", delete(revoke_api_key))
+        .route("/audit/chain/verify", get(verify_audit_chain))
+        .route("/audit/export", get(export_audit_log))
+        .layer(middleware::from_fn(admin_middleware));
 
     // Create protected routes
     let protected_routes = Router::new()
         .route("/auth/me", get(get_current_user))
+        .route("/auth/logout", post(logout))
+        .route("/auth/change-password", post(change_password))
         .route("/dashboard/stats", get(get_dashboard_stats))
         .route("/ci-types", post(create_ci_type))
         .route("/ci-types", get(list_ci_types))
         .route("/ci-types/:id", get(get_ci_type))
         .route("/ci-types/:id", put(update_ci_type))
         .route("/ci-types/:id", delete(delete_ci_type))
+        .route("/ci-types/:id/schema-versions", get(list_ci_type_schema_versions))
+        .route("/ci-types/:id/effective-schema", get(get_ci_type_effective_schema))
         .route("/ci-assets", post(create_ci_asset))
         .route("/ci-assets", get(list_ci_assets))
+        .route("/ci-assets/bulk", post(bulk_ci_assets))
+        .route("/ci-assets/upsert", put(upsert_ci_asset))
         .route("/ci-assets/:id", get(get_ci_asset))
         .route("/ci-assets/:id", put(update_ci_asset))
         .route("/ci-assets/:id", delete(delete_ci_asset))
+        .route("/ci-assets/:id/history", get(get_ci_asset_history))
+        .route("/ci-assets/:id/restore", post(restore_ci_asset))
+        .route("/trash/ci-assets", get(list_deleted_ci_assets))
+        .route("/trash/ci-assets/:id/restore", post(restore_deleted_ci_asset))
         .route("/graph/data", get(get_graph_data))
         .route("/graph/nodes/:id/neighbors", get(get_node_neighbors))
         .route("/graph/search", get(search_nodes))
@@ -147,11 +214,16 @@ fn create_app(app_state: AppState) -> Router {
         // Relationship Instances Management (Phase 3.1)
         .route("/relationships", post(relationship::create_relationship))
         .route("/relationships", get(relationship::list_relationships))
+        .route("/relationships/bulk", post(relationship::bulk_relationships))
         .route("/relationships/:id", get(relationship::get_relationship))
         .route("/relationships/:id", put(relationship::update_relationship))
         .route("/relationships/:id", delete(relationship::delete_relationship))
+        .route("/relationships/:id/restore", post(relationship::restore_relationship))
+        .route("/trash/relationships", get(relationship::list_deleted_relationships))
+        .route("/trash/relationships/:id/restore", post(relationship::restore_deleted_relationship))
+        .merge(admin_routes)
         .layer(middleware::from_fn_with_state(
-            app_state.config.auth.jwt_secret.clone(),
+            app_state.clone(),
             auth_middleware,
         ));
 
@@ -1,15 +1,19 @@
 use axum::{
-    extract::{Request, State, FromRequestParts},
-    http::{request::Parts, header, StatusCode},
+    extract::{ConnectInfo, Request, State, FromRef, FromRequestParts},
+    http::{request::Parts, header, HeaderMap, StatusCode},
     middleware::Next,
     response::Response,
-    Json,
     async_trait,
 };
-use jsonwebtoken::{decode, DecodingKey, Validation, Header};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, SocketAddr};
 use uuid::Uuid;
 use crate::error::AppError;
+use crate::utils::hash_token;
+
+/// Header automation clients send their service account API key in
+pub const API_KEY_HEADER: &str = "x-api-key";
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Claims {
@@ -20,6 +24,21 @@ pub struct Claims {
     pub is_admin: bool,
     pub exp: usize,  // Expiration time as Unix timestamp
     pub iat: usize,  // Issued at time as Unix timestamp
+    #[serde(default)]
+    pub jti: String, // Token ID, used for revocation
+    #[serde(default)]
+    pub iat_us: i64, // Issued at in microseconds; `iat` is too coarse to order against a revocation
+}
+
+impl Claims {
+    /// When the token was issued, as precisely as it says. Tokens from before
+    /// `iat_us` existed only know the second.
+    pub fn issued_at(&self) -> DateTime<Utc> {
+        DateTime::from_timestamp_micros(self.iat_us)
+            .filter(|_| self.iat_us > 0)
+            .or_else(|| DateTime::from_timestamp(self.iat as i64, 0))
+            .unwrap_or(DateTime::<Utc>::MIN_UTC)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -29,34 +48,33 @@ pub struct AuthContext {
     pub first_name: String,
     pub last_name: String,
     pub is_admin: bool,
+    pub token_id: Option<String>,
+    pub token_expires_at: Option<usize>,
+    pub is_service_account: bool,
+    pub api_key_id: Option<Uuid>, // Set when authenticated with an API key instead of a JWT
+    pub ip_address: Option<String>, // Client the request came from, for the audit log
+    pub user_agent: Option<String>,
 }
 
-#[async_trait]
-impl<S> FromRequestParts<S> for AuthContext
-where
-    S: Send + Sync,
-{
-    type Rejection = AppError;
-
-    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
-        // Get the authorization header
-        let auth_header = parts
-            .headers
-            .get(header::AUTHORIZATION)
-            .and_then(|header| header.to_str().ok())
-            .and_then(|header| header.strip_prefix("Bearer "))
-            .ok_or_else(|| AppError::authentication("No authorization header provided"))?;
-
-        // Decode the JWT token
-        let token_data = decode::<Claims>(
-            auth_header,
-            &DecodingKey::from_secret("your-secret-key".as_ref()),
-            &Validation::default(),
-        )
-        .map_err(|_| AppError::authentication("Invalid token"))?;
-
-        let claims = token_data.claims;
+impl AuthContext {
+    /// The system user, for changes background jobs make
+    pub fn system() -> Self {
+        AuthContext {
+            user_id: crate::database::SYSTEM_USER_ID,
+            email: "system@localhost".to_string(),
+            first_name: "System".to_string(),
+            last_name: String::new(),
+            is_admin: false,
+            token_id: None,
+            token_expires_at: None,
+            is_service_account: true,
+            api_key_id: None,
+            ip_address: None,
+            user_agent: None,
+        }
+    }
 
+    fn from_claims(claims: Claims) -> Result<Self, AppError> {
         Ok(AuthContext {
             user_id: Uuid::parse_str(&claims.sub)
                 .map_err(|_| AppError::authentication("Invalid user ID in token"))?,
@@ -64,67 +82,174 @@ where
             first_name: claims.first_name,
             last_name: claims.last_name,
             is_admin: claims.is_admin,
+            token_id: Some(claims.jti).filter(|jti| !jti.is_empty()),
+            token_expires_at: Some(claims.exp),
+            is_service_account: false,
+            api_key_id: None,
+            ip_address: None,
+            user_agent: None,
         })
     }
 }
 
-pub async fn auth_middleware(
-    State(jwt_secret): State<String>,
-    mut request: Request,
-    next: Next,
-) -> Result<Response, StatusCode> {
-    let auth_header = request
-        .headers()
+/// Verify a bearer token and make sure it hasn't been revoked server-side and
+/// its user is still active
+pub async fn authenticate_bearer(app_state: &crate::AppState, token: &str) -> Result<AuthContext, AppError> {
+    // Signature, algorithm and expiry are all checked by the keyring
+    let claims: Claims = app_state
+        .jwt_keys
+        .verify(token)
+        .map_err(|_| AppError::authentication("Invalid token"))?;
+
+    let mut auth_context = AuthContext::from_claims(claims.clone())?;
+
+    let status = app_state
+        .database
+        .session_repository
+        .access_token_status(&claims.jti, auth_context.user_id, claims.issued_at())
+        .await?
+        .ok_or_else(|| AppError::authentication("User no longer exists"))?;
+
+    if status.revoked {
+        return Err(AppError::authentication("Token has been revoked"));
+    }
+
+    // Deactivation and admin changes take effect without waiting for the token to expire
+    if !status.is_active {
+        return Err(AppError::authentication("User account is inactive"));
+    }
+    auth_context.is_admin = status.is_admin;
+
+    Ok(auth_context)
+}
+
+/// Resolve a service account API key to the service account's identity
+pub async fn authenticate_api_key(app_state: &crate::AppState, key: &str) -> Result<AuthContext, AppError> {
+    let repository = &app_state.database.service_account_repository;
+
+    let (api_key_id, user) = repository
+        .find_usable_api_key(&hash_token(key))
+        .await?
+        .ok_or_else(|| AppError::authentication("Invalid API key"))?;
+
+    repository.touch_api_key(api_key_id).await?;
+
+    Ok(AuthContext {
+        user_id: user.id,
+        email: user.email,
+        first_name: user.first_name,
+        last_name: user.last_name,
+        is_admin: false,
+        token_id: None,
+        token_expires_at: None,
+        is_service_account: true,
+        api_key_id: Some(api_key_id),
+        ip_address: None,
+        user_agent: None,
+    })
+}
+
+/// Authenticate a request from its API key header, or failing that its bearer token
+pub async fn authenticate_request(
+    app_state: &crate::AppState,
+    headers: &HeaderMap,
+    remote_addr: Option<SocketAddr>,
+) -> Result<AuthContext, AppError> {
+    let mut auth_context = if let Some(key) = headers.get(API_KEY_HEADER).and_then(|value| value.to_str().ok()) {
+        authenticate_api_key(app_state, key).await?
+    } else {
+        let token = bearer_token(headers)
+            .ok_or_else(|| AppError::authentication("No authorization header provided"))?;
+
+        authenticate_bearer(app_state, token).await?
+    };
+
+    auth_context.ip_address = client_ip(headers, remote_addr, &app_state.config.server.trusted_proxies);
+    auth_context.user_agent = headers
+        .get(header::USER_AGENT)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+
+    Ok(auth_context)
+}
+
+/// The client address. Proxy headers are only believed when the peer is one
+/// of the trusted proxies; X-Forwarded-For is then read from the right, and
+/// the first address that isn't a trusted proxy is the client. Anyone else
+/// gets the peer address, whatever headers they send.
+fn client_ip(headers: &HeaderMap, remote_addr: Option<SocketAddr>, trusted_proxies: &[IpAddr]) -> Option<String> {
+    let remote_ip = remote_addr.map(|addr| addr.ip());
+    if !remote_ip.is_some_and(|ip| trusted_proxies.contains(&ip)) {
+        return remote_ip.map(|ip| ip.to_string());
+    }
+
+    let forwarded: Vec<&str> = headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .filter(|ip| !ip.is_empty())
+        .collect();
+
+    forwarded
+        .iter()
+        .rev()
+        .find(|ip| !ip.parse::<IpAddr>().is_ok_and(|ip| trusted_proxies.contains(&ip)))
+        .or(forwarded.first())
+        .copied()
+        .or_else(|| headers.get("x-real-ip").and_then(|value| value.to_str().ok()).map(str::trim).filter(|ip| !ip.is_empty()))
+        .map(str::to_string)
+        .or_else(|| remote_ip.map(|ip| ip.to_string()))
+}
+
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
         .get(header::AUTHORIZATION)
         .and_then(|header| header.to_str().ok())
-        .and_then(|header| {
-            if header.starts_with("Bearer ") {
-                Some(&header[7..])
-            } else {
-                None
-            }
-        });
-
-    let token = match auth_header {
-        Some(token) => token,
-        None => {
-            return Err(StatusCode::UNAUTHORIZED);
-        }
-    };
+        .and_then(|header| header.strip_prefix("Bearer "))
+}
 
-    let token_data = match decode::<Claims>(
-        token,
-        &DecodingKey::from_secret(jwt_secret.as_ref()),
-        &Validation::default(),
-    ) {
-        Ok(data) => data,
-        Err(_) => {
-            return Err(StatusCode::UNAUTHORIZED);
+#[async_trait]
+impl<S> FromRequestParts<S> for AuthContext
+where
+    crate::AppState: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        // Routes behind auth_middleware have already been authenticated
+        if let Some(auth_context) = parts.extensions.get::<AuthContext>() {
+            return Ok(auth_context.clone());
         }
-    };
 
-    let claims = token_data.claims;
+        let remote_addr = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| *addr);
 
-    // Check if token is expired
-    let now = chrono::Utc::now().timestamp() as usize;
-    if claims.exp < now {
-        return Err(StatusCode::UNAUTHORIZED);
+        authenticate_request(&crate::AppState::from_ref(state), &parts.headers, remote_addr).await
     }
+}
 
-    // Parse user ID from claims
-    let user_id = match Uuid::parse_str(&claims.sub) {
-        Ok(id) => id,
-        Err(_) => {
-            return Err(StatusCode::UNAUTHORIZED);
-        }
-    };
+pub async fn auth_middleware(
+    State(app_state): State<crate::AppState>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, StatusCode> {
+    let remote_addr = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| *addr);
 
-    let auth_context = AuthContext {
-        user_id,
-        email: claims.email,
-        first_name: claims.first_name,
-        last_name: claims.last_name,
-        is_admin: claims.is_admin,
+    let auth_context = match authenticate_request(&app_state, request.headers(), remote_addr).await {
+        Ok(auth_context) => auth_context,
+        Err(AppError::Authentication(_)) => return Err(StatusCode::UNAUTHORIZED),
+        Err(e) => {
+            tracing::error!("Failed to authenticate request: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
     };
 
     // Add auth context to request extensions
@@ -157,4 +282,46 @@ pub async fn admin_middleware(
     }
 
     Ok(next.run(request).await)
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    const PROXY: &str = "10.0.0.1";
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.append(*name, HeaderValue::from_static(value));
+        }
+        headers
+    }
+
+    fn peer(ip: &str) -> Option<SocketAddr> {
+        Some(SocketAddr::new(ip.parse().unwrap(), 443))
+    }
+
+    #[test]
+    fn untrusted_peers_cannot_spoof_their_address() {
+        let trusted = [PROXY.parse().unwrap()];
+        let spoofed = headers(&[("x-forwarded-for", "1.2.3.4"), ("x-real-ip", "5.6.7.8")]);
+
+        assert_eq!(client_ip(&spoofed, peer("203.0.113.9"), &trusted).as_deref(), Some("203.0.113.9"));
+        assert_eq!(client_ip(&spoofed, peer("203.0.113.9"), &[]).as_deref(), Some("203.0.113.9"));
+    }
+
+    #[test]
+    fn trusted_proxies_report_the_client() {
+        let trusted = [PROXY.parse().unwrap(), "10.0.0.2".parse().unwrap()];
+
+        let forwarded = headers(&[("x-forwarded-for", "1.2.3.4, 198.51.100.7, 10.0.0.2")]);
+        assert_eq!(client_ip(&forwarded, peer(PROXY), &trusted).as_deref(), Some("198.51.100.7"));
+
+        let real_ip = headers(&[("x-real-ip", "198.51.100.7")]);
+        assert_eq!(client_ip(&real_ip, peer(PROXY), &trusted).as_deref(), Some("198.51.100.7"));
+
+        assert_eq!(client_ip(&HeaderMap::new(), peer(PROXY), &trusted).as_deref(), Some(PROXY));
+    }
+}
@@ -1,9 +1,11 @@
 pub mod auth;
 pub mod cors;
+pub mod jwt;
 pub mod logging;
 pub mod rate_limit;
 
-pub use auth::{auth_middleware, AuthContext, Claims, extract_auth_context};
+pub use auth::{auth_middleware, admin_middleware, authenticate_bearer, authenticate_request, AuthContext, Claims, extract_auth_context, API_KEY_HEADER};
+pub use jwt::{AuditExportSigner, JwtKeyring};
 pub use cors::cors_middleware;
 pub use logging::logging_middleware;
 pub use rate_limit::{rate_limit_middleware, RateLimiter};
\ No newline at end of file
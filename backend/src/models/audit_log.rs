@@ -4,6 +4,8 @@ use serde_json::Value;
 use uuid::Uuid;
 use validator::Validate;
 
+use crate::utils::json_diff::JsonDiff;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AuditLog {
     pub id: Uuid,
@@ -16,6 +18,9 @@ pub struct AuditLog {
     pub ip_address: Option<String>,
     pub user_agent: Option<String>,
     pub created_at: DateTime<Utc>,
+    pub chain_seq: i64,
+    pub prev_hash: String,
+    pub entry_hash: String,
 }
 
 #[derive(Debug, Deserialize, Validate)]
@@ -32,4 +37,141 @@ pub struct CreateAuditLogRequest {
     pub new_values: Option<Value>,
     pub ip_address: Option<String>,
     pub user_agent: Option<String>,
-}
\ No newline at end of file
+}
+
+/// Tables audited by the application rather than by database triggers.
+/// `entity_type` is the table name, matching the trigger-written rows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditedEntity {
+    CiType,
+    CiAsset,
+    RelationshipType,
+    Relationship,
+}
+
+impl AuditedEntity {
+    pub fn table(&self) -> &'static str {
+        match self {
+            AuditedEntity::CiType => "ci_types",
+            AuditedEntity::CiAsset => "ci_assets",
+            AuditedEntity::RelationshipType => "relationship_types",
+            AuditedEntity::Relationship => "relationships",
+        }
+    }
+}
+
+/// Spelled like `TG_OP` so trigger and application rows read the same.
+/// Soft deletes are recorded as `DELETE`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditAction {
+    Insert,
+    Update,
+    Delete,
+}
+
+impl AuditAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditAction::Insert => "INSERT",
+            AuditAction::Update => "UPDATE",
+            AuditAction::Delete => "DELETE",
+        }
+    }
+}
+
+/// Query parameters for `/audit/logs`
+#[derive(Debug, Default, Deserialize)]
+pub struct AuditLogFilter {
+    pub entity_type: Option<String>,
+    pub entity_id: Option<Uuid>,
+    pub action: Option<String>, // Case-insensitive
+    pub performed_by: Option<Uuid>,
+    pub created_after: Option<DateTime<Utc>>,
+    pub created_before: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub include_diff: bool,
+}
+
+/// An audit log row with the actor resolved, and optionally what changed
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditLogEntry {
+    #[serde(flatten)]
+    pub log: AuditLog,
+    pub performed_by_name: Option<String>,
+    pub performed_by_email: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub diff: Option<JsonDiff>,
+}
+
+/// An audit entry with the exact text its hash covers, so the chain can be
+/// re-verified without access to the database
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditChainEntry {
+    #[serde(flatten)]
+    pub log: AuditLog,
+    pub canonical: String,
+}
+
+/// Query parameters for `/audit/chain/verify`
+#[derive(Debug, Default, Deserialize)]
+pub struct AuditChainVerifyQuery {
+    pub from_sequence: Option<i64>, // Start mid-chain, trusting that entry's prev_hash
+}
+
+/// The first place where the chain stops adding up
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditChainBreak {
+    pub chain_seq: i64,
+    pub id: Option<Uuid>, // None when the entry itself is missing
+    pub reason: String,
+    pub expected: String,
+    pub actual: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditChainReport {
+    pub valid: bool,
+    pub entries_checked: i64,
+    pub first_sequence: Option<i64>,
+    pub last_sequence: Option<i64>,
+    pub head_hash: Option<String>, // Hash of the last entry that verified
+    pub broken_link: Option<AuditChainBreak>,
+}
+
+/// Query parameters for `/audit/export`
+#[derive(Debug, Deserialize)]
+pub struct AuditExportQuery {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+}
+
+/// Signed summary of an export. The head hash commits to every entry through
+/// the chain, so signing it covers the whole export.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditExportManifest {
+    pub typ: String,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub first_sequence: Option<i64>,
+    pub last_sequence: Option<i64>,
+    pub anchor_hash: Option<String>, // prev_hash of the first entry
+    pub head_hash: Option<String>,   // entry_hash of the last entry
+    pub entry_count: i64,
+    pub exported_by: Option<Uuid>, // None for exports made with the CLI
+    pub iat: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SignedAuditExport {
+    pub manifest: AuditExportManifest,
+    pub signature: String, // Compact JWS whose claims are the manifest
+    pub entries: Vec<AuditChainEntry>,
+}
+
+/// Body of the `/restore` endpoints. Exactly one of the two picks the version:
+/// the state just before an audit entry, or the state at a past instant.
+#[derive(Debug, Deserialize)]
+pub struct RestoreVersionRequest {
+    pub audit_log_id: Option<Uuid>,
+    pub as_of: Option<DateTime<Utc>>,
+}
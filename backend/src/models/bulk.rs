@@ -0,0 +1,93 @@
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::error::{ErrorDetails, FieldError, FieldErrorCode};
+
+/// Operations accepted in one bulk request
+pub const MAX_BULK_OPERATIONS: usize = 1000;
+
+/// How the operations of a bulk request are applied
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BulkMode {
+    /// All operations commit together, or none does
+    #[default]
+    Atomic,
+    /// Each valid operation is applied on its own; failures are reported per item
+    BestEffort,
+}
+
+/// A batch of operations, validated as a whole before any is applied
+#[derive(Debug, Deserialize)]
+pub struct BulkRequest<T> {
+    #[serde(default)]
+    pub mode: BulkMode,
+    pub operations: Vec<T>,
+}
+
+impl<T> BulkRequest<T> {
+    pub fn check_size(&self) -> Result<(), FieldError> {
+        if self.operations.is_empty() || self.operations.len() > MAX_BULK_OPERATIONS {
+            return Err(FieldError::new(
+                "/operations",
+                FieldErrorCode::InvalidLength,
+                format!("A bulk request takes between 1 and {} operations", MAX_BULK_OPERATIONS),
+            ).with_expected(json!({ "min": 1, "max": MAX_BULK_OPERATIONS })));
+        }
+        Ok(())
+    }
+}
+
+/// JSON pointer to an operation of a bulk request, under which its errors go
+pub fn operation_path(index: usize) -> String {
+    format!("/operations/{}", index)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BulkItemStatus {
+    Applied,
+    Failed,
+}
+
+/// What became of one operation, by its position in the request
+#[derive(Debug, Clone, Serialize)]
+pub struct BulkItemResult {
+    pub index: usize,
+    pub status: BulkItemStatus,
+    pub id: Option<Uuid>, // The record created, updated or deleted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ErrorDetails>, // Shaped like the `error` of an error response
+}
+
+impl BulkItemResult {
+    pub fn applied(index: usize, id: Uuid) -> Self {
+        Self { index, status: BulkItemStatus::Applied, id: Some(id), error: None }
+    }
+
+    pub fn failed(index: usize, id: Option<Uuid>, error: ErrorDetails) -> Self {
+        Self { index, status: BulkItemStatus::Failed, id, error: Some(error) }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BulkResult {
+    pub mode: BulkMode,
+    pub applied: usize,
+    pub failed: usize,
+    pub items: Vec<BulkItemResult>,
+}
+
+impl BulkResult {
+    pub fn new(mode: BulkMode, mut items: Vec<BulkItemResult>) -> Self {
+        items.sort_by_key(|item| item.index);
+        let applied = items.iter().filter(|item| item.status == BulkItemStatus::Applied).count();
+        Self {
+            mode,
+            applied,
+            failed: items.len() - applied,
+            items,
+        }
+    }
+}
@@ -4,19 +4,23 @@ use serde_json::Value;
 use uuid::Uuid;
 use validator::Validate;
 
+use crate::utils::json_diff::JsonDiff;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CIAsset {
     pub id: Uuid,
     pub ci_type_id: Uuid,
     pub name: String,
     pub attributes: Value,
+    #[serde(default)]
+    pub computed_attributes: Value, // Derived from `attributes` by the CI type; read-only
     pub created_by: Uuid,
     pub updated_by: Option<Uuid>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize, Validate)]
+#[derive(Debug, Serialize, Deserialize, Validate)]
 pub struct CreateCIAssetRequest {
     pub ci_type_id: Uuid,
 
@@ -34,6 +38,50 @@ pub struct UpdateCIAssetRequest {
     pub attributes: Option<Value>,
 }
 
+/// One operation of a bulk request on CI assets, tagged by `op`
+#[derive(Debug, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum CIAssetOperation {
+    Create(CreateCIAssetRequest),
+    Update(CIAssetUpdate),
+    Delete { id: Uuid },
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CIAssetUpdate {
+    pub id: Uuid,
+    #[serde(flatten)]
+    pub changes: UpdateCIAssetRequest,
+}
+
+impl CIAssetOperation {
+    /// The existing asset the operation changes
+    pub fn target(&self) -> Option<Uuid> {
+        match self {
+            CIAssetOperation::Create(_) => None,
+            CIAssetOperation::Update(update) => Some(update.id),
+            CIAssetOperation::Delete { id } => Some(*id),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UpsertOutcome {
+    Created,
+    Updated,
+    Unchanged, // The matching asset already had this name and these attributes
+}
+
+/// What an upsert did with the asset its natural key identifies
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpsertedCIAsset {
+    pub id: Uuid,
+    pub outcome: UpsertOutcome,
+    #[serde(default)]
+    pub replayed: bool, // Answered from an earlier request with the same Idempotency-Key
+}
+
 #[derive(Debug, Serialize)]
 pub struct CIAssetResponse {
     pub id: Uuid,
@@ -50,12 +98,59 @@ pub struct CIAssetResponse {
 #[derive(Debug, Deserialize)]
 pub struct CIAssetFilter {
     pub ci_type_id: Option<Uuid>,
+    #[serde(default)]
+    pub include_subtypes: bool, // Also match assets of ci_type_id's subtypes
     pub name: Option<String>,
     pub created_by: Option<Uuid>,
     pub created_after: Option<DateTime<Utc>>,
     pub created_before: Option<DateTime<Utc>>,
-    pub limit: Option<i64>,
-    pub offset: Option<i64>,
+    pub filter: Option<String>, // Attribute filter expression, e.g. `attributes.env = "prod"`
+    pub sort: Option<String>, // Comma-separated `field[:asc|desc]` keys
+}
+
+/// Query parameters for `GET /ci-assets/:id`
+#[derive(Debug, Default, Deserialize)]
+pub struct CIAssetAsOfQuery {
+    pub as_of: Option<DateTime<Utc>>, // Rebuild the asset as it was at this instant
+}
+
+/// One recorded change to an asset and the asset as it was right after it
+#[derive(Debug, Clone, Serialize)]
+pub struct CIAssetVersion {
+    pub version: i64, // 1 is the oldest recorded change
+    pub audit_log_id: Uuid,
+    pub action: String,
+    pub deleted: bool,
+    pub ci_type_id: Option<Uuid>,
+    pub name: Option<String>, // None once deleted
+    pub attributes: Option<Value>,
+    pub changes: JsonDiff, // Attributes relative to the previous version
+    pub changed_by: Uuid,
+    pub changed_by_name: Option<String>,
+    pub changed_at: DateTime<Utc>,
+}
+
+/// An asset's name and attributes at a past instant
+#[derive(Debug, Clone, Serialize)]
+pub struct CIAssetAsOf {
+    pub id: Uuid,
+    pub ci_type_id: Uuid,
+    pub name: String,
+    pub attributes: Value,
+    pub as_of: DateTime<Utc>,
+    pub version: Option<i64>, // None when the state predates the recorded history
+}
+
+/// An asset after being restored to an earlier version or out of the trash
+#[derive(Debug, Clone, Serialize)]
+pub struct RestoredCIAsset {
+    pub id: Uuid,
+    pub ci_type_id: Uuid,
+    pub name: String,
+    pub attributes: Value,
+    pub undeleted: bool,  // The asset had been soft-deleted
+    pub changes: JsonDiff, // From the attributes before the restore to the restored ones
+    pub restored_relationships: Vec<Uuid>, // Relationships its deletion took along that came back with it
 }
 
 impl From<(CIAsset, String)> for CIAssetResponse {
@@ -172,4 +172,11 @@ pub struct LifecycleTypeSummary {
     pub state_count: i64,
     pub ci_type_count: i64,
     pub created_at: DateTime<Utc>,
+}
+
+/// Query parameters for `GET /lifecycle-types`
+#[derive(Debug, Default, Deserialize)]
+pub struct LifecycleTypeFilter {
+    #[serde(default)]
+    pub include_inactive: bool,
 }
\ No newline at end of file
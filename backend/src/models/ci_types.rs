@@ -1,9 +1,12 @@
+use crate::error::FieldError;
 use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use serde_json::Value;
 use uuid::Uuid;
 use validator::Validate;
 
+use crate::utils::schema_migration::SchemaMigration;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CIType {
     pub id: Uuid,
@@ -14,6 +17,9 @@ pub struct CIType {
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub deleted_at: Option<DateTime<Utc>>,
+    pub schema_version: Option<i32>, // None while attributes has no "schema"
+    pub parent_id: Option<Uuid>,
+    pub is_abstract: bool, // Only extended by other types; has no assets of its own
 }
 
 #[derive(Debug, Deserialize, Validate)]
@@ -25,6 +31,10 @@ pub struct CreateCITypeRequest {
     pub description: Option<String>,
 
     pub attributes: Option<Value>,
+
+    pub parent_id: Option<Uuid>,
+    #[serde(default)]
+    pub is_abstract: bool,
 }
 
 #[derive(Debug, Deserialize, Validate)]
@@ -36,6 +46,67 @@ pub struct UpdateCITypeRequest {
     pub description: Option<String>,
 
     pub attributes: Option<Value>,
+
+    // Absent leaves the parent alone; null detaches the type from it
+    #[serde(default, deserialize_with = "present")]
+    pub parent_id: Option<Option<Uuid>>,
+    pub is_abstract: Option<bool>,
+
+    // Only used when the type's effective schema changes
+    pub migration: Option<SchemaMigration>,
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// Distinguishes a field sent as null (`Some(None)`) from one left out (`None`)
+fn present<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+/// A CI type's schema with everything it inherits merged in
+#[derive(Debug, Clone, Serialize)]
+pub struct EffectiveSchema {
+    pub ci_type_id: Uuid,
+    pub lineage: Vec<Uuid>, // The type first, then its ancestors
+    pub schema: Option<Value>,
+}
+
+/// One version of a CI type's attribute schema
+#[derive(Debug, Clone, Serialize)]
+pub struct CITypeSchemaVersion {
+    pub id: Uuid,
+    pub ci_type_id: Uuid,
+    pub version: i32,
+    pub schema: Value,
+    pub migration: Option<Value>,
+    pub created_by: Uuid,
+    pub created_at: DateTime<Utc>,
+}
+
+/// An asset that would not conform to a new schema
+#[derive(Debug, Clone, Serialize)]
+pub struct SchemaViolation {
+    pub asset_id: Uuid,
+    pub asset_name: String,
+    pub errors: Vec<FieldError>,
+}
+
+/// What a schema change does to a CI type's existing assets
+#[derive(Debug, Clone, Serialize)]
+pub struct SchemaChangeReport {
+    pub from_version: Option<i32>,
+    pub to_version: Option<i32>, // Same as from_version when only an inherited schema changes
+    pub dry_run: bool,
+    pub assets_checked: i64, // Including assets of subtypes
+    pub assets_to_migrate: i64, // Assets whose attributes the migration rewrites
+    pub assets_migrated: i64,
+    pub assets_skipped: Vec<Uuid>, // Changed since the check and no longer fit; left on their old schema
+    pub violation_count: i64,
+    pub violations: Vec<SchemaViolation>, // The first few, up to a limit
 }
 
 #[derive(Debug, Serialize)]
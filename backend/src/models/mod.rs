@@ -5,24 +5,43 @@ pub mod relationship_types;
 pub mod audit_log;
 pub mod valuation;
 pub mod user;
+pub mod rbac;
+pub mod service_account;
+pub mod bulk;
+pub mod trash;
 
-pub use ci_types::{CIType, CreateCITypeRequest, UpdateCITypeRequest, CITypeResponse};
+pub use ci_types::{CIType, CreateCITypeRequest, UpdateCITypeRequest, CITypeResponse, CITypeSchemaVersion, SchemaViolation, SchemaChangeReport, EffectiveSchema};
 pub use ci_lifecycle::{
     CILifecycle, CreateLifecycleRequest,
     LifecycleType, LifecycleState, LifecycleTransition, CITypeLifecycleMapping,
     CreateLifecycleTypeRequest, UpdateLifecycleTypeRequest,
     CreateLifecycleStateRequest, UpdateLifecycleStateRequest,
     CreateLifecycleTransitionRequest, CreateCITypeLifecycleRequest,
-    LifecycleTypeResponse, LifecycleTypeSummary
+    LifecycleTypeResponse, LifecycleTypeSummary, LifecycleTypeFilter
 };
-pub use ci_assets::{CIAsset, CreateCIAssetRequest, UpdateCIAssetRequest, CIAssetFilter, CIAssetResponse};
+pub use ci_assets::{CIAsset, CreateCIAssetRequest, UpdateCIAssetRequest, CIAssetFilter, CIAssetResponse, CIAssetAsOfQuery, CIAssetVersion, CIAssetAsOf, RestoredCIAsset, CIAssetOperation, CIAssetUpdate, UpsertOutcome, UpsertedCIAsset};
 pub use relationship_types::{
     RelationshipType, RelationshipTypeWithDetails, CreateRelationshipTypeRequest,
     UpdateRelationshipTypeRequest, RelationshipTypeFilter, RelationshipTypeResponse,
-    RelationshipTypeSummary,
+    RelationshipTypeSummary, RelationshipSchemaViolation, RelationshipSchemaReport,
     Relationship, RelationshipWithDetails, CreateRelationshipRequest,
-    UpdateRelationshipRequest, RelationshipFilter, RelationshipResponse
+    UpdateRelationshipRequest, RelationshipFilter, RelationshipResponse,
+    RelationshipOperation, RelationshipUpdate
 };
-pub use audit_log::{AuditLog, CreateAuditLogRequest};
+pub use audit_log::{AuditLog, CreateAuditLogRequest, AuditLogFilter, AuditLogEntry, AuditedEntity, AuditAction, AuditChainEntry, AuditChainVerifyQuery, AuditChainBreak, AuditChainReport, AuditExportQuery, AuditExportManifest, SignedAuditExport, RestoreVersionRequest};
 pub use valuation::{ValuationRecord, AmortizationEntry, CreateValuationRequest};
-pub use user::{User, CreateUserRequest, LoginRequest, LoginResponse, UserResponse, UpdateUserRequest, ChangePasswordRequest};
\ No newline at end of file
+pub use user::{
+    User, CreateUserRequest, LoginRequest, LoginResponse, UserResponse, UpdateUserRequest, UserFilter, ChangePasswordRequest,
+    ForgotPasswordRequest, ResetPasswordRequest,
+    RefreshTokenRequest, LogoutRequest, OidcAuthorizeResponse, OidcCallbackQuery
+};
+pub use rbac::{
+    ResourceType, PermissionAction, Role, RolePermission, RoleBinding, RoleResponse,
+    CreateRoleRequest, UpdateRoleRequest, GrantPermissionRequest, CreateRoleBindingRequest
+};
+pub use service_account::{
+    ServiceAccount, ApiKey, ApiKeyPermission, ServiceAccountResponse, CreateApiKeyResponse,
+    CreateServiceAccountRequest, CreateApiKeyRequest
+};
+pub use bulk::{BulkMode, BulkRequest, BulkResult, BulkItemResult, BulkItemStatus, MAX_BULK_OPERATIONS, operation_path};
+pub use trash::{DeletedCIAssetFilter, DeletedRelationshipFilter, DeletedCIAsset, DeletedRelationship};
\ No newline at end of file
@@ -0,0 +1,173 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use uuid::Uuid;
+use validator::Validate;
+
+/// Kinds of CMDB objects a permission can be granted on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResourceType {
+    CiType,
+    CiAsset,
+    RelationshipType,
+    Relationship,
+    Lifecycle,
+    AuditLog,
+}
+
+impl ResourceType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ResourceType::CiType => "ci_type",
+            ResourceType::CiAsset => "ci_asset",
+            ResourceType::RelationshipType => "relationship_type",
+            ResourceType::Relationship => "relationship",
+            ResourceType::Lifecycle => "lifecycle",
+            ResourceType::AuditLog => "audit_log",
+        }
+    }
+}
+
+impl fmt::Display for ResourceType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ResourceType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ci_type" => Ok(ResourceType::CiType),
+            "ci_asset" => Ok(ResourceType::CiAsset),
+            "relationship_type" => Ok(ResourceType::RelationshipType),
+            "relationship" => Ok(ResourceType::Relationship),
+            "lifecycle" => Ok(ResourceType::Lifecycle),
+            "audit_log" => Ok(ResourceType::AuditLog),
+            _ => Err(format!("Unknown resource type '{}'", s)),
+        }
+    }
+}
+
+/// Actions a permission allows; `Manage` implies all of the others
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PermissionAction {
+    Read,
+    Create,
+    Update,
+    Delete,
+    Manage,
+}
+
+impl PermissionAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PermissionAction::Read => "read",
+            PermissionAction::Create => "create",
+            PermissionAction::Update => "update",
+            PermissionAction::Delete => "delete",
+            PermissionAction::Manage => "manage",
+        }
+    }
+
+    /// Whether holding this action is enough to perform `requested`
+    pub fn covers(&self, requested: PermissionAction) -> bool {
+        *self == PermissionAction::Manage || *self == requested
+    }
+}
+
+impl fmt::Display for PermissionAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for PermissionAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "read" => Ok(PermissionAction::Read),
+            "create" => Ok(PermissionAction::Create),
+            "update" => Ok(PermissionAction::Update),
+            "delete" => Ok(PermissionAction::Delete),
+            "manage" => Ok(PermissionAction::Manage),
+            _ => Err(format!("Unknown permission action '{}'", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Role {
+    pub id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub is_system: bool,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RolePermission {
+    pub id: Uuid,
+    pub role_id: Uuid,
+    pub resource_type: ResourceType,
+    pub action: PermissionAction,
+    pub scope_id: Option<Uuid>, // None = every instance of the resource type
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoleBinding {
+    pub id: Uuid,
+    pub role_id: Uuid,
+    pub user_id: Uuid,
+    pub user_email: String,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RoleResponse {
+    #[serde(flatten)]
+    pub role: Role,
+    pub permissions: Vec<RolePermission>,
+    pub bindings: Vec<RoleBinding>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateRoleRequest {
+    #[validate(length(min = 1, max = 100))]
+    pub name: String,
+
+    #[validate(length(max = 1000))]
+    pub description: Option<String>,
+
+    pub permissions: Option<Vec<GrantPermissionRequest>>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateRoleRequest {
+    #[validate(length(min = 1, max = 100))]
+    pub name: Option<String>,
+
+    #[validate(length(max = 1000))]
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GrantPermissionRequest {
+    pub resource_type: ResourceType,
+    pub action: PermissionAction,
+    pub scope_id: Option<Uuid>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateRoleBindingRequest {
+    pub user_id: Uuid,
+}
@@ -1,3 +1,4 @@
+use crate::error::FieldError;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
@@ -14,6 +15,7 @@ pub struct RelationshipType {
     pub is_bidirectional: bool,
     pub reverse_name: Option<String>,
     pub attributes_schema: Value,
+    pub include_subtypes: bool, // CI type constraints also accept subtypes
     pub created_by: Uuid,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
@@ -47,6 +49,13 @@ pub struct CreateRelationshipTypeRequest {
     pub reverse_name: Option<String>,
 
     pub attributes_schema: Option<Value>,
+
+    #[serde(default = "default_include_subtypes")]
+    pub include_subtypes: bool,
+}
+
+fn default_include_subtypes() -> bool {
+    true
 }
 
 #[derive(Debug, Deserialize, Validate)]
@@ -67,6 +76,30 @@ pub struct UpdateRelationshipTypeRequest {
     pub reverse_name: Option<String>,
 
     pub attributes_schema: Option<Value>,
+
+    pub include_subtypes: Option<bool>,
+
+    /// Only report which relationships a new `attributes_schema` would reject
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// A relationship whose attributes don't fit a proposed schema
+#[derive(Debug, Clone, Serialize)]
+pub struct RelationshipSchemaViolation {
+    pub relationship_id: Uuid,
+    pub from_ci_asset_id: Uuid,
+    pub to_ci_asset_id: Uuid,
+    pub errors: Vec<FieldError>,
+}
+
+/// What an `attributes_schema` change does to a relationship type's existing relationships
+#[derive(Debug, Clone, Serialize)]
+pub struct RelationshipSchemaReport {
+    pub dry_run: bool,
+    pub relationships_checked: i64,
+    pub violation_count: i64,
+    pub violations: Vec<RelationshipSchemaViolation>, // The first few, up to a limit
 }
 
 #[derive(Debug, Deserialize)]
@@ -75,8 +108,6 @@ pub struct RelationshipTypeFilter {
     pub from_ci_type_id: Option<Uuid>,
     pub to_ci_type_id: Option<Uuid>,
     pub is_bidirectional: Option<bool>,
-    pub limit: Option<i64>,
-    pub offset: Option<i64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -106,6 +137,7 @@ pub struct RelationshipTypeSummary {
     pub from_ci_type_name: Option<String>,
     pub to_ci_type_name: Option<String>,
     pub relationship_count: i64,
+    pub created_at: DateTime<Utc>,
 }
 
 // Relationship Instance Models (Phase 3.1)
@@ -154,14 +186,39 @@ pub struct UpdateRelationshipRequest {
     pub attributes: Option<Value>,
 }
 
+/// One operation of a bulk request on relationships, tagged by `op`
+#[derive(Debug, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum RelationshipOperation {
+    Create(CreateRelationshipRequest),
+    Update(RelationshipUpdate),
+    Delete { id: Uuid },
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RelationshipUpdate {
+    pub id: Uuid,
+    #[serde(flatten)]
+    pub changes: UpdateRelationshipRequest,
+}
+
+impl RelationshipOperation {
+    /// The existing relationship the operation changes
+    pub fn target(&self) -> Option<Uuid> {
+        match self {
+            RelationshipOperation::Create(_) => None,
+            RelationshipOperation::Update(update) => Some(update.id),
+            RelationshipOperation::Delete { id } => Some(*id),
+        }
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct RelationshipFilter {
     pub relationship_type_id: Option<Uuid>,
     pub ci_asset_id: Option<Uuid>, // Find all relationships for this asset (from or to)
     pub from_ci_asset_id: Option<Uuid>,
     pub to_ci_asset_id: Option<Uuid>,
-    pub limit: Option<i64>,
-    pub offset: Option<i64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
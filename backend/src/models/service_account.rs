@@ -0,0 +1,72 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use validator::Validate;
+
+use super::rbac::{GrantPermissionRequest, PermissionAction, ResourceType};
+
+/// A non-human principal used by automation; its id is also a users.id
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceAccount {
+    pub id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub is_active: bool,
+    pub created_by: Uuid,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKey {
+    pub id: Uuid,
+    pub service_account_id: Uuid,
+    pub name: String,
+    pub key_prefix: String,
+    pub permissions: Vec<ApiKeyPermission>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_by: Uuid,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKeyPermission {
+    pub resource_type: ResourceType,
+    pub action: PermissionAction,
+    pub scope_id: Option<Uuid>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ServiceAccountResponse {
+    #[serde(flatten)]
+    pub service_account: ServiceAccount,
+    pub api_keys: Vec<ApiKey>,
+}
+
+/// Returned once when a key is created; the plaintext key is not stored
+#[derive(Debug, Clone, Serialize)]
+pub struct CreateApiKeyResponse {
+    pub api_key: ApiKey,
+    pub key: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateServiceAccountRequest {
+    #[validate(length(min = 1, max = 100))]
+    pub name: String,
+
+    #[validate(length(max = 1000))]
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateApiKeyRequest {
+    #[validate(length(min = 1, max = 100))]
+    pub name: String,
+
+    pub expires_at: Option<DateTime<Utc>>,
+
+    #[validate(length(min = 1))]
+    pub permissions: Vec<GrantPermissionRequest>,
+}
@@ -0,0 +1,45 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct DeletedCIAssetFilter {
+    pub ci_type_id: Option<Uuid>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct DeletedRelationshipFilter {
+    pub relationship_type_id: Option<Uuid>,
+    pub ci_asset_id: Option<Uuid>, // Deleted relationships from or to this asset
+}
+
+/// A soft-deleted asset, as the trash lists it
+#[derive(Debug, Clone, Serialize)]
+pub struct DeletedCIAsset {
+    pub id: Uuid,
+    pub name: String,
+    pub ci_type_id: Uuid,
+    pub ci_type_name: String,
+    pub attributes: Value,
+    pub deleted_at: DateTime<Utc>,
+    pub deleted_by: Option<Uuid>,
+    pub deleted_by_name: Option<String>,
+}
+
+/// A soft-deleted relationship, as the trash lists it
+#[derive(Debug, Clone, Serialize)]
+pub struct DeletedRelationship {
+    pub id: Uuid,
+    pub relationship_type_id: Uuid,
+    pub relationship_type_name: String,
+    pub from_ci_asset_id: Uuid,
+    pub from_ci_asset_name: String,
+    pub to_ci_asset_id: Uuid,
+    pub to_ci_asset_name: String,
+    pub attributes: Value,
+    pub deleted_at: DateTime<Utc>,
+    pub deleted_by: Option<Uuid>,
+    pub deleted_by_name: Option<String>,
+    pub deleted_with_asset_id: Option<Uuid>, // Deleting this asset took the relationship along; restoring it brings it back
+}
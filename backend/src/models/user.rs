@@ -46,6 +46,35 @@ pub struct LoginResponse {
     pub user: UserResponse,
     pub token: String,
     pub expires_in: u64, // seconds
+    pub refresh_token: String,
+    pub refresh_expires_in: u64, // seconds
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct RefreshTokenRequest {
+    #[validate(length(min = 1))]
+    pub refresh_token: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct LogoutRequest {
+    pub refresh_token: Option<String>,
+}
+
+/// Where to send the browser to sign in with the identity provider
+#[derive(Debug, Serialize)]
+pub struct OidcAuthorizeResponse {
+    pub authorization_url: String,
+    pub state: String,
+}
+
+/// Query parameters the identity provider redirects back with
+#[derive(Debug, Deserialize)]
+pub struct OidcCallbackQuery {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    pub error: Option<String>,
+    pub error_description: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -58,7 +87,7 @@ pub struct UserResponse {
     pub created_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize, Validate)]
+#[derive(Debug, Default, Deserialize, Validate)]
 pub struct UpdateUserRequest {
     #[validate(email)]
     pub email: Option<String>,
@@ -73,6 +102,14 @@ pub struct UpdateUserRequest {
     pub is_admin: Option<bool>,
 }
 
+/// Query parameters for the admin user listing
+#[derive(Debug, Default, Deserialize)]
+pub struct UserFilter {
+    pub search: Option<String>, // Matches email, first or last name
+    pub is_active: Option<bool>,
+    pub is_admin: Option<bool>,
+}
+
 #[derive(Debug, Deserialize, Validate)]
 pub struct ChangePasswordRequest {
     #[validate(length(min = 1))]
@@ -82,6 +119,21 @@ pub struct ChangePasswordRequest {
     pub new_password: String,
 }
 
+#[derive(Debug, Deserialize, Validate)]
+pub struct ForgotPasswordRequest {
+    #[validate(email)]
+    pub email: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ResetPasswordRequest {
+    #[validate(length(min = 1))]
+    pub token: String,
+
+    #[validate(length(min = 8))]
+    pub new_password: String,
+}
+
 impl From<User> for UserResponse {
     fn from(user: User) -> Self {
         Self {
@@ -1,38 +1,235 @@
-use crate::database::{PgPool, Neo4jPool};
-use crate::models::{User, CreateUserRequest, LoginRequest};
-use crate::utils::{hash_password, verify_password, create_jwt};
+use crate::config::AuthConfig;
+use crate::database::{UserRepository, SessionRepository, RbacRepository, RefreshTokenUse, UNUSABLE_PASSWORD_HASH, is_unique_violation};
+use crate::models::{User, CreateUserRequest, LoginRequest, LoginResponse, UserResponse, RefreshTokenRequest};
+use crate::middleware::{AuthContext, Claims, JwtKeyring};
+use crate::utils::{hash_password, verify_password, validate_password_strength, generate_secure_token, hash_token};
 use crate::error::{AppError, AppResult};
+use chrono::{Duration, TimeZone, Utc};
 use uuid::Uuid;
+use validator::Validate;
+
+const REFRESH_TOKEN_LENGTH: usize = 64;
 
 pub struct AuthService {
-    pg_pool: PgPool,
-    neo4j_pool: Neo4jPool,
+    user_repository: UserRepository,
+    session_repository: SessionRepository,
+    rbac_repository: RbacRepository,
+    jwt_keys: JwtKeyring,
+    access_token_ttl: Duration,
+    refresh_token_ttl: Duration,
+    default_role: Option<String>,
 }
 
 impl AuthService {
-    pub fn new(pg_pool: PgPool, neo4j_pool: Neo4jPool) -> Self {
+    pub fn new(
+        user_repository: UserRepository,
+        session_repository: SessionRepository,
+        rbac_repository: RbacRepository,
+        jwt_keys: JwtKeyring,
+        config: &AuthConfig,
+    ) -> Self {
         Self {
-            pg_pool,
-            neo4j_pool,
+            user_repository,
+            session_repository,
+            rbac_repository,
+            jwt_keys,
+            access_token_ttl: Duration::hours(config.jwt_expiration_hours as i64),
+            refresh_token_ttl: Duration::days(config.refresh_token_expiration_days as i64),
+            default_role: config.default_role.clone(),
         }
     }
 
     pub async fn authenticate_user(
         &self,
         request: LoginRequest,
-        jwt_secret: &str,
-        jwt_expiration_hours: u64,
-    ) -> AppResult<(crate::models::LoginResponse, User)> {
-        // TODO: Implement actual user authentication
-        // For now, return a mock response
-        Err(AppError::not_found("User not found"))
+    ) -> AppResult<(LoginResponse, User)> {
+        request.validate()?;
+
+        // Use the same message for unknown email and wrong password so the
+        // endpoint can't be used to enumerate accounts
+        let (user, password_hash) = self.user_repository
+            .get_user_with_password_by_email(&request.email)
+            .await?
+            .ok_or_else(|| AppError::authentication("Invalid email or password"))?;
+
+        // Single sign-on users have no password to check against
+        if password_hash == UNUSABLE_PASSWORD_HASH
+            || !verify_password(&request.password, &password_hash)?
+        {
+            return Err(AppError::authentication("Invalid email or password"));
+        }
+
+        if !user.is_active {
+            return Err(AppError::authorization("User account is inactive"));
+        }
+
+        let login_response = self.start_session(&user).await?;
+
+        Ok((login_response, user))
+    }
+
+    /// Issue an access token and a new refresh token family for an authenticated user
+    pub async fn start_session(&self, user: &User) -> AppResult<LoginResponse> {
+        let refresh_token = generate_secure_token(REFRESH_TOKEN_LENGTH)?;
+        self.session_repository
+            .create_refresh_token(
+                user.id,
+                Uuid::new_v4(),
+                &hash_token(&refresh_token),
+                Utc::now() + self.refresh_token_ttl,
+            )
+            .await?;
+
+        self.issue_tokens(user, refresh_token)
+    }
+
+    /// Exchange a refresh token for a new access token and a new refresh token
+    pub async fn refresh_session(&self, request: RefreshTokenRequest) -> AppResult<LoginResponse> {
+        request.validate()?;
+
+        let new_refresh_token = generate_secure_token(REFRESH_TOKEN_LENGTH)?;
+
+        let outcome = self.session_repository
+            .rotate_refresh_token(
+                &hash_token(&request.refresh_token),
+                &hash_token(&new_refresh_token),
+                Utc::now() + self.refresh_token_ttl,
+            )
+            .await?;
+
+        let user_id = match outcome {
+            RefreshTokenUse::Rotated { user_id, .. } => user_id,
+            RefreshTokenUse::Reused { user_id, family_id } => {
+                tracing::warn!(
+                    "Refresh token reuse detected for user {}; revoked token family {} and the user's access tokens",
+                    user_id, family_id
+                );
+                return Err(AppError::authentication("Refresh token has already been used"));
+            }
+            RefreshTokenUse::Expired | RefreshTokenUse::NotFound => {
+                return Err(AppError::authentication("Invalid or expired refresh token"));
+            }
+        };
+
+        let user = self.user_repository
+            .get_user_by_id(user_id)
+            .await?
+            .ok_or_else(|| AppError::authentication("Invalid or expired refresh token"))?;
+
+        if !user.is_active {
+            self.session_repository.revoke_all_user_sessions(user.id).await?;
+            return Err(AppError::authorization("User account is inactive"));
+        }
+
+        self.issue_tokens(&user, new_refresh_token)
+    }
+
+    /// Revoke the caller's access token and, if given, its refresh token family
+    pub async fn logout(&self, auth_context: &AuthContext, refresh_token: Option<&str>) -> AppResult<()> {
+        if let (Some(jti), Some(exp)) = (&auth_context.token_id, auth_context.token_expires_at) {
+            let expires_at = Utc.timestamp_opt(exp as i64, 0)
+                .single()
+                .unwrap_or_else(|| Utc::now() + self.access_token_ttl);
+
+            self.session_repository
+                .revoke_access_token(jti, auth_context.user_id, expires_at, "logout")
+                .await?;
+        }
+
+        if let Some(refresh_token) = refresh_token {
+            self.session_repository
+                .revoke_refresh_token_family(&hash_token(refresh_token), auth_context.user_id)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Force-logout a user everywhere: all refresh tokens and all access tokens issued so far
+    pub async fn revoke_user_sessions(&self, user_id: Uuid) -> AppResult<()> {
+        self.get_user(user_id).await?;
+
+        self.session_repository.revoke_all_user_sessions(user_id).await?;
+        Ok(())
+    }
+
+    fn issue_tokens(&self, user: &User, refresh_token: String) -> AppResult<LoginResponse> {
+        let now = Utc::now();
+        let claims = Claims {
+            sub: user.id.to_string(),
+            email: user.email.clone(),
+            first_name: user.first_name.clone(),
+            last_name: user.last_name.clone(),
+            is_admin: user.is_admin,
+            iat: now.timestamp() as usize,
+            exp: (now + self.access_token_ttl).timestamp() as usize,
+            jti: Uuid::new_v4().to_string(),
+            iat_us: now.timestamp_micros(),
+        };
+        let token = self.jwt_keys.sign(&claims)?;
+
+        Ok(LoginResponse {
+            user: UserResponse::from(user.clone()),
+            token,
+            expires_in: self.access_token_ttl.num_seconds() as u64,
+            refresh_token,
+            refresh_expires_in: self.refresh_token_ttl.num_seconds() as u64,
+        })
     }
 
     pub async fn create_user(
         &self,
         request: CreateUserRequest,
     ) -> AppResult<User> {
-        // TODO: Implement actual user creation
-        Err(AppError::not_found("User creation not implemented"))
+        request.validate()?;
+
+        validate_password_strength(&request.password)?;
+
+        if self.user_repository.email_exists(&request.email).await? {
+            return Err(AppError::conflict(format!("User with email '{}' already exists", request.email)));
+        }
+
+        let password_hash = hash_password(&request.password)?;
+
+        // Self-registration never grants admin rights, regardless of the request
+        let user = self.user_repository
+            .create_user(
+                &request.email,
+                &password_hash,
+                &request.first_name,
+                &request.last_name,
+                false,
+            )
+            .await
+            .map_err(|e| {
+                // A concurrent registration can still race past the check above
+                if is_unique_violation(&e) {
+                    AppError::conflict(format!("User with email '{}' already exists", request.email))
+                } else {
+                    AppError::from(e)
+                }
+            })?;
+
+        self.assign_default_role(user.id).await?;
+
+        Ok(user)
     }
-}
\ No newline at end of file
+
+    /// Bind a newly created user to the configured default role, if any
+    pub async fn assign_default_role(&self, user_id: Uuid) -> AppResult<()> {
+        if let Some(ref role_name) = self.default_role {
+            match self.rbac_repository.get_role_by_name(role_name).await? {
+                Some(role) => self.rbac_repository.bind_role(role.id, user_id, None).await?,
+                None => tracing::warn!("Default role '{}' does not exist; user {} has no roles", role_name, user_id),
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn get_user(&self, id: Uuid) -> AppResult<User> {
+        self.user_repository
+            .get_user_by_id(id)
+            .await?
+            .ok_or_else(|| AppError::not_found("User not found"))
+    }
+}
@@ -1,31 +1,75 @@
-use crate::database::{PgPool, Neo4jPool, CIRepository, GraphRepository};
-use crate::models::{CIType, CreateCITypeRequest, UpdateCITypeRequest, CreateCIAssetRequest, CITypeResponse, CIAssetFilter};
-use crate::error::{AppError, AppResult};
+use crate::database::{PgPool, Neo4jPool, CIRepository, RelationshipRepository, GraphRepository, GraphNode, RbacRepository, AuditRepository, IdempotencyRepository, IdempotentResponse, is_unique_violation};
+use crate::models::{CIType, CreateCITypeRequest, UpdateCITypeRequest, CreateCIAssetRequest, CITypeResponse, CIAssetFilter, ResourceType, PermissionAction, AuditedEntity, AuditAction, AuditLogEntry, CIAssetVersion, CIAssetAsOf, RestoreVersionRequest, RestoredCIAsset, RelationshipFilter, CITypeSchemaVersion, SchemaViolation, SchemaChangeReport, EffectiveSchema, CIAssetOperation, BulkRequest, BulkResult, BulkItemResult, BulkMode, operation_path, UpsertOutcome, UpsertedCIAsset, DeletedCIAssetFilter, DeletedCIAsset};
+use crate::middleware::AuthContext;
+use crate::services::{AuditService, PermissionSet, HistoricalState, state_as_of, restore_target};
+use crate::error::{AppError, AppResult, FieldError, FieldErrorCode};
+use crate::utils::{calculate_json_diff, apply_json_diff, migrate_attributes, SchemaMigration, SchemaCache, ComputedAttributes, AttributeConstraints, UniqueValue, QueryFields, parse_filter, parse_sort, KeysetCursor, Page, PageRequest};
 use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
 use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use sqlx::{Connection, PgConnection};
+use std::collections::HashMap;
 use uuid::Uuid;
 use validator::Validate;
 use jsonschema::{JSONSchema, ValidationError as JsonSchemaValidationError};
 
+/// Assets checked or migrated per query and transaction during a schema change
+const SCHEMA_BATCH_SIZE: i64 = 500;
+/// Violations listed in a schema change report; the rest are only counted
+const MAX_REPORTED_VIOLATIONS: usize = 100;
+/// How long retries with the same Idempotency-Key get the first outcome back
+const IDEMPOTENCY_WINDOW_HOURS: i64 = 24;
+/// What idempotency keys record as the endpoint of an upsert
+const UPSERT_ENDPOINT: &str = "PUT /ci-assets/upsert";
+
+/// How `update_ci_type` went. A schema change that existing assets would
+/// violate is not applied; the report says which assets are in the way.
+pub enum CITypeUpdate {
+    Applied { ci_type: CIType, schema_change: Option<SchemaChangeReport> },
+    DryRun(SchemaChangeReport),
+    Rejected(SchemaChangeReport),
+}
+
 pub struct CIService {
     ci_repository: CIRepository,
+    relationship_repository: RelationshipRepository,
     graph_repository: GraphRepository,
+    rbac_repository: RbacRepository,
+    audit_service: AuditService,
+    idempotency_repository: IdempotencyRepository,
+    schema_cache: SchemaCache,
 }
 
 impl CIService {
-    pub fn new(ci_repository: CIRepository, graph_repository: GraphRepository) -> Self {
+    pub fn new(
+        ci_repository: CIRepository,
+        relationship_repository: RelationshipRepository,
+        graph_repository: GraphRepository,
+        rbac_repository: RbacRepository,
+        audit_repository: AuditRepository,
+        idempotency_repository: IdempotencyRepository,
+        schema_cache: SchemaCache,
+    ) -> Self {
         Self {
             ci_repository,
+            relationship_repository,
             graph_repository,
+            audit_service: AuditService::new(audit_repository, rbac_repository.clone()),
+            rbac_repository,
+            idempotency_repository,
+            schema_cache,
         }
     }
 
     // CI Type operations
 
-    pub async fn create_ci_type(&self, request: CreateCITypeRequest, user_id: Uuid) -> AppResult<CIType> {
+    pub async fn create_ci_type(&self, request: CreateCITypeRequest, auth_context: &AuthContext) -> AppResult<CIType> {
+        PermissionSet::load(&self.rbac_repository, auth_context).await?
+            .require(ResourceType::CiType, PermissionAction::Create, None)?;
+
         // Validate the request
-        request.validate()
-            .map_err(|e| AppError::validation(&e.to_string()))?;
+        request.validate()?;
 
         // Check if CI type with the same name already exists
         if let Some(_) = self.ci_repository.get_ci_type_by_name(&request.name).await? {
@@ -35,21 +79,58 @@ impl CIService {
         // Set default attributes if not provided
         let attributes = request.attributes.unwrap_or_else(|| json!({}));
 
+        // The parent's schema and ours have to make a usable schema together,
+        // and so do the computed attributes we inherit and declare
+        let inherited_lineage = match request.parent_id {
+            Some(parent_id) => {
+                let lineage = self.ci_repository.get_ci_type_lineage(parent_id).await?;
+                if lineage.is_empty() {
+                    return Err(AppError::not_found(&format!("Parent CI type with id '{}' not found", parent_id)));
+                }
+                lineage
+            }
+            None => Vec::new(),
+        };
+        let schema = merge_optional_schemas(merged_schema(&inherited_lineage).as_ref(), attributes.get("schema"));
+        if let Some(ref schema) = schema {
+            compile_schema(schema)?;
+        }
+        self.check_constraint_declarations(schema.as_ref()).await?;
+        check_computed_definitions(&inherited_lineage, &attributes, schema.as_ref())?;
+
         // Create the CI type
+        let mut tx = self.ci_repository.begin().await?;
         let ci_type_id = self.ci_repository.create_ci_type(
+            &mut tx,
             &request.name,
             request.description.as_deref(),
             &attributes,
-            user_id,
+            request.parent_id,
+            request.is_abstract,
+            auth_context.user_id,
         ).await?;
 
+        if let Some(schema) = attributes.get("schema") {
+            self.ci_repository
+                .create_schema_version(&mut tx, ci_type_id, schema, None, auth_context.user_id)
+                .await?;
+        }
+
+        self.audit_service
+            .record_change(&mut tx, auth_context, AuditedEntity::CiType, ci_type_id, AuditAction::Insert, None)
+            .await?;
+        tx.commit().await?;
+
         // Retrieve the created CI type
         self.ci_repository.get_ci_type_by_id(ci_type_id)
             .await?
             .ok_or_else(|| AppError::internal("Failed to retrieve created CI type"))
     }
 
-    pub async fn get_ci_type_by_id(&self, id: Uuid) -> AppResult<Option<CIType>> {
+    pub async fn get_ci_type_by_id(&self, id: Uuid, auth_context: &AuthContext) -> AppResult<Option<CIType>> {
+        PermissionSet::load(&self.rbac_repository, auth_context).await?
+            .require(ResourceType::CiType, PermissionAction::Read, Some(id))?;
+
         Ok(self.ci_repository.get_ci_type_by_id(id).await?)
     }
 
@@ -57,25 +138,34 @@ impl CIService {
         Ok(self.ci_repository.get_ci_type_by_name(name).await?)
     }
 
-    pub async fn list_ci_types(&self, limit: Option<i64>, offset: Option<i64>) -> AppResult<Vec<CIType>> {
-        let limit = limit.unwrap_or(50);
-        let offset = offset.unwrap_or(0);
+    /// One page of CI types, newest first
+    pub async fn list_ci_types(&self, page: &PageRequest, auth_context: &AuthContext) -> AppResult<Page<CIType>> {
+        let cursor = page.cursor(0)?;
+        let limit = page.limit()?;
 
-        if limit < 1 || limit > 100 {
-            return Err(AppError::validation("Limit must be between 1 and 100"));
-        }
+        let allowed_ids = PermissionSet::load(&self.rbac_repository, auth_context).await?
+            .readable_scopes(ResourceType::CiType);
 
-        if offset < 0 {
-            return Err(AppError::validation("Offset must be non-negative"));
-        }
+        let ci_types = self.ci_repository.list_ci_types(cursor.as_ref(), limit + 1, allowed_ids.as_deref()).await?;
+        let total = if page.include_total {
+            Some(self.ci_repository.count_ci_types(allowed_ids.as_deref()).await?)
+        } else {
+            None
+        };
 
-        Ok(self.ci_repository.list_ci_types(limit, offset).await?)
+        Ok(Page::from_rows(ci_types, limit, |last| KeysetCursor::new(last.created_at, last.id)).with_total(total))
     }
 
-    pub async fn update_ci_type(&self, id: Uuid, request: UpdateCITypeRequest) -> AppResult<CIType> {
+    /// Update a CI type. Replacing its schema creates a new schema version.
+    /// Whenever the effective schema changes, through its own schema or its
+    /// parent, every asset of the type and its subtypes is checked against it
+    /// first (with `migration` applied, if given), then migrated in batches.
+    pub async fn update_ci_type(&self, id: Uuid, request: UpdateCITypeRequest, auth_context: &AuthContext) -> AppResult<CITypeUpdate> {
+        PermissionSet::load(&self.rbac_repository, auth_context).await?
+            .require(ResourceType::CiType, PermissionAction::Update, Some(id))?;
+
         // Validate the request
-        request.validate()
-            .map_err(|e| AppError::validation(&e.to_string()))?;
+        request.validate()?;
 
         // Check if CI type exists
         let existing_ci_type = self.ci_repository.get_ci_type_by_id(id).await?
@@ -90,25 +180,449 @@ impl CIService {
             }
         }
 
+        let parent_id = request.parent_id.unwrap_or(existing_ci_type.parent_id);
+        let parent_changed = parent_id != existing_ci_type.parent_id;
+
+        // The new parent must not be this type or one of its descendants
+        let inherited_lineage = match parent_id {
+            Some(parent_id) => {
+                let lineage = self.ci_repository.get_ci_type_lineage(parent_id).await?;
+                check_parent_lineage(id, parent_id, &lineage)?;
+                lineage
+            }
+            None => Vec::new(),
+        };
+
+        if request.is_abstract == Some(true) && !existing_ci_type.is_abstract {
+            if !self.ci_repository.list_ci_assets_for_schema(id, None, None, 1).await?.is_empty() {
+                return Err(AppError::conflict("CI type has assets, so it cannot be made abstract"));
+            }
+        }
+
+        let own_schema = match request.attributes {
+            Some(ref attributes) => attributes.get("schema"),
+            None => existing_ci_type.attributes.get("schema"),
+        };
+        let schema_changed = own_schema != existing_ci_type.attributes.get("schema");
+
+        if !schema_changed && !parent_changed && (request.migration.is_some() || request.dry_run) {
+            return Err(AppError::bad_request("migration and dry_run only apply when the schema changes"));
+        }
+
+        let computed_changed = parent_changed || request.attributes.as_ref()
+            .map_or(false, |attributes| attributes.get("computed") != existing_ci_type.attributes.get("computed"));
+        if computed_changed || schema_changed {
+            let schema = merge_optional_schemas(merged_schema(&inherited_lineage).as_ref(), own_schema);
+            let attributes = request.attributes.as_ref().unwrap_or(&existing_ci_type.attributes);
+            self.check_constraint_declarations(schema.as_ref()).await?;
+            check_computed_definitions(&inherited_lineage, attributes, schema.as_ref())?;
+        }
+
+        let report = if schema_changed || parent_changed {
+            let inherited = merged_schema(&inherited_lineage);
+            let subtree = self
+                .subtree_schemas(&existing_ci_type, merge_optional_schemas(inherited.as_ref(), own_schema))
+                .await?;
+
+            let mut report = self.check_schema_change(&subtree, request.migration.as_ref(), request.dry_run).await?;
+            report.from_version = existing_ci_type.schema_version;
+            report.to_version = match own_schema {
+                Some(_) if schema_changed => Some(existing_ci_type.schema_version.unwrap_or(0) + 1),
+                Some(_) => existing_ci_type.schema_version,
+                None => None,
+            };
+
+            if request.dry_run {
+                return Ok(CITypeUpdate::DryRun(report));
+            }
+            if report.violation_count > 0 {
+                return Ok(CITypeUpdate::Rejected(report));
+            }
+            Some(report)
+        } else {
+            None
+        };
+
         // Update the CI type
+        let mut tx = self.ci_repository.begin().await?;
+
+        // The lineage was read outside the transaction; a concurrent parent
+        // change may since have made the new parent one of our subtypes, or
+        // changed what we inherit from it
+        if let Some(parent_id) = parent_id.filter(|_| parent_changed) {
+            let lineage = self.ci_repository.lock_ci_type_lineage(&mut tx, parent_id).await?;
+            check_parent_lineage(id, parent_id, &lineage)?;
+            if !lineage.iter().map(|ancestor| ancestor.id).eq(inherited_lineage.iter().map(|ancestor| ancestor.id)) {
+                return Err(AppError::conflict("The CI type hierarchy changed during the update; try again"));
+            }
+        }
+
+        let old_values = self.audit_service.snapshot(&mut tx, AuditedEntity::CiType, id).await?;
         let updated = self.ci_repository.update_ci_type(
+            &mut tx,
             id,
             request.name.as_deref(),
             request.description.as_deref(),
             request.attributes.as_ref(),
+            request.parent_id,
+            request.is_abstract,
         ).await?;
 
         if !updated {
             return Err(AppError::internal("Failed to update CI type"));
         }
 
+        if schema_changed {
+            match own_schema {
+                Some(schema) => {
+                    let migration = request.migration.as_ref().map(serde_json::to_value).transpose()?;
+                    self.ci_repository
+                        .create_schema_version(&mut tx, id, schema, migration.as_ref(), auth_context.user_id)
+                        .await?;
+                }
+                None => self.ci_repository.clear_schema_version(&mut tx, id).await?,
+            }
+        }
+
+        self.audit_service
+            .record_change(&mut tx, auth_context, AuditedEntity::CiType, id, AuditAction::Update, old_values)
+            .await?;
+        tx.commit().await?;
+
         // Retrieve the updated CI type
-        self.ci_repository.get_ci_type_by_id(id)
+        let ci_type = self.ci_repository.get_ci_type_by_id(id)
             .await?
-            .ok_or_else(|| AppError::internal("Failed to retrieve updated CI type"))
+            .ok_or_else(|| AppError::internal("Failed to retrieve updated CI type"))?;
+
+        // The change is live, so assets are moved onto it in their own
+        // transactions. Any that fail stay on the version they were on and are
+        // listed in the report.
+        self.schema_cache.invalidate(id);
+
+        let schema_change = match report {
+            Some(mut report) => {
+                let effective = self.effective_schema(&ci_type).await?;
+                let subtree = self.subtree_schemas(&ci_type, effective).await?;
+                // Subtypes inherit the change, so their compiled schemas are stale too
+                for (subtype, _) in &subtree {
+                    self.schema_cache.invalidate(subtype.id);
+                }
+                self.migrate_assets_to_schema(&subtree, schema_changed, request.migration.as_ref(), &mut report, auth_context)
+                    .await?;
+                self.reindex_unique_values(&subtree).await?;
+                Some(report)
+            }
+            None => None,
+        };
+
+        // Stored values follow from the definitions and the attributes, and
+        // either may have just changed for the whole subtree
+        let migrated = schema_change.as_ref().map_or(false, |report| report.assets_migrated > 0);
+        if computed_changed || migrated {
+            let descendants = self.ci_repository.get_ci_type_descendants(id).await?;
+            for ci_type_id in std::iter::once(id).chain(descendants.iter().map(|subtype| subtype.id)) {
+                refresh_computed_attributes(&self.ci_repository, ci_type_id).await?;
+            }
+        }
+
+        Ok(CITypeUpdate::Applied { ci_type, schema_change })
+    }
+
+    /// Every schema version the CI type has had, oldest first
+    pub async fn list_ci_type_schema_versions(&self, id: Uuid, auth_context: &AuthContext) -> AppResult<Vec<CITypeSchemaVersion>> {
+        PermissionSet::load(&self.rbac_repository, auth_context).await?
+            .require(ResourceType::CiType, PermissionAction::Read, Some(id))?;
+
+        self.ci_repository.get_ci_type_by_id(id).await?
+            .ok_or_else(|| AppError::not_found(&format!("CI type with id '{}' not found", id)))?;
+
+        Ok(self.ci_repository.list_schema_versions(id).await?)
+    }
+
+    /// The schema the CI type's assets are validated against
+    pub async fn get_effective_schema(&self, id: Uuid, auth_context: &AuthContext) -> AppResult<EffectiveSchema> {
+        PermissionSet::load(&self.rbac_repository, auth_context).await?
+            .require(ResourceType::CiType, PermissionAction::Read, Some(id))?;
+
+        let lineage = self.ci_repository.get_ci_type_lineage(id).await?;
+        if lineage.is_empty() {
+            return Err(AppError::not_found(&format!("CI type with id '{}' not found", id)));
+        }
+
+        Ok(EffectiveSchema {
+            ci_type_id: id,
+            lineage: lineage.iter().map(|ci_type| ci_type.id).collect(),
+            schema: merged_schema(&lineage),
+        })
+    }
+
+    /// The CI type's own schema merged over everything it inherits
+    async fn effective_schema(&self, ci_type: &CIType) -> AppResult<Option<Value>> {
+        let inherited = match ci_type.parent_id {
+            Some(parent_id) => merged_schema(&self.ci_repository.get_ci_type_lineage(parent_id).await?),
+            None => None,
+        };
+
+        Ok(merge_optional_schemas(inherited.as_ref(), ci_type.attributes.get("schema")))
+    }
+
+    /// `root` and each of its subtypes with its effective schema, given the
+    /// effective schema `root` has (or would have)
+    async fn subtree_schemas(&self, root: &CIType, root_schema: Option<Value>) -> AppResult<Vec<(CIType, Option<Value>)>> {
+        let mut schemas: HashMap<Uuid, Option<Value>> = HashMap::new();
+        schemas.insert(root.id, root_schema.clone());
+
+        let mut subtree = vec![(root.clone(), root_schema)];
+        // Parents come before their children, so each parent is already resolved
+        for ci_type in self.ci_repository.get_ci_type_descendants(root.id).await? {
+            let inherited = ci_type.parent_id.and_then(|parent_id| schemas.get(&parent_id).cloned().flatten());
+            let schema = merge_optional_schemas(inherited.as_ref(), ci_type.attributes.get("schema"));
+
+            schemas.insert(ci_type.id, schema.clone());
+            subtree.push((ci_type, schema));
+        }
+
+        Ok(subtree)
+    }
+
+    /// Check every live asset in a subtree against its proposed effective schema
+    async fn check_schema_change(
+        &self,
+        subtree: &[(CIType, Option<Value>)],
+        migration: Option<&SchemaMigration>,
+        dry_run: bool,
+    ) -> AppResult<SchemaChangeReport> {
+        let mut report = SchemaChangeReport {
+            from_version: None,
+            to_version: None,
+            dry_run,
+            assets_checked: 0,
+            assets_to_migrate: 0,
+            assets_migrated: 0,
+            assets_skipped: Vec::new(),
+            violation_count: 0,
+            violations: Vec::new(),
+        };
+
+        // Unique values held by the subtree's assets checked so far
+        let mut claimed = HashMap::new();
+        let root_id = subtree.first().map(|(root, _)| root.id);
+
+        for (ci_type, schema) in subtree {
+            let compiled = schema.as_ref().map(compile_schema).transpose()?;
+            let constraints = constraints_of(ci_type, schema.as_ref())?;
+
+            let mut after_id = None;
+            loop {
+                let assets = self.ci_repository
+                    .list_ci_assets_for_schema(ci_type.id, None, after_id, SCHEMA_BATCH_SIZE)
+                    .await?;
+
+                for (asset_id, asset_name, attributes) in &assets {
+                    report.assets_checked += 1;
+
+                    let errors = match migrate(attributes, migration) {
+                        Ok(migrated) => {
+                            if &migrated != attributes {
+                                report.assets_to_migrate += 1;
+                            }
+                            let mut errors = compiled.as_ref().map_or_else(Vec::new, |compiled| schema_errors(compiled, &migrated));
+                            if errors.is_empty() && !constraints.is_empty() {
+                                errors = self
+                                    .subtree_constraint_errors(root_id.unwrap_or(ci_type.id), ci_type.id, &constraints, *asset_id, &migrated, &mut claimed)
+                                    .await?;
+                            }
+                            errors
+                        }
+                        Err(e) => vec![FieldError::new("", FieldErrorCode::InvalidValue, e)],
+                    };
+
+                    if !errors.is_empty() {
+                        report.violation_count += 1;
+                        if report.violations.len() < MAX_REPORTED_VIOLATIONS {
+                            report.violations.push(SchemaViolation {
+                                asset_id: *asset_id,
+                                asset_name: asset_name.clone(),
+                                errors,
+                            });
+                        }
+                    }
+                }
+
+                match assets.last() {
+                    Some((last_id, _, _)) if assets.len() as i64 == SCHEMA_BATCH_SIZE => after_id = Some(*last_id),
+                    _ => break,
+                }
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Migrate a subtree's assets to its current effective schemas, one batch
+    /// per transaction. When the root got a new schema version its assets are
+    /// moved onto it; subtypes keep their own versions. Rewritten assets are
+    /// audited, and assets that changed since the check and no longer fit are
+    /// skipped. Both are counted in `report`.
+    async fn migrate_assets_to_schema(
+        &self,
+        subtree: &[(CIType, Option<Value>)],
+        root_version_changed: bool,
+        migration: Option<&SchemaMigration>,
+        report: &mut SchemaChangeReport,
+        auth_context: &AuthContext,
+    ) -> AppResult<()> {
+        for (index, (ci_type, schema)) in subtree.iter().enumerate() {
+            let new_version = if index == 0 && root_version_changed { ci_type.schema_version } else { None };
+            if new_version.is_none() && migration.map_or(true, SchemaMigration::is_empty) {
+                continue;
+            }
+            let compiled = schema.as_ref().map(compile_schema).transpose()?;
+
+            let mut after_id = None;
+            loop {
+                let assets = self.ci_repository
+                    .list_ci_assets_for_schema(ci_type.id, new_version, after_id, SCHEMA_BATCH_SIZE)
+                    .await?;
+
+                let mut tx = self.ci_repository.begin().await?;
+                let mut unchanged = Vec::new();
+                let mut rewritten = Vec::new();
+
+                for (asset_id, _, _) in &assets {
+                    // Re-read under lock; the asset may have changed since it was listed
+                    let Some(old_values) = self.audit_service.snapshot(&mut tx, AuditedEntity::CiAsset, *asset_id).await? else {
+                        continue;
+                    };
+                    if old_values.get("deleted_at").map_or(false, |deleted_at| !deleted_at.is_null()) {
+                        continue;
+                    }
+
+                    let attributes = old_values.get("attributes").cloned().unwrap_or_else(|| json!({}));
+                    let migrated = match migrate(&attributes, migration) {
+                        Ok(migrated) if compiled.as_ref().map_or(true, |compiled| schema_errors(compiled, &migrated).is_empty()) => migrated,
+                        _ => {
+                            tracing::warn!("CI asset {} no longer fits the schema of CI type {}; left as it was", asset_id, ci_type.id);
+                            report.assets_skipped.push(*asset_id);
+                            continue;
+                        }
+                    };
+
+                    if migrated == attributes {
+                        unchanged.push(*asset_id);
+                        continue;
+                    }
+
+                    self.ci_repository
+                        .update_ci_asset(&mut tx, *asset_id, None, Some(&migrated), ci_type.schema_version, auth_context.user_id)
+                        .await?;
+                    self.audit_service
+                        .record_change(&mut tx, auth_context, AuditedEntity::CiAsset, *asset_id, AuditAction::Update, Some(old_values))
+                        .await?;
+                    rewritten.push(*asset_id);
+                }
+
+                if let Some(version) = new_version {
+                    self.ci_repository.set_ci_assets_schema_version(&mut tx, &unchanged, version).await?;
+                }
+                tx.commit().await?;
+
+                for asset_id in &rewritten {
+                    self.sync_asset_to_graph(*asset_id).await;
+                }
+                report.assets_migrated += rewritten.len() as i64;
+
+                match assets.last() {
+                    Some((last_id, _, _)) if assets.len() as i64 == SCHEMA_BATCH_SIZE => after_id = Some(*last_id),
+                    _ => break,
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Uniqueness and reference errors of one asset while checking a subtree
+    /// before a schema change. `claimed` collects the unique values of the
+    /// subtree's assets checked so far, keyed by attribute, scope and value.
+    async fn subtree_constraint_errors(
+        &self,
+        root_id: Uuid,
+        ci_type_id: Uuid,
+        constraints: &AttributeConstraints,
+        asset_id: Uuid,
+        attributes: &Value,
+        claimed: &mut HashMap<(String, Option<Uuid>, String), Uuid>,
+    ) -> AppResult<Vec<FieldError>> {
+        let mut errors = self.reference_errors(constraints, attributes).await?;
+
+        for unique_value in constraints.unique_values(ci_type_id, attributes) {
+            let key = (unique_value.attribute.clone(), unique_value.scope, unique_value.value.to_string());
+            let owner = match claimed.get(&key) {
+                Some(owner) => Some(*owner),
+                // Values unique within a type only compete with assets of that
+                // type, all of which are in the subtree; global ones with any asset
+                None if unique_value.scope.is_none() => {
+                    self.ci_repository.find_unique_value_owner_outside(root_id, &unique_value).await?
+                }
+                None => None,
+            };
+
+            match owner {
+                Some(owner) => errors.push(duplicate_value_error(&unique_value, owner)),
+                None => {
+                    claimed.insert(key, asset_id);
+                }
+            }
+        }
+
+        Ok(errors)
+    }
+
+    /// Record the unique values of a subtree's assets again after a schema
+    /// change, which may have added, dropped or renamed unique attributes.
+    /// Assets whose values were claimed in the meantime are left as they were.
+    async fn reindex_unique_values(&self, subtree: &[(CIType, Option<Value>)]) -> AppResult<()> {
+        for (ci_type, schema) in subtree {
+            let constraints = constraints_of(ci_type, schema.as_ref())?;
+
+            let mut after_id = None;
+            loop {
+                let assets = self.ci_repository
+                    .list_ci_assets_for_schema(ci_type.id, None, after_id, SCHEMA_BATCH_SIZE)
+                    .await?;
+
+                let mut tx = self.ci_repository.begin().await?;
+                for (asset_id, _, attributes) in &assets {
+                    let values = constraints.unique_values(ci_type.id, attributes);
+
+                    let mut taken = false;
+                    for unique_value in &values {
+                        taken |= self.ci_repository.find_unique_value_owner(&mut tx, *asset_id, unique_value).await?.is_some();
+                    }
+                    if taken {
+                        tracing::warn!("CI asset {} holds a value another asset claimed; its unique values were not updated", asset_id);
+                        continue;
+                    }
+
+                    self.ci_repository.replace_unique_values(&mut tx, *asset_id, &values).await?;
+                }
+                tx.commit().await?;
+
+                match assets.last() {
+                    Some((last_id, _, _)) if assets.len() as i64 == SCHEMA_BATCH_SIZE => after_id = Some(*last_id),
+                    _ => break,
+                }
+            }
+        }
+
+        Ok(())
     }
 
-    pub async fn delete_ci_type(&self, id: Uuid) -> AppResult<()> {
+    pub async fn delete_ci_type(&self, id: Uuid, auth_context: &AuthContext) -> AppResult<()> {
+        PermissionSet::load(&self.rbac_repository, auth_context).await?
+            .require(ResourceType::CiType, PermissionAction::Delete, Some(id))?;
+
         // Check if CI type exists
         let _existing_ci_type = self.ci_repository.get_ci_type_by_id(id).await?
             .ok_or_else(|| AppError::not_found(&format!("CI type with id '{}' not found", id)))?;
@@ -117,170 +631,1366 @@ impl CIService {
         // Note: This would need to be implemented in the repository
         // For now, we'll allow deletion
 
+        if self.ci_repository.has_subtypes(id).await? {
+            return Err(AppError::conflict("CI type has subtypes; delete or re-parent them first"));
+        }
+
         // Delete the CI type (soft delete)
-        let deleted = self.ci_repository.delete_ci_type(id).await?;
+        let mut tx = self.ci_repository.begin().await?;
+        let old_values = self.audit_service.snapshot(&mut tx, AuditedEntity::CiType, id).await?;
+        let deleted = self.ci_repository.delete_ci_type(&mut tx, id).await?;
 
         if !deleted {
             return Err(AppError::internal("Failed to delete CI type"));
         }
 
+        self.audit_service
+            .record_change(&mut tx, auth_context, AuditedEntity::CiType, id, AuditAction::Delete, old_values)
+            .await?;
+        tx.commit().await?;
+
+        self.schema_cache.invalidate(id);
+
         Ok(())
     }
 
-    pub async fn count_ci_types(&self) -> AppResult<i64> {
-        Ok(self.ci_repository.count_ci_types().await?)
+    // Helper methods
+
+    /// The type followed by its ancestors, nearest first
+    async fn lineage(&self, ci_type: &CIType) -> AppResult<Vec<CIType>> {
+        Ok(match ci_type.parent_id {
+            Some(_) => self.ci_repository.get_ci_type_lineage(ci_type.id).await?,
+            None => vec![ci_type.clone()],
+        })
     }
 
-    // Helper methods
+    /// The computed attributes of a CI type, including inherited ones
+    async fn computed_attributes(&self, ci_type: &CIType) -> AppResult<ComputedAttributes> {
+        computed_for_lineage(&self.lineage(ci_type).await?)
+    }
 
-    /// Validate asset attributes against CI type schema
-    fn validate_attributes_against_schema(&self, ci_type: &CIType, attributes: &Value) -> AppResult<()> {
-        // Get the schema from CI type attributes
-        let schema_value = ci_type.attributes.get("schema")
-            .ok_or_else(|| AppError::validation("CI type does not have a schema defined"))?;
+    /// The uniqueness and reference constraints of a CI type's effective schema
+    async fn attribute_constraints(&self, ci_type: &CIType) -> AppResult<AttributeConstraints> {
+        let schema = self.effective_schema(ci_type).await?;
+        constraints_of(ci_type, schema.as_ref())
+    }
 
-        // Compile the JSON schema
-        let schema = JSONSchema::compile(schema_value)
-            .map_err(|e| AppError::validation(&format!("Invalid JSON schema: {}", e)))?;
+    /// Constraint keywords in a proposed effective schema must be well formed,
+    /// and references must name an existing CI type
+    async fn check_constraint_declarations(&self, schema: Option<&Value>) -> AppResult<()> {
+        let constraints = AttributeConstraints::from_schema(schema)
+            .map_err(|errors| AppError::invalid_fields("Invalid attribute constraints", errors))?;
 
-        // Validate the attributes against the schema
-        if let Err(errors) = schema.validate(attributes) {
-            let error_messages: Vec<String> = errors.into_iter()
-                .map(|err| format!("{}: {:?}", err.instance_path, err.kind))
-                .collect();
-            return Err(AppError::validation(&format!("Attribute validation failed: {}", error_messages.join(", "))));
+        let mut errors = Vec::new();
+        for (name, ci_type_id) in constraints.references() {
+            if self.ci_repository.get_ci_type_by_id(*ci_type_id).await?.is_none() {
+                errors.push(FieldError::new(
+                    format!("/attributes/schema/properties/{}/reference", name),
+                    FieldErrorCode::InvalidReference,
+                    format!("CI type {} does not exist", ci_type_id),
+                ));
+            }
         }
 
-        Ok(())
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(AppError::invalid_fields("Invalid attribute constraints", errors))
+        }
     }
 
-    // CI Asset operations (enhanced implementations)
+    /// Reference attributes must hold the ID of a live asset of the referenced
+    /// CI type or one of its subtypes. Unset and null ones are left to the schema.
+    async fn reference_errors(&self, constraints: &AttributeConstraints, attributes: &Value) -> AppResult<Vec<FieldError>> {
+        let mut errors = Vec::new();
 
-    pub async fn create_ci_asset(&self, request: CreateCIAssetRequest, user_id: Uuid) -> AppResult<Uuid> {
-        // Validate the request
-        request.validate()
-            .map_err(|e| AppError::validation(&e.to_string()))?;
+        for (name, ci_type_id) in constraints.references() {
+            let value = match attributes.get(name.as_str()) {
+                None | Some(Value::Null) => continue,
+                Some(value) => value,
+            };
+            let exists = match value.as_str().and_then(|id| Uuid::parse_str(id).ok()) {
+                Some(asset_id) => self.ci_repository.is_ci_asset_of_type(asset_id, *ci_type_id).await?,
+                None => false,
+            };
 
-        // Check if CI type exists
-        let ci_type = self.ci_repository.get_ci_type_by_id(request.ci_type_id).await?
-            .ok_or_else(|| AppError::not_found(&format!("CI type with id '{}' not found", request.ci_type_id)))?;
+            if !exists {
+                errors.push(FieldError::new(
+                    format!("/attributes/{}", name),
+                    FieldErrorCode::InvalidReference,
+                    format!("{} is not the ID of a CI asset of CI type {}", value, ci_type_id),
+                ).with_expected(json!({ "ci_type_id": ci_type_id })));
+            }
+        }
 
-        // Set default attributes if not provided
-        let attributes = request.attributes.unwrap_or_else(|| json!({}));
+        Ok(errors)
+    }
 
-        // Validate attributes against CI type schema (if schema exists)
-        if ci_type.attributes.get("schema").is_some() {
-            self.validate_attributes_against_schema(&ci_type, &attributes)?;
+    async fn check_references(&self, constraints: &AttributeConstraints, attributes: &Value) -> AppResult<()> {
+        let errors = self.reference_errors(constraints, attributes).await?;
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(AppError::invalid_fields("Attributes reference CI assets that don't exist", errors))
         }
+    }
+
+    /// Record the unique attribute values an asset now holds, in the
+    /// transaction that writes it. Values another asset already holds are
+    /// reported as conflicts on their fields.
+    async fn claim_unique_values(&self, conn: &mut PgConnection, asset_id: Uuid, values: &[UniqueValue]) -> AppResult<()> {
+        let mut errors = Vec::new();
+        for unique_value in values {
+            if let Some(owner) = self.ci_repository.find_unique_value_owner(conn, asset_id, unique_value).await? {
+                errors.push(duplicate_value_error(unique_value, owner));
+            }
+        }
+        if !errors.is_empty() {
+            return Err(AppError::conflicting_fields("Attribute values are already in use", errors));
+        }
+
+        // A concurrent write may claim a value after the check; the unique
+        // index has the final say
+        self.ci_repository.replace_unique_values(conn, asset_id, values).await.map_err(|e| {
+            if is_unique_violation(&e) {
+                AppError::conflict("An attribute value was claimed by another CI asset at the same time")
+            } else {
+                e.into()
+            }
+        })
+    }
+
+    /// Validate asset attributes against the CI type's effective schema, which
+    /// includes everything it inherits. Types without one accept anything.
+    async fn validate_attributes_against_schema(&self, ci_type: &CIType, attributes: &Value) -> AppResult<()> {
+        self.validate_attributes_against_lineage(ci_type, &self.lineage(ci_type).await?, attributes)
+    }
+
+    /// Validate asset attributes against the schema of a type's lineage, as
+    /// returned by `lineage`
+    fn validate_attributes_against_lineage(&self, ci_type: &CIType, lineage: &[CIType], attributes: &Value) -> AppResult<()> {
+        let Some(schema_value) = merged_schema(lineage) else {
+            return Ok(());
+        };
+
+        // Inherited schemas are part of this one, so the latest change anywhere
+        // in the lineage decides whether a cached compilation is still current
+        let updated_at = lineage.iter().map(|ancestor| ancestor.updated_at).max().unwrap_or(ci_type.updated_at);
+        let schema = self.schema_cache
+            .get_or_compile(ci_type.id, updated_at, &schema_value)
+            .map_err(|e| AppError::internal(format!("CI type '{}' has an invalid schema: {}", ci_type.name, e)))?;
+
+        // Validate the attributes against the schema
+        let errors = schema_errors(&schema, attributes);
+        if !errors.is_empty() {
+            return Err(AppError::invalid_fields(
+                format!("Attributes do not match the schema of CI type '{}'", ci_type.name),
+                errors.into_iter().map(|error| error.nested_in("/attributes")).collect(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    // CI Asset operations (enhanced implementations)
+
+    pub async fn create_ci_asset(&self, request: CreateCIAssetRequest, auth_context: &AuthContext) -> AppResult<Uuid> {
+        let permissions = PermissionSet::load(&self.rbac_repository, auth_context).await?;
+        let change = self.prepare_create(request, &permissions, &mut HashMap::new()).await?;
 
         // Create the CI asset
-        let asset_id = self.ci_repository.create_ci_asset(
-            request.ci_type_id,
-            &request.name,
-            &attributes,
-            user_id,
-        ).await?;
+        let mut tx = self.ci_repository.begin().await?;
+        let asset_id = self.apply_asset_change(&mut tx, &change, auth_context).await?
+            .ok_or_else(|| AppError::internal("Failed to create CI asset"))?;
+        tx.commit().await?;
+
+        self.sync_asset_to_graph(asset_id).await;
 
         Ok(asset_id)
     }
 
-    pub async fn get_ci_asset(&self, id: Uuid) -> AppResult<Option<(Uuid, String, Value, Uuid, Uuid)>> {
-        Ok(self.ci_repository.get_ci_asset(id).await?)
+    /// An asset with its computed attributes, evaluated afresh so that values
+    /// depending on the current date are up to date
+    pub async fn get_ci_asset(&self, id: Uuid, auth_context: &AuthContext) -> AppResult<Option<(Uuid, String, Value, Uuid, Uuid, Value)>> {
+        let Some((id, name, attributes, ci_type_id, created_by)) = self.ci_repository.get_ci_asset(id).await? else {
+            return Ok(None);
+        };
+
+        PermissionSet::load(&self.rbac_repository, auth_context).await?
+            .require(ResourceType::CiAsset, PermissionAction::Read, Some(ci_type_id))?;
+
+        let computed_values = match self.ci_repository.get_ci_type_by_id(ci_type_id).await? {
+            Some(ci_type) => self.computed_attributes(&ci_type).await?
+                .evaluate_lenient(&attributes, Utc::now().date_naive()),
+            None => json!({}),
+        };
+
+        Ok(Some((id, name, attributes, ci_type_id, created_by, computed_values)))
     }
 
-    pub async fn list_ci_assets(
+    /// One page of assets matching the filter, in the requested order
+    pub async fn list_ci_assets_filtered(
         &self,
-        ci_type_id: Option<Uuid>,
-        limit: Option<i64>,
-        offset: Option<i64>,
-    ) -> AppResult<Vec<(Uuid, String, Value, Uuid)>> {
+        filter: &CIAssetFilter,
+        page: &PageRequest,
+        auth_context: &AuthContext,
+    ) -> AppResult<Page<(Uuid, String, Value, Uuid, Value)>> {
+        let fields = self.query_fields(filter).await?;
+        let query = filter.filter.as_deref()
+            .filter(|source| !source.trim().is_empty())
+            .map(|source| parse_filter(source, &fields))
+            .transpose()
+            .map_err(AppError::invalid_field)?;
+        let sort = match filter.sort.as_deref() {
+            Some(source) => parse_sort(source, &fields).map_err(AppError::invalid_field)?,
+            None => Vec::new(),
+        };
+
+        let cursor = page.cursor(sort.len())?;
+        let limit = page.limit()?;
+
+        let allowed_type_ids = PermissionSet::load(&self.rbac_repository, auth_context).await?
+            .readable_scopes(ResourceType::CiAsset);
+
+        let assets = self.ci_repository
+            .list_ci_assets_filtered(filter, query.as_ref(), &sort, allowed_type_ids.as_deref(), cursor.as_ref(), limit + 1)
+            .await?;
+        let total = if page.include_total {
+            Some(self.ci_repository.count_ci_assets_filtered(filter, query.as_ref(), allowed_type_ids.as_deref()).await?)
+        } else {
+            None
+        };
+
+        Ok(Page::from_rows(assets, limit, |(_, cursor)| cursor.clone())
+            .with_total(total)
+            .map(|(asset, _)| asset))
+    }
+
+    /// The fields a filter or sort may use. Listing a single CI type checks
+    /// them against its schema; across types or subtypes any attribute goes.
+    async fn query_fields(&self, filter: &CIAssetFilter) -> AppResult<QueryFields> {
+        let ci_type = match filter.ci_type_id.filter(|_| !filter.include_subtypes) {
+            Some(ci_type_id) => self.ci_repository.get_ci_type_by_id(ci_type_id).await?,
+            None => None,
+        };
+        let Some(ci_type) = ci_type else {
+            return Ok(QueryFields::untyped());
+        };
+
+        let schema = self.effective_schema(&ci_type).await?;
+        let computed = self.computed_attributes(&ci_type).await?;
+        Ok(QueryFields::for_ci_type(schema, computed.names().cloned()))
+    }
+
+    /// Search CI assets by text (full-text search)
+    pub async fn search_ci_assets(&self, query: &str, limit: Option<i64>, offset: Option<i64>, auth_context: &AuthContext) -> AppResult<Vec<(Uuid, String, Value, Uuid, Value)>> {
         let limit = limit.unwrap_or(50);
         let offset = offset.unwrap_or(0);
 
-        if limit < 1 || limit > 100 {
-            return Err(AppError::validation("Limit must be between 1 and 100"));
+        check_page(limit, offset)?;
+
+        if query.trim().is_empty() {
+            return Err(AppError::invalid_field(FieldError::new("/q", FieldErrorCode::Required, "Search query cannot be empty")));
         }
 
-        if offset < 0 {
-            return Err(AppError::validation("Offset must be non-negative"));
+        let allowed_type_ids = PermissionSet::load(&self.rbac_repository, auth_context).await?
+            .readable_scopes(ResourceType::CiAsset);
+
+        Ok(self.ci_repository.search_ci_assets(query, limit, offset, allowed_type_ids.as_deref()).await?)
+    }
+
+    pub async fn update_ci_asset(
+        &self,
+        id: Uuid,
+        name: Option<&str>,
+        attributes: Option<&Value>,
+        auth_context: &AuthContext,
+    ) -> AppResult<bool> {
+        let permissions = PermissionSet::load(&self.rbac_repository, auth_context).await?;
+        let change = self.prepare_update(id, name.map(str::to_string), attributes.cloned(), &permissions, &mut HashMap::new()).await?;
+
+        let mut tx = self.ci_repository.begin().await?;
+        let updated = self.apply_asset_change(&mut tx, &change, auth_context).await?.is_some();
+
+        // Nothing to record when there was nothing to change
+        if updated {
+            tx.commit().await?;
+
+            self.sync_asset_to_graph(id).await;
         }
 
-        Ok(self.ci_repository.list_ci_assets(ci_type_id, limit, offset).await?)
+        Ok(updated)
     }
 
-    /// Enhanced asset listing with advanced filtering and search
-    pub async fn list_ci_assets_filtered(&self, filter: &CIAssetFilter) -> AppResult<Vec<(Uuid, String, Value, Uuid)>> {
-        let limit = filter.limit.unwrap_or(50);
-        let offset = filter.offset.unwrap_or(0);
+    pub async fn delete_ci_asset(&self, id: Uuid, auth_context: &AuthContext) -> AppResult<()> {
+        let permissions = PermissionSet::load(&self.rbac_repository, auth_context).await?;
+        let change = self.prepare_delete(id, &permissions).await?;
 
-        if limit < 1 || limit > 100 {
-            return Err(AppError::validation("Limit must be between 1 and 100"));
+        let mut tx = self.ci_repository.begin().await?;
+        if self.apply_asset_change(&mut tx, &change, auth_context).await?.is_none() {
+            return Err(AppError::internal("Failed to delete CI asset"));
         }
+        tx.commit().await?;
 
-        if offset < 0 {
-            return Err(AppError::validation("Offset must be non-negative"));
+        if let Err(e) = self.graph_repository.delete_node(id).await {
+            tracing::warn!("Failed to delete CI asset {} from Neo4j: {}", id, e);
         }
 
-        Ok(self.ci_repository.list_ci_assets_filtered(filter, limit, offset).await?)
+        Ok(())
     }
 
-    /// Search CI assets by text (full-text search)
-    pub async fn search_ci_assets(&self, query: &str, limit: Option<i64>, offset: Option<i64>) -> AppResult<Vec<(Uuid, String, Value, Uuid)>> {
-        let limit = limit.unwrap_or(50);
-        let offset = offset.unwrap_or(0);
+    /// Create the asset of a CI type that the request's natural key
+    /// identifies, or bring it up to date with the request. With an
+    /// `idempotency_key`, retries of the same request get the first outcome
+    /// back instead of being applied again.
+    pub async fn upsert_ci_asset(
+        &self,
+        request: CreateCIAssetRequest,
+        idempotency_key: Option<&str>,
+        auth_context: &AuthContext,
+    ) -> AppResult<UpsertedCIAsset> {
+        let request_hash = hex::encode(Sha256::digest(serde_json::to_vec(&request)?));
+        if let Some(key) = idempotency_key {
+            if key.is_empty() || key.len() > 255 {
+                return Err(AppError::bad_request("Idempotency-Key must be 1 to 255 characters long"));
+            }
+            if let Some(previous) = self.idempotency_repository.find(auth_context.user_id, key).await? {
+                return replay_upsert(previous, &request_hash);
+            }
+        }
+
+        request.validate()?;
+        let permissions = PermissionSet::load(&self.rbac_repository, auth_context).await?;
 
-        if limit < 1 || limit > 100 {
-            return Err(AppError::validation("Limit must be between 1 and 100"));
+        let mut rules = HashMap::new();
+        let attributes = request.attributes.clone().unwrap_or_else(|| json!({}));
+        let natural_key = upsert_key(self.asset_rules(&mut rules, request.ci_type_id).await?, &attributes)?;
+
+        let name = request.name.clone();
+        let existing = self.upsert_target(&natural_key).await?;
+        let (change, outcome) = match &existing {
+            None => (self.prepare_create(request, &permissions, &mut rules).await?, UpsertOutcome::Created),
+            Some(asset) => {
+                self.prepare_upsert_update(asset, name.clone(), attributes.clone(), &permissions, &mut rules).await?
+            }
+        };
+
+        let mut result = self
+            .commit_upsert(&change, outcome, existing.as_ref().map(|asset| asset.0), idempotency_key, &request_hash, auth_context)
+            .await;
+
+        // A concurrent upsert may have created the asset first, leaving this
+        // one to collide on the natural key. Updating that asset is what this
+        // request would have done had it come second.
+        if existing.is_none() && matches!(result, Err(AppError::Conflict(_) | AppError::ConflictingFields(..))) {
+            if let Some(asset) = self.upsert_target(&natural_key).await? {
+                let (change, outcome) = self.prepare_upsert_update(&asset, name, attributes, &permissions, &mut rules).await?;
+                result = self
+                    .commit_upsert(&change, outcome, Some(asset.0), idempotency_key, &request_hash, auth_context)
+                    .await;
+            }
         }
 
-        if offset < 0 {
-            return Err(AppError::validation("Offset must be non-negative"));
+        let upserted = match result {
+            Ok(Some(upserted)) => upserted,
+            result => {
+                // A concurrent request with the same key may have committed
+                // first, making this one fail or find the key taken. Its
+                // outcome is then the one to report.
+                if let Some(key) = idempotency_key {
+                    if let Some(previous) = self.idempotency_repository.find(auth_context.user_id, key).await? {
+                        return replay_upsert(previous, &request_hash);
+                    }
+                }
+                result?;
+                return Err(AppError::conflict("Another request with this Idempotency-Key is in progress"));
+            }
+        };
+
+        if outcome != UpsertOutcome::Unchanged {
+            self.sync_asset_to_graph(upserted.id).await;
         }
 
-        if query.trim().is_empty() {
-            return Err(AppError::validation("Search query cannot be empty"));
+        Ok(upserted)
+    }
+
+    /// The live asset holding an upsert's natural key
+    async fn upsert_target(&self, natural_key: &UniqueValue) -> AppResult<Option<(Uuid, String, Value, Uuid, Uuid)>> {
+        Ok(match self.ci_repository.find_unique_value_holder(natural_key).await? {
+            Some(id) => self.ci_repository.get_ci_asset(id).await?,
+            None => None,
+        })
+    }
+
+    /// The update an upsert makes to the asset holding its natural key
+    async fn prepare_upsert_update(
+        &self,
+        asset: &(Uuid, String, Value, Uuid, Uuid),
+        name: String,
+        attributes: Value,
+        permissions: &PermissionSet,
+        rules: &mut HashMap<Uuid, AssetRules>,
+    ) -> AppResult<(AssetChange, UpsertOutcome)> {
+        let (id, current_name, current_attributes, _, _) = asset;
+        let outcome = if *current_name == name && *current_attributes == attributes {
+            UpsertOutcome::Unchanged
+        } else {
+            UpsertOutcome::Updated
+        };
+
+        Ok((self.prepare_update(*id, Some(name), Some(attributes), permissions, rules).await?, outcome))
+    }
+
+    /// Write an upsert and the outcome recorded for its idempotency key in
+    /// one transaction. None when the key already holds an outcome.
+    async fn commit_upsert(
+        &self,
+        change: &AssetChange,
+        outcome: UpsertOutcome,
+        existing_id: Option<Uuid>,
+        idempotency_key: Option<&str>,
+        request_hash: &str,
+        auth_context: &AuthContext,
+    ) -> AppResult<Option<UpsertedCIAsset>> {
+        let mut tx = self.ci_repository.begin().await?;
+        let id = match existing_id.filter(|_| outcome == UpsertOutcome::Unchanged) {
+            Some(id) => id,
+            None => self.apply_asset_change(&mut tx, change, auth_context).await?
+                .ok_or_else(|| AppError::conflict("The CI asset was deleted while it was being updated"))?,
+        };
+        let upserted = UpsertedCIAsset { id, outcome, replayed: false };
+
+        if let Some(key) = idempotency_key {
+            let recorded = self.idempotency_repository.record(
+                &mut tx,
+                auth_context.user_id,
+                key,
+                UPSERT_ENDPOINT,
+                request_hash,
+                &serde_json::to_value(&upserted)?,
+                Utc::now() + Duration::hours(IDEMPOTENCY_WINDOW_HOURS),
+            ).await?;
+            if !recorded {
+                return Ok(None);
+            }
         }
+        tx.commit().await?;
 
-        Ok(self.ci_repository.search_ci_assets(query, limit, offset).await?)
+        Ok(Some(upserted))
     }
 
-    pub async fn update_ci_asset(
+    /// Apply a batch of asset operations. Every operation is validated before
+    /// any is written; an atomic batch then commits as a whole, while a
+    /// best-effort one applies what it can and reports the rest per item.
+    /// Neo4j is synced once for the whole batch.
+    pub async fn bulk_ci_assets(&self, request: BulkRequest<CIAssetOperation>, auth_context: &AuthContext) -> AppResult<BulkResult> {
+        request.check_size().map_err(AppError::invalid_field)?;
+        let mode = request.mode;
+        let permissions = PermissionSet::load(&self.rbac_repository, auth_context).await?;
+
+        // An asset changes at most once per batch, so that every operation
+        // is validated against the asset as it is now
+        let mut rules = HashMap::new();
+        let mut targets = HashMap::new();
+        let mut prepared = Vec::with_capacity(request.operations.len());
+        for (index, operation) in request.operations.into_iter().enumerate() {
+            let target = operation.target();
+            let change = match target.and_then(|id| targets.get(&id).map(|first| (id, *first))) {
+                Some((id, first)) => Err(AppError::invalid_field(FieldError::new(
+                    "/id",
+                    FieldErrorCode::RuleViolation,
+                    format!("CI asset {} is already changed by operation {}", id, first),
+                ))),
+                None => self.prepare_asset_operation(operation, &permissions, &mut rules).await,
+            };
+            if let Some(id) = target {
+                targets.entry(id).or_insert(index);
+            }
+            prepared.push((index, target, change.map_err(|e| e.nested_in(&operation_path(index)))));
+        }
+
+        let mut items = Vec::with_capacity(prepared.len());
+        let mut written = Vec::new();
+        let mut tx = self.ci_repository.begin().await?;
+
+        match mode {
+            BulkMode::Atomic => {
+                let (valid, invalid): (Vec<_>, Vec<_>) = prepared.into_iter().partition(|(_, _, change)| change.is_ok());
+                if !invalid.is_empty() {
+                    let count = invalid.len();
+                    let errors = invalid.into_iter().filter_map(|(_, _, change)| change.err()).collect();
+                    return Err(AppError::combined(format!("{} of the operations are invalid; none was applied", count), errors));
+                }
+
+                for (index, _, change) in valid {
+                    let change = change?;
+                    let id = self.apply_bulk_change(&mut tx, &change, auth_context).await
+                        .map_err(|e| e.nested_in(&operation_path(index)))?;
+                    items.push(BulkItemResult::applied(index, id));
+                    written.push((id, change));
+                }
+            }
+            BulkMode::BestEffort => {
+                for (index, target, change) in prepared {
+                    let change = match change {
+                        Ok(change) => change,
+                        Err(e) => {
+                            items.push(BulkItemResult::failed(index, target, e.details()));
+                            continue;
+                        }
+                    };
+
+                    // A savepoint per operation keeps a failed write from
+                    // taking the others down with it
+                    let mut savepoint = Connection::begin(&mut *tx).await?;
+                    match self.apply_bulk_change(&mut savepoint, &change, auth_context).await {
+                        Ok(id) => {
+                            savepoint.commit().await?;
+                            items.push(BulkItemResult::applied(index, id));
+                            written.push((id, change));
+                        }
+                        Err(e) => {
+                            savepoint.rollback().await?;
+                            items.push(BulkItemResult::failed(index, target, e.nested_in(&operation_path(index)).details()));
+                        }
+                    }
+                }
+            }
+        }
+        tx.commit().await?;
+
+        let (deleted, upserted): (Vec<_>, Vec<_>) = written.into_iter()
+            .partition(|(_, change)| matches!(change, AssetChange::Delete { .. }));
+        self.sync_assets_to_graph(
+            &upserted.into_iter().map(|(id, _)| id).collect::<Vec<_>>(),
+            &deleted.into_iter().map(|(id, _)| id).collect::<Vec<_>>(),
+        ).await;
+
+        Ok(BulkResult::new(mode, items))
+    }
+
+    /// Apply one change of a bulk request. An asset that vanished since it
+    /// was validated fails the operation.
+    async fn apply_bulk_change(&self, conn: &mut PgConnection, change: &AssetChange, auth_context: &AuthContext) -> AppResult<Uuid> {
+        self.apply_asset_change(conn, change, auth_context).await?
+            .ok_or_else(|| AppError::not_found("The CI asset was deleted while the batch was applied"))
+    }
+
+    async fn prepare_asset_operation(
+        &self,
+        operation: CIAssetOperation,
+        permissions: &PermissionSet,
+        rules: &mut HashMap<Uuid, AssetRules>,
+    ) -> AppResult<AssetChange> {
+        match operation {
+            CIAssetOperation::Create(request) => self.prepare_create(request, permissions, rules).await,
+            CIAssetOperation::Update(update) => {
+                update.changes.validate()?;
+                if update.changes.name.is_none() && update.changes.attributes.is_none() {
+                    return Err(AppError::invalid_field(FieldError::new(
+                        "",
+                        FieldErrorCode::Required,
+                        "An update needs a name, attributes or both",
+                    )));
+                }
+                self.prepare_update(update.id, update.changes.name, update.changes.attributes, permissions, rules).await
+            }
+            CIAssetOperation::Delete { id } => self.prepare_delete(id, permissions).await,
+        }
+    }
+
+    /// The rules of a CI type, looked up once per batch
+    async fn asset_rules<'a>(&self, rules: &'a mut HashMap<Uuid, AssetRules>, ci_type_id: Uuid) -> AppResult<&'a AssetRules> {
+        if !rules.contains_key(&ci_type_id) {
+            let ci_type = self.ci_repository.get_ci_type_by_id(ci_type_id).await?
+                .ok_or_else(|| AppError::not_found(&format!("CI type with id '{}' not found", ci_type_id)))?;
+            let lineage = self.lineage(&ci_type).await?;
+            let computed = computed_for_lineage(&lineage)?;
+            let constraints = constraints_of(&ci_type, merged_schema(&lineage).as_ref())?;
+            rules.insert(ci_type_id, AssetRules { ci_type, lineage, computed, constraints });
+        }
+        Ok(&rules[&ci_type_id])
+    }
+
+    /// Check a new asset against its CI type before anything is written
+    async fn prepare_create(
+        &self,
+        request: CreateCIAssetRequest,
+        permissions: &PermissionSet,
+        rules: &mut HashMap<Uuid, AssetRules>,
+    ) -> AppResult<AssetChange> {
+        // Validate the request
+        request.validate()?;
+
+        permissions.require(ResourceType::CiAsset, PermissionAction::Create, Some(request.ci_type_id))?;
+
+        // Check if CI type exists
+        let rules = self.asset_rules(rules, request.ci_type_id).await?;
+        let ci_type = &rules.ci_type;
+
+        // Set default attributes if not provided
+        let attributes = request.attributes.unwrap_or_else(|| json!({}));
+
+        if ci_type.is_abstract {
+            return Err(AppError::bad_request(&format!(
+                "CI type '{}' is abstract; create the asset with one of its subtypes", ci_type.name
+            )));
+        }
+
+        // Computed attributes are derived here, never taken from the client
+        reject_computed_writes(&rules.computed, &attributes)?;
+
+        // Validate attributes against CI type schema (if schema exists)
+        self.validate_attributes_against_lineage(ci_type, &rules.lineage, &attributes)?;
+
+        self.check_references(&rules.constraints, &attributes).await?;
+
+        let computed_values = rules.computed.evaluate(&attributes, Utc::now().date_naive()).map_err(AppError::invalid_field)?;
+
+        Ok(AssetChange::Create {
+            ci_type_id: ci_type.id,
+            name: request.name,
+            unique_values: rules.constraints.unique_values(ci_type.id, &attributes),
+            attributes,
+            computed_values,
+            schema_version: ci_type.schema_version,
+        })
+    }
+
+    /// Check an asset update against the asset's CI type before anything is written
+    async fn prepare_update(
         &self,
         id: Uuid,
-        name: Option<&str>,
-        attributes: Option<&Value>,
-        user_id: Uuid,
-    ) -> AppResult<bool> {
+        name: Option<String>,
+        attributes: Option<Value>,
+        permissions: &PermissionSet,
+        rules: &mut HashMap<Uuid, AssetRules>,
+    ) -> AppResult<AssetChange> {
         // Check if CI asset exists
         let existing_asset = self.ci_repository.get_ci_asset(id).await?
             .ok_or_else(|| AppError::not_found(&format!("CI asset with id '{}' not found", id)))?;
 
+        permissions.require(ResourceType::CiAsset, PermissionAction::Update, Some(existing_asset.3))?;
+
         // If attributes are being updated, validate them against the CI type schema
+        let mut schema_version = None;
+        let mut computed_values = None;
+        let mut unique_values = None;
         if let Some(ref new_attributes) = attributes {
-            // Get the CI type for this asset
-            let ci_type = self.ci_repository.get_ci_type_by_id(existing_asset.3).await?
-                .ok_or_else(|| AppError::not_found("CI type not found for this asset"))?;
+            let rules = self.asset_rules(rules, existing_asset.3).await?;
+            let ci_type = &rules.ci_type;
+
+            reject_computed_writes(&rules.computed, new_attributes)?;
 
             // Validate attributes against CI type schema (if schema exists)
-            if ci_type.attributes.get("schema").is_some() {
-                self.validate_attributes_against_schema(&ci_type, new_attributes)?;
-            }
+            self.validate_attributes_against_lineage(ci_type, &rules.lineage, new_attributes)?;
+            schema_version = ci_type.schema_version;
+
+            self.check_references(&rules.constraints, new_attributes).await?;
+            unique_values = Some(rules.constraints.unique_values(ci_type.id, new_attributes));
+            computed_values = Some(rules.computed.evaluate(new_attributes, Utc::now().date_naive()).map_err(AppError::invalid_field)?);
         }
 
-        Ok(self.ci_repository.update_ci_asset(id, name, attributes, user_id).await?)
+        Ok(AssetChange::Update { id, name, attributes, computed_values, schema_version, unique_values })
     }
 
-    pub async fn delete_ci_asset(&self, id: Uuid, user_id: Uuid) -> AppResult<()> {
+    async fn prepare_delete(&self, id: Uuid, permissions: &PermissionSet) -> AppResult<AssetChange> {
         // Check if CI asset exists
-        let _existing_asset = self.ci_repository.get_ci_asset(id).await?
+        let existing_asset = self.ci_repository.get_ci_asset(id).await?
             .ok_or_else(|| AppError::not_found(&format!("CI asset with id '{}' not found", id)))?;
 
-        let deleted = self.ci_repository.delete_ci_asset(id, user_id).await?;
+        permissions.require(ResourceType::CiAsset, PermissionAction::Delete, Some(existing_asset.3))?;
 
-        if !deleted {
-            return Err(AppError::internal("Failed to delete CI asset"));
+        Ok(AssetChange::Delete { id })
+    }
+
+    /// Write a validated change and its audit entry on the caller's
+    /// transaction. None when there was nothing to write, such as an update
+    /// without changes or an asset deleted in the meantime.
+    async fn apply_asset_change(&self, conn: &mut PgConnection, change: &AssetChange, auth_context: &AuthContext) -> AppResult<Option<Uuid>> {
+        match change {
+            AssetChange::Create { ci_type_id, name, attributes, computed_values, schema_version, unique_values } => {
+                let id = self.ci_repository.create_ci_asset(
+                    conn,
+                    *ci_type_id,
+                    name,
+                    attributes,
+                    computed_values,
+                    *schema_version,
+                    auth_context.user_id,
+                ).await?;
+                self.claim_unique_values(conn, id, unique_values).await?;
+
+                self.audit_service
+                    .record_change(conn, auth_context, AuditedEntity::CiAsset, id, AuditAction::Insert, None)
+                    .await?;
+                Ok(Some(id))
+            }
+            AssetChange::Update { id, name, attributes, computed_values, schema_version, unique_values } => {
+                let old_values = self.audit_service.snapshot(conn, AuditedEntity::CiAsset, *id).await?;
+                let updated = self.ci_repository
+                    .update_ci_asset(conn, *id, name.as_deref(), attributes.as_ref(), *schema_version, auth_context.user_id)
+                    .await?;
+                if !updated {
+                    return Ok(None);
+                }
+
+                if let Some(computed_values) = computed_values {
+                    self.ci_repository.set_computed_attributes(conn, *id, computed_values, None).await?;
+                }
+                if let Some(unique_values) = unique_values {
+                    self.claim_unique_values(conn, *id, unique_values).await?;
+                }
+                self.audit_service
+                    .record_change(conn, auth_context, AuditedEntity::CiAsset, *id, AuditAction::Update, old_values)
+                    .await?;
+                Ok(Some(*id))
+            }
+            AssetChange::Delete { id } => {
+                let old_values = self.audit_service.snapshot(conn, AuditedEntity::CiAsset, *id).await?;
+                if !self.ci_repository.delete_ci_asset(conn, *id, auth_context.user_id).await? {
+                    return Ok(None);
+                }
+                // Deleted assets don't hold on to their unique values
+                self.ci_repository.release_unique_values(conn, *id).await?;
+
+                // Its relationships go to the trash along with it, and come back when it is restored
+                for relationship_id in self.relationship_repository.live_relationship_ids_of_asset(conn, *id).await? {
+                    let relationship_values = self.audit_service.snapshot(conn, AuditedEntity::Relationship, relationship_id).await?;
+                    self.relationship_repository
+                        .delete_relationship(conn, relationship_id, auth_context.user_id, Some(*id))
+                        .await?;
+                    self.audit_service
+                        .record_change(conn, auth_context, AuditedEntity::Relationship, relationship_id, AuditAction::Delete, relationship_values)
+                        .await?;
+                }
+
+                self.audit_service
+                    .record_change(conn, auth_context, AuditedEntity::CiAsset, *id, AuditAction::Delete, old_values)
+                    .await?;
+                Ok(Some(*id))
+            }
+        }
+    }
+
+    /// Audit entries for an asset, after checking the caller may read it.
+    /// Works for deleted assets too, which is what incident reviews need.
+    async fn ci_asset_history_entries(&self, id: Uuid, auth_context: &AuthContext) -> AppResult<Vec<AuditLogEntry>> {
+        let entries = self.audit_service.entity_history(AuditedEntity::CiAsset, id).await?;
+
+        let ci_type_id = match entries.last() {
+            Some(entry) => asset_snapshot(entry.log.new_values.as_ref().or(entry.log.old_values.as_ref()))
+                .map(|snapshot| snapshot.ci_type_id),
+            None => self.ci_repository.get_ci_asset(id).await?.map(|asset| asset.3),
+        }
+        .ok_or_else(|| AppError::not_found(&format!("CI asset with id '{}' not found", id)))?;
+
+        PermissionSet::load(&self.rbac_repository, auth_context).await?
+            .require(ResourceType::CiAsset, PermissionAction::Read, Some(ci_type_id))?;
+
+        Ok(entries)
+    }
+
+    /// Every recorded version of an asset, oldest first
+    pub async fn get_ci_asset_history(&self, id: Uuid, auth_context: &AuthContext) -> AppResult<Vec<CIAssetVersion>> {
+        let entries = self.ci_asset_history_entries(id, auth_context).await?;
+
+        let mut previous_attributes = entries
+            .first()
+            .and_then(|entry| asset_snapshot(entry.log.old_values.as_ref()))
+            .map(|snapshot| snapshot.attributes)
+            .unwrap_or_else(|| json!({}));
+
+        let mut versions = Vec::with_capacity(entries.len());
+        for (index, entry) in entries.into_iter().enumerate() {
+            let deleted = entry.log.action == AuditAction::Delete.as_str();
+            let snapshot = if deleted { None } else { asset_snapshot(entry.log.new_values.as_ref()) };
+            let attributes = snapshot.as_ref().map(|s| s.attributes.clone()).unwrap_or_else(|| json!({}));
+
+            versions.push(CIAssetVersion {
+                version: index as i64 + 1,
+                audit_log_id: entry.log.id,
+                action: entry.log.action,
+                deleted,
+                ci_type_id: snapshot.as_ref().map(|s| s.ci_type_id),
+                name: snapshot.as_ref().map(|s| s.name.clone()),
+                attributes: snapshot.map(|s| s.attributes),
+                changes: calculate_json_diff(&previous_attributes, &attributes)?,
+                changed_by: entry.log.performed_by,
+                changed_by_name: entry.performed_by_name,
+                changed_at: entry.log.created_at,
+            });
+
+            previous_attributes = attributes;
+        }
+
+        Ok(versions)
+    }
+
+    /// The asset's name and attributes as they were at `as_of`
+    pub async fn get_ci_asset_as_of(&self, id: Uuid, as_of: DateTime<Utc>, auth_context: &AuthContext) -> AppResult<CIAssetAsOf> {
+        let entries = self.ci_asset_history_entries(id, auth_context).await?;
+        let not_found = || AppError::not_found(&format!("CI asset with id '{}' did not exist at {}", id, as_of.to_rfc3339()));
+
+        match state_as_of(&entries, as_of) {
+            HistoricalState::Existed(values, version) => Ok(asset_snapshot(Some(values))
+                .ok_or_else(not_found)?
+                .into_as_of(id, as_of, version)),
+            HistoricalState::Absent => Err(not_found()),
+            // Never changed since auditing began, so the current row is the answer
+            HistoricalState::Unrecorded => {
+                let asset = self.ci_repository.get_ci_asset_by_id(id).await?
+                    .filter(|asset| asset.created_at <= as_of)
+                    .ok_or_else(not_found)?;
+
+                Ok(CIAssetAsOf {
+                    id,
+                    ci_type_id: asset.ci_type_id,
+                    name: asset.name,
+                    attributes: asset.attributes,
+                    as_of,
+                    version: None,
+                })
+            }
+        }
+    }
+
+    /// Put an asset back to an earlier version, picked by audit entry or by
+    /// time. The restore is itself a new, audited update, validated against
+    /// the CI type's current schema. Soft-deleted assets are undeleted.
+    pub async fn restore_ci_asset(
+        &self,
+        id: Uuid,
+        request: &RestoreVersionRequest,
+        auth_context: &AuthContext,
+    ) -> AppResult<RestoredCIAsset> {
+        let entries = self.ci_asset_history_entries(id, auth_context).await?;
+        let target = asset_snapshot(Some(restore_target(&entries, request)?))
+            .ok_or_else(|| AppError::internal("Audit entry does not hold a CI asset snapshot"))?;
+
+        self.restore_asset(id, Some(target), auth_context).await
+    }
+
+    /// One page of soft-deleted assets, most recently deleted first
+    pub async fn list_deleted_ci_assets(
+        &self,
+        filter: &DeletedCIAssetFilter,
+        page: &PageRequest,
+        auth_context: &AuthContext,
+    ) -> AppResult<Page<DeletedCIAsset>> {
+        let cursor = page.cursor(0)?;
+        let limit = page.limit()?;
+
+        let allowed_type_ids = PermissionSet::load(&self.rbac_repository, auth_context).await?
+            .readable_scopes(ResourceType::CiAsset);
+
+        let assets = self.ci_repository
+            .list_deleted_ci_assets(filter.ci_type_id, allowed_type_ids.as_deref(), cursor.as_ref(), limit + 1)
+            .await?;
+        let total = if page.include_total {
+            Some(self.ci_repository.count_deleted_ci_assets(filter.ci_type_id, allowed_type_ids.as_deref()).await?)
+        } else {
+            None
+        };
+
+        Ok(Page::from_rows(assets, limit, |last| KeysetCursor::new(last.deleted_at, last.id)).with_total(total))
+    }
+
+    /// Take an asset out of the trash as it was when deleted, along with the
+    /// relationships its deletion took along
+    pub async fn restore_deleted_ci_asset(&self, id: Uuid, auth_context: &AuthContext) -> AppResult<RestoredCIAsset> {
+        self.restore_asset(id, None, auth_context).await
+    }
+
+    /// Write `target` over an asset, or without one undelete it as it is.
+    /// Undeleting also restores the relationships the deletion took along.
+    async fn restore_asset(&self, id: Uuid, target: Option<AssetSnapshot>, auth_context: &AuthContext) -> AppResult<RestoredCIAsset> {
+        let mut tx = self.ci_repository.begin().await?;
+        let old_values = self.audit_service.snapshot(&mut tx, AuditedEntity::CiAsset, id).await?
+            .ok_or_else(|| AppError::not_found(&format!("CI asset with id '{}' not found", id)))?;
+        let current = asset_snapshot(Some(&old_values))
+            .ok_or_else(|| AppError::internal("Unreadable CI asset row"))?;
+
+        PermissionSet::load(&self.rbac_repository, auth_context).await?
+            .require(ResourceType::CiAsset, PermissionAction::Update, Some(current.ci_type_id))?;
+
+        let undeleted = old_values.get("deleted_at").map_or(false, |deleted_at| !deleted_at.is_null());
+        let target = match target {
+            Some(target) => target,
+            None if undeleted => AssetSnapshot {
+                ci_type_id: current.ci_type_id,
+                name: current.name.clone(),
+                attributes: current.attributes.clone(),
+            },
+            None => return Err(AppError::not_found(&format!("CI asset with id '{}' is not in the trash", id))),
+        };
+
+        let ci_type = self.ci_repository.get_ci_type_by_id(current.ci_type_id).await?
+            .ok_or_else(|| AppError::bad_request("The asset's CI type has been deleted"))?;
+
+        let changes = calculate_json_diff(&current.attributes, &target.attributes)?;
+        let attributes = apply_json_diff(&current.attributes, &changes)?;
+
+        // Old versions may predate schema changes, so they are held to today's schema
+        self.validate_attributes_against_schema(&ci_type, &attributes).await?;
+        let computed_values = self.computed_attributes(&ci_type).await?
+            .evaluate(&attributes, Utc::now().date_naive())
+            .map_err(AppError::invalid_field)?;
+        let constraints = self.attribute_constraints(&ci_type).await?;
+        self.check_references(&constraints, &attributes).await?;
+
+        self.ci_repository
+            .restore_ci_asset(&mut tx, id, &target.name, &attributes, ci_type.schema_version, auth_context.user_id)
+            .await?;
+        self.ci_repository.set_computed_attributes(&mut tx, id, &computed_values, None).await?;
+        self.claim_unique_values(&mut tx, id, &constraints.unique_values(ci_type.id, &attributes)).await?;
+        self.audit_service
+            .record_change(&mut tx, auth_context, AuditedEntity::CiAsset, id, AuditAction::Update, Some(old_values))
+            .await?;
+        let restored_relationships = if undeleted {
+            self.restore_relationships_deleted_with(&mut tx, id, auth_context).await?
+        } else {
+            Vec::new()
+        };
+        tx.commit().await?;
+
+        self.sync_asset_to_graph(id).await;
+        if undeleted {
+            // Deleting the node dropped its edges too
+            self.sync_asset_relationships_to_graph(id).await;
         }
 
+        Ok(RestoredCIAsset {
+            id,
+            ci_type_id: current.ci_type_id,
+            name: target.name,
+            attributes,
+            undeleted,
+            changes,
+            restored_relationships,
+        })
+    }
+
+    /// Undelete the relationships that went to the trash with an asset, as
+    /// they were. One whose other asset is in the trash too is left to come
+    /// back with that asset instead; one whose type has been deleted, or
+    /// that has been recreated since, stays in the trash.
+    async fn restore_relationships_deleted_with(&self, conn: &mut PgConnection, asset_id: Uuid, auth_context: &AuthContext) -> AppResult<Vec<Uuid>> {
+        let mut restored = Vec::new();
+        for relationship in self.relationship_repository.relationships_deleted_with(conn, asset_id).await? {
+            if !relationship.other_asset_live {
+                self.relationship_repository.set_deleted_with(conn, relationship.id, relationship.other_asset_id).await?;
+            } else if relationship.restorable {
+                let old_values = self.audit_service.snapshot(conn, AuditedEntity::Relationship, relationship.id).await?;
+                self.relationship_repository.restore_relationship(conn, relationship.id, &relationship.attributes).await?;
+                self.audit_service
+                    .record_change(conn, auth_context, AuditedEntity::Relationship, relationship.id, AuditAction::Update, old_values)
+                    .await?;
+                restored.push(relationship.id);
+            }
+        }
+
+        Ok(restored)
+    }
+
+    /// Mirror an asset into Neo4j. The graph is derived from PostgreSQL, so a
+    /// failed sync is logged rather than failing the change behind it.
+    async fn sync_asset_to_graph(&self, id: Uuid) {
+        if let Err(e) = self.try_sync_asset_to_graph(id).await {
+            tracing::warn!("Failed to sync CI asset {} to Neo4j: {}", id, e);
+        }
+    }
+
+    async fn try_sync_asset_to_graph(&self, id: Uuid) -> Result<()> {
+        let asset = self.ci_repository.get_ci_asset_by_id(id).await?
+            .ok_or_else(|| anyhow::anyhow!("CI asset not found"))?;
+        let ci_type = self.ci_repository.get_ci_type_by_id(asset.ci_type_id).await?
+            .ok_or_else(|| anyhow::anyhow!("CI type not found"))?;
+
+        self.graph_repository
+            .create_ci_node(id, &asset.name, &ci_type.name, asset.ci_type_id, &asset.attributes)
+            .await
+    }
+
+    /// Mirror the assets a batch wrote into Neo4j with a few queries for the
+    /// whole batch. Like `sync_asset_to_graph`, failures are only logged.
+    async fn sync_assets_to_graph(&self, upserted: &[Uuid], deleted: &[Uuid]) {
+        if !upserted.is_empty() {
+            let nodes = match self.ci_repository.get_ci_assets_by_ids(upserted).await {
+                Ok(assets) => assets.into_iter()
+                    .map(|(asset, ci_type)| GraphNode {
+                        id: asset.id,
+                        name: asset.name,
+                        ci_type,
+                        ci_type_id: asset.ci_type_id,
+                        attributes: asset.attributes,
+                    })
+                    .collect::<Vec<_>>(),
+                Err(e) => {
+                    tracing::warn!("Failed to load {} CI assets for Neo4j: {}", upserted.len(), e);
+                    Vec::new()
+                }
+            };
+            if let Err(e) = self.graph_repository.upsert_ci_nodes(&nodes).await {
+                tracing::warn!("Failed to sync {} CI assets to Neo4j: {}", nodes.len(), e);
+            }
+        }
+
+        if !deleted.is_empty() {
+            if let Err(e) = self.graph_repository.delete_nodes(deleted).await {
+                tracing::warn!("Failed to delete {} CI assets from Neo4j: {}", deleted.len(), e);
+            }
+        }
+    }
+
+    /// Recreate the Neo4j edges of every live relationship touching an asset
+    async fn sync_asset_relationships_to_graph(&self, id: Uuid) {
+        const PAGE_SIZE: i64 = 500;
+        let filter = RelationshipFilter {
+            relationship_type_id: None,
+            ci_asset_id: Some(id),
+            from_ci_asset_id: None,
+            to_ci_asset_id: None,
+        };
+        let mut cursor = None;
+
+        loop {
+            let relationships = match self.relationship_repository.list_relationships(&filter, None, cursor.as_ref(), PAGE_SIZE).await {
+                Ok(relationships) => relationships,
+                Err(e) => {
+                    tracing::warn!("Failed to load relationships of CI asset {} for Neo4j: {}", id, e);
+                    return;
+                }
+            };
+
+            for relationship in &relationships {
+                if let Err(e) = self.graph_repository
+                    .create_relationship(
+                        relationship.from_ci_asset_id,
+                        relationship.to_ci_asset_id,
+                        &relationship.relationship_type_name,
+                        relationship.relationship_type_id,
+                        Some(relationship.attributes.clone()),
+                        &relationship.from_ci_type_name,
+                        &relationship.to_ci_type_name,
+                        relationship.is_bidirectional,
+                    )
+                    .await
+                {
+                    tracing::warn!("Failed to sync relationship {} to Neo4j: {}", relationship.id, e);
+                }
+            }
+
+            match relationships.last() {
+                Some(last) if relationships.len() as i64 == PAGE_SIZE => {
+                    cursor = Some(KeysetCursor::new(last.created_at, last.id));
+                }
+                _ => return,
+            }
+        }
+    }
+}
+
+/// What a CI type requires of its assets' attributes, looked up once for
+/// all the assets of a type in a request
+struct AssetRules {
+    ci_type: CIType,
+    lineage: Vec<CIType>,
+    computed: ComputedAttributes,
+    constraints: AttributeConstraints,
+}
+
+/// An asset change that passed validation, ready to be written
+enum AssetChange {
+    Create {
+        ci_type_id: Uuid,
+        name: String,
+        attributes: Value,
+        computed_values: Value,
+        schema_version: Option<i32>,
+        unique_values: Vec<UniqueValue>,
+    },
+    Update {
+        id: Uuid,
+        name: Option<String>,
+        attributes: Option<Value>,
+        computed_values: Option<Value>,
+        schema_version: Option<i32>, // What the new attributes were validated against
+        unique_values: Option<Vec<UniqueValue>>,
+    },
+    Delete {
+        id: Uuid,
+    },
+}
+
+/// The natural key an upsert is matched by. The CI type has to declare one,
+/// and the request has to give every part of it.
+fn upsert_key(rules: &AssetRules, attributes: &Value) -> AppResult<UniqueValue> {
+    let natural_key = rules.constraints.natural_key();
+    if natural_key.is_empty() {
+        return Err(AppError::invalid_field(FieldError::new(
+            "/ci_type_id",
+            FieldErrorCode::RuleViolation,
+            format!("CI type '{}' declares no natural key to upsert by", rules.ci_type.name),
+        )));
+    }
+
+    rules.constraints.natural_key_value(rules.ci_type.id, attributes).ok_or_else(|| {
+        let errors = natural_key.iter()
+            .filter(|name| attributes.get(name.as_str()).map_or(true, Value::is_null))
+            .map(|name| FieldError::new(
+                format!("/attributes/{}", name),
+                FieldErrorCode::Required,
+                format!("'{}' is part of the natural key", name),
+            ))
+            .collect();
+        AppError::invalid_fields("An upsert needs every part of the natural key", errors)
+    })
+}
+
+/// The outcome recorded for an Idempotency-Key, provided this request is a
+/// retry of the one that recorded it
+fn replay_upsert(previous: IdempotentResponse, request_hash: &str) -> AppResult<UpsertedCIAsset> {
+    if previous.endpoint != UPSERT_ENDPOINT || previous.request_hash != request_hash {
+        return Err(AppError::conflict("The Idempotency-Key was already used for a different request"));
+    }
+
+    let mut upserted: UpsertedCIAsset = serde_json::from_value(previous.response)?;
+    upserted.replayed = true;
+    Ok(upserted)
+}
+
+/// The parts of a `ci_assets` row snapshot that versions expose
+struct AssetSnapshot {
+    ci_type_id: Uuid,
+    name: String,
+    attributes: Value,
+}
+
+impl AssetSnapshot {
+    fn into_as_of(self, id: Uuid, as_of: DateTime<Utc>, version: Option<i64>) -> CIAssetAsOf {
+        CIAssetAsOf {
+            id,
+            ci_type_id: self.ci_type_id,
+            name: self.name,
+            attributes: self.attributes,
+            as_of,
+            version,
+        }
+    }
+}
+
+fn asset_snapshot(values: Option<&Value>) -> Option<AssetSnapshot> {
+    let values = values?;
+
+    Some(AssetSnapshot {
+        ci_type_id: values.get("ci_type_id")?.as_str()?.parse().ok()?,
+        name: values.get("name")?.as_str()?.to_string(),
+        attributes: values.get("attributes").cloned().unwrap_or_else(|| json!({})),
+    })
+}
+
+/// The new parent of type `id` must exist and must not be the type itself or one of its subtypes
+fn check_parent_lineage(id: Uuid, parent_id: Uuid, lineage: &[CIType]) -> AppResult<()> {
+    if lineage.is_empty() {
+        return Err(AppError::not_found(&format!("Parent CI type with id '{}' not found", parent_id)));
+    }
+    if lineage.iter().any(|ancestor| ancestor.id == id) {
+        return Err(AppError::bad_request("A CI type cannot inherit from itself or one of its subtypes"));
+    }
+    Ok(())
+}
+
+fn compile_schema(schema: &Value) -> AppResult<JSONSchema> {
+    JSONSchema::compile(schema)
+        .map_err(|e| AppError::invalid_field(FieldError::new(
+            "/attributes/schema",
+            FieldErrorCode::InvalidValue,
+            format!("Invalid JSON schema: {}", e),
+        )))
+}
+
+/// Every way `attributes` fails the schema; empty when it conforms
+fn schema_errors(schema: &JSONSchema, attributes: &Value) -> Vec<FieldError> {
+    match schema.validate(attributes) {
+        Ok(()) => Vec::new(),
+        Err(errors) => errors.map(FieldError::from).collect(),
+    }
+}
+
+/// Reject a page size or offset outside what the list endpoints allow
+fn check_page(limit: i64, offset: i64) -> AppResult<()> {
+    if limit < 1 || limit > 100 {
+        return Err(AppError::invalid_field(
+            FieldError::new("/limit", FieldErrorCode::OutOfRange, "Limit must be between 1 and 100")
+                .with_expected(json!({ "min": 1, "max": 100 })),
+        ));
+    }
+
+    if offset < 0 {
+        return Err(AppError::invalid_field(
+            FieldError::new("/offset", FieldErrorCode::OutOfRange, "Offset must be non-negative")
+                .with_expected(json!({ "min": 0 })),
+        ));
+    }
+
+    Ok(())
+}
+
+fn migrate(attributes: &Value, migration: Option<&SchemaMigration>) -> Result<Value, String> {
+    match migration {
+        Some(migration) => migrate_attributes(attributes, migration),
+        None => Ok(attributes.clone()),
+    }
+}
+
+/// Re-evaluate and store the computed attributes of every live asset of a CI
+/// type (not its subtypes), one batch per transaction. Values that can't be
+/// evaluated are stored as null. Returns how many assets were refreshed.
+pub async fn refresh_computed_attributes(ci_repository: &CIRepository, ci_type_id: Uuid) -> AppResult<i64> {
+    let lineage = ci_repository.get_ci_type_lineage(ci_type_id).await?;
+    let computed = computed_for_lineage(&lineage)?;
+    let today = Utc::now().date_naive();
+
+    let mut refreshed = 0;
+    let mut after_id = None;
+    loop {
+        let assets = ci_repository
+            .list_ci_assets_for_schema(ci_type_id, None, after_id, SCHEMA_BATCH_SIZE)
+            .await?;
+
+        let mut tx = ci_repository.begin().await?;
+        for (asset_id, _, attributes) in &assets {
+            // An asset edited since it was listed already has current values
+            let values = computed.evaluate_lenient(attributes, today);
+            if ci_repository.set_computed_attributes(&mut tx, *asset_id, &values, Some(attributes)).await? {
+                refreshed += 1;
+            }
+        }
+        tx.commit().await?;
+
+        match assets.last() {
+            Some((last_id, _, _)) if assets.len() as i64 == SCHEMA_BATCH_SIZE => after_id = Some(*last_id),
+            _ => break,
+        }
+    }
+
+    Ok(refreshed)
+}
+
+/// Computed attributes of a lineage, given the type first and its ancestors after
+fn computed_for_lineage(lineage: &[CIType]) -> AppResult<ComputedAttributes> {
+    ComputedAttributes::from_layers(lineage.iter().rev().map(|ci_type| &ci_type.attributes)).map_err(|errors| {
+        let name = lineage.first().map_or("", |ci_type| ci_type.name.as_str());
+        let messages: Vec<&str> = errors.iter().map(|error| error.message.as_str()).collect();
+        AppError::internal(format!("CI type '{}' has invalid computed attributes: {}", name, messages.join("; ")))
+    })
+}
+
+/// Parse the computed attributes a type would have with `attributes` over
+/// what it inherits. None may share a name with a schema property, or
+/// clients would be asked for a value they aren't allowed to write.
+fn check_computed_definitions(inherited_lineage: &[CIType], attributes: &Value, schema: Option<&Value>) -> AppResult<()> {
+    let layers = inherited_lineage.iter().rev().map(|ci_type| &ci_type.attributes).chain(std::iter::once(attributes));
+    let computed = ComputedAttributes::from_layers(layers)
+        .map_err(|errors| AppError::invalid_fields("Invalid computed attributes", errors))?;
+
+    let properties = schema.and_then(|schema| schema.get("properties")).and_then(Value::as_object);
+    let errors: Vec<FieldError> = computed.names()
+        .filter(|name| properties.map_or(false, |properties| properties.contains_key(name.as_str())))
+        .map(|name| FieldError::new(
+            format!("/attributes/computed/{}", name),
+            FieldErrorCode::RuleViolation,
+            format!("'{}' is also a schema property, but computed attributes can't be written", name),
+        ))
+        .collect();
+
+    if errors.is_empty() {
         Ok(())
+    } else {
+        Err(AppError::invalid_fields("Invalid computed attributes", errors))
+    }
+}
+
+/// Constraints of a stored CI type's effective schema, which were checked
+/// when the schema was saved
+fn constraints_of(ci_type: &CIType, schema: Option<&Value>) -> AppResult<AttributeConstraints> {
+    AttributeConstraints::from_schema(schema).map_err(|errors| {
+        let messages: Vec<&str> = errors.iter().map(|error| error.message.as_str()).collect();
+        AppError::internal(format!("CI type '{}' has invalid attribute constraints: {}", ci_type.name, messages.join("; ")))
+    })
+}
+
+fn duplicate_value_error(unique_value: &UniqueValue, owner: Uuid) -> FieldError {
+    // A natural key spans several attributes, so the error is on all of them
+    if unique_value.is_natural_key() {
+        return FieldError::new(
+            "/attributes",
+            FieldErrorCode::DuplicateValue,
+            format!("Natural key {} already identifies CI asset {}", unique_value.value, owner),
+        );
     }
-}
\ No newline at end of file
+
+    let within = if unique_value.scope.is_some() { "its CI type" } else { "all CI assets" };
+    FieldError::new(
+        format!("/attributes/{}", unique_value.attribute),
+        FieldErrorCode::DuplicateValue,
+        format!("{} is already used by CI asset {}; it must be unique within {}", unique_value.value, owner, within),
+    )
+}
+
+/// Clients may not set computed attributes, not even to their current value
+fn reject_computed_writes(computed: &ComputedAttributes, attributes: &Value) -> AppResult<()> {
+    let errors = computed.written_by_client(attributes);
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(AppError::invalid_fields("Computed attributes are read-only", errors))
+    }
+}
+
+/// Effective schema of a lineage, given the type first and its ancestors after
+fn merged_schema(lineage: &[CIType]) -> Option<Value> {
+    lineage
+        .iter()
+        .rev()
+        .fold(None, |inherited, ci_type| merge_optional_schemas(inherited.as_ref(), ci_type.attributes.get("schema")))
+}
+
+fn merge_optional_schemas(inherited: Option<&Value>, own: Option<&Value>) -> Option<Value> {
+    match (inherited, own) {
+        (Some(inherited), Some(own)) => Some(merge_schemas(inherited, own)),
+        (Some(schema), None) | (None, Some(schema)) => Some(schema.clone()),
+        (None, None) => None,
+    }
+}
+
+/// Extend an inherited schema with a subtype's own. Properties are combined
+/// with the subtype's definition winning, `required` lists are joined, and any
+/// other keyword the subtype sets replaces the inherited one.
+fn merge_schemas(inherited: &Value, own: &Value) -> Value {
+    let (Value::Object(inherited), Value::Object(own)) = (inherited, own) else {
+        return own.clone();
+    };
+
+    let mut merged = inherited.clone();
+    for (keyword, value) in own {
+        match (keyword.as_str(), merged.get_mut(keyword), value) {
+            ("properties", Some(Value::Object(properties)), Value::Object(own_properties)) => {
+                properties.extend(own_properties.iter().map(|(name, schema)| (name.clone(), schema.clone())));
+            }
+            ("required", Some(Value::Array(required)), Value::Array(own_required)) => {
+                for name in own_required {
+                    if !required.contains(name) {
+                        required.push(name.clone());
+                    }
+                }
+            }
+            _ => {
+                merged.insert(keyword.clone(), value.clone());
+            }
+        }
+    }
+
+    Value::Object(merged)
+}
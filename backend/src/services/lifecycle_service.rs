@@ -5,10 +5,13 @@ use crate::{
         CreateLifecycleTypeRequest, UpdateLifecycleTypeRequest,
         CreateLifecycleStateRequest, UpdateLifecycleStateRequest,
         CreateLifecycleTransitionRequest, CreateCITypeLifecycleRequest,
-        LifecycleTypeResponse, LifecycleTypeSummary,
+        LifecycleTypeResponse, LifecycleTypeSummary, LifecycleTypeFilter,
+        ResourceType, PermissionAction,
     },
-    database::{LifecycleRepository, CIRepository},
+    database::{LifecycleRepository, CIRepository, RbacRepository},
     middleware::AuthContext,
+    services::PermissionSet,
+    utils::{KeysetCursor, Page, PageRequest},
 };
 use validator::Validate;
 use uuid::Uuid;
@@ -16,13 +19,19 @@ use uuid::Uuid;
 pub struct LifecycleService {
     lifecycle_repository: LifecycleRepository,
     ci_repository: CIRepository,
+    rbac_repository: RbacRepository,
 }
 
 impl LifecycleService {
-    pub fn new(lifecycle_repository: LifecycleRepository, ci_repository: CIRepository) -> Self {
+    pub fn new(
+        lifecycle_repository: LifecycleRepository,
+        ci_repository: CIRepository,
+        rbac_repository: RbacRepository,
+    ) -> Self {
         Self {
             lifecycle_repository,
             ci_repository,
+            rbac_repository,
         }
     }
 
@@ -32,14 +41,14 @@ impl LifecycleService {
         request: CreateLifecycleTypeRequest,
         auth_context: &AuthContext,
     ) -> AppResult<LifecycleType> {
+        PermissionSet::load(&self.rbac_repository, auth_context).await?
+            .require(ResourceType::Lifecycle, PermissionAction::Create, None)?;
+
         // Validate request
-        request.validate().map_err(|e| {
-            AppError::validation(format!("Invalid lifecycle type request: {}", e))
-        })?;
+        request.validate()?;
 
         // Check for duplicate name
-        let existing = self.lifecycle_repository.list_lifecycle_types(false).await?;
-        if existing.iter().any(|lt| lt.name == request.name) {
+        if self.lifecycle_repository.lifecycle_type_name_exists(&request.name).await? {
             return Err(AppError::validation(
                 "Lifecycle type with this name already exists".to_string(),
             ));
@@ -51,7 +60,10 @@ impl LifecycleService {
             .await
     }
 
-    pub async fn get_lifecycle_type(&self, id: Uuid) -> AppResult<LifecycleTypeResponse> {
+    pub async fn get_lifecycle_type(&self, id: Uuid, auth_context: &AuthContext) -> AppResult<LifecycleTypeResponse> {
+        PermissionSet::load(&self.rbac_repository, auth_context).await?
+            .require(ResourceType::Lifecycle, PermissionAction::Read, Some(id))?;
+
         let lifecycle_type = self
             .lifecycle_repository
             .get_lifecycle_type_with_details(id)
@@ -61,21 +73,45 @@ impl LifecycleService {
         Ok(lifecycle_type)
     }
 
-    pub async fn list_lifecycle_types(&self, include_inactive: bool) -> AppResult<Vec<LifecycleTypeSummary>> {
-        self.lifecycle_repository
-            .list_lifecycle_types(include_inactive)
-            .await
+    /// One page of the lifecycle types the caller may read, by name
+    pub async fn list_lifecycle_types(
+        &self,
+        filter: &LifecycleTypeFilter,
+        page: &PageRequest,
+        auth_context: &AuthContext,
+    ) -> AppResult<Page<LifecycleTypeSummary>> {
+        let cursor = page.cursor(1)?;
+        let limit = page.limit()?;
+
+        let allowed_ids = PermissionSet::load(&self.rbac_repository, auth_context).await?
+            .readable_scopes(ResourceType::Lifecycle);
+
+        let lifecycle_types = self.lifecycle_repository
+            .list_lifecycle_types(filter, allowed_ids.as_deref(), cursor.as_ref(), limit + 1)
+            .await?;
+        let total = if page.include_total {
+            Some(self.lifecycle_repository.count_lifecycle_types(filter, allowed_ids.as_deref()).await?)
+        } else {
+            None
+        };
+
+        Ok(Page::from_rows(lifecycle_types, limit, |last| {
+            KeysetCursor::with_keys(vec![last.name.clone().into()], last.created_at, last.id)
+        })
+        .with_total(total))
     }
 
     pub async fn update_lifecycle_type(
         &self,
         id: Uuid,
         request: UpdateLifecycleTypeRequest,
+        auth_context: &AuthContext,
     ) -> AppResult<LifecycleType> {
+        PermissionSet::load(&self.rbac_repository, auth_context).await?
+            .require(ResourceType::Lifecycle, PermissionAction::Update, Some(id))?;
+
         // Validate request
-        request.validate().map_err(|e| {
-            AppError::validation(format!("Invalid lifecycle type update request: {}", e))
-        })?;
+        request.validate()?;
 
         // Check if lifecycle type exists
         let existing = self
@@ -87,8 +123,7 @@ impl LifecycleService {
         // If updating name, check for duplicates
         if let Some(ref name) = request.name {
             if name != &existing.name {
-                let all_types = self.lifecycle_repository.list_lifecycle_types(false).await?;
-                if all_types.iter().any(|lt| lt.name == *name) {
+                if self.lifecycle_repository.lifecycle_type_name_exists(name).await? {
                     return Err(AppError::validation(
                         "Lifecycle type with this name already exists".to_string(),
                     ));
@@ -97,11 +132,14 @@ impl LifecycleService {
         }
 
         self.lifecycle_repository
-            .update_lifecycle_type(id, &request)
+            .update_lifecycle_type(id, &request, auth_context.user_id)
             .await
     }
 
     pub async fn delete_lifecycle_type(&self, id: Uuid, auth_context: &AuthContext) -> AppResult<()> {
+        PermissionSet::load(&self.rbac_repository, auth_context).await?
+            .require(ResourceType::Lifecycle, PermissionAction::Delete, Some(id))?;
+
         // Check if lifecycle type exists
         let lifecycle_type = self
             .lifecycle_repository
@@ -119,7 +157,7 @@ impl LifecycleService {
         // For now, allow deletion
 
         self.lifecycle_repository
-            .delete_lifecycle_type(id)
+            .delete_lifecycle_type(id, auth_context.user_id)
             .await?;
 
         Ok(())
@@ -132,9 +170,11 @@ impl LifecycleService {
         auth_context: &AuthContext,
     ) -> AppResult<LifecycleState> {
         // Validate request
-        request.validate().map_err(|e| {
-            AppError::validation(format!("Invalid lifecycle state request: {}", e))
-        })?;
+        request.validate()?;
+
+        // States are part of their lifecycle type, so changing them is an update of it
+        PermissionSet::load(&self.rbac_repository, auth_context).await?
+            .require(ResourceType::Lifecycle, PermissionAction::Update, Some(request.lifecycle_type_id))?;
 
         // Check if lifecycle type exists
         let lifecycle_type = self
@@ -185,26 +225,30 @@ impl LifecycleService {
         }
 
         self.lifecycle_repository
-            .create_lifecycle_state(&request)
+            .create_lifecycle_state(&request, auth_context.user_id)
             .await
     }
 
-    pub async fn get_lifecycle_state(&self, id: Uuid) -> AppResult<LifecycleState> {
-        self.lifecycle_repository
+    pub async fn get_lifecycle_state(&self, id: Uuid, auth_context: &AuthContext) -> AppResult<LifecycleState> {
+        let state = self.lifecycle_repository
             .get_lifecycle_state(id)
             .await?
-            .ok_or_else(|| AppError::not_found("Lifecycle state not found"))
+            .ok_or_else(|| AppError::not_found("Lifecycle state not found"))?;
+
+        PermissionSet::load(&self.rbac_repository, auth_context).await?
+            .require(ResourceType::Lifecycle, PermissionAction::Read, Some(state.lifecycle_type_id))?;
+
+        Ok(state)
     }
 
     pub async fn update_lifecycle_state(
         &self,
         id: Uuid,
         request: UpdateLifecycleStateRequest,
+        auth_context: &AuthContext,
     ) -> AppResult<LifecycleState> {
         // Validate request
-        request.validate().map_err(|e| {
-            AppError::validation(format!("Invalid lifecycle state update request: {}", e))
-        })?;
+        request.validate()?;
 
         // Check if state exists
         let existing_state = self
@@ -213,6 +257,9 @@ impl LifecycleService {
             .await?
             .ok_or_else(|| AppError::not_found("Lifecycle state not found"))?;
 
+        PermissionSet::load(&self.rbac_repository, auth_context).await?
+            .require(ResourceType::Lifecycle, PermissionAction::Update, Some(existing_state.lifecycle_type_id))?;
+
         // Get lifecycle type details for validation
         let lifecycle_details = self
             .lifecycle_repository
@@ -262,7 +309,7 @@ impl LifecycleService {
         }
 
         self.lifecycle_repository
-            .update_lifecycle_state(id, &request)
+            .update_lifecycle_state(id, &request, auth_context.user_id)
             .await
     }
 
@@ -274,6 +321,9 @@ impl LifecycleService {
             .await?
             .ok_or_else(|| AppError::not_found("Lifecycle state not found"))?;
 
+        PermissionSet::load(&self.rbac_repository, auth_context).await?
+            .require(ResourceType::Lifecycle, PermissionAction::Update, Some(existing_state.lifecycle_type_id))?;
+
         // Check if state is being used in any transitions
         let lifecycle_details = self
             .lifecycle_repository
@@ -292,7 +342,7 @@ impl LifecycleService {
         }
 
         self.lifecycle_repository
-            .delete_lifecycle_state(id)
+            .delete_lifecycle_state(id, auth_context.user_id)
             .await?;
 
         Ok(())
@@ -305,9 +355,11 @@ impl LifecycleService {
         auth_context: &AuthContext,
     ) -> AppResult<CITypeLifecycleMapping> {
         // Validate request
-        request.validate().map_err(|e| {
-            AppError::validation(format!("Invalid CI type lifecycle mapping request: {}", e))
-        })?;
+        request.validate()?;
+
+        // Attaching a lifecycle configures the CI type
+        PermissionSet::load(&self.rbac_repository, auth_context).await?
+            .require(ResourceType::CiType, PermissionAction::Update, Some(request.ci_type_id))?;
 
         // Check if CI type exists
         self.ci_repository
@@ -331,15 +383,25 @@ impl LifecycleService {
             .await
     }
 
-    pub async fn get_lifecycles_for_ci_type(&self, ci_type_id: Uuid) -> AppResult<Vec<LifecycleTypeSummary>> {
+    pub async fn get_lifecycles_for_ci_type(
+        &self,
+        ci_type_id: Uuid,
+        auth_context: &AuthContext,
+    ) -> AppResult<Vec<LifecycleTypeSummary>> {
+        let permissions = PermissionSet::load(&self.rbac_repository, auth_context).await?;
+        permissions.require(ResourceType::CiType, PermissionAction::Read, Some(ci_type_id))?;
+
         // Check if CI type exists
         self.ci_repository
             .get_ci_type_by_id(ci_type_id)
             .await?
             .ok_or_else(|| AppError::not_found("CI type not found"))?;
 
-        self.lifecycle_repository
+        let mut lifecycle_types = self.lifecycle_repository
             .get_lifecycles_for_ci_type(ci_type_id)
-            .await
+            .await?;
+        lifecycle_types.retain(|lt| permissions.allows(ResourceType::Lifecycle, PermissionAction::Read, Some(lt.id)));
+
+        Ok(lifecycle_types)
     }
 }
\ No newline at end of file
@@ -6,6 +6,12 @@ pub mod amortization_service;
 pub mod import_export;
 pub mod lifecycle_service;
 pub mod relationship_service;
+pub mod rbac_service;
+pub mod service_account_service;
+pub mod oidc_service;
+pub mod notifier;
+pub mod password_service;
+pub mod user_service;
 
 pub use auth_service::*;
 pub use ci_service::*;
@@ -14,4 +20,10 @@ pub use audit_service::*;
 pub use amortization_service::*;
 pub use import_export::*;
 pub use lifecycle_service::*;
-pub use relationship_service::*;
\ No newline at end of file
+pub use relationship_service::*;
+pub use rbac_service::*;
+pub use service_account_service::*;
+pub use oidc_service::*;
+pub use notifier::*;
+pub use password_service::*;
+pub use user_service::*;
\ No newline at end of file
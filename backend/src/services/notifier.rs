@@ -0,0 +1,118 @@
+use crate::config::NotificationConfig;
+use crate::error::{AppError, AppResult};
+use async_trait::async_trait;
+use chrono::Utc;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+
+/// A message for a user, typically delivered as an email
+#[derive(Debug, Clone)]
+pub struct Notification {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+    pub secrets: Vec<String>, // Parts of the body, such as reset tokens, that must not reach logs
+}
+
+impl Notification {
+    /// The body with every secret replaced, for places that aren't the recipient's inbox
+    pub fn redacted_body(&self) -> String {
+        self.secrets
+            .iter()
+            .filter(|secret| !secret.is_empty())
+            .fold(self.body.clone(), |body, secret| body.replace(secret.as_str(), "[redacted]"))
+    }
+}
+
+/// Delivers notifications to users. Implement this to plug in SMTP or an
+/// email API; the log and file notifiers are meant for development.
+#[async_trait]
+pub trait Notifier: Send + Sync {
+    async fn send(&self, notification: &Notification) -> AppResult<()>;
+}
+
+/// Writes notifications to the application log, with their secrets redacted
+pub struct LogNotifier;
+
+#[async_trait]
+impl Notifier for LogNotifier {
+    async fn send(&self, notification: &Notification) -> AppResult<()> {
+        tracing::info!(
+            "Notification to {}: {}\n{}",
+            notification.to, notification.subject, notification.redacted_body()
+        );
+        Ok(())
+    }
+}
+
+/// Appends notifications to a local file, one after another like a mailbox
+pub struct FileNotifier {
+    path: PathBuf,
+    write_lock: Mutex<()>,
+}
+
+impl FileNotifier {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            write_lock: Mutex::new(()),
+        }
+    }
+}
+
+#[async_trait]
+impl Notifier for FileNotifier {
+    async fn send(&self, notification: &Notification) -> AppResult<()> {
+        let entry = format!(
+            "--- {}\nTo: {}\nSubject: {}\n\n{}\n\n",
+            Utc::now().to_rfc3339(),
+            notification.to,
+            notification.subject,
+            notification.body
+        );
+
+        // Keep concurrent notifications from interleaving
+        let _guard = self.write_lock.lock().await;
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+        file.write_all(entry.as_bytes()).await?;
+
+        Ok(())
+    }
+}
+
+pub fn notifier_from_config(config: &NotificationConfig) -> AppResult<Arc<dyn Notifier>> {
+    match config.backend.as_str() {
+        "log" => Ok(Arc::new(LogNotifier)),
+        "file" => Ok(Arc::new(FileNotifier::new(&config.file_path))),
+        other => Err(AppError::configuration(format!(
+            "Unknown notifier '{}' (expected 'log' or 'file')",
+            other
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redacted_body_hides_every_secret() {
+        let notification = Notification {
+            to: "jane@example.com".to_string(),
+            subject: "Reset your CMDB password".to_string(),
+            body: "Open https://cmdb.example.com/reset?token=abc123 or enter abc123".to_string(),
+            secrets: vec!["abc123".to_string(), String::new()],
+        };
+
+        assert_eq!(
+            notification.redacted_body(),
+            "Open https://cmdb.example.com/reset?token=[redacted] or enter [redacted]"
+        );
+    }
+}
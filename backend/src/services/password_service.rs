@@ -0,0 +1,151 @@
+use crate::config::AuthConfig;
+use crate::database::{UserRepository, SessionRepository, UNUSABLE_PASSWORD_HASH};
+use crate::models::{LoginResponse, ChangePasswordRequest, ForgotPasswordRequest, ResetPasswordRequest};
+use crate::middleware::AuthContext;
+use crate::services::{AuthService, Notification, Notifier};
+use crate::utils::{hash_password, verify_password, validate_password_strength, generate_password_reset_token, hash_token};
+use crate::error::{AppError, AppResult};
+use chrono::{Duration, Utc};
+use std::sync::Arc;
+use validator::Validate;
+
+/// At most one reset email per user in this window
+const PASSWORD_RESET_THROTTLE_SECONDS: i64 = 60;
+
+/// Password reset by email and password change for logged-in users
+pub struct PasswordService {
+    user_repository: UserRepository,
+    session_repository: SessionRepository,
+    auth_service: AuthService,
+    notifier: Arc<dyn Notifier>,
+    reset_token_ttl: Duration,
+    reset_url: Option<String>,
+}
+
+impl PasswordService {
+    pub fn new(
+        user_repository: UserRepository,
+        session_repository: SessionRepository,
+        auth_service: AuthService,
+        notifier: Arc<dyn Notifier>,
+        config: &AuthConfig,
+    ) -> Self {
+        Self {
+            user_repository,
+            session_repository,
+            auth_service,
+            notifier,
+            reset_token_ttl: Duration::minutes(config.password_reset_token_ttl_minutes as i64),
+            reset_url: config.password_reset_url.clone(),
+        }
+    }
+
+    /// Email a reset token to the user, if the address belongs to an active
+    /// password user. Succeeds either way so accounts can't be enumerated.
+    pub async fn request_reset(&self, request: ForgotPasswordRequest) -> AppResult<()> {
+        request.validate()?;
+
+        let (user, password_hash) = match self.user_repository
+            .get_user_with_password_by_email(&request.email)
+            .await?
+        {
+            Some(found) => found,
+            None => return Ok(()),
+        };
+
+        // Single sign-on users authenticate at their identity provider
+        if !user.is_active || password_hash == UNUSABLE_PASSWORD_HASH {
+            return Ok(());
+        }
+
+        let throttle_since = Utc::now() - Duration::seconds(PASSWORD_RESET_THROTTLE_SECONDS);
+        if self.session_repository.password_reset_requested_since(user.id, throttle_since).await? {
+            tracing::info!("Skipping password reset email for user {}: requested too recently", user.id);
+            return Ok(());
+        }
+
+        let token = generate_password_reset_token()?;
+        self.session_repository
+            .create_password_reset_token(user.id, &hash_token(&token), Utc::now() + self.reset_token_ttl)
+            .await?;
+
+        let instructions = match self.reset_url {
+            Some(ref url) => format!("Open this link to choose a new password:\n{}?token={}", url, token),
+            None => format!("Use this token to choose a new password:\n{}", token),
+        };
+
+        self.notifier
+            .send(&Notification {
+                to: user.email.clone(),
+                subject: "Reset your CMDB password".to_string(),
+                body: format!(
+                    "Hello {},\n\nA password reset was requested for your account.\n\n{}\n\nThe link expires in {} minutes. If you did not request this, you can ignore this message.",
+                    user.first_name,
+                    instructions,
+                    self.reset_token_ttl.num_minutes()
+                ),
+                secrets: vec![token],
+            })
+            .await?;
+
+        tracing::info!("Password reset requested for user {}", user.id);
+        Ok(())
+    }
+
+    /// Set a new password with a reset token and log the user out everywhere
+    pub async fn confirm_reset(&self, request: ResetPasswordRequest) -> AppResult<()> {
+        request.validate()?;
+
+        validate_password_strength(&request.new_password)?;
+        let password_hash = hash_password(&request.new_password)?;
+
+        let user_id = self.session_repository
+            .redeem_password_reset_token(&hash_token(&request.token), &password_hash)
+            .await?
+            .ok_or_else(|| AppError::authentication("Invalid or expired password reset token"))?;
+
+        tracing::info!("Password reset completed for user {}", user_id);
+        Ok(())
+    }
+
+    /// Change the caller's password after re-checking the current one.
+    ///
+    /// All existing sessions are revoked; the caller gets a fresh one back.
+    pub async fn change_password(
+        &self,
+        auth_context: &AuthContext,
+        request: ChangePasswordRequest,
+    ) -> AppResult<LoginResponse> {
+        request.validate()?;
+
+        if auth_context.is_service_account {
+            return Err(AppError::authorization("Service accounts do not have a password"));
+        }
+
+        let current_hash = self.user_repository
+            .get_password_hash(auth_context.user_id)
+            .await?
+            .ok_or_else(|| AppError::not_found("User not found"))?;
+
+        if current_hash == UNUSABLE_PASSWORD_HASH
+            || !verify_password(&request.current_password, &current_hash)?
+        {
+            return Err(AppError::authentication("Current password is incorrect"));
+        }
+
+        if request.new_password == request.current_password {
+            return Err(AppError::validation("New password must differ from the current password"));
+        }
+
+        validate_password_strength(&request.new_password)?;
+        let password_hash = hash_password(&request.new_password)?;
+
+        self.user_repository.update_password(auth_context.user_id, &password_hash).await?;
+        self.session_repository.revoke_all_user_sessions(auth_context.user_id).await?;
+
+        let user = self.auth_service.get_user(auth_context.user_id).await?;
+        tracing::info!("User {} changed their password", user.id);
+
+        self.auth_service.start_session(&user).await
+    }
+}
@@ -0,0 +1,228 @@
+use crate::database::{RbacRepository, PermissionGrant, UserRepository, is_unique_violation};
+use crate::models::{
+    ResourceType, PermissionAction, Role, RolePermission, RoleResponse,
+    CreateRoleRequest, UpdateRoleRequest, GrantPermissionRequest,
+};
+use crate::middleware::AuthContext;
+use crate::error::{AppError, AppResult};
+use crate::utils::{KeysetCursor, Page, PageRequest};
+use uuid::Uuid;
+use validator::Validate;
+
+/// The effective permissions of one caller, loaded once per service call.
+///
+/// Admins are allowed everything. Requests made with an API key get exactly
+/// the grants of that key. Everyone else gets the union of the grants on their
+/// roles. A grant without a scope covers every instance of its resource type,
+/// a scoped grant only the CI type, relationship type or lifecycle type it
+/// names.
+#[derive(Debug, Clone)]
+pub struct PermissionSet {
+    is_admin: bool,
+    grants: Vec<PermissionGrant>,
+}
+
+impl PermissionSet {
+    pub async fn load(rbac_repository: &RbacRepository, auth_context: &AuthContext) -> AppResult<Self> {
+        if auth_context.is_admin {
+            return Ok(Self { is_admin: true, grants: Vec::new() });
+        }
+
+        let grants = match auth_context.api_key_id {
+            Some(api_key_id) => rbac_repository.get_api_key_grants(api_key_id).await?,
+            None => rbac_repository.get_user_grants(auth_context.user_id).await?,
+        };
+
+        Ok(Self::from_grants(grants))
+    }
+
+    /// The permissions of a non-admin caller holding exactly `grants`
+    pub fn from_grants(grants: Vec<PermissionGrant>) -> Self {
+        Self { is_admin: false, grants }
+    }
+
+    pub fn allows(&self, resource_type: ResourceType, action: PermissionAction, scope_id: Option<Uuid>) -> bool {
+        self.is_admin
+            || self.grants.iter().any(|grant| {
+                grant.resource_type == resource_type
+                    && grant.action.covers(action)
+                    && (grant.scope_id.is_none() || grant.scope_id == scope_id)
+            })
+    }
+
+    pub fn require(&self, resource_type: ResourceType, action: PermissionAction, scope_id: Option<Uuid>) -> AppResult<()> {
+        if self.allows(resource_type, action, scope_id) {
+            Ok(())
+        } else {
+            Err(AppError::authorization(format!(
+                "Missing '{}' permission on {}",
+                action, resource_type
+            )))
+        }
+    }
+
+    /// Scopes the caller may read for a resource type, for filtering lists.
+    ///
+    /// `None` means unrestricted; `Some` lists the only scope ids visible,
+    /// which may be empty.
+    pub fn readable_scopes(&self, resource_type: ResourceType) -> Option<Vec<Uuid>> {
+        if self.is_admin {
+            return None;
+        }
+
+        let mut scopes = Vec::new();
+        for grant in &self.grants {
+            if grant.resource_type != resource_type || !grant.action.covers(PermissionAction::Read) {
+                continue;
+            }
+            match grant.scope_id {
+                None => return None,
+                Some(scope_id) => scopes.push(scope_id),
+            }
+        }
+
+        scopes.sort();
+        scopes.dedup();
+        Some(scopes)
+    }
+}
+
+/// Role management, exposed to admins only
+pub struct RbacService {
+    rbac_repository: RbacRepository,
+    user_repository: UserRepository,
+}
+
+impl RbacService {
+    pub fn new(rbac_repository: RbacRepository, user_repository: UserRepository) -> Self {
+        Self {
+            rbac_repository,
+            user_repository,
+        }
+    }
+
+    /// One page of roles, by name
+    pub async fn list_roles(&self, page: &PageRequest) -> AppResult<Page<Role>> {
+        let cursor = page.cursor(1)?;
+        let limit = page.limit()?;
+
+        let roles = self.rbac_repository.list_roles(cursor.as_ref(), limit + 1).await?;
+        let total = if page.include_total {
+            Some(self.rbac_repository.count_roles().await?)
+        } else {
+            None
+        };
+
+        Ok(Page::from_rows(roles, limit, |last| KeysetCursor::with_keys(vec![last.name.clone().into()], last.created_at, last.id))
+            .with_total(total))
+    }
+
+    pub async fn get_role(&self, id: Uuid) -> AppResult<RoleResponse> {
+        let role = self.find_role(id).await?;
+
+        Ok(RoleResponse {
+            permissions: self.rbac_repository.list_role_permissions(id).await?,
+            bindings: self.rbac_repository.list_role_bindings(id).await?,
+            role,
+        })
+    }
+
+    pub async fn create_role(&self, request: CreateRoleRequest, auth_context: &AuthContext) -> AppResult<RoleResponse> {
+        request.validate()?;
+
+        if self.rbac_repository.get_role_by_name(&request.name).await?.is_some() {
+            return Err(AppError::conflict(format!("Role '{}' already exists", request.name)));
+        }
+
+        let permissions = request.permissions.unwrap_or_default();
+        let role = self.rbac_repository
+            .create_role(&request.name, request.description.as_deref(), &permissions, auth_context.user_id)
+            .await
+            .map_err(|e| {
+                if is_unique_violation(&e) {
+                    AppError::conflict(format!("Role '{}' already exists", request.name))
+                } else {
+                    AppError::from(e)
+                }
+            })?;
+
+        self.get_role(role.id).await
+    }
+
+    pub async fn update_role(&self, id: Uuid, request: UpdateRoleRequest) -> AppResult<Role> {
+        request.validate()?;
+
+        let existing = self.find_role(id).await?;
+
+        if let Some(ref name) = request.name {
+            if existing.is_system && name != &existing.name {
+                return Err(AppError::validation("System roles cannot be renamed"));
+            }
+            if let Some(other) = self.rbac_repository.get_role_by_name(name).await? {
+                if other.id != id {
+                    return Err(AppError::conflict(format!("Role '{}' already exists", name)));
+                }
+            }
+        }
+
+        self.rbac_repository
+            .update_role(id, request.name.as_deref(), request.description.as_deref())
+            .await?
+            .ok_or_else(|| AppError::not_found("Role not found"))
+    }
+
+    pub async fn delete_role(&self, id: Uuid) -> AppResult<()> {
+        let role = self.find_role(id).await?;
+
+        if role.is_system {
+            return Err(AppError::validation("System roles cannot be deleted"));
+        }
+
+        self.rbac_repository.delete_role(id).await?;
+        Ok(())
+    }
+
+    pub async fn grant_permission(&self, role_id: Uuid, request: GrantPermissionRequest) -> AppResult<RolePermission> {
+        self.find_role(role_id).await?;
+
+        self.rbac_repository
+            .add_role_permission(role_id, &request)
+            .await?
+            .ok_or_else(|| AppError::conflict("Role already holds this permission"))
+    }
+
+    pub async fn revoke_permission(&self, role_id: Uuid, permission_id: Uuid) -> AppResult<()> {
+        if !self.rbac_repository.remove_role_permission(role_id, permission_id).await? {
+            return Err(AppError::not_found("Permission not found on this role"));
+        }
+        Ok(())
+    }
+
+    pub async fn bind_user(&self, role_id: Uuid, user_id: Uuid, auth_context: &AuthContext) -> AppResult<()> {
+        self.find_role(role_id).await?;
+
+        self.user_repository
+            .get_user_by_id(user_id)
+            .await?
+            .ok_or_else(|| AppError::not_found("User not found"))?;
+
+        self.rbac_repository
+            .bind_role(role_id, user_id, Some(auth_context.user_id))
+            .await?;
+        Ok(())
+    }
+
+    pub async fn unbind_user(&self, role_id: Uuid, user_id: Uuid) -> AppResult<()> {
+        if !self.rbac_repository.unbind_role(role_id, user_id).await? {
+            return Err(AppError::not_found("User is not bound to this role"));
+        }
+        Ok(())
+    }
+
+    async fn find_role(&self, id: Uuid) -> AppResult<Role> {
+        self.rbac_repository
+            .get_role(id)
+            .await?
+            .ok_or_else(|| AppError::not_found("Role not found"))
+    }
+}
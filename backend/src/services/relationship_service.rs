@@ -1,20 +1,55 @@
 use anyhow::Result;
 use validator::Validate;
-use crate::database::repositories::{RelationshipRepository, CIRepository, GraphRepository};
+use crate::database::repositories::{RelationshipRepository, CIRepository, GraphRepository, RbacRepository, AuditRepository};
+use crate::middleware::AuthContext;
+use crate::services::{AuditService, PermissionSet, restore_target};
+use crate::error::{AppError, AppResult, FieldError, FieldErrorCode};
+use crate::utils::{KeysetCursor, Page, PageRequest, SchemaCache};
 use crate::models::{
+    ResourceType, PermissionAction, AuditedEntity, AuditAction,
     RelationshipType, CreateRelationshipTypeRequest,
     UpdateRelationshipTypeRequest, RelationshipTypeFilter, RelationshipTypeResponse,
     RelationshipTypeSummary, CIType,
     Relationship, CreateRelationshipRequest, UpdateRelationshipRequest,
-    RelationshipFilter, RelationshipResponse, RelationshipWithDetails
+    RelationshipFilter, RelationshipResponse, RelationshipWithDetails, RestoreVersionRequest,
+    RelationshipSchemaViolation, RelationshipSchemaReport,
+    RelationshipOperation, BulkRequest, BulkResult, BulkItemResult, BulkMode, operation_path,
+    DeletedRelationshipFilter, DeletedRelationship
 };
+use jsonschema::JSONSchema;
+use serde_json::{json, Value};
+use sqlx::{Connection, PgConnection};
 use uuid::Uuid;
+use std::collections::HashMap;
 use std::sync::Arc;
 
+/// Relationships read per query when checking a schema change
+const SCHEMA_BATCH_SIZE: i64 = 500;
+/// Violations listed in a schema change report; the rest are only counted
+const MAX_REPORTED_VIOLATIONS: usize = 100;
+
+/// How `update_relationship_type` went. A new attributes schema that existing
+/// relationships would violate is not applied.
+pub enum RelationshipTypeUpdate {
+    Applied(RelationshipType),
+    DryRun(RelationshipSchemaReport),
+    Rejected(RelationshipSchemaReport),
+}
+
+/// A relationship change that passed validation, ready to be written
+enum RelationshipChange {
+    Create(CreateRelationshipRequest),
+    Update { id: Uuid, request: UpdateRelationshipRequest },
+    Delete(RelationshipWithDetails), // As it was, for removing its Neo4j edge
+}
+
 pub struct RelationshipService {
     relationship_repository: RelationshipRepository,
     ci_repository: CIRepository,
     graph_repository: Arc<GraphRepository>,
+    rbac_repository: RbacRepository,
+    audit_service: AuditService,
+    schema_cache: SchemaCache,
 }
 
 impl RelationshipService {
@@ -22,23 +57,30 @@ impl RelationshipService {
         relationship_repository: RelationshipRepository,
         ci_repository: CIRepository,
         graph_repository: Arc<GraphRepository>,
+        rbac_repository: RbacRepository,
+        audit_repository: AuditRepository,
+        schema_cache: SchemaCache,
     ) -> Self {
         Self {
             relationship_repository,
             ci_repository,
             graph_repository,
+            audit_service: AuditService::new(audit_repository, rbac_repository.clone()),
+            rbac_repository,
+            schema_cache,
         }
     }
 
     pub async fn create_relationship_type(
         &self,
         request: CreateRelationshipTypeRequest,
-        user_id: Uuid,
+        auth_context: &AuthContext,
     ) -> Result<RelationshipType> {
+        PermissionSet::load(&self.rbac_repository, auth_context).await?
+            .require(ResourceType::RelationshipType, PermissionAction::Create, None)?;
+
         // Validate request
-        if let Err(validation_errors) = request.validate() {
-            return Err(anyhow::anyhow!("Validation failed: {}", validation_errors));
-        }
+        request.validate().map_err(AppError::from)?;
 
         // Check if name already exists
         if self
@@ -84,11 +126,21 @@ impl RelationshipService {
             }
         }
 
+        if let Some(schema) = request.attributes_schema.as_ref().filter(|schema| has_schema(schema)) {
+            compile_schema(schema)?;
+        }
+
+        let mut tx = self.relationship_repository.begin().await?;
         let relationship_type = self
             .relationship_repository
-            .create(&request, user_id)
+            .create(&mut tx, &request, auth_context.user_id)
             .await?;
 
+        self.audit_service
+            .record_change(&mut tx, auth_context, AuditedEntity::RelationshipType, relationship_type.id, AuditAction::Insert, None)
+            .await?;
+        tx.commit().await?;
+
         // Initialize Neo4j constraints for this relationship type
         let (from_ci_type_name, to_ci_type_name) = match (request.from_ci_type_id, request.to_ci_type_id) {
             (Some(from_id), Some(to_id)) => {
@@ -126,30 +178,54 @@ impl RelationshipService {
         Ok(relationship_type)
     }
 
-    pub async fn get_relationship_type(&self, id: Uuid) -> Result<Option<RelationshipType>> {
+    pub async fn get_relationship_type(&self, id: Uuid, auth_context: &AuthContext) -> Result<Option<RelationshipType>> {
+        PermissionSet::load(&self.rbac_repository, auth_context).await?
+            .require(ResourceType::RelationshipType, PermissionAction::Read, Some(id))?;
+
         self.relationship_repository
             .get_by_id(id)
             .await
     }
 
+    /// One page of relationship types, by name
     pub async fn list_relationship_types(
         &self,
         filter: RelationshipTypeFilter,
-    ) -> Result<Vec<RelationshipTypeSummary>> {
-        self.relationship_repository
-            .list(&filter)
-            .await
+        page: &PageRequest,
+        auth_context: &AuthContext,
+    ) -> Result<Page<RelationshipTypeSummary>> {
+        let cursor = page.cursor(1)?;
+        let limit = page.limit()?;
+
+        let allowed_ids = PermissionSet::load(&self.rbac_repository, auth_context).await?
+            .readable_scopes(ResourceType::RelationshipType);
+
+        let relationship_types = self.relationship_repository
+            .list(&filter, allowed_ids.as_deref(), cursor.as_ref(), limit + 1)
+            .await?;
+        let total = if page.include_total {
+            Some(self.relationship_repository.count(&filter, allowed_ids.as_deref()).await?)
+        } else {
+            None
+        };
+
+        Ok(Page::from_rows(relationship_types, limit, |last| {
+            KeysetCursor::with_keys(vec![last.name.clone().into()], last.created_at, last.id)
+        })
+        .with_total(total))
     }
 
     pub async fn update_relationship_type(
         &self,
         id: Uuid,
         request: UpdateRelationshipTypeRequest,
-    ) -> Result<RelationshipType> {
+        auth_context: &AuthContext,
+    ) -> Result<RelationshipTypeUpdate> {
+        PermissionSet::load(&self.rbac_repository, auth_context).await?
+            .require(ResourceType::RelationshipType, PermissionAction::Update, Some(id))?;
+
         // Validate request
-        if let Err(validation_errors) = request.validate() {
-            return Err(anyhow::anyhow!("Validation failed: {}", validation_errors));
-        }
+        request.validate().map_err(AppError::from)?;
 
         // Check if relationship type exists
         let existing = self
@@ -170,34 +246,384 @@ impl RelationshipService {
             }
         }
 
+        let schema_changed = request.attributes_schema.as_ref()
+            .map_or(false, |schema| schema != &existing.attributes_schema);
+
+        if request.dry_run && !schema_changed {
+            return Err(anyhow::anyhow!("dry_run only applies when attributes_schema changes"));
+        }
+
+        // Existing relationships must all fit a new schema before it is applied
+        if let Some(schema) = request.attributes_schema.as_ref().filter(|schema| schema_changed && has_schema(schema)) {
+            let report = self.check_schema_change(id, &compile_schema(schema)?, request.dry_run).await?;
+
+            if request.dry_run {
+                return Ok(RelationshipTypeUpdate::DryRun(report));
+            }
+            if report.violation_count > 0 {
+                return Ok(RelationshipTypeUpdate::Rejected(report));
+            }
+        }
+
+        let mut tx = self.relationship_repository.begin().await?;
+        let old_values = self.audit_service.snapshot(&mut tx, AuditedEntity::RelationshipType, id).await?;
         let updated = self
             .relationship_repository
-            .update(id, &request)
+            .update(&mut tx, id, &request)
             .await?;
 
-        Ok(updated)
+        self.audit_service
+            .record_change(&mut tx, auth_context, AuditedEntity::RelationshipType, id, AuditAction::Update, old_values)
+            .await?;
+        tx.commit().await?;
+
+        self.schema_cache.invalidate(id);
+
+        Ok(RelationshipTypeUpdate::Applied(updated))
+    }
+
+    /// Check every live relationship of a type against a proposed attributes schema
+    async fn check_schema_change(&self, relationship_type_id: Uuid, schema: &JSONSchema, dry_run: bool) -> Result<RelationshipSchemaReport> {
+        let mut report = RelationshipSchemaReport {
+            dry_run,
+            relationships_checked: 0,
+            violation_count: 0,
+            violations: Vec::new(),
+        };
+
+        let mut after_id = None;
+        loop {
+            let relationships = self.relationship_repository
+                .list_relationships_for_schema(relationship_type_id, after_id, SCHEMA_BATCH_SIZE)
+                .await?;
+
+            for (relationship_id, from_ci_asset_id, to_ci_asset_id, attributes) in &relationships {
+                report.relationships_checked += 1;
+
+                let errors = schema_errors(schema, attributes);
+                if !errors.is_empty() {
+                    report.violation_count += 1;
+                    if report.violations.len() < MAX_REPORTED_VIOLATIONS {
+                        report.violations.push(RelationshipSchemaViolation {
+                            relationship_id: *relationship_id,
+                            from_ci_asset_id: *from_ci_asset_id,
+                            to_ci_asset_id: *to_ci_asset_id,
+                            errors,
+                        });
+                    }
+                }
+            }
+
+            match relationships.last() {
+                Some((last_id, _, _, _)) if relationships.len() as i64 == SCHEMA_BATCH_SIZE => after_id = Some(*last_id),
+                _ => break,
+            }
+        }
+
+        Ok(report)
     }
 
-    pub async fn delete_relationship_type(&self, id: Uuid) -> Result<()> {
+    /// Reject attributes that don't fit the relationship type's schema, naming
+    /// each offending field
+    fn validate_attributes(&self, rel_type: &RelationshipType, attributes: &Value) -> Result<()> {
+        if !has_schema(&rel_type.attributes_schema) {
+            return Ok(());
+        }
+
+        let schema = self.schema_cache
+            .get_or_compile(rel_type.id, rel_type.updated_at, &rel_type.attributes_schema)
+            .map_err(|e| anyhow::anyhow!("Relationship type has an invalid attributes schema: {}", e))?;
+
+        let errors = schema_errors(&schema, attributes);
+        if !errors.is_empty() {
+            return Err(AppError::invalid_fields(
+                format!("Attributes do not match the schema of relationship type '{}'", rel_type.name),
+                errors.into_iter().map(|error| error.nested_in("/attributes")).collect(),
+            ).into());
+        }
+
+        Ok(())
+    }
+
+    pub async fn delete_relationship_type(&self, id: Uuid, auth_context: &AuthContext) -> Result<()> {
+        PermissionSet::load(&self.rbac_repository, auth_context).await?
+            .require(ResourceType::RelationshipType, PermissionAction::Delete, Some(id))?;
+
+        let mut tx = self.relationship_repository.begin().await?;
+        let old_values = self.audit_service.snapshot(&mut tx, AuditedEntity::RelationshipType, id).await?;
         self.relationship_repository
-            .delete(id)
+            .delete(&mut tx, id)
             .await?;
+
+        self.audit_service
+            .record_change(&mut tx, auth_context, AuditedEntity::RelationshipType, id, AuditAction::Delete, old_values)
+            .await?;
+        tx.commit().await?;
+
+        self.schema_cache.invalidate(id);
         Ok(())
     }
 
     // === Relationship Instance Methods (Phase 3.1) ===
 
+    /// Whether an asset of `ci_type_id` may sit at an end of the relationship
+    /// type constrained to `constraint`
+    async fn fits_constraint(&self, rel_type: &RelationshipType, ci_type_id: Uuid, constraint: Uuid) -> Result<bool> {
+        if ci_type_id == constraint {
+            return Ok(true);
+        }
+        if !rel_type.include_subtypes {
+            return Ok(false);
+        }
+        self.ci_repository.is_ci_type_within(ci_type_id, constraint).await
+    }
+
     /// Create a new relationship instance between two CI assets
     pub async fn create_relationship_instance(
         &self,
         request: CreateRelationshipRequest,
-        user_id: Uuid,
+        auth_context: &AuthContext,
     ) -> Result<RelationshipWithDetails> {
-        // Validate request
-        if let Err(validation_errors) = request.validate() {
-            return Err(anyhow::anyhow!("Validation failed: {}", validation_errors));
+        let permissions = PermissionSet::load(&self.rbac_repository, auth_context).await?;
+        let change = self.prepare_create(request, &permissions).await?;
+
+        // Create relationship in PostgreSQL
+        let mut tx = self.relationship_repository.begin().await?;
+        let id = self.apply_relationship_change(&mut tx, &change, auth_context).await?;
+        tx.commit().await?;
+
+        // Get the full relationship details to return
+        let relationship_details = self.relationship_repository
+            .get_relationship_by_id(id)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Failed to retrieve created relationship"))?;
+
+        // Sync to Neo4j
+        if let Err(e) = self.graph_repository
+            .create_relationship(
+                relationship_details.from_ci_asset_id,
+                relationship_details.to_ci_asset_id,
+                &relationship_details.relationship_type_name,
+                relationship_details.relationship_type_id,
+                Some(relationship_details.attributes.clone()),
+                &relationship_details.from_ci_type_name,
+                &relationship_details.to_ci_type_name,
+                relationship_details.is_bidirectional,
+            )
+            .await
+        {
+            tracing::warn!("Failed to sync relationship to Neo4j: {}", e);
+            // Don't fail the request if Neo4j sync fails, just log it
+        }
+
+        Ok(relationship_details)
+    }
+
+    /// Get a relationship by ID
+    pub async fn get_relationship_instance(&self, id: Uuid, auth_context: &AuthContext) -> Result<Option<RelationshipWithDetails>> {
+        let relationship = self.relationship_repository
+            .get_relationship_by_id(id)
+            .await?;
+
+        if let Some(ref relationship) = relationship {
+            PermissionSet::load(&self.rbac_repository, auth_context).await?
+                .require(ResourceType::Relationship, PermissionAction::Read, Some(relationship.relationship_type_id))?;
+        }
+
+        Ok(relationship)
+    }
+
+    /// One page of relationships matching the filter, newest first
+    pub async fn list_relationship_instances(
+        &self,
+        filter: RelationshipFilter,
+        page: &PageRequest,
+        auth_context: &AuthContext,
+    ) -> Result<Page<RelationshipResponse>> {
+        let cursor = page.cursor(0)?;
+        let limit = page.limit()?;
+
+        let allowed_type_ids = PermissionSet::load(&self.rbac_repository, auth_context).await?
+            .readable_scopes(ResourceType::Relationship);
+
+        let relationships = self.relationship_repository
+            .list_relationships(&filter, allowed_type_ids.as_deref(), cursor.as_ref(), limit + 1)
+            .await?;
+        let total = if page.include_total {
+            Some(self.relationship_repository.count_relationships(&filter, allowed_type_ids.as_deref()).await?)
+        } else {
+            None
+        };
+
+        Ok(Page::from_rows(relationships, limit, |last| KeysetCursor::new(last.created_at, last.id)).with_total(total))
+    }
+
+    /// Update a relationship's attributes
+    pub async fn update_relationship_instance(
+        &self,
+        id: Uuid,
+        request: UpdateRelationshipRequest,
+        auth_context: &AuthContext,
+    ) -> Result<RelationshipWithDetails> {
+        let permissions = PermissionSet::load(&self.rbac_repository, auth_context).await?;
+        let change = self.prepare_update(id, request, &permissions).await?;
+
+        // Update in PostgreSQL
+        let mut tx = self.relationship_repository.begin().await?;
+        self.apply_relationship_change(&mut tx, &change, auth_context).await?;
+        tx.commit().await?;
+
+        // Get the updated relationship with full details
+        let relationship_details = self.relationship_repository
+            .get_relationship_by_id(id)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Relationship not found"))?;
+
+        // Note: We don't update Neo4j for attribute changes since Neo4j relationships
+        // are primarily for graph visualization and topology, not attribute storage
+
+        Ok(relationship_details)
+    }
+
+    /// Delete a relationship
+    pub async fn delete_relationship_instance(&self, id: Uuid, auth_context: &AuthContext) -> Result<()> {
+        let permissions = PermissionSet::load(&self.rbac_repository, auth_context).await?;
+        let change = self.prepare_delete(id, &permissions).await?;
+
+        // Delete from PostgreSQL
+        let mut tx = self.relationship_repository.begin().await?;
+        self.apply_relationship_change(&mut tx, &change, auth_context).await?;
+        tx.commit().await?;
+
+        // Delete from Neo4j
+        if let RelationshipChange::Delete(relationship) = &change {
+            if let Err(e) = self.graph_repository
+                .delete_relationship(
+                    relationship.from_ci_asset_id,
+                    relationship.to_ci_asset_id,
+                    relationship.relationship_type_id,
+                )
+                .await
+            {
+                tracing::warn!("Failed to delete relationship from Neo4j: {}", e);
+                // Don't fail the request if Neo4j deletion fails, just log it
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Apply a batch of relationship operations. Every operation is validated
+    /// before any is written; an atomic batch then commits as a whole, while
+    /// a best-effort one applies what it can and reports the rest per item.
+    /// Neo4j is synced once for the whole batch.
+    pub async fn bulk_relationships(&self, request: BulkRequest<RelationshipOperation>, auth_context: &AuthContext) -> AppResult<BulkResult> {
+        request.check_size().map_err(AppError::invalid_field)?;
+        let mode = request.mode;
+        let permissions = PermissionSet::load(&self.rbac_repository, auth_context).await?;
+
+        // A relationship changes at most once per batch, and is created at
+        // most once, so that every operation is checked against the current state
+        let mut targets = HashMap::new();
+        let mut created = HashMap::new();
+        let mut prepared = Vec::with_capacity(request.operations.len());
+        for (index, operation) in request.operations.into_iter().enumerate() {
+            let target = operation.target();
+            let first = match &operation {
+                RelationshipOperation::Create(create) => {
+                    let key = (create.relationship_type_id, create.from_ci_asset_id, create.to_ci_asset_id);
+                    created.get(&key).copied().or_else(|| {
+                        created.insert(key, index);
+                        None
+                    })
+                }
+                _ => target.and_then(|id| targets.get(&id).copied().or_else(|| {
+                    targets.insert(id, index);
+                    None
+                })),
+            };
+            let change = match first {
+                Some(first) => Err(AppError::invalid_field(FieldError::new(
+                    "",
+                    FieldErrorCode::RuleViolation,
+                    format!("The relationship is already changed by operation {}", first),
+                ))),
+                None => self.prepare_operation(operation, &permissions).await.map_err(as_app_error),
+            };
+            prepared.push((index, target, change.map_err(|e| e.nested_in(&operation_path(index)))));
         }
 
+        let mut items = Vec::with_capacity(prepared.len());
+        let mut written = Vec::new();
+        let mut tx = self.relationship_repository.begin().await?;
+
+        match mode {
+            BulkMode::Atomic => {
+                let (valid, invalid): (Vec<_>, Vec<_>) = prepared.into_iter().partition(|(_, _, change)| change.is_ok());
+                if !invalid.is_empty() {
+                    let count = invalid.len();
+                    let errors = invalid.into_iter().filter_map(|(_, _, change)| change.err()).collect();
+                    return Err(AppError::combined(format!("{} of the operations are invalid; none was applied", count), errors));
+                }
+
+                for (index, _, change) in valid {
+                    let change = change?;
+                    let id = self.apply_relationship_change(&mut tx, &change, auth_context).await
+                        .map_err(|e| as_app_error(e).nested_in(&operation_path(index)))?;
+                    items.push(BulkItemResult::applied(index, id));
+                    written.push((id, change));
+                }
+            }
+            BulkMode::BestEffort => {
+                for (index, target, change) in prepared {
+                    let change = match change {
+                        Ok(change) => change,
+                        Err(e) => {
+                            items.push(BulkItemResult::failed(index, target, e.details()));
+                            continue;
+                        }
+                    };
+
+                    // A savepoint per operation keeps a failed write from
+                    // taking the others down with it
+                    let mut savepoint = Connection::begin(&mut *tx).await?;
+                    match self.apply_relationship_change(&mut savepoint, &change, auth_context).await {
+                        Ok(id) => {
+                            savepoint.commit().await?;
+                            items.push(BulkItemResult::applied(index, id));
+                            written.push((id, change));
+                        }
+                        Err(e) => {
+                            savepoint.rollback().await?;
+                            let e = as_app_error(e).nested_in(&operation_path(index));
+                            items.push(BulkItemResult::failed(index, target, e.details()));
+                        }
+                    }
+                }
+            }
+        }
+        tx.commit().await?;
+
+        self.sync_relationships_to_graph(&written).await;
+
+        Ok(BulkResult::new(mode, items))
+    }
+
+    async fn prepare_operation(&self, operation: RelationshipOperation, permissions: &PermissionSet) -> Result<RelationshipChange> {
+        match operation {
+            RelationshipOperation::Create(request) => self.prepare_create(request, permissions).await,
+            RelationshipOperation::Update(update) => self.prepare_update(update.id, update.changes, permissions).await,
+            RelationshipOperation::Delete { id } => self.prepare_delete(id, permissions).await,
+        }
+    }
+
+    /// Check a new relationship against its type before anything is written
+    async fn prepare_create(&self, request: CreateRelationshipRequest, permissions: &PermissionSet) -> Result<RelationshipChange> {
+        // Validate request
+        request.validate().map_err(AppError::from)?;
+
+        permissions.require(ResourceType::Relationship, PermissionAction::Create, Some(request.relationship_type_id))?;
+
         // Validate that assets are not the same
         if request.from_ci_asset_id == request.to_ci_asset_id {
             return Err(anyhow::anyhow!("Cannot create relationship from an asset to itself"));
@@ -222,7 +648,7 @@ impl RelationshipService {
 
         // Validate relationship type constraints (if specified)
         if let Some(from_ci_type_id) = rel_type.from_ci_type_id {
-            if from_asset.ci_type_id != from_ci_type_id {
+            if !self.fits_constraint(&rel_type, from_asset.ci_type_id, from_ci_type_id).await? {
                 return Err(anyhow::anyhow!(
                     "Source asset type does not match relationship type constraint"
                 ));
@@ -230,13 +656,15 @@ impl RelationshipService {
         }
 
         if let Some(to_ci_type_id) = rel_type.to_ci_type_id {
-            if to_asset.ci_type_id != to_ci_type_id {
+            if !self.fits_constraint(&rel_type, to_asset.ci_type_id, to_ci_type_id).await? {
                 return Err(anyhow::anyhow!(
                     "Target asset type does not match relationship type constraint"
                 ));
             }
         }
 
+        self.validate_attributes(&rel_type, request.attributes.as_ref().unwrap_or(&json!({})))?;
+
         // Check if relationship already exists
         if self.relationship_repository
             .relationship_exists(
@@ -251,120 +679,291 @@ impl RelationshipService {
             ));
         }
 
-        // Create relationship in PostgreSQL
-        let relationship = self.relationship_repository
-            .create_relationship(&request, user_id)
-            .await?;
+        Ok(RelationshipChange::Create(request))
+    }
 
-        // Get CI type names for Neo4j
-        let from_ci_type = self.ci_repository
-            .get_ci_type_by_id(from_asset.ci_type_id)
-            .await?
-            .ok_or_else(|| anyhow::anyhow!("Source CI type not found"))?;
+    /// Check new attributes against the relationship type before anything is written
+    async fn prepare_update(&self, id: Uuid, request: UpdateRelationshipRequest, permissions: &PermissionSet) -> Result<RelationshipChange> {
+        // Validate request
+        request.validate().map_err(AppError::from)?;
 
-        let to_ci_type = self.ci_repository
-            .get_ci_type_by_id(to_asset.ci_type_id)
+        let existing = self.relationship_repository
+            .get_relationship_by_id(id)
             .await?
-            .ok_or_else(|| anyhow::anyhow!("Target CI type not found"))?;
+            .ok_or_else(|| anyhow::anyhow!("Relationship not found"))?;
 
-        // Sync to Neo4j
-        if let Err(e) = self.graph_repository
-            .create_relationship(
-                request.from_ci_asset_id,
-                request.to_ci_asset_id,
-                &rel_type.name,
-                request.relationship_type_id,
-                request.attributes.clone(),
-                &from_ci_type.name,
-                &to_ci_type.name,
-                rel_type.is_bidirectional,
-            )
-            .await
-        {
-            tracing::warn!("Failed to sync relationship to Neo4j: {}", e);
-            // Don't fail the request if Neo4j sync fails, just log it
+        permissions.require(ResourceType::Relationship, PermissionAction::Update, Some(existing.relationship_type_id))?;
+
+        // Without new attributes the stored ones stay, and they were valid when written
+        if let Some(ref attributes) = request.attributes {
+            let rel_type = self.relationship_repository
+                .get_by_id(existing.relationship_type_id)
+                .await?
+                .ok_or_else(|| anyhow::anyhow!("Relationship type not found"))?;
+
+            self.validate_attributes(&rel_type, attributes)?;
         }
 
-        // Get the full relationship details to return
-        let relationship_details = self.relationship_repository
-            .get_relationship_by_id(relationship.id)
+        Ok(RelationshipChange::Update { id, request })
+    }
+
+    async fn prepare_delete(&self, id: Uuid, permissions: &PermissionSet) -> Result<RelationshipChange> {
+        // Get relationship details before deletion
+        let relationship = self.relationship_repository
+            .get_relationship_by_id(id)
             .await?
-            .ok_or_else(|| anyhow::anyhow!("Failed to retrieve created relationship"))?;
+            .ok_or_else(|| anyhow::anyhow!("Relationship not found"))?;
 
-        Ok(relationship_details)
+        permissions.require(ResourceType::Relationship, PermissionAction::Delete, Some(relationship.relationship_type_id))?;
+
+        Ok(RelationshipChange::Delete(relationship))
     }
 
-    /// Get a relationship by ID
-    pub async fn get_relationship_instance(&self, id: Uuid) -> Result<Option<RelationshipWithDetails>> {
-        self.relationship_repository
-            .get_relationship_by_id(id)
-            .await
+    /// Write a validated change and its audit entry on the caller's
+    /// transaction, returning the relationship's ID
+    async fn apply_relationship_change(&self, conn: &mut PgConnection, change: &RelationshipChange, auth_context: &AuthContext) -> Result<Uuid> {
+        match change {
+            RelationshipChange::Create(request) => {
+                let relationship = self.relationship_repository
+                    .create_relationship(conn, request, auth_context.user_id)
+                    .await?;
+
+                self.audit_service
+                    .record_change(conn, auth_context, AuditedEntity::Relationship, relationship.id, AuditAction::Insert, None)
+                    .await?;
+                Ok(relationship.id)
+            }
+            RelationshipChange::Update { id, request } => {
+                let old_values = self.audit_service.snapshot(conn, AuditedEntity::Relationship, *id).await?;
+                self.relationship_repository
+                    .update_relationship(conn, *id, request)
+                    .await?;
+
+                self.audit_service
+                    .record_change(conn, auth_context, AuditedEntity::Relationship, *id, AuditAction::Update, old_values)
+                    .await?;
+                Ok(*id)
+            }
+            RelationshipChange::Delete(relationship) => {
+                let old_values = self.audit_service.snapshot(conn, AuditedEntity::Relationship, relationship.id).await?;
+                self.relationship_repository
+                    .delete_relationship(conn, relationship.id, auth_context.user_id, None)
+                    .await?;
+
+                self.audit_service
+                    .record_change(conn, auth_context, AuditedEntity::Relationship, relationship.id, AuditAction::Delete, old_values)
+                    .await?;
+                Ok(relationship.id)
+            }
+        }
     }
 
-    /// List relationships with optional filtering
-    pub async fn list_relationship_instances(
+    /// Mirror the relationships a batch created or deleted into Neo4j with a
+    /// few queries for the whole batch. Attribute updates aren't mirrored,
+    /// as with single updates. Failures are only logged.
+    async fn sync_relationships_to_graph(&self, written: &[(Uuid, RelationshipChange)]) {
+        let created: Vec<Uuid> = written.iter()
+            .filter(|(_, change)| matches!(change, RelationshipChange::Create(_)))
+            .map(|(id, _)| *id)
+            .collect();
+        let deleted: Vec<RelationshipWithDetails> = written.iter()
+            .filter_map(|(_, change)| match change {
+                RelationshipChange::Delete(relationship) => Some(relationship.clone()),
+                _ => None,
+            })
+            .collect();
+
+        if !created.is_empty() {
+            let relationships = match self.relationship_repository.get_relationships_by_ids(&created).await {
+                Ok(relationships) => relationships,
+                Err(e) => {
+                    tracing::warn!("Failed to load {} relationships for Neo4j: {}", created.len(), e);
+                    Vec::new()
+                }
+            };
+            if let Err(e) = self.graph_repository.upsert_relationships(&relationships).await {
+                tracing::warn!("Failed to sync {} relationships to Neo4j: {}", relationships.len(), e);
+            }
+        }
+
+        if !deleted.is_empty() {
+            if let Err(e) = self.graph_repository.delete_relationships(&deleted).await {
+                tracing::warn!("Failed to delete {} relationships from Neo4j: {}", deleted.len(), e);
+            }
+        }
+    }
+
+    /// Put a relationship's attributes back to an earlier version, picked by
+    /// audit entry or by time. A soft-deleted relationship is undeleted as long
+    /// as both its assets are live and no duplicate has been created since.
+    pub async fn restore_relationship_instance(
         &self,
-        filter: RelationshipFilter,
-    ) -> Result<Vec<RelationshipResponse>> {
-        self.relationship_repository
-            .list_relationships(&filter)
-            .await
+        id: Uuid,
+        request: &RestoreVersionRequest,
+        auth_context: &AuthContext,
+    ) -> Result<RelationshipWithDetails> {
+        self.restore_relationship(id, Some(request), auth_context).await
     }
 
-    /// Update a relationship's attributes
-    pub async fn update_relationship_instance(
+    /// One page of soft-deleted relationships, most recently deleted first
+    pub async fn list_deleted_relationships(
+        &self,
+        filter: &DeletedRelationshipFilter,
+        page: &PageRequest,
+        auth_context: &AuthContext,
+    ) -> AppResult<Page<DeletedRelationship>> {
+        let cursor = page.cursor(0)?;
+        let limit = page.limit()?;
+
+        let allowed_type_ids = PermissionSet::load(&self.rbac_repository, auth_context).await?
+            .readable_scopes(ResourceType::Relationship);
+
+        let relationships = self.relationship_repository
+            .list_deleted_relationships(filter, allowed_type_ids.as_deref(), cursor.as_ref(), limit + 1)
+            .await?;
+        let total = if page.include_total {
+            Some(self.relationship_repository.count_deleted_relationships(filter, allowed_type_ids.as_deref()).await?)
+        } else {
+            None
+        };
+
+        Ok(Page::from_rows(relationships, limit, |last| KeysetCursor::new(last.deleted_at, last.id)).with_total(total))
+    }
+
+    /// Take a relationship out of the trash as it was when deleted, under the
+    /// same conditions as undeleting it by restoring a version
+    pub async fn restore_deleted_relationship(&self, id: Uuid, auth_context: &AuthContext) -> AppResult<RelationshipWithDetails> {
+        self.restore_relationship(id, None, auth_context).await.map_err(as_app_error)
+    }
+
+    /// Restore a relationship to the version `request` picks, or without one
+    /// undelete it with the attributes it was deleted with
+    async fn restore_relationship(
         &self,
         id: Uuid,
-        request: UpdateRelationshipRequest,
+        request: Option<&RestoreVersionRequest>,
+        auth_context: &AuthContext,
     ) -> Result<RelationshipWithDetails> {
-        // Validate request
-        if let Err(validation_errors) = request.validate() {
-            return Err(anyhow::anyhow!("Validation failed: {}", validation_errors));
+        let mut tx = self.relationship_repository.begin().await?;
+        let old_values = self.audit_service.snapshot(&mut tx, AuditedEntity::Relationship, id).await?
+            .ok_or_else(|| anyhow::anyhow!("Relationship not found"))?;
+        let relationship: Relationship = serde_json::from_value(old_values.clone())?;
+
+        PermissionSet::load(&self.rbac_repository, auth_context).await?
+            .require(ResourceType::Relationship, PermissionAction::Update, Some(relationship.relationship_type_id))?;
+
+        let deleted = old_values.get("deleted_at").map_or(false, |deleted_at| !deleted_at.is_null());
+        let attributes = match request {
+            Some(request) => {
+                let history = self.audit_service.entity_history(AuditedEntity::Relationship, id).await?;
+                restore_target(&history, request)?
+                    .get("attributes")
+                    .cloned()
+                    .unwrap_or_else(|| json!({}))
+            }
+            None if deleted => relationship.attributes.clone(),
+            None => return Err(AppError::not_found("Relationship is not in the trash").into()),
+        };
+
+        let rel_type = self.relationship_repository
+            .get_by_id(relationship.relationship_type_id)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Relationship type has been deleted"))?;
+
+        // The schema may have changed since this version was current
+        self.validate_attributes(&rel_type, &attributes)?;
+
+        if deleted {
+            if self.ci_repository.get_ci_asset_by_id(relationship.from_ci_asset_id).await?.is_none() {
+                return Err(anyhow::anyhow!("Source asset has been deleted; restore it first"));
+            }
+
+            if self.ci_repository.get_ci_asset_by_id(relationship.to_ci_asset_id).await?.is_none() {
+                return Err(anyhow::anyhow!("Target asset has been deleted; restore it first"));
+            }
+
+            if self.relationship_repository
+                .relationship_exists(
+                    relationship.relationship_type_id,
+                    relationship.from_ci_asset_id,
+                    relationship.to_ci_asset_id,
+                )
+                .await?
+            {
+                return Err(anyhow::anyhow!(
+                    "Relationship already exists between these assets"
+                ));
+            }
         }
 
-        // Update in PostgreSQL
         self.relationship_repository
-            .update_relationship(id, &request)
+            .restore_relationship(&mut tx, id, &attributes)
             .await?;
 
-        // Get the updated relationship with full details
+        self.audit_service
+            .record_change(&mut tx, auth_context, AuditedEntity::Relationship, id, AuditAction::Update, Some(old_values))
+            .await?;
+        tx.commit().await?;
+
         let relationship_details = self.relationship_repository
             .get_relationship_by_id(id)
             .await?
             .ok_or_else(|| anyhow::anyhow!("Relationship not found"))?;
 
-        // Note: We don't update Neo4j for attribute changes since Neo4j relationships
-        // are primarily for graph visualization and topology, not attribute storage
+        // MERGE makes this a no-op for relationships that were never deleted
+        if let Err(e) = self.graph_repository
+            .create_relationship(
+                relationship_details.from_ci_asset_id,
+                relationship_details.to_ci_asset_id,
+                &relationship_details.relationship_type_name,
+                relationship_details.relationship_type_id,
+                Some(relationship_details.attributes.clone()),
+                &relationship_details.from_ci_type_name,
+                &relationship_details.to_ci_type_name,
+                relationship_details.is_bidirectional,
+            )
+            .await
+        {
+            tracing::warn!("Failed to sync restored relationship to Neo4j: {}", e);
+        }
 
         Ok(relationship_details)
     }
+}
 
-    /// Delete a relationship
-    pub async fn delete_relationship_instance(&self, id: Uuid) -> Result<()> {
-        // Get relationship details before deletion
-        let relationship = self.relationship_repository
-            .get_relationship_by_id(id)
-            .await?
-            .ok_or_else(|| anyhow::anyhow!("Relationship not found"))?;
+/// The `AppError` a relationship error stands for. Plain messages are rules
+/// the request broke; database errors stay internal.
+fn as_app_error(error: anyhow::Error) -> AppError {
+    match error.downcast::<AppError>() {
+        Ok(error) => error,
+        Err(error) if error.is::<sqlx::Error>() => AppError::Generic(error),
+        Err(error) => AppError::bad_request(error.to_string()),
+    }
+}
 
-        // Delete from PostgreSQL
-        self.relationship_repository
-            .delete_relationship(id)
-            .await?;
+/// An empty or missing `attributes_schema` means attributes are unconstrained
+fn has_schema(schema: &Value) -> bool {
+    match schema {
+        Value::Null => false,
+        Value::Object(keywords) => !keywords.is_empty(),
+        _ => true,
+    }
+}
 
-        // Delete from Neo4j
-        if let Err(e) = self.graph_repository
-            .delete_relationship(
-                relationship.from_ci_asset_id,
-                relationship.to_ci_asset_id,
-                relationship.relationship_type_id,
-            )
-            .await
-        {
-            tracing::warn!("Failed to delete relationship from Neo4j: {}", e);
-            // Don't fail the request if Neo4j deletion fails, just log it
-        }
+fn compile_schema(schema: &Value) -> Result<JSONSchema> {
+    JSONSchema::compile(schema).map_err(|e| {
+        AppError::invalid_field(FieldError::new(
+            "/attributes_schema",
+            FieldErrorCode::InvalidValue,
+            format!("Invalid attributes schema: {}", e),
+        )).into()
+    })
+}
 
-        Ok(())
+/// Every way `attributes` fails the schema; empty when it conforms
+fn schema_errors(schema: &JSONSchema, attributes: &Value) -> Vec<FieldError> {
+    match schema.validate(attributes) {
+        Ok(()) => Vec::new(),
+        Err(errors) => errors.map(FieldError::from).collect(),
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,145 @@
+use crate::database::{ServiceAccountRepository, is_unique_violation};
+use crate::models::{
+    ServiceAccount, ServiceAccountResponse, CreateServiceAccountRequest,
+    CreateApiKeyRequest, CreateApiKeyResponse,
+};
+use crate::middleware::AuthContext;
+use crate::utils::{generate_secure_token, hash_token, KeysetCursor, Page, PageRequest};
+use crate::error::{AppError, AppResult};
+use chrono::Utc;
+use uuid::Uuid;
+use validator::Validate;
+
+/// Prefix on every API key, so leaked keys are easy to recognise and grep for
+const API_KEY_PREFIX: &str = "cmdb_";
+const API_KEY_LENGTH: usize = 48;
+/// Characters of the key kept in plaintext for identifying it in listings
+const API_KEY_DISPLAY_LENGTH: usize = 12;
+
+pub struct ServiceAccountService {
+    service_account_repository: ServiceAccountRepository,
+}
+
+impl ServiceAccountService {
+    pub fn new(service_account_repository: ServiceAccountRepository) -> Self {
+        Self { service_account_repository }
+    }
+
+    pub async fn create_service_account(
+        &self,
+        request: CreateServiceAccountRequest,
+        auth_context: &AuthContext,
+    ) -> AppResult<ServiceAccount> {
+        request.validate()?;
+
+        if self.service_account_repository.name_exists(&request.name).await? {
+            return Err(AppError::conflict(format!("Service account '{}' already exists", request.name)));
+        }
+
+        self.service_account_repository
+            .create_service_account(&request.name, request.description.as_deref(), auth_context.user_id)
+            .await
+            .map_err(|e| {
+                if is_unique_violation(&e) {
+                    AppError::conflict(format!("Service account '{}' already exists", request.name))
+                } else {
+                    AppError::from(e)
+                }
+            })
+    }
+
+    /// One page of service accounts, by name
+    pub async fn list_service_accounts(&self, page: &PageRequest) -> AppResult<Page<ServiceAccount>> {
+        let cursor = page.cursor(1)?;
+        let limit = page.limit()?;
+
+        let service_accounts = self.service_account_repository
+            .list_service_accounts(cursor.as_ref(), limit + 1)
+            .await?;
+        let total = if page.include_total {
+            Some(self.service_account_repository.count_service_accounts().await?)
+        } else {
+            None
+        };
+
+        Ok(Page::from_rows(service_accounts, limit, |last| {
+            KeysetCursor::with_keys(vec![last.name.clone().into()], last.created_at, last.id)
+        })
+        .with_total(total))
+    }
+
+    pub async fn get_service_account(&self, id: Uuid) -> AppResult<ServiceAccountResponse> {
+        let service_account = self.find_service_account(id).await?;
+
+        Ok(ServiceAccountResponse {
+            api_keys: self.service_account_repository.list_api_keys(id).await?,
+            service_account,
+        })
+    }
+
+    /// Deactivate the account; all of its keys stop working immediately
+    pub async fn deactivate_service_account(&self, id: Uuid) -> AppResult<()> {
+        self.find_service_account(id).await?;
+
+        self.service_account_repository.deactivate_service_account(id).await?;
+        Ok(())
+    }
+
+    /// Issue a new key. The plaintext key is only ever returned here.
+    pub async fn create_api_key(
+        &self,
+        service_account_id: Uuid,
+        request: CreateApiKeyRequest,
+        auth_context: &AuthContext,
+    ) -> AppResult<CreateApiKeyResponse> {
+        request.validate()?;
+
+        let service_account = self.find_service_account(service_account_id).await?;
+        if !service_account.is_active {
+            return Err(AppError::validation("Cannot issue keys for an inactive service account"));
+        }
+
+        if let Some(expires_at) = request.expires_at {
+            if expires_at <= Utc::now() {
+                return Err(AppError::validation("API key expiry must be in the future"));
+            }
+        }
+
+        let key = format!("{}{}", API_KEY_PREFIX, generate_secure_token(API_KEY_LENGTH)?);
+
+        let api_key_id = self.service_account_repository
+            .create_api_key(
+                service_account_id,
+                &request.name,
+                &key[..API_KEY_DISPLAY_LENGTH],
+                &hash_token(&key),
+                request.expires_at,
+                &request.permissions,
+                auth_context.user_id,
+            )
+            .await?;
+
+        let api_key = self.service_account_repository
+            .list_api_keys(service_account_id)
+            .await?
+            .into_iter()
+            .find(|api_key| api_key.id == api_key_id)
+            .ok_or_else(|| AppError::internal("Failed to retrieve created API key"))?;
+
+        Ok(CreateApiKeyResponse { api_key, key })
+    }
+
+    pub async fn revoke_api_key(&self, service_account_id: Uuid, api_key_id: Uuid) -> AppResult<()> {
+        if !self.service_account_repository.revoke_api_key(service_account_id, api_key_id).await? {
+            return Err(AppError::not_found("Active API key not found for this service account"));
+        }
+        Ok(())
+    }
+
+    async fn find_service_account(&self, id: Uuid) -> AppResult<ServiceAccount> {
+        self.service_account_repository
+            .get_service_account(id)
+            .await?
+            .ok_or_else(|| AppError::not_found("Service account not found"))
+    }
+}
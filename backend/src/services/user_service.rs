@@ -0,0 +1,90 @@
+use crate::database::{UserRepository, SessionRepository, UserUpdate, is_unique_violation};
+use crate::models::{User, UpdateUserRequest, UserFilter};
+use crate::error::{AppError, AppResult};
+use crate::utils::{KeysetCursor, Page, PageRequest};
+use uuid::Uuid;
+use validator::Validate;
+
+/// User administration, exposed to admins only
+pub struct UserService {
+    user_repository: UserRepository,
+    session_repository: SessionRepository,
+}
+
+impl UserService {
+    pub fn new(user_repository: UserRepository, session_repository: SessionRepository) -> Self {
+        Self {
+            user_repository,
+            session_repository,
+        }
+    }
+
+    /// One page of users, by email
+    pub async fn list_users(&self, filter: &UserFilter, page: &PageRequest) -> AppResult<Page<User>> {
+        let cursor = page.cursor(1)?;
+        let limit = page.limit()?;
+
+        let users = self.user_repository.list_users(filter, cursor.as_ref(), limit + 1).await?;
+        let total = if page.include_total {
+            Some(self.user_repository.count_users(filter).await?)
+        } else {
+            None
+        };
+
+        Ok(Page::from_rows(users, limit, |last| KeysetCursor::with_keys(vec![last.email.clone().into()], last.created_at, last.id))
+            .with_total(total))
+    }
+
+    pub async fn get_user(&self, id: Uuid) -> AppResult<User> {
+        self.user_repository
+            .get_user_by_id(id)
+            .await?
+            .ok_or_else(|| AppError::not_found("User not found"))
+    }
+
+    pub async fn update_user(&self, id: Uuid, request: UpdateUserRequest) -> AppResult<User> {
+        request.validate()?;
+
+        if let Some(ref email) = request.email {
+            if let Some(other) = self.user_repository.get_user_by_email(email).await? {
+                if other.id != id {
+                    return Err(AppError::conflict(format!("User with email '{}' already exists", email)));
+                }
+            }
+        }
+
+        let outcome = self.user_repository
+            .update_user(id, &request)
+            .await
+            .map_err(|e| {
+                if is_unique_violation(&e) {
+                    AppError::conflict("User with this email already exists")
+                } else {
+                    AppError::from(e)
+                }
+            })?;
+
+        let user = match outcome {
+            UserUpdate::Updated(user) => user,
+            UserUpdate::NotFound => return Err(AppError::not_found("User not found")),
+            UserUpdate::LastAdmin => {
+                return Err(AppError::conflict("The last active admin cannot be demoted or deactivated"));
+            }
+        };
+
+        // Refresh tokens would otherwise outlive the deactivation
+        if !user.is_active {
+            self.session_repository.revoke_all_user_sessions(user.id).await?;
+        }
+
+        Ok(user)
+    }
+
+    pub async fn deactivate_user(&self, id: Uuid) -> AppResult<User> {
+        self.update_user(id, UpdateUserRequest {
+            is_active: Some(false),
+            ..Default::default()
+        })
+        .await
+    }
+}
@@ -0,0 +1,809 @@
+use chrono::DateTime;
+use serde_json::{json, Map, Value};
+use std::collections::BTreeSet;
+use std::num::IntErrorKind;
+
+use crate::error::{FieldError, FieldErrorCode};
+
+/// Longest filter expression accepted, in bytes
+pub const MAX_FILTER_LENGTH: usize = 2000;
+/// Most comparisons one filter may make
+pub const MAX_FILTER_CONDITIONS: usize = 50;
+/// Deepest nesting of parentheses and NOT accepted
+pub const MAX_FILTER_DEPTH: usize = 16;
+/// Most sort keys accepted
+pub const MAX_SORT_KEYS: usize = 3;
+
+/// A parsed filter over CI assets, e.g.
+/// `attributes.os = "ubuntu" AND attributes.ram_gb >= 64 AND attributes.tags CONTAINS "pci"`.
+///
+/// Filters are parsed and checked here and turned into SQL by the
+/// repository, which binds every value as a parameter.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AssetFilter {
+    And(Vec<AssetFilter>),
+    Or(Vec<AssetFilter>),
+    Not(Box<AssetFilter>),
+    Condition(AssetField, FilterOp),
+}
+
+/// What a filter or sort key refers to
+#[derive(Debug, Clone, PartialEq)]
+pub enum AssetField {
+    Name,
+    CreatedAt,
+    UpdatedAt,
+    Attribute(Vec<String>), // `attributes.rack.room` is ["rack", "room"]
+    Computed(Vec<String>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FilterOp {
+    Eq(Value),
+    Ne(Value),
+    Lt(Value),
+    Le(Value),
+    Gt(Value),
+    Ge(Value),
+    In(Vec<Value>),
+    Contains(Value), // A list attribute has this item
+    Like(String),    // Case-insensitive, `%` and `_` as wildcards
+    IsNull,
+    IsNotNull,
+}
+
+#[derive(Debug, Clone)]
+pub struct SortKey {
+    pub field: AssetField,
+    pub descending: bool,
+}
+
+/// The fields a filter may use and their types. With a CI type, attributes
+/// are checked against its effective schema and computed attributes against
+/// its definitions; without one, any attribute is accepted.
+#[derive(Debug, Clone, Default)]
+pub struct QueryFields {
+    schema: Option<Value>,
+    computed: Option<BTreeSet<String>>,
+}
+
+impl AssetField {
+    /// The JSON path into `attributes` or `computed_attributes`, if any
+    pub fn json_path(&self) -> Option<&[String]> {
+        match self {
+            AssetField::Attribute(path) | AssetField::Computed(path) => Some(path),
+            _ => None,
+        }
+    }
+
+    /// `{"a": {"b": value}}` for the path `a.b`, as used with `@>`
+    pub fn nest(path: &[String], value: Value) -> Value {
+        path.iter().rev().fold(value, |inner, segment| {
+            let mut object = Map::new();
+            object.insert(segment.clone(), inner);
+            Value::Object(object)
+        })
+    }
+}
+
+impl QueryFields {
+    pub fn untyped() -> Self {
+        Self::default()
+    }
+
+    pub fn for_ci_type<I: IntoIterator<Item = String>>(schema: Option<Value>, computed: I) -> Self {
+        Self {
+            schema,
+            computed: Some(computed.into_iter().collect()),
+        }
+    }
+
+    /// The JSON types the field can hold; empty when anything goes
+    fn types_of(&self, field: &AssetField) -> Result<Vec<String>, String> {
+        match field {
+            AssetField::Name => Ok(vec!["string".to_string()]),
+            AssetField::CreatedAt | AssetField::UpdatedAt => Ok(vec!["timestamp".to_string()]),
+            AssetField::Computed(path) => match &self.computed {
+                Some(names) if !names.contains(&path[0]) => Err(format!("unknown computed attribute '{}'", path[0])),
+                _ => Ok(Vec::new()),
+            },
+            AssetField::Attribute(path) => {
+                let Some(mut property) = self.schema.as_ref() else {
+                    return Ok(Vec::new());
+                };
+                for segment in path {
+                    match property.get("properties") {
+                        Some(properties) => {
+                            property = properties.get(segment)
+                                .ok_or_else(|| format!("unknown attribute '{}'", path.join(".")))?;
+                        }
+                        // Below a property without declared children anything goes
+                        None => return Ok(Vec::new()),
+                    }
+                }
+                Ok(json_types(property))
+            }
+        }
+    }
+
+    fn item_types_of(&self, field: &AssetField) -> Vec<String> {
+        let AssetField::Attribute(path) = field else {
+            return Vec::new();
+        };
+        let mut property = match self.schema.as_ref() {
+            Some(schema) => schema,
+            None => return Vec::new(),
+        };
+        for segment in path {
+            match property.get("properties").and_then(|properties| properties.get(segment)) {
+                Some(child) => property = child,
+                None => return Vec::new(),
+            }
+        }
+        property.get("items").map(json_types).unwrap_or_default()
+    }
+}
+
+fn json_types(property: &Value) -> Vec<String> {
+    match property.get("type") {
+        Some(Value::String(t)) => vec![t.clone()],
+        Some(Value::Array(types)) => types.iter().filter_map(Value::as_str).map(str::to_string).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Whether a literal fits a field of the given JSON types
+fn accepts(types: &[String], literal: &Value) -> bool {
+    if types.is_empty() {
+        return true;
+    }
+    let has = |t: &str| types.iter().any(|declared| declared == t);
+    match literal {
+        Value::Null => has("null"),
+        Value::Bool(_) => has("boolean"),
+        Value::Number(n) => has("number") || (has("integer") && n.as_f64().map_or(false, |n| n.fract() == 0.0)),
+        // Timestamps are compared with RFC 3339 strings
+        Value::String(_) => has("string") || has("timestamp"),
+        _ => false,
+    }
+}
+
+fn describe(types: &[String]) -> String {
+    types.join(" or ")
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Keyword(&'static str),
+    Literal(Value),
+    Op(&'static str),
+    LParen,
+    RParen,
+    Comma,
+}
+
+const KEYWORDS: [&str; 10] = ["AND", "OR", "NOT", "IN", "CONTAINS", "LIKE", "IS", "NULL", "TRUE", "FALSE"];
+
+fn tokenize(source: &str) -> Result<Vec<(usize, Token)>, FieldError> {
+    let invalid = |message: String| FieldError::new("/filter", FieldErrorCode::InvalidValue, message);
+    let chars: Vec<(usize, char)> = source.char_indices().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let (pos, c) = chars[i];
+        let next = chars.get(i + 1).map(|&(_, c)| c);
+
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() || (c == '-' && next.map_or(false, |n| n.is_ascii_digit())) {
+            let start = i;
+            i += 1;
+            while i < chars.len() && (chars[i].1.is_ascii_digit() || chars[i].1 == '.') {
+                i += 1;
+            }
+            let text: String = chars[start..i].iter().map(|&(_, c)| c).collect();
+            tokens.push((pos, Token::Literal(number_literal(&text, pos)?)));
+        } else if c == '"' || c == '\'' {
+            let mut text = String::new();
+            i += 1;
+            loop {
+                match chars.get(i).map(|&(_, c)| c) {
+                    None => return Err(invalid(format!("unterminated string starting at position {}", pos))),
+                    Some(ch) if ch == c => break,
+                    Some('\\') => {
+                        let escaped = chars.get(i + 1).map(|&(_, c)| c)
+                            .ok_or_else(|| invalid(format!("unterminated string starting at position {}", pos)))?;
+                        text.push(escaped);
+                        i += 2;
+                    }
+                    Some(ch) => {
+                        text.push(ch);
+                        i += 1;
+                    }
+                }
+            }
+            i += 1;
+            tokens.push((pos, Token::Literal(Value::String(text))));
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].1.is_alphanumeric() || chars[i].1 == '_' || chars[i].1 == '.') {
+                i += 1;
+            }
+            let word: String = chars[start..i].iter().map(|&(_, c)| c).collect();
+            let token = match KEYWORDS.iter().find(|keyword| keyword.eq_ignore_ascii_case(&word)) {
+                Some(&"NULL") => Token::Literal(Value::Null),
+                Some(&"TRUE") => Token::Literal(Value::Bool(true)),
+                Some(&"FALSE") => Token::Literal(Value::Bool(false)),
+                Some(keyword) => Token::Keyword(keyword),
+                None => Token::Ident(word),
+            };
+            tokens.push((pos, token));
+        } else {
+            let (token, length) = match (c, next) {
+                ('(', _) => (Token::LParen, 1),
+                (')', _) => (Token::RParen, 1),
+                (',', _) => (Token::Comma, 1),
+                ('!', Some('=')) | ('<', Some('>')) => (Token::Op("!="), 2),
+                ('<', Some('=')) => (Token::Op("<="), 2),
+                ('>', Some('=')) => (Token::Op(">="), 2),
+                ('=', Some('=')) => (Token::Op("="), 2),
+                ('=', _) => (Token::Op("="), 1),
+                ('<', _) => (Token::Op("<"), 1),
+                ('>', _) => (Token::Op(">"), 1),
+                _ => return Err(invalid(format!("unexpected character '{}' at position {}", c, pos))),
+            };
+            i += length;
+            tokens.push((pos, token));
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// A number as written: integers must fit in 64 bits and decimals in a
+/// finite double, rather than being clamped or turned into null
+fn number_literal(text: &str, pos: usize) -> Result<Value, FieldError> {
+    let invalid = || FieldError::new("/filter", FieldErrorCode::InvalidValue, format!("invalid number '{}' at position {}", text, pos));
+    let out_of_range = || FieldError::new("/filter", FieldErrorCode::OutOfRange, format!("number '{}' at position {} is out of range", text, pos));
+
+    if text.contains('.') {
+        let number: f64 = text.parse().map_err(|_| invalid())?;
+        return serde_json::Number::from_f64(number).map(Value::Number).ok_or_else(out_of_range);
+    }
+
+    match text.parse::<i64>() {
+        Ok(number) => Ok(Value::from(number)),
+        Err(e) if matches!(e.kind(), IntErrorKind::PosOverflow | IntErrorKind::NegOverflow) => {
+            Err(out_of_range().with_expected(json!({ "min": i64::MIN, "max": i64::MAX })))
+        }
+        Err(_) => Err(invalid()),
+    }
+}
+
+fn parse_field(name: &str) -> Result<AssetField, String> {
+    let mut segments = name.split('.');
+    let root = segments.next().unwrap_or_default();
+    let path: Vec<String> = segments.map(str::to_string).collect();
+    if path.iter().any(String::is_empty) {
+        return Err(format!("invalid field '{}'", name));
+    }
+
+    match (root, path.is_empty()) {
+        ("name", true) => Ok(AssetField::Name),
+        ("created_at", true) => Ok(AssetField::CreatedAt),
+        ("updated_at", true) => Ok(AssetField::UpdatedAt),
+        ("attributes", false) => Ok(AssetField::Attribute(path)),
+        ("computed", false) => Ok(AssetField::Computed(path)),
+        _ => Err(format!(
+            "unknown field '{}'; use name, created_at, updated_at, attributes.<name> or computed.<name>",
+            name
+        )),
+    }
+}
+
+struct Parser<'a> {
+    tokens: Vec<(usize, Token)>,
+    pos: usize,
+    depth: usize,
+    conditions: usize,
+    fields: &'a QueryFields,
+    source_len: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(_, token)| token)
+    }
+
+    fn position(&self) -> usize {
+        self.tokens.get(self.pos).map_or(self.source_len, |(pos, _)| *pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).map(|(_, token)| token.clone());
+        self.pos += 1;
+        token
+    }
+
+    fn eat_keyword(&mut self, keyword: &'static str) -> bool {
+        if self.peek() == Some(&Token::Keyword(keyword)) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn error(&self, message: &str) -> String {
+        format!("{} at position {}", message, self.position())
+    }
+
+    fn nested<T>(&mut self, parse: impl FnOnce(&mut Self) -> Result<T, String>) -> Result<T, String> {
+        self.depth += 1;
+        if self.depth > MAX_FILTER_DEPTH {
+            return Err(format!("filter is nested more than {} levels deep", MAX_FILTER_DEPTH));
+        }
+        let result = parse(self);
+        self.depth -= 1;
+        result
+    }
+
+    fn or(&mut self) -> Result<AssetFilter, String> {
+        let mut terms = vec![self.and()?];
+        while self.eat_keyword("OR") {
+            terms.push(self.and()?);
+        }
+        Ok(if terms.len() == 1 { terms.remove(0) } else { AssetFilter::Or(terms) })
+    }
+
+    fn and(&mut self) -> Result<AssetFilter, String> {
+        let mut terms = vec![self.not()?];
+        while self.eat_keyword("AND") {
+            terms.push(self.not()?);
+        }
+        Ok(if terms.len() == 1 { terms.remove(0) } else { AssetFilter::And(terms) })
+    }
+
+    fn not(&mut self) -> Result<AssetFilter, String> {
+        if self.eat_keyword("NOT") {
+            return self.nested(|p| p.not()).map(|inner| AssetFilter::Not(Box::new(inner)));
+        }
+        if self.peek() == Some(&Token::LParen) {
+            self.pos += 1;
+            let inner = self.nested(|p| p.or())?;
+            if self.next() != Some(Token::RParen) {
+                return Err(self.error("expected ')'"));
+            }
+            return Ok(inner);
+        }
+        self.condition()
+    }
+
+    fn literal(&mut self) -> Result<Value, String> {
+        match self.next() {
+            Some(Token::Literal(value)) => Ok(value),
+            _ => {
+                self.pos -= 1;
+                Err(self.error("expected a string, number, true, false or null"))
+            }
+        }
+    }
+
+    fn condition(&mut self) -> Result<AssetFilter, String> {
+        let start = self.position();
+        let field = match self.next() {
+            Some(Token::Ident(name)) => parse_field(&name).map_err(|e| format!("{} at position {}", e, start))?,
+            _ => {
+                self.pos -= 1;
+                return Err(self.error("expected a field such as attributes.<name>"));
+            }
+        };
+
+        self.conditions += 1;
+        if self.conditions > MAX_FILTER_CONDITIONS {
+            return Err(format!("filter has more than {} conditions", MAX_FILTER_CONDITIONS));
+        }
+
+        let op_position = self.position();
+        let op = match self.next() {
+            Some(Token::Op(op)) => {
+                let value = self.literal()?;
+                match op {
+                    "=" => FilterOp::Eq(value),
+                    "!=" => FilterOp::Ne(value),
+                    "<" => FilterOp::Lt(value),
+                    "<=" => FilterOp::Le(value),
+                    ">" => FilterOp::Gt(value),
+                    _ => FilterOp::Ge(value),
+                }
+            }
+            Some(Token::Keyword("IN")) => {
+                if self.next() != Some(Token::LParen) {
+                    return Err(self.error("expected '(' after IN"));
+                }
+                let mut values = vec![self.literal()?];
+                while self.peek() == Some(&Token::Comma) {
+                    self.pos += 1;
+                    values.push(self.literal()?);
+                }
+                if self.next() != Some(Token::RParen) {
+                    return Err(self.error("expected ')'"));
+                }
+                FilterOp::In(values)
+            }
+            Some(Token::Keyword("CONTAINS")) => FilterOp::Contains(self.literal()?),
+            Some(Token::Keyword("LIKE")) => match self.literal()? {
+                Value::String(pattern) => FilterOp::Like(pattern),
+                _ => return Err(format!("LIKE needs a string pattern at position {}", op_position)),
+            },
+            Some(Token::Keyword("IS")) => {
+                let negated = self.eat_keyword("NOT");
+                if self.next() != Some(Token::Literal(Value::Null)) {
+                    return Err(self.error("expected NULL"));
+                }
+                if negated { FilterOp::IsNotNull } else { FilterOp::IsNull }
+            }
+            _ => {
+                self.pos -= 1;
+                return Err(self.error("expected =, !=, <, <=, >, >=, IN, CONTAINS, LIKE or IS"));
+            }
+        };
+
+        self.check_types(&field, &op).map_err(|e| format!("{} at position {}", e, start))?;
+        Ok(AssetFilter::Condition(field, op))
+    }
+
+    /// Reject comparisons that can never match the field's declared type
+    fn check_types(&self, field: &AssetField, op: &FilterOp) -> Result<(), String> {
+        if matches!(field, AssetField::CreatedAt | AssetField::UpdatedAt) {
+            let literals: Vec<&Value> = match op {
+                FilterOp::Eq(value) | FilterOp::Ne(value) | FilterOp::Lt(value)
+                | FilterOp::Le(value) | FilterOp::Gt(value) | FilterOp::Ge(value) => vec![value],
+                FilterOp::In(values) => values.iter().collect(),
+                _ => Vec::new(),
+            };
+            if let Some(literal) = literals.into_iter().find(|literal| {
+                literal.as_str().map_or(true, |text| DateTime::parse_from_rfc3339(text).is_err())
+            }) {
+                return Err(format!("{} needs an RFC 3339 timestamp, got {}", field_name(field), literal));
+            }
+        }
+
+        let types = self.fields.types_of(field)?;
+        let has = |t: &str| types.is_empty() || types.iter().any(|declared| declared == t);
+        let mismatch = |literal: &Value| format!("{} can't be compared with {}", field_name(field), literal);
+
+        match op {
+            FilterOp::Eq(value) | FilterOp::Ne(value) => {
+                if !accepts(&types, value) {
+                    return Err(format!("{}; it holds {}", mismatch(value), describe(&types)));
+                }
+            }
+            FilterOp::In(values) => {
+                if let Some(value) = values.iter().find(|value| !accepts(&types, value)) {
+                    return Err(format!("{}; it holds {}", mismatch(value), describe(&types)));
+                }
+            }
+            FilterOp::Lt(value) | FilterOp::Le(value) | FilterOp::Gt(value) | FilterOp::Ge(value) => {
+                if !(value.is_number() || value.is_string()) {
+                    return Err(format!("ordering comparisons need a number or string, got {}", value));
+                }
+                let orderable = has("number") || has("integer") || has("string") || has("timestamp");
+                if !orderable || !accepts(&types, value) {
+                    return Err(format!("{}; it holds {}", mismatch(value), describe(&types)));
+                }
+            }
+            FilterOp::Contains(value) => {
+                if matches!(field, AssetField::Name | AssetField::CreatedAt | AssetField::UpdatedAt) || !has("array") {
+                    return Err(format!("CONTAINS needs a list field, and {} isn't one", field_name(field)));
+                }
+                let item_types = self.fields.item_types_of(field);
+                if !accepts(&item_types, value) {
+                    return Err(format!("{} holds items of type {}, not {}", field_name(field), describe(&item_types), value));
+                }
+            }
+            FilterOp::Like(_) => {
+                if !has("string") {
+                    return Err(format!("LIKE needs a text field, and {} holds {}", field_name(field), describe(&types)));
+                }
+            }
+            FilterOp::IsNull | FilterOp::IsNotNull => {}
+        }
+        Ok(())
+    }
+}
+
+fn field_name(field: &AssetField) -> String {
+    match field {
+        AssetField::Name => "name".to_string(),
+        AssetField::CreatedAt => "created_at".to_string(),
+        AssetField::UpdatedAt => "updated_at".to_string(),
+        AssetField::Attribute(path) => format!("attributes.{}", path.join(".")),
+        AssetField::Computed(path) => format!("computed.{}", path.join(".")),
+    }
+}
+
+/// Parse and type-check a filter expression. Errors point at `/filter`.
+pub fn parse_filter(source: &str, fields: &QueryFields) -> Result<AssetFilter, FieldError> {
+    let invalid = |message: String| FieldError::new("/filter", FieldErrorCode::InvalidValue, message);
+
+    if source.len() > MAX_FILTER_LENGTH {
+        return Err(invalid(format!("filter is longer than {} characters", MAX_FILTER_LENGTH)));
+    }
+
+    let tokens = tokenize(source)?;
+    if tokens.is_empty() {
+        return Err(FieldError::new("/filter", FieldErrorCode::Required, "filter is empty"));
+    }
+
+    let mut parser = Parser { tokens, pos: 0, depth: 0, conditions: 0, fields, source_len: source.len() };
+    let filter = parser.or().map_err(invalid)?;
+    if parser.peek().is_some() {
+        return Err(invalid(parser.error("unexpected input; expected AND, OR or the end of the filter")));
+    }
+
+    Ok(filter)
+}
+
+/// Parse a sort specification: comma-separated fields, each optionally
+/// followed by `:asc` or `:desc`, e.g. `attributes.ram_gb:desc,name`.
+/// Errors point at `/sort`.
+pub fn parse_sort(source: &str, fields: &QueryFields) -> Result<Vec<SortKey>, FieldError> {
+    let invalid = |message: String| FieldError::new("/sort", FieldErrorCode::InvalidValue, message);
+
+    let keys: Vec<&str> = source.split(',').map(str::trim).filter(|key| !key.is_empty()).collect();
+    if keys.len() > MAX_SORT_KEYS {
+        return Err(invalid(format!("at most {} sort keys are allowed", MAX_SORT_KEYS)));
+    }
+
+    keys.into_iter()
+        .map(|key| {
+            let (name, direction) = key.split_once(':').unwrap_or((key, "asc"));
+            let descending = match direction.to_ascii_lowercase().as_str() {
+                "asc" => false,
+                "desc" => true,
+                _ => return Err(invalid(format!("sort direction must be asc or desc, got '{}'", direction))),
+            };
+            let field = parse_field(name).map_err(invalid)?;
+            fields.types_of(&field).map_err(invalid)?;
+            Ok(SortKey { field, descending })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attribute(path: &str) -> AssetField {
+        AssetField::Attribute(path.split('.').map(str::to_string).collect())
+    }
+
+    fn condition(path: &str, op: FilterOp) -> AssetFilter {
+        AssetFilter::Condition(attribute(path), op)
+    }
+
+    fn parse(source: &str) -> AssetFilter {
+        parse_filter(source, &QueryFields::untyped()).unwrap()
+    }
+
+    fn error(source: &str) -> FieldError {
+        parse_filter(source, &QueryFields::untyped()).unwrap_err()
+    }
+
+    fn typed() -> QueryFields {
+        QueryFields::for_ci_type(
+            Some(json!({
+                "type": "object",
+                "properties": {
+                    "os": { "type": "string" },
+                    "ram_gb": { "type": "integer" },
+                    "tags": { "type": "array", "items": { "type": "string" } },
+                    "rack": { "type": "object", "properties": { "room": { "type": "string" } } },
+                },
+            })),
+            vec!["age_days".to_string()],
+        )
+    }
+
+    #[test]
+    fn parses_each_operator() {
+        let cases = [
+            ("attributes.a = 1", FilterOp::Eq(json!(1))),
+            ("attributes.a == 1", FilterOp::Eq(json!(1))),
+            ("attributes.a != 1", FilterOp::Ne(json!(1))),
+            ("attributes.a <> 1", FilterOp::Ne(json!(1))),
+            ("attributes.a < 1", FilterOp::Lt(json!(1))),
+            ("attributes.a <= 1", FilterOp::Le(json!(1))),
+            ("attributes.a > -1", FilterOp::Gt(json!(-1))),
+            ("attributes.a >= 1.5", FilterOp::Ge(json!(1.5))),
+            ("attributes.a IN (1, 'two', true)", FilterOp::In(vec![json!(1), json!("two"), json!(true)])),
+            ("attributes.a CONTAINS 'pci'", FilterOp::Contains(json!("pci"))),
+            ("attributes.a LIKE 'web%'", FilterOp::Like("web%".to_string())),
+            ("attributes.a IS NULL", FilterOp::IsNull),
+            ("attributes.a IS NOT NULL", FilterOp::IsNotNull),
+            ("attributes.a = null", FilterOp::Eq(Value::Null)),
+        ];
+        for (source, op) in cases {
+            assert_eq!(parse(source), condition("a", op), "{}", source);
+        }
+    }
+
+    #[test]
+    fn parses_fields() {
+        assert_eq!(parse("name = 'web01'"), AssetFilter::Condition(AssetField::Name, FilterOp::Eq(json!("web01"))));
+        assert_eq!(
+            parse("attributes.rack.room = 'B2'"),
+            condition("rack.room", FilterOp::Eq(json!("B2"))),
+        );
+        assert_eq!(
+            parse("computed.age_days > 30"),
+            AssetFilter::Condition(AssetField::Computed(vec!["age_days".to_string()]), FilterOp::Gt(json!(30))),
+        );
+        assert_eq!(
+            parse("created_at >= '2024-01-01T00:00:00Z'"),
+            AssetFilter::Condition(AssetField::CreatedAt, FilterOp::Ge(json!("2024-01-01T00:00:00Z"))),
+        );
+    }
+
+    #[test]
+    fn parses_quoted_strings() {
+        assert_eq!(parse(r#"attributes.a = "say \"hi\"""#), condition("a", FilterOp::Eq(json!("say \"hi\""))));
+        assert_eq!(parse(r"attributes.a = 'it\'s'"), condition("a", FilterOp::Eq(json!("it's"))));
+        assert_eq!(parse(r#"attributes.a = 'a "b" AND c'"#), condition("a", FilterOp::Eq(json!("a \"b\" AND c"))));
+        assert_eq!(parse("attributes.a = ''"), condition("a", FilterOp::Eq(json!(""))));
+    }
+
+    #[test]
+    fn keywords_are_case_insensitive() {
+        assert_eq!(
+            parse("attributes.a is not null and not attributes.b = TRUE"),
+            AssetFilter::And(vec![
+                condition("a", FilterOp::IsNotNull),
+                AssetFilter::Not(Box::new(condition("b", FilterOp::Eq(json!(true))))),
+            ]),
+        );
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        let (a, b, c) = (
+            condition("a", FilterOp::Eq(json!(1))),
+            condition("b", FilterOp::Eq(json!(2))),
+            condition("c", FilterOp::Eq(json!(3))),
+        );
+
+        assert_eq!(
+            parse("attributes.a = 1 OR attributes.b = 2 AND attributes.c = 3"),
+            AssetFilter::Or(vec![a.clone(), AssetFilter::And(vec![b.clone(), c.clone()])]),
+        );
+        assert_eq!(
+            parse("(attributes.a = 1 OR attributes.b = 2) AND attributes.c = 3"),
+            AssetFilter::And(vec![AssetFilter::Or(vec![a.clone(), b.clone()]), c.clone()]),
+        );
+        assert_eq!(
+            parse("NOT (attributes.a = 1 OR attributes.b = 2)"),
+            AssetFilter::Not(Box::new(AssetFilter::Or(vec![a.clone(), b]))),
+        );
+        assert_eq!(parse("NOT NOT attributes.a = 1"), AssetFilter::Not(Box::new(AssetFilter::Not(Box::new(a)))));
+    }
+
+    #[test]
+    fn errors_point_at_the_offending_position() {
+        let cases = [
+            ("attributes.a ~ 1", "unexpected character '~' at position 13"),
+            ("attributes.a = 'open", "unterminated string starting at position 15"),
+            ("attributes.a = ", "expected a string, number, true, false or null at position 15"),
+            ("attributes.a = b", "expected a string, number, true, false or null at position 15"),
+            ("attributes.a 1", "expected =, !=, <, <=, >, >=, IN, CONTAINS, LIKE or IS at position 13"),
+            ("name = 'a' AND", "expected a field such as attributes.<name> at position 14"),
+            ("name = 'a' AND foo = 1", "unknown field 'foo'; use name, created_at, updated_at, attributes.<name> or computed.<name> at position 15"),
+            ("name = 'a' name = 'b'", "unexpected input; expected AND, OR or the end of the filter at position 11"),
+            ("(name = 'a'", "expected ')' at position 11"),
+            ("attributes.a IN 1", "expected '(' after IN at position 17"),
+            ("attributes.a LIKE 5", "LIKE needs a string pattern at position 13"),
+            ("attributes. = 1", "invalid field 'attributes.' at position 0"),
+        ];
+        for (source, message) in cases {
+            let error = error(source);
+            assert_eq!(error.path, "/filter");
+            assert_eq!(error.code, FieldErrorCode::InvalidValue, "{}", source);
+            assert_eq!(error.message, message, "{}", source);
+        }
+    }
+
+    #[test]
+    fn empty_and_oversized_filters_are_rejected() {
+        assert_eq!(error("  ").code, FieldErrorCode::Required);
+
+        let long = format!("name = '{}'", "x".repeat(MAX_FILTER_LENGTH));
+        assert!(error(&long).message.contains("longer than"));
+
+        let deep = format!("{}name = 'x'", "NOT ".repeat(MAX_FILTER_DEPTH + 1));
+        assert!(error(&deep).message.contains("nested"));
+        let shallow = format!("{}name = 'x'", "NOT ".repeat(MAX_FILTER_DEPTH));
+        assert!(parse_filter(&shallow, &QueryFields::untyped()).is_ok());
+
+        let many = vec!["name = 'x'"; MAX_FILTER_CONDITIONS + 1].join(" OR ");
+        assert!(error(&many).message.contains("conditions"));
+    }
+
+    #[test]
+    fn numbers_must_be_representable() {
+        assert_eq!(parse("attributes.a = 9223372036854775807"), condition("a", FilterOp::Eq(json!(i64::MAX))));
+        assert_eq!(parse("attributes.a = -9223372036854775808"), condition("a", FilterOp::Eq(json!(i64::MIN))));
+        assert_eq!(parse("attributes.a = 1.0"), condition("a", FilterOp::Eq(json!(1.0))));
+
+        for source in [
+            "attributes.a = 9223372036854775808",
+            "attributes.a > -9223372036854775809",
+            "attributes.a IN (1, 99999999999999999999)",
+        ] {
+            let error = error(source);
+            assert_eq!(error.code, FieldErrorCode::OutOfRange, "{}", source);
+            assert_eq!(error.expected, Some(json!({ "min": i64::MIN, "max": i64::MAX })));
+        }
+
+        let huge = format!("attributes.a = 1{}.5", "0".repeat(400));
+        assert_eq!(error(&huge).code, FieldErrorCode::OutOfRange);
+
+        let malformed = error("attributes.a = 1.2.3");
+        assert_eq!(malformed.code, FieldErrorCode::InvalidValue);
+        assert_eq!(malformed.message, "invalid number '1.2.3' at position 15");
+    }
+
+    #[test]
+    fn typed_fields_reject_impossible_comparisons() {
+        let fields = typed();
+        for source in [
+            "attributes.os = 'ubuntu'",
+            "attributes.ram_gb >= 64",
+            "attributes.ram_gb IN (16, 32)",
+            "attributes.tags CONTAINS 'pci'",
+            "attributes.os LIKE 'ubu%'",
+            "attributes.rack.room = 'B2'",
+            "computed.age_days > 30",
+            "attributes.os IS NULL",
+        ] {
+            assert!(parse_filter(source, &fields).is_ok(), "{} should parse", source);
+        }
+
+        for (source, message) in [
+            ("attributes.ram_gb = 'lots'", "attributes.ram_gb can't be compared with \"lots\"; it holds integer at position 0"),
+            ("attributes.ram_gb = 1.5", "attributes.ram_gb can't be compared with 1.5; it holds integer at position 0"),
+            ("attributes.nope = 1", "unknown attribute 'nope' at position 0"),
+            ("computed.nope = 1", "unknown computed attribute 'nope' at position 0"),
+            ("attributes.tags CONTAINS 5", "attributes.tags holds items of type string, not 5 at position 0"),
+            ("attributes.os CONTAINS 'x'", "CONTAINS needs a list field, and attributes.os isn't one at position 0"),
+            ("attributes.ram_gb LIKE '1%'", "LIKE needs a text field, and attributes.ram_gb holds integer at position 0"),
+            ("attributes.os < true", "ordering comparisons need a number or string, got true at position 0"),
+            ("created_at > 'yesterday'", "created_at needs an RFC 3339 timestamp, got \"yesterday\" at position 0"),
+        ] {
+            assert_eq!(parse_filter(source, &fields).unwrap_err().message, message, "{}", source);
+        }
+    }
+
+    #[test]
+    fn parses_sort_keys() {
+        let keys = parse_sort("attributes.ram_gb:desc, name", &typed()).unwrap();
+        assert_eq!(keys.len(), 2);
+        assert_eq!((&keys[0].field, keys[0].descending), (&attribute("ram_gb"), true));
+        assert_eq!((&keys[1].field, keys[1].descending), (&AssetField::Name, false));
+
+        assert!(parse_sort("", &typed()).unwrap().is_empty());
+        assert!(parse_sort("updated_at:ASC", &typed()).is_ok());
+
+        for (source, message) in [
+            ("name:sideways", "sort direction must be asc or desc, got 'sideways'"),
+            ("attributes.nope", "unknown attribute 'nope'"),
+            ("colour", "unknown field 'colour'; use name, created_at, updated_at, attributes.<name> or computed.<name>"),
+            ("name,created_at,updated_at,attributes.os", "at most 3 sort keys are allowed"),
+        ] {
+            let error = parse_sort(source, &typed()).unwrap_err();
+            assert_eq!(error.path, "/sort");
+            assert_eq!(error.message, message, "{}", source);
+        }
+    }
+}
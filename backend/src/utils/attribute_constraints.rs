@@ -0,0 +1,295 @@
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use uuid::Uuid;
+
+use crate::error::{FieldError, FieldErrorCode};
+
+/// Constraints a CI type's schema puts on attribute values beyond their
+/// shape, declared with two extra keywords on top-level properties:
+///
+/// - `"unique": true` (or `"type"`) makes values unique among assets of the
+///   same CI type, and `"unique": "global"` among all assets
+/// - `"reference": "<CI type id>"` makes the value the ID of a live asset of
+///   that type or one of its subtypes
+///
+/// and one on the schema itself: `"natural_key": ["hostname", "domain"]`
+/// names the properties that together identify an asset within its CI type,
+/// which is how upserts find the asset to update.
+#[derive(Debug, Clone, Default)]
+pub struct AttributeConstraints {
+    unique: BTreeMap<String, UniqueScope>,
+    references: BTreeMap<String, Uuid>,
+    natural_key: Vec<String>,
+}
+
+/// The `attribute` a natural key value is claimed under. `$` keeps it apart
+/// from property names in practice.
+pub const NATURAL_KEY: &str = "$natural_key";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UniqueScope {
+    Type,
+    Global,
+}
+
+/// A value an asset holds for a unique attribute
+#[derive(Debug, Clone, PartialEq)]
+pub struct UniqueValue {
+    pub attribute: String,
+    pub scope: Option<Uuid>, // The CI type the value is unique within; None for all assets
+    pub value: Value,
+}
+
+impl AttributeConstraints {
+    /// Read the constraints of an effective schema. Errors point at the
+    /// offending keyword under `/attributes/schema`.
+    pub fn from_schema(schema: Option<&Value>) -> Result<Self, Vec<FieldError>> {
+        let mut constraints = Self::default();
+        let mut errors = Vec::new();
+
+        let no_properties = Map::new();
+        let properties = schema
+            .and_then(|schema| schema.get("properties"))
+            .and_then(Value::as_object)
+            .unwrap_or(&no_properties);
+
+        match schema.and_then(|schema| schema.get("natural_key")) {
+            None | Some(Value::Null) => {}
+            Some(natural_key) => match natural_key_of(natural_key, properties) {
+                Ok(names) => constraints.natural_key = names,
+                Err(error) => errors.push(error),
+            },
+        }
+
+        for (name, property) in properties {
+            let path = format!("/attributes/schema/properties/{}", name);
+
+            match property.get("unique") {
+                None | Some(Value::Bool(false)) => {}
+                Some(Value::Bool(true)) => {
+                    constraints.unique.insert(name.clone(), UniqueScope::Type);
+                }
+                Some(Value::String(scope)) if scope == "type" => {
+                    constraints.unique.insert(name.clone(), UniqueScope::Type);
+                }
+                Some(Value::String(scope)) if scope == "global" => {
+                    constraints.unique.insert(name.clone(), UniqueScope::Global);
+                }
+                Some(_) => errors.push(FieldError::new(
+                    format!("{}/unique", path),
+                    FieldErrorCode::NotAllowed,
+                    "unique must be true, \"type\" or \"global\"",
+                ).with_expected(serde_json::json!([true, "type", "global"]))),
+            }
+
+            if let Some(reference) = property.get("reference") {
+                match reference.as_str().and_then(|id| Uuid::parse_str(id).ok()) {
+                    Some(ci_type_id) => {
+                        constraints.references.insert(name.clone(), ci_type_id);
+                    }
+                    None => errors.push(FieldError::new(
+                        format!("{}/reference", path),
+                        FieldErrorCode::InvalidFormat,
+                        "reference must be the ID of a CI type",
+                    ).with_expected(Value::from("uuid"))),
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(constraints)
+        } else {
+            Err(errors)
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.unique.is_empty() && self.references.is_empty() && self.natural_key.is_empty()
+    }
+
+    /// The properties that identify an asset within its CI type, in key order;
+    /// empty when the type declares no natural key
+    pub fn natural_key(&self) -> &[String] {
+        &self.natural_key
+    }
+
+    /// The natural key an asset of `ci_type_id` has, as an array of its parts.
+    /// None without a natural key or while any part is unset or null.
+    pub fn natural_key_value(&self, ci_type_id: Uuid, attributes: &Value) -> Option<UniqueValue> {
+        if self.natural_key.is_empty() {
+            return None;
+        }
+        let parts = self.natural_key.iter()
+            .map(|name| attributes.get(name).filter(|value| !value.is_null()).cloned())
+            .collect::<Option<Vec<_>>>()?;
+
+        Some(UniqueValue {
+            attribute: NATURAL_KEY.to_string(),
+            scope: Some(ci_type_id),
+            value: Value::Array(parts),
+        })
+    }
+
+    /// Reference attributes and the CI type their values must belong to
+    pub fn references(&self) -> impl Iterator<Item = (&String, &Uuid)> {
+        self.references.iter()
+    }
+
+    /// The values an asset of `ci_type_id` claims, its natural key included.
+    /// Unset and null attributes claim nothing.
+    pub fn unique_values(&self, ci_type_id: Uuid, attributes: &Value) -> Vec<UniqueValue> {
+        let natural_key = self.natural_key_value(ci_type_id, attributes);
+        self.unique.iter()
+            .filter_map(|(name, scope)| {
+                let value = attributes.get(name).filter(|value| !value.is_null())?;
+                Some(UniqueValue {
+                    attribute: name.clone(),
+                    scope: match scope {
+                        UniqueScope::Type => Some(ci_type_id),
+                        UniqueScope::Global => None,
+                    },
+                    value: value.clone(),
+                })
+            })
+            .chain(natural_key)
+            .collect()
+    }
+}
+
+impl UniqueValue {
+    pub fn is_natural_key(&self) -> bool {
+        self.attribute == NATURAL_KEY
+    }
+}
+
+/// A natural key is a non-empty list of distinct property names
+fn natural_key_of(natural_key: &Value, properties: &Map<String, Value>) -> Result<Vec<String>, FieldError> {
+    let invalid = |message: String| FieldError::new("/attributes/schema/natural_key", FieldErrorCode::InvalidValue, message);
+
+    let names = natural_key.as_array()
+        .filter(|names| !names.is_empty())
+        .and_then(|names| names.iter().map(|name| name.as_str().map(str::to_string)).collect::<Option<Vec<_>>>())
+        .ok_or_else(|| invalid("natural_key must be a non-empty array of property names".to_string()))?;
+
+    let mut seen = BTreeSet::new();
+    for name in &names {
+        if !properties.contains_key(name) {
+            return Err(invalid(format!("natural_key names '{}', which is not a property of the schema", name)));
+        }
+        if !seen.insert(name) {
+            return Err(invalid(format!("natural_key names '{}' more than once", name)));
+        }
+    }
+
+    Ok(names)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn constraints(schema: Value) -> AttributeConstraints {
+        AttributeConstraints::from_schema(Some(&schema)).unwrap()
+    }
+
+    fn errors(schema: Value) -> Vec<FieldError> {
+        AttributeConstraints::from_schema(Some(&schema)).unwrap_err()
+    }
+
+    #[test]
+    fn uniqueness_is_scoped_to_the_type_or_global() {
+        let ci_type_id = Uuid::new_v4();
+        let constraints = constraints(json!({
+            "properties": {
+                "hostname": { "type": "string", "unique": true },
+                "asset_tag": { "type": "string", "unique": "type" },
+                "serial": { "type": "string", "unique": "global" },
+                "notes": { "type": "string", "unique": false },
+            }
+        }));
+
+        let values = constraints.unique_values(ci_type_id, &json!({
+            "hostname": "web-1",
+            "asset_tag": "A-1",
+            "serial": "SN-1",
+            "notes": "shared",
+        }));
+
+        let value = |attribute: &str, scope, value: &str| UniqueValue {
+            attribute: attribute.to_string(),
+            scope,
+            value: json!(value),
+        };
+        assert_eq!(values, vec![
+            value("asset_tag", Some(ci_type_id), "A-1"),
+            value("hostname", Some(ci_type_id), "web-1"),
+            value("serial", None, "SN-1"),
+        ]);
+    }
+
+    #[test]
+    fn missing_and_null_values_claim_nothing() {
+        let constraints = constraints(json!({
+            "properties": {
+                "hostname": { "type": "string", "unique": true },
+                "serial": { "type": "string", "unique": "global" },
+            },
+            "natural_key": ["hostname"],
+        }));
+
+        assert!(constraints.unique_values(Uuid::new_v4(), &json!({ "serial": null })).is_empty());
+        assert!(constraints.unique_values(Uuid::new_v4(), &json!({})).is_empty());
+    }
+
+    #[test]
+    fn natural_key_is_claimed_once_every_part_is_set() {
+        let ci_type_id = Uuid::new_v4();
+        let constraints = constraints(json!({
+            "properties": { "hostname": {}, "domain": {} },
+            "natural_key": ["hostname", "domain"],
+        }));
+
+        let values = constraints.unique_values(ci_type_id, &json!({ "hostname": "web-1", "domain": "example.com" }));
+        assert_eq!(values.len(), 1);
+        assert!(values[0].is_natural_key());
+        assert_eq!(values[0].scope, Some(ci_type_id));
+        assert_eq!(values[0].value, json!(["web-1", "example.com"]));
+
+        assert!(constraints.natural_key_value(ci_type_id, &json!({ "hostname": "web-1", "domain": null })).is_none());
+    }
+
+    #[test]
+    fn schema_without_constraints_has_none() {
+        assert!(AttributeConstraints::from_schema(None).unwrap().is_empty());
+        assert!(constraints(json!({ "properties": { "hostname": { "type": "string" } } })).is_empty());
+    }
+
+    #[test]
+    fn bad_declarations_are_rejected_at_their_keyword() {
+        let errors = errors(json!({
+            "properties": {
+                "hostname": { "unique": "everywhere" },
+                "rack": { "reference": "rack-type" },
+            }
+        }));
+
+        let found: Vec<(&str, FieldErrorCode)> = errors.iter().map(|error| (error.path.as_str(), error.code)).collect();
+        assert_eq!(found, vec![
+            ("/attributes/schema/properties/hostname/unique", FieldErrorCode::NotAllowed),
+            ("/attributes/schema/properties/rack/reference", FieldErrorCode::InvalidFormat),
+        ]);
+    }
+
+    #[test]
+    fn bad_natural_keys_are_rejected() {
+        let properties = json!({ "hostname": {}, "domain": {} });
+
+        for natural_key in [json!([]), json!("hostname"), json!([1]), json!(["fqdn"]), json!(["hostname", "hostname"])] {
+            let errors = errors(json!({ "properties": properties, "natural_key": natural_key }));
+            assert_eq!(errors.len(), 1, "{}", natural_key);
+            assert_eq!(errors[0].path, "/attributes/schema/natural_key");
+            assert_eq!(errors[0].code, FieldErrorCode::InvalidValue);
+        }
+    }
+}
@@ -7,6 +7,8 @@ use uuid::Uuid;
 
 use crate::error::AppError;
 
+const PASSWORD_RESET_TOKEN_LENGTH: usize = 48;
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct JwtClaims {
     pub sub: String,           // User ID
@@ -69,27 +71,10 @@ pub fn decode_jwt(token: &str, jwt_secret: &str) -> Result<JwtClaims, AppError>
     Ok(token_data.claims)
 }
 
-pub fn generate_password_reset_token(user_id: Uuid, jwt_secret: &str) -> Result<String, AppError> {
-    let now = Utc::now();
-    let exp = now + Duration::hours(1); // 1 hour expiration
-
-    let claims = JwtClaims {
-        sub: user_id.to_string(),
-        email: "".to_string(),  // Not needed for password reset
-        first_name: "".to_string(),
-        last_name: "".to_string(),
-        is_admin: false,
-        iat: now.timestamp() as usize,
-        exp: exp.timestamp() as usize,
-    };
-
-    let token = encode(
-        &Header::default(),
-        &claims,
-        &EncodingKey::from_secret(jwt_secret.as_ref()),
-    )?;
-
-    Ok(token)
+/// Generate a single-use password reset token. Only its hash is stored, and
+/// the row is marked used on redemption, so unlike a JWT it can't be replayed.
+pub fn generate_password_reset_token() -> Result<String, AppError> {
+    generate_secure_token(PASSWORD_RESET_TOKEN_LENGTH)
 }
 
 // Password validation
@@ -142,4 +127,10 @@ pub fn generate_secure_token(length: usize) -> Result<String, AppError> {
         .collect();
 
     Ok(token)
-}
\ No newline at end of file
+}
+
+/// Hash an opaque token (refresh token, reset token, API key) for storage
+pub fn hash_token(token: &str) -> String {
+    use sha2::{Digest, Sha256};
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
@@ -0,0 +1,112 @@
+use chrono::NaiveDate;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+use crate::error::{FieldError, FieldErrorCode};
+use crate::utils::expression::Expression;
+
+/// The derived attributes of a CI type, declared as `name: expression` pairs
+/// under `attributes.computed` and inherited like the schema: a subtype sees
+/// its ancestors' definitions and can override them by name.
+#[derive(Debug, Clone, Default)]
+pub struct ComputedAttributes {
+    expressions: BTreeMap<String, Expression>,
+}
+
+impl ComputedAttributes {
+    /// Merge the definitions of each layer of CI type attributes, root first.
+    /// Errors point at `/attributes/computed/<name>`.
+    pub fn from_layers<'a>(layers: impl IntoIterator<Item = &'a Value>) -> Result<Self, Vec<FieldError>> {
+        let mut sources = BTreeMap::new();
+        let mut errors = Vec::new();
+
+        for layer in layers {
+            match layer.get("computed") {
+                None | Some(Value::Null) => {}
+                Some(Value::Object(definitions)) => {
+                    for (name, source) in definitions {
+                        sources.insert(name.clone(), source.clone());
+                    }
+                }
+                Some(_) => errors.push(FieldError::new(
+                    "/attributes/computed",
+                    FieldErrorCode::InvalidType,
+                    "Computed attributes must be an object of name: expression pairs",
+                ).with_expected(Value::from("object"))),
+            }
+        }
+
+        let mut expressions = BTreeMap::new();
+        for (name, source) in sources {
+            let path = format!("/attributes/computed/{}", name);
+            let parsed = match source.as_str() {
+                Some(source) => Expression::parse(source),
+                None => {
+                    errors.push(FieldError::new(&path, FieldErrorCode::InvalidType, "The expression must be a string")
+                        .with_expected(Value::from("string")));
+                    continue;
+                }
+            };
+            match parsed {
+                Ok(expression) => {
+                    expressions.insert(name, expression);
+                }
+                Err(e) => errors.push(FieldError::new(&path, FieldErrorCode::InvalidValue, format!("Invalid expression: {}", e))),
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(Self { expressions })
+        } else {
+            Err(errors)
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.expressions.is_empty()
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &String> {
+        self.expressions.keys()
+    }
+
+    /// Whether any value depends on the current date and needs refreshing
+    pub fn uses_today(&self) -> bool {
+        self.expressions.values().any(Expression::uses_today)
+    }
+
+    /// Attributes a client tried to set that are computed, as read-only errors
+    pub fn written_by_client(&self, attributes: &Value) -> Vec<FieldError> {
+        self.names()
+            .filter(|name| attributes.get(name.as_str()).is_some())
+            .map(|name| FieldError::new(
+                format!("/attributes/{}", name),
+                FieldErrorCode::ReadOnly,
+                format!("'{}' is computed by the CI type and can't be written", name),
+            ))
+            .collect()
+    }
+
+    /// Every value, failing on the first expression that can't be evaluated
+    /// against these attributes. Errors point at `/computed/<name>`.
+    pub fn evaluate(&self, attributes: &Value, today: NaiveDate) -> Result<Value, FieldError> {
+        let mut values = Map::new();
+        for (name, expression) in &self.expressions {
+            let value = expression.evaluate(attributes, today).map_err(|e| FieldError::new(
+                format!("/computed/{}", name),
+                FieldErrorCode::InvalidValue,
+                format!("Computed attribute '{}' ({}) can't be evaluated: {}", name, expression.source(), e),
+            ))?;
+            values.insert(name.clone(), value);
+        }
+        Ok(Value::Object(values))
+    }
+
+    /// Every value, with those that can't be evaluated left null. Used where
+    /// there is no client to report to, such as migrations and refreshes.
+    pub fn evaluate_lenient(&self, attributes: &Value, today: NaiveDate) -> Value {
+        Value::Object(self.expressions.iter()
+            .map(|(name, expression)| (name.clone(), expression.evaluate(attributes, today).unwrap_or(Value::Null)))
+            .collect())
+    }
+}
@@ -0,0 +1,100 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::error::{AppError, FieldError, FieldErrorCode};
+
+/// Position in a list ordered by `(created_at, id)`, handed to clients as an
+/// opaque string. The id breaks ties between rows with the same timestamp.
+/// Lists sorted by other keys first (a name, an attribute) carry the row's
+/// values for them in `keys`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeysetCursor {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keys: Vec<Value>,
+    pub created_at: DateTime<Utc>,
+    pub id: Uuid,
+}
+
+impl KeysetCursor {
+    pub fn new(created_at: DateTime<Utc>, id: Uuid) -> Self {
+        Self { keys: Vec::new(), created_at, id }
+    }
+
+    pub fn with_keys(keys: Vec<Value>, created_at: DateTime<Utc>, id: Uuid) -> Self {
+        Self { keys, created_at, id }
+    }
+
+    /// The leading key of lists ordered by a text column such as a name
+    pub fn text_key(&self) -> Option<&str> {
+        self.keys.first().and_then(Value::as_str)
+    }
+
+    pub fn encode(&self) -> String {
+        // Serializing two plain fields can't fail
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).unwrap_or_default())
+    }
+
+    pub fn decode(cursor: &str) -> Result<Self, AppError> {
+        URL_SAFE_NO_PAD
+            .decode(cursor)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .ok_or_else(|| AppError::invalid_field(FieldError::new(
+                "/cursor",
+                FieldErrorCode::InvalidFormat,
+                "Invalid pagination cursor; pass the next_cursor of the previous page",
+            )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{http::StatusCode, response::IntoResponse};
+    use serde_json::json;
+
+    fn cursor() -> KeysetCursor {
+        KeysetCursor::with_keys(vec![json!("web-1"), json!(3)], Utc::now(), Uuid::new_v4())
+    }
+
+    fn assert_rejected(encoded: &str) {
+        let error = KeysetCursor::decode(encoded).unwrap_err();
+        assert!(matches!(error, AppError::InvalidFields(_, ref errors) if errors[0].path == "/cursor"));
+        assert_eq!(error.into_response().status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn cursor_survives_a_round_trip() {
+        let original = cursor();
+        let decoded = KeysetCursor::decode(&original.encode()).unwrap();
+
+        assert_eq!(decoded.keys, original.keys);
+        assert_eq!(decoded.created_at, original.created_at);
+        assert_eq!(decoded.id, original.id);
+        assert_eq!(decoded.text_key(), Some("web-1"));
+    }
+
+    #[test]
+    fn cursor_without_keys_leaves_them_out() {
+        let original = KeysetCursor::new(Utc::now(), Uuid::new_v4());
+        let json: Value = serde_json::from_slice(&URL_SAFE_NO_PAD.decode(original.encode()).unwrap()).unwrap();
+
+        assert!(json.get("keys").is_none());
+        assert!(KeysetCursor::decode(&original.encode()).unwrap().keys.is_empty());
+    }
+
+    #[test]
+    fn malformed_cursor_is_a_bad_request() {
+        let encoded = cursor().encode();
+
+        assert_rejected("");
+        assert_rejected("not base64!");
+        assert_rejected(&encoded[..encoded.len() - 4]);
+        assert_rejected(&URL_SAFE_NO_PAD.encode("plain text"));
+        assert_rejected(&URL_SAFE_NO_PAD.encode(json!({ "created_at": "yesterday", "id": Uuid::nil() }).to_string()));
+        assert_rejected(&URL_SAFE_NO_PAD.encode(json!({ "id": Uuid::nil() }).to_string()));
+    }
+}
@@ -0,0 +1,718 @@
+use chrono::NaiveDate;
+use serde_json::{Number, Value};
+
+/// Longest expression source accepted, in bytes
+pub const MAX_EXPRESSION_LENGTH: usize = 1024;
+/// Deepest nesting of sub-expressions accepted
+pub const MAX_EXPRESSION_DEPTH: usize = 32;
+
+/// A parsed expression of the derived-attribute language.
+///
+/// The language is deliberately small: literals, attribute references
+/// (`hostname`, or `rack.room` for nested objects), the `today` keyword,
+/// arithmetic, comparisons, boolean logic and a fixed set of pure functions.
+/// It has no loops, assignments or I/O, and both its size and nesting are
+/// bounded, so evaluating it is cheap and can't escape the asset it reads.
+///
+/// Missing attributes evaluate to null, and null propagates through
+/// operators, so `hostname + "." + domain` is null until both are set.
+#[derive(Debug, Clone)]
+pub struct Expression {
+    source: String,
+    root: Expr,
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Literal(Value),
+    Attribute(Vec<String>),
+    Today,
+    Not(Box<Expr>),
+    Negate(Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    Call(Function, Vec<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BinaryOp {
+    Or,
+    And,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Function {
+    Lower,
+    Upper,
+    Trim,
+    Len,
+    Round,
+    Min,
+    Max,
+    Coalesce,
+    If,
+    DaysUntil,
+}
+
+impl Function {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "lower" => Function::Lower,
+            "upper" => Function::Upper,
+            "trim" => Function::Trim,
+            "len" => Function::Len,
+            "round" => Function::Round,
+            "min" => Function::Min,
+            "max" => Function::Max,
+            "coalesce" => Function::Coalesce,
+            "if" => Function::If,
+            "days_until" => Function::DaysUntil,
+            _ => return None,
+        })
+    }
+
+    /// Accepted argument counts, inclusive
+    fn arity(self) -> (usize, usize) {
+        match self {
+            Function::Lower | Function::Upper | Function::Trim | Function::Len
+            | Function::Round | Function::DaysUntil => (1, 1),
+            Function::Min | Function::Max => (2, 2),
+            Function::If => (3, 3),
+            Function::Coalesce => (1, 16),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Str(String),
+    Ident(String),
+    Op(&'static str),
+    LParen,
+    RParen,
+    Comma,
+}
+
+const OPERATORS: [&str; 15] = [
+    "||", "&&", "==", "!=", "<=", ">=", "<", ">", "+", "-", "*", "/", "%", "!", ".",
+];
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            let text: String = chars[start..i].iter().collect();
+            let number = text.parse().map_err(|_| format!("invalid number '{}'", text))?;
+            tokens.push(Token::Number(number));
+        } else if c == '"' || c == '\'' {
+            let mut text = String::new();
+            i += 1;
+            loop {
+                match chars.get(i) {
+                    None => return Err("unterminated string".to_string()),
+                    Some(&ch) if ch == c => break,
+                    Some('\\') => {
+                        text.push(*chars.get(i + 1).ok_or("unterminated string")?);
+                        i += 2;
+                    }
+                    Some(&ch) => {
+                        text.push(ch);
+                        i += 1;
+                    }
+                }
+            }
+            i += 1;
+            tokens.push(Token::Str(text));
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push(Token::Ident(chars[start..i].iter().collect()));
+        } else if c == '(' {
+            tokens.push(Token::LParen);
+            i += 1;
+        } else if c == ')' {
+            tokens.push(Token::RParen);
+            i += 1;
+        } else if c == ',' {
+            tokens.push(Token::Comma);
+            i += 1;
+        } else {
+            let rest: String = chars[i..chars.len().min(i + 2)].iter().collect();
+            let op = OPERATORS.iter()
+                .find(|op| rest.starts_with(*op))
+                .ok_or_else(|| format!("unexpected character '{}'", c))?;
+            tokens.push(Token::Op(*op));
+            i += op.len();
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn eat_op(&mut self, ops: &[&'static str]) -> Option<&'static str> {
+        match self.peek() {
+            Some(Token::Op(op)) if ops.contains(op) => {
+                let op = *op;
+                self.pos += 1;
+                Some(op)
+            }
+            _ => None,
+        }
+    }
+
+    fn expect(&mut self, expected: Token, what: &str) -> Result<(), String> {
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            _ => Err(format!("expected {}", what)),
+        }
+    }
+
+    fn nested<T>(&mut self, parse: impl FnOnce(&mut Self) -> Result<T, String>) -> Result<T, String> {
+        self.depth += 1;
+        if self.depth > MAX_EXPRESSION_DEPTH {
+            return Err(format!("expression is nested more than {} levels deep", MAX_EXPRESSION_DEPTH));
+        }
+        let result = parse(self);
+        self.depth -= 1;
+        result
+    }
+
+    fn expression(&mut self) -> Result<Expr, String> {
+        self.nested(|p| p.binary(0))
+    }
+
+    /// Left-associative binary operators, loosest first
+    fn binary(&mut self, level: usize) -> Result<Expr, String> {
+        const LEVELS: [&[&str]; 5] = [
+            &["||"],
+            &["&&"],
+            &["==", "!=", "<=", ">=", "<", ">"],
+            &["+", "-"],
+            &["*", "/", "%"],
+        ];
+
+        if level == LEVELS.len() {
+            return self.unary();
+        }
+
+        let mut left = self.binary(level + 1)?;
+        while let Some(op) = self.eat_op(LEVELS[level]) {
+            let right = self.binary(level + 1)?;
+            let op = match op {
+                "||" => BinaryOp::Or,
+                "&&" => BinaryOp::And,
+                "==" => BinaryOp::Eq,
+                "!=" => BinaryOp::Ne,
+                "<" => BinaryOp::Lt,
+                "<=" => BinaryOp::Le,
+                ">" => BinaryOp::Gt,
+                ">=" => BinaryOp::Ge,
+                "+" => BinaryOp::Add,
+                "-" => BinaryOp::Sub,
+                "*" => BinaryOp::Mul,
+                "/" => BinaryOp::Div,
+                _ => BinaryOp::Rem,
+            };
+            left = Expr::Binary(op, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.eat_op(&["!"]).is_some() {
+            return self.nested(|p| p.unary()).map(|e| Expr::Not(Box::new(e)));
+        }
+        if self.eat_op(&["-"]).is_some() {
+            return self.nested(|p| p.unary()).map(|e| Expr::Negate(Box::new(e)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expr, String> {
+        match self.next() {
+            Some(Token::Number(n)) => Ok(Expr::Literal(number(n))),
+            Some(Token::Str(s)) => Ok(Expr::Literal(Value::String(s))),
+            Some(Token::LParen) => {
+                let inner = self.expression()?;
+                self.expect(Token::RParen, "')'")?;
+                Ok(inner)
+            }
+            Some(Token::Ident(name)) => match name.as_str() {
+                "true" => Ok(Expr::Literal(Value::Bool(true))),
+                "false" => Ok(Expr::Literal(Value::Bool(false))),
+                "null" => Ok(Expr::Literal(Value::Null)),
+                "today" => Ok(Expr::Today),
+                _ if self.peek() == Some(&Token::LParen) => self.call(&name),
+                _ => {
+                    let mut path = vec![name];
+                    while self.eat_op(&["."]).is_some() {
+                        match self.next() {
+                            Some(Token::Ident(segment)) => path.push(segment),
+                            _ => return Err("expected an attribute name after '.'".to_string()),
+                        }
+                    }
+                    Ok(Expr::Attribute(path))
+                }
+            },
+            Some(token) => Err(format!("unexpected {:?}", token)),
+            None => Err("unexpected end of expression".to_string()),
+        }
+    }
+
+    fn call(&mut self, name: &str) -> Result<Expr, String> {
+        let function = Function::parse(name).ok_or_else(|| format!("unknown function '{}'", name))?;
+        self.expect(Token::LParen, "'('")?;
+
+        let mut args = Vec::new();
+        if self.peek() != Some(&Token::RParen) {
+            loop {
+                args.push(self.expression()?);
+                if self.peek() != Some(&Token::Comma) {
+                    break;
+                }
+                self.pos += 1;
+            }
+        }
+        self.expect(Token::RParen, "')'")?;
+
+        let (min, max) = function.arity();
+        if args.len() < min || args.len() > max {
+            return Err(if min == max {
+                format!("{}() takes {} argument(s), got {}", name, min, args.len())
+            } else {
+                format!("{}() takes {} to {} arguments, got {}", name, min, max, args.len())
+            });
+        }
+        Ok(Expr::Call(function, args))
+    }
+}
+
+impl Expression {
+    pub fn parse(source: &str) -> Result<Self, String> {
+        if source.len() > MAX_EXPRESSION_LENGTH {
+            return Err(format!("expression is longer than {} characters", MAX_EXPRESSION_LENGTH));
+        }
+
+        let mut parser = Parser { tokens: tokenize(source)?, pos: 0, depth: 0 };
+        let root = parser.expression()?;
+        if let Some(token) = parser.peek() {
+            return Err(format!("unexpected {:?} after the end of the expression", token));
+        }
+
+        Ok(Self { source: source.to_string(), root })
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Whether the value depends on the current date, and so goes stale
+    pub fn uses_today(&self) -> bool {
+        fn walk(expr: &Expr) -> bool {
+            match expr {
+                Expr::Today => true,
+                Expr::Literal(_) | Expr::Attribute(_) => false,
+                Expr::Not(inner) | Expr::Negate(inner) => walk(inner),
+                Expr::Binary(_, left, right) => walk(left) || walk(right),
+                Expr::Call(function, args) => matches!(function, Function::DaysUntil) || args.iter().any(walk),
+            }
+        }
+        walk(&self.root)
+    }
+
+    /// Evaluate against an asset's attributes. Type errors, such as
+    /// multiplying a string, are reported rather than coerced.
+    pub fn evaluate(&self, attributes: &Value, today: NaiveDate) -> Result<Value, String> {
+        eval(&self.root, attributes, today)
+    }
+}
+
+fn eval(expr: &Expr, attributes: &Value, today: NaiveDate) -> Result<Value, String> {
+    match expr {
+        Expr::Literal(value) => Ok(value.clone()),
+        Expr::Today => Ok(Value::String(today.format("%Y-%m-%d").to_string())),
+        Expr::Attribute(path) => Ok(path.iter()
+            .try_fold(attributes, |value, segment| value.get(segment))
+            .cloned()
+            .unwrap_or(Value::Null)),
+        Expr::Not(inner) => match eval(inner, attributes, today)? {
+            Value::Null => Ok(Value::Null),
+            Value::Bool(b) => Ok(Value::Bool(!b)),
+            other => Err(format!("'!' needs a boolean, got {}", type_name(&other))),
+        },
+        Expr::Negate(inner) => match eval(inner, attributes, today)? {
+            Value::Null => Ok(Value::Null),
+            other => Ok(number(-as_number(&other, "-")?)),
+        },
+        Expr::Binary(BinaryOp::And, left, right) => {
+            match as_bool(eval(left, attributes, today)?, "&&")? {
+                Some(false) => Ok(Value::Bool(false)),
+                left => Ok(match (left, as_bool(eval(right, attributes, today)?, "&&")?) {
+                    (_, Some(false)) => Value::Bool(false),
+                    (Some(true), Some(true)) => Value::Bool(true),
+                    _ => Value::Null,
+                }),
+            }
+        }
+        Expr::Binary(BinaryOp::Or, left, right) => {
+            match as_bool(eval(left, attributes, today)?, "||")? {
+                Some(true) => Ok(Value::Bool(true)),
+                left => Ok(match (left, as_bool(eval(right, attributes, today)?, "||")?) {
+                    (_, Some(true)) => Value::Bool(true),
+                    (Some(false), Some(false)) => Value::Bool(false),
+                    _ => Value::Null,
+                }),
+            }
+        }
+        Expr::Binary(op, left, right) => {
+            let left = eval(left, attributes, today)?;
+            let right = eval(right, attributes, today)?;
+            binary(*op, left, right)
+        }
+        Expr::Call(function, args) => {
+            let args = args.iter()
+                .map(|arg| eval(arg, attributes, today))
+                .collect::<Result<Vec<_>, _>>()?;
+            call(*function, args, today)
+        }
+    }
+}
+
+fn binary(op: BinaryOp, left: Value, right: Value) -> Result<Value, String> {
+    match op {
+        BinaryOp::Eq => return Ok(Value::Bool(loose_eq(&left, &right))),
+        BinaryOp::Ne => return Ok(Value::Bool(!loose_eq(&left, &right))),
+        _ => {}
+    }
+
+    if left.is_null() || right.is_null() {
+        return Ok(Value::Null);
+    }
+
+    match op {
+        BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge => {
+            let ordering = match (&left, &right) {
+                (Value::Number(a), Value::Number(b)) => a.as_f64().partial_cmp(&b.as_f64()),
+                (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+                _ => None,
+            }
+            .ok_or_else(|| format!("can't compare {} with {}", type_name(&left), type_name(&right)))?;
+
+            Ok(Value::Bool(match op {
+                BinaryOp::Lt => ordering.is_lt(),
+                BinaryOp::Le => ordering.is_le(),
+                BinaryOp::Gt => ordering.is_gt(),
+                _ => ordering.is_ge(),
+            }))
+        }
+        // '+' joins text when either side is a string
+        BinaryOp::Add if left.is_string() || right.is_string() => {
+            Ok(Value::String(format!("{}{}", as_text(&left)?, as_text(&right)?)))
+        }
+        _ => {
+            let symbol = match op {
+                BinaryOp::Add => "+",
+                BinaryOp::Sub => "-",
+                BinaryOp::Mul => "*",
+                BinaryOp::Div => "/",
+                _ => "%",
+            };
+            let a = as_number(&left, symbol)?;
+            let b = as_number(&right, symbol)?;
+
+            Ok(match op {
+                BinaryOp::Add => number(a + b),
+                BinaryOp::Sub => number(a - b),
+                BinaryOp::Mul => number(a * b),
+                // Dividing by zero has no sensible value
+                BinaryOp::Div | BinaryOp::Rem if b == 0.0 => Value::Null,
+                BinaryOp::Div => number(a / b),
+                _ => number(a % b),
+            })
+        }
+    }
+}
+
+fn call(function: Function, args: Vec<Value>, today: NaiveDate) -> Result<Value, String> {
+    let mut args = args.into_iter();
+    let first = args.next().unwrap_or(Value::Null);
+
+    if let Function::Coalesce = function {
+        return Ok(std::iter::once(first).chain(args).find(|v| !v.is_null()).unwrap_or(Value::Null));
+    }
+    if let Function::If = function {
+        let (then, otherwise) = (args.next().unwrap_or(Value::Null), args.next().unwrap_or(Value::Null));
+        return Ok(match as_bool(first, "if()")? {
+            Some(true) => then,
+            Some(false) => otherwise,
+            None => Value::Null,
+        });
+    }
+    if first.is_null() {
+        return Ok(Value::Null);
+    }
+
+    Ok(match function {
+        Function::Lower => Value::String(as_text(&first)?.to_lowercase()),
+        Function::Upper => Value::String(as_text(&first)?.to_uppercase()),
+        Function::Trim => Value::String(as_text(&first)?.trim().to_string()),
+        Function::Len => match &first {
+            Value::String(s) => Value::from(s.chars().count()),
+            Value::Array(items) => Value::from(items.len()),
+            Value::Object(fields) => Value::from(fields.len()),
+            other => return Err(format!("len() needs text or a list, got {}", type_name(other))),
+        },
+        Function::Round => number(as_number(&first, "round()")?.round()),
+        Function::Min | Function::Max => {
+            let second = args.next().unwrap_or(Value::Null);
+            if second.is_null() {
+                return Ok(Value::Null);
+            }
+            let (a, b) = (as_number(&first, "min()/max()")?, as_number(&second, "min()/max()")?);
+            number(if matches!(function, Function::Min) { a.min(b) } else { a.max(b) })
+        }
+        Function::DaysUntil => {
+            let text = as_text(&first)?;
+            let date = NaiveDate::parse_from_str(text.get(..10).unwrap_or(&text), "%Y-%m-%d")
+                .map_err(|_| format!("days_until() needs a YYYY-MM-DD date, got '{}'", text))?;
+            Value::from((date - today).num_days())
+        }
+        Function::Coalesce | Function::If => unreachable!(),
+    })
+}
+
+/// Equality where 2 and 2.0 are the same number
+fn loose_eq(left: &Value, right: &Value) -> bool {
+    match (left, right) {
+        (Value::Number(a), Value::Number(b)) => a.as_f64() == b.as_f64(),
+        _ => left == right,
+    }
+}
+
+fn as_number(value: &Value, op: &str) -> Result<f64, String> {
+    value.as_f64().ok_or_else(|| format!("'{}' needs numbers, got {}", op, type_name(value)))
+}
+
+fn as_bool(value: Value, op: &str) -> Result<Option<bool>, String> {
+    match value {
+        Value::Null => Ok(None),
+        Value::Bool(b) => Ok(Some(b)),
+        other => Err(format!("'{}' needs booleans, got {}", op, type_name(&other))),
+    }
+}
+
+fn as_text(value: &Value) -> Result<String, String> {
+    match value {
+        Value::String(s) => Ok(s.clone()),
+        Value::Number(n) => Ok(n.to_string()),
+        Value::Bool(b) => Ok(b.to_string()),
+        other => Err(format!("can't use {} as text", type_name(other))),
+    }
+}
+
+/// Whole results are kept as integers, so `sockets * cores` stays `16`
+fn number(n: f64) -> Value {
+    if n.fract() == 0.0 && n.abs() < 9_007_199_254_740_992.0 {
+        Value::from(n as i64)
+    } else {
+        Number::from_f64(n).map(Value::Number).unwrap_or(Value::Null)
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "text",
+        Value::Array(_) => "a list",
+        Value::Object(_) => "an object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn today() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 3, 1).unwrap()
+    }
+
+    fn eval_with(source: &str, attributes: Value) -> Result<Value, String> {
+        Expression::parse(source)?.evaluate(&attributes, today())
+    }
+
+    fn eval_str(source: &str) -> Value {
+        eval_with(source, json!({})).unwrap()
+    }
+
+    #[test]
+    fn operators_bind_by_precedence() {
+        assert_eq!(eval_str("1 + 2 * 3"), json!(7));
+        assert_eq!(eval_str("(1 + 2) * 3"), json!(9));
+        assert_eq!(eval_str("10 - 4 - 3"), json!(3));
+        assert_eq!(eval_str("-2 * 3"), json!(-6));
+        assert_eq!(eval_str("7 % 4 + 1"), json!(4));
+        assert_eq!(eval_str("1 + 1 == 2 && 3 > 2"), json!(true));
+        assert_eq!(eval_str("false && false || true"), json!(true));
+        assert_eq!(eval_str("!true || true"), json!(true));
+        assert_eq!(eval_str("!(true || true)"), json!(false));
+    }
+
+    #[test]
+    fn whole_numbers_stay_integers() {
+        assert_eq!(eval_with("sockets * cores", json!({"sockets": 2, "cores": 8})), Ok(json!(16)));
+        assert_eq!(eval_str("7 / 2"), json!(3.5));
+        assert_eq!(eval_str("round(2.5)"), json!(3));
+        assert_eq!(eval_str("2 == 2.0"), json!(true));
+    }
+
+    #[test]
+    fn attributes_are_read_by_path() {
+        let attributes = json!({"hostname": "web01", "rack": {"room": "B2"}});
+
+        assert_eq!(eval_with("upper(hostname)", attributes.clone()), Ok(json!("WEB01")));
+        assert_eq!(eval_with("rack.room + '-' + hostname", attributes.clone()), Ok(json!("B2-web01")));
+        assert_eq!(eval_with("rack.shelf", attributes), Ok(Value::Null));
+    }
+
+    #[test]
+    fn null_propagates_through_operators_and_functions() {
+        let attributes = json!({"hostname": "web01"});
+
+        assert_eq!(eval_with("hostname + '.' + domain", attributes.clone()), Ok(Value::Null));
+        assert_eq!(eval_with("coalesce(domain, 'local')", attributes.clone()), Ok(json!("local")));
+        assert_eq!(eval_str("missing * 2"), Value::Null);
+        assert_eq!(eval_str("-missing"), Value::Null);
+        assert_eq!(eval_str("!missing"), Value::Null);
+        assert_eq!(eval_str("missing < 3"), Value::Null);
+        assert_eq!(eval_str("lower(missing)"), Value::Null);
+        assert_eq!(eval_str("max(missing, 3)"), Value::Null);
+        assert_eq!(eval_str("if(missing, 1, 2)"), Value::Null);
+        assert_eq!(eval_str("1 / 0"), Value::Null);
+        assert_eq!(eval_str("missing == null"), json!(true));
+        assert_eq!(eval_str("missing != 1"), json!(true));
+    }
+
+    #[test]
+    fn boolean_logic_treats_null_as_unknown() {
+        assert_eq!(eval_str("missing && false"), json!(false));
+        assert_eq!(eval_str("false && missing"), json!(false));
+        assert_eq!(eval_str("missing && true"), Value::Null);
+        assert_eq!(eval_str("missing || true"), json!(true));
+        assert_eq!(eval_str("true || missing"), json!(true));
+        assert_eq!(eval_str("missing || false"), Value::Null);
+    }
+
+    #[test]
+    fn dates_are_relative_to_today() {
+        let attributes = json!({"warranty_end": "2024-02-28T10:00:00Z", "eol": "2025-03-01"});
+
+        assert_eq!(eval_str("today"), json!("2024-03-01"));
+        assert_eq!(eval_str("today < '2024-12-31'"), json!(true));
+        assert_eq!(eval_str("days_until('2024-03-31')"), json!(30));
+        assert_eq!(eval_with("days_until(warranty_end)", attributes.clone()), Ok(json!(-2)));
+        assert_eq!(eval_with("days_until(eol)", attributes.clone()), Ok(json!(365)));
+        assert_eq!(eval_with("if(days_until(warranty_end) < 0, 'expired', 'covered')", attributes), Ok(json!("expired")));
+    }
+
+    #[test]
+    fn only_date_dependent_expressions_go_stale() {
+        assert!(Expression::parse("today").unwrap().uses_today());
+        assert!(Expression::parse("coalesce(name, days_until(eol))").unwrap().uses_today());
+        assert!(!Expression::parse("sockets * cores").unwrap().uses_today());
+    }
+
+    #[test]
+    fn type_errors_are_reported() {
+        for source in [
+            "'a' * 2",
+            "!1",
+            "1 && true",
+            "false || 'yes'",
+            "1 < 'a'",
+            "-'a'",
+            "len(5)",
+            "if(1, 2, 3)",
+            "days_until('soon')",
+            "upper(rack)",
+        ] {
+            assert!(eval_with(source, json!({"rack": {"room": "B2"}})).is_err(), "{} should fail", source);
+        }
+    }
+
+    #[test]
+    fn malformed_expressions_are_rejected() {
+        for source in [
+            "",
+            "1 +",
+            "(1 + 2",
+            "1 + 2)",
+            "1 2",
+            "'unterminated",
+            "1 # 2",
+            "1..2",
+            "rack.",
+            "nosuch(1)",
+            "lower()",
+            "min(1)",
+            "coalesce(1,",
+            "hostname(1)",
+        ] {
+            assert!(Expression::parse(source).is_err(), "{} should be rejected", source);
+        }
+    }
+
+    #[test]
+    fn oversized_expressions_are_rejected() {
+        let long = format!("'{}'", "x".repeat(MAX_EXPRESSION_LENGTH));
+        assert!(Expression::parse(&long).unwrap_err().contains("longer than"));
+
+        let deep = format!("{}1{}", "(".repeat(MAX_EXPRESSION_DEPTH + 1), ")".repeat(MAX_EXPRESSION_DEPTH + 1));
+        assert!(Expression::parse(&deep).unwrap_err().contains("nested"));
+
+        let shallow = format!("{}1{}", "(".repeat(MAX_EXPRESSION_DEPTH - 1), ")".repeat(MAX_EXPRESSION_DEPTH - 1));
+        assert_eq!(Expression::parse(&shallow).unwrap().evaluate(&json!({}), today()), Ok(json!(1)));
+    }
+}
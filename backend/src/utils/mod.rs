@@ -3,9 +3,25 @@ pub mod csv;
 pub mod validation;
 pub mod json_diff;
 pub mod date_utils;
+pub mod cursor;
+pub mod pagination;
+pub mod schema_migration;
+pub mod schema_cache;
+pub mod expression;
+pub mod computed_attributes;
+pub mod attribute_constraints;
+pub mod asset_query;
 
-pub use auth::{hash_password, verify_password, create_jwt, decode_jwt};
+pub use auth::{hash_password, verify_password, create_jwt, decode_jwt, generate_secure_token, generate_password_reset_token, hash_token};
 pub use csv::{read_csv, write_csv};
 pub use validation::{validate_ci_type, validate_ci_asset, validate_password_strength};
 pub use json_diff::{calculate_json_diff, apply_json_diff};
-pub use date_utils::{parse_date, format_date, calculate_depreciation};
\ No newline at end of file
+pub use date_utils::{parse_date, format_date, calculate_depreciation};
+pub use cursor::KeysetCursor;
+pub use pagination::{PageRequest, Page};
+pub use schema_migration::{SchemaMigration, AttributeCoercion, migrate_attributes};
+pub use schema_cache::SchemaCache;
+pub use expression::Expression;
+pub use computed_attributes::ComputedAttributes;
+pub use attribute_constraints::{AttributeConstraints, UniqueValue};
+pub use asset_query::{AssetFilter, AssetField, FilterOp, SortKey, QueryFields, parse_filter, parse_sort};
\ No newline at end of file
@@ -0,0 +1,157 @@
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::error::{AppError, AppResult, FieldError, FieldErrorCode, PaginatedResponse};
+use crate::utils::KeysetCursor;
+
+pub const DEFAULT_PAGE_SIZE: i64 = 50;
+pub const MAX_PAGE_SIZE: i64 = 200;
+
+/// Paging parameters every list endpoint accepts next to its filters
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PageRequest {
+    pub cursor: Option<String>, // next_cursor of the previous page
+    pub limit: Option<i64>,
+    #[serde(default)]
+    pub include_total: bool, // Also count every match, which costs a second query
+}
+
+impl PageRequest {
+    pub fn limit(&self) -> AppResult<i64> {
+        let limit = self.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+        if !(1..=MAX_PAGE_SIZE).contains(&limit) {
+            return Err(AppError::invalid_field(
+                FieldError::new("/limit", FieldErrorCode::OutOfRange, format!("Limit must be between 1 and {}", MAX_PAGE_SIZE))
+                    .with_expected(json!({ "min": 1, "max": MAX_PAGE_SIZE })),
+            ));
+        }
+        Ok(limit)
+    }
+
+    /// The position to resume from. `keys` is the number of sort values the
+    /// list orders by ahead of `(created_at, id)`; a cursor minted by a list
+    /// ordered differently is rejected.
+    pub fn cursor(&self, keys: usize) -> AppResult<Option<KeysetCursor>> {
+        let Some(cursor) = self.cursor.as_deref().map(KeysetCursor::decode).transpose()? else {
+            return Ok(None);
+        };
+        if cursor.keys.len() != keys {
+            return Err(AppError::invalid_field(FieldError::new(
+                "/cursor",
+                FieldErrorCode::InvalidValue,
+                "The cursor belongs to a list with a different sort order",
+            )));
+        }
+        Ok(Some(cursor))
+    }
+}
+
+/// One page of a list, and where the next one starts
+#[derive(Debug, Clone)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub limit: i64,
+    pub next_cursor: Option<String>,
+    pub total: Option<i64>,
+}
+
+impl<T> Page<T> {
+    /// Build a page from rows fetched with `limit + 1`: the extra row only
+    /// tells that another page follows, which resumes after the last row kept.
+    pub fn from_rows(mut rows: Vec<T>, limit: i64, cursor_of: impl Fn(&T) -> KeysetCursor) -> Self {
+        let next_cursor = if rows.len() as i64 > limit {
+            rows.truncate(limit as usize);
+            rows.last().map(|last| cursor_of(last).encode())
+        } else {
+            None
+        };
+
+        Self { items: rows, limit, next_cursor, total: None }
+    }
+
+    pub fn with_total(mut self, total: Option<i64>) -> Self {
+        self.total = total;
+        self
+    }
+
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> Page<U> {
+        Page {
+            items: self.items.into_iter().map(f).collect(),
+            limit: self.limit,
+            next_cursor: self.next_cursor,
+            total: self.total,
+        }
+    }
+
+    pub fn into_response(self, message: &str) -> PaginatedResponse<T> {
+        PaginatedResponse::with_message(self.items, self.limit, self.next_cursor, self.total, message.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{http::StatusCode, response::IntoResponse};
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    fn request(cursor: Option<String>, limit: Option<i64>) -> PageRequest {
+        PageRequest { cursor, limit, include_total: false }
+    }
+
+    fn rejected_at(error: AppError) -> String {
+        let AppError::InvalidFields(_, ref errors) = error else { panic!("unexpected error: {:?}", error) };
+        let path = errors[0].path.clone();
+        assert_eq!(error.into_response().status(), StatusCode::BAD_REQUEST);
+        path
+    }
+
+    #[test]
+    fn limit_defaults_and_stays_within_bounds() {
+        assert_eq!(request(None, None).limit().unwrap(), DEFAULT_PAGE_SIZE);
+        assert_eq!(request(None, Some(1)).limit().unwrap(), 1);
+        assert_eq!(request(None, Some(MAX_PAGE_SIZE)).limit().unwrap(), MAX_PAGE_SIZE);
+
+        for limit in [0, -1, MAX_PAGE_SIZE + 1] {
+            assert_eq!(rejected_at(request(None, Some(limit)).limit().unwrap_err()), "/limit");
+        }
+    }
+
+    #[test]
+    fn cursor_is_returned_for_a_list_with_the_same_sort_keys() {
+        let minted = KeysetCursor::with_keys(vec![json!("web-1")], Utc::now(), Uuid::new_v4());
+        let cursor = request(Some(minted.encode()), None).cursor(1).unwrap().unwrap();
+
+        assert_eq!(cursor.id, minted.id);
+        assert!(request(None, None).cursor(1).unwrap().is_none());
+    }
+
+    #[test]
+    fn cursor_with_the_wrong_number_of_keys_is_rejected() {
+        let keyed = KeysetCursor::with_keys(vec![json!("web-1")], Utc::now(), Uuid::new_v4()).encode();
+        let plain = KeysetCursor::new(Utc::now(), Uuid::new_v4()).encode();
+
+        assert_eq!(rejected_at(request(Some(keyed.clone()), None).cursor(0).unwrap_err()), "/cursor");
+        assert_eq!(rejected_at(request(Some(keyed), None).cursor(2).unwrap_err()), "/cursor");
+        assert_eq!(rejected_at(request(Some(plain), None).cursor(1).unwrap_err()), "/cursor");
+    }
+
+    #[test]
+    fn tampered_cursor_is_rejected() {
+        assert_eq!(rejected_at(request(Some("tampered".to_string()), None).cursor(0).unwrap_err()), "/cursor");
+    }
+
+    #[test]
+    fn page_hands_out_a_cursor_only_when_more_rows_follow() {
+        let rows: Vec<(i64, Uuid)> = (0..3).map(|n| (n, Uuid::new_v4())).collect();
+        let cursor_of = |row: &(i64, Uuid)| KeysetCursor::new(Utc::now(), row.1);
+
+        let page = Page::from_rows(rows.clone(), 2, cursor_of);
+        assert_eq!(page.items.len(), 2);
+        assert_eq!(KeysetCursor::decode(&page.next_cursor.unwrap()).unwrap().id, rows[1].1);
+
+        let last_page = Page::from_rows(rows, 3, cursor_of);
+        assert_eq!(last_page.items.len(), 3);
+        assert!(last_page.next_cursor.is_none());
+    }
+}
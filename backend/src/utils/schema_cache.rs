@@ -0,0 +1,44 @@
+use chrono::{DateTime, Utc};
+use jsonschema::JSONSchema;
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+use uuid::Uuid;
+
+/// Compiled JSON schemas shared across requests. Entries are keyed by the ID
+/// of the type that owns the schema and stamped with its `updated_at`, so an
+/// entry for an older version of the type is recompiled rather than reused.
+#[derive(Clone, Default)]
+pub struct SchemaCache {
+    entries: Arc<RwLock<HashMap<Uuid, CachedSchema>>>,
+}
+
+struct CachedSchema {
+    updated_at: DateTime<Utc>,
+    schema: Arc<JSONSchema>,
+}
+
+impl SchemaCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The compiled form of `schema`, compiling it on a miss
+    pub fn get_or_compile(&self, id: Uuid, updated_at: DateTime<Utc>, schema: &Value) -> Result<Arc<JSONSchema>, String> {
+        if let Some(cached) = self.entries.read().unwrap().get(&id) {
+            if cached.updated_at == updated_at {
+                return Ok(cached.schema.clone());
+            }
+        }
+
+        let compiled = Arc::new(JSONSchema::compile(schema).map_err(|e| e.to_string())?);
+        self.entries.write().unwrap().insert(id, CachedSchema { updated_at, schema: compiled.clone() });
+        Ok(compiled)
+    }
+
+    pub fn invalidate(&self, id: Uuid) {
+        self.entries.write().unwrap().remove(&id);
+    }
+}
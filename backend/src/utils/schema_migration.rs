@@ -0,0 +1,205 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Number, Value};
+use std::collections::BTreeMap;
+
+/// Rewrites for existing asset attributes when a CI type's schema changes.
+/// Steps run in order: removals, renames, coercions, then defaults. They only
+/// touch top-level attributes.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SchemaMigration {
+    #[serde(default)]
+    pub removals: Vec<String>, // Attributes the new schema no longer has
+    #[serde(default)]
+    pub renames: BTreeMap<String, String>, // old name -> new name
+    #[serde(default)]
+    pub coercions: BTreeMap<String, AttributeCoercion>,
+    #[serde(default)]
+    pub defaults: Map<String, Value>, // Set when the attribute is missing or null
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AttributeCoercion {
+    String,
+    Number,
+    Integer,
+    Boolean,
+}
+
+impl AttributeCoercion {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AttributeCoercion::String => "string",
+            AttributeCoercion::Number => "number",
+            AttributeCoercion::Integer => "integer",
+            AttributeCoercion::Boolean => "boolean",
+        }
+    }
+}
+
+impl SchemaMigration {
+    pub fn is_empty(&self) -> bool {
+        self.removals.is_empty() && self.renames.is_empty() && self.coercions.is_empty() && self.defaults.is_empty()
+    }
+}
+
+/// Apply a migration to one asset's attributes. Errors name the attribute that
+/// could not be converted.
+pub fn migrate_attributes(attributes: &Value, migration: &SchemaMigration) -> Result<Value, String> {
+    let mut attributes = match attributes {
+        Value::Object(object) => object.clone(),
+        Value::Null => Map::new(),
+        _ => return Err("attributes are not an object".to_string()),
+    };
+
+    for name in &migration.removals {
+        attributes.remove(name);
+    }
+
+    for (from, to) in &migration.renames {
+        if let Some(value) = attributes.remove(from) {
+            if attributes.contains_key(to) {
+                return Err(format!("cannot rename '{}' to '{}': '{}' already exists", from, to, to));
+            }
+            attributes.insert(to.clone(), value);
+        }
+    }
+
+    for (name, coercion) in &migration.coercions {
+        if let Some(value) = attributes.get_mut(name) {
+            if !value.is_null() {
+                *value = coerce(value, *coercion)
+                    .ok_or_else(|| format!("cannot convert '{}' value {} to {}", name, value, coercion.as_str()))?;
+            }
+        }
+    }
+
+    for (name, default) in &migration.defaults {
+        let entry = attributes.entry(name.clone()).or_insert(Value::Null);
+        if entry.is_null() {
+            *entry = default.clone();
+        }
+    }
+
+    Ok(Value::Object(attributes))
+}
+
+fn coerce(value: &Value, coercion: AttributeCoercion) -> Option<Value> {
+    match coercion {
+        AttributeCoercion::String => Some(match value {
+            Value::String(_) => value.clone(),
+            Value::Number(n) => Value::String(n.to_string()),
+            Value::Bool(b) => Value::String(b.to_string()),
+            _ => return None,
+        }),
+        AttributeCoercion::Number => match value {
+            Value::Number(_) => Some(value.clone()),
+            Value::String(s) => s.trim().parse::<f64>().ok().and_then(Number::from_f64).map(Value::Number),
+            Value::Bool(b) => Some(Value::from(*b as i64)),
+            _ => None,
+        },
+        AttributeCoercion::Integer => match value {
+            Value::Number(n) if n.is_i64() || n.is_u64() => Some(value.clone()),
+            Value::Number(n) => n.as_f64().filter(|f| f.fract() == 0.0).map(|f| Value::from(f as i64)),
+            Value::String(s) => s.trim().parse::<i64>().ok().map(Value::from),
+            Value::Bool(b) => Some(Value::from(*b as i64)),
+            _ => None,
+        },
+        AttributeCoercion::Boolean => match value {
+            Value::Bool(_) => Some(value.clone()),
+            Value::String(s) => match s.trim().to_lowercase().as_str() {
+                "true" | "yes" | "1" => Some(Value::Bool(true)),
+                "false" | "no" | "0" => Some(Value::Bool(false)),
+                _ => None,
+            },
+            Value::Number(n) => match n.as_f64() {
+                Some(f) if f == 1.0 => Some(Value::Bool(true)),
+                Some(f) if f == 0.0 => Some(Value::Bool(false)),
+                _ => None,
+            },
+            _ => None,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn migration(value: Value) -> SchemaMigration {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn renames_move_the_value() {
+        let migration = migration(json!({ "renames": { "hostname": "fqdn" } }));
+
+        assert_eq!(
+            migrate_attributes(&json!({ "hostname": "web-1", "cpu": 4 }), &migration),
+            Ok(json!({ "fqdn": "web-1", "cpu": 4 })),
+        );
+        // Assets without the old attribute are left alone
+        assert_eq!(migrate_attributes(&json!({ "cpu": 4 }), &migration), Ok(json!({ "cpu": 4 })));
+    }
+
+    #[test]
+    fn rename_onto_an_existing_attribute_fails() {
+        let migration = migration(json!({ "renames": { "hostname": "fqdn" } }));
+
+        let error = migrate_attributes(&json!({ "hostname": "web-1", "fqdn": "web-1.example.com" }), &migration)
+            .unwrap_err();
+        assert!(error.contains("'fqdn' already exists"), "{}", error);
+    }
+
+    #[test]
+    fn defaults_fill_missing_and_null_attributes_only() {
+        let migration = migration(json!({ "defaults": { "environment": "production", "cpu": 1 } }));
+
+        assert_eq!(
+            migrate_attributes(&json!({ "environment": null, "cpu": 4 }), &migration),
+            Ok(json!({ "environment": "production", "cpu": 4 })),
+        );
+        assert_eq!(
+            migrate_attributes(&Value::Null, &migration),
+            Ok(json!({ "environment": "production", "cpu": 1 })),
+        );
+    }
+
+    #[test]
+    fn removals_drop_the_attribute() {
+        let migration = migration(json!({ "removals": ["legacy_id", "never_set"] }));
+
+        assert!(!migration.is_empty());
+        assert_eq!(
+            migrate_attributes(&json!({ "legacy_id": 17, "cpu": 4 }), &migration),
+            Ok(json!({ "cpu": 4 })),
+        );
+    }
+
+    #[test]
+    fn steps_run_in_order() {
+        // The removed name is free for the rename; the renamed value is
+        // coerced; the default sees the coerced value and leaves it be
+        let migration = migration(json!({
+            "removals": ["cores"],
+            "renames": { "cpu_count": "cores" },
+            "coercions": { "cores": "integer" },
+            "defaults": { "cores": 1 },
+        }));
+
+        assert_eq!(
+            migrate_attributes(&json!({ "cores": "four", "cpu_count": "4" }), &migration),
+            Ok(json!({ "cores": 4 })),
+        );
+    }
+
+    #[test]
+    fn failed_coercion_names_the_attribute() {
+        let migration = migration(json!({ "coercions": { "cpu": "integer" } }));
+
+        let error = migrate_attributes(&json!({ "cpu": "four" }), &migration).unwrap_err();
+        assert!(error.contains("'cpu'"), "{}", error);
+        assert!(migrate_attributes(&json!(["not", "an", "object"]), &migration).is_err());
+    }
+}
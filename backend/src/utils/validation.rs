@@ -2,11 +2,11 @@ use crate::{error::AppError, models::CreateCITypeRequest};
 use validator::Validate;
 
 pub fn validate_ci_type(request: &CreateCITypeRequest) -> Result<(), AppError> {
-    request.validate().map_err(|e| AppError::validation(format!("Validation failed: {}", e)))
+    Ok(request.validate()?)
 }
 
 pub fn validate_ci_asset(request: &crate::models::CreateCIAssetRequest) -> Result<(), AppError> {
-    request.validate().map_err(|e| AppError::validation(format!("Validation failed: {}", e)))
+    Ok(request.validate()?)
 }
 
 pub fn validate_password_strength(password: &str) -> Result<(), AppError> {
@@ -70,7 +70,7 @@ class ApiClient {
       // Handle other HTTP errors
       if (!response.ok) {
         const errorData = await response.json().catch(() => ({}));
-        const message = errorData.message || errorData.error || `HTTP ${response.status}: ${response.statusText}`;
+        const message = errorData.error?.message || errorData.message || `HTTP ${response.status}: ${response.statusText}`;
         throw new Error(message);
       }
 